@@ -0,0 +1,199 @@
+use std::io;
+
+use reader::{Reader, invalid_data, read_list, read_u8, read_u16};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+  /// Index of the descriptor of the annotation type
+  pub type_idx: u16,
+  pub elements: Vec<ElementValuePair>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementValuePair {
+  pub name_idx: u16,
+  pub value: ElementValue
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElementValue {
+  /// Constant of the pool, whose tag gives the type: `B`, `C`, `D`, `F`, `I`, `J`, `S`, `Z` or `s`
+  Constant { tag: u8, value_idx: u16 },
+  Enum { type_name_idx: u16, const_name_idx: u16 },
+  /// Index of the return descriptor of the class
+  Class(u16),
+  Annotation(Annotation),
+  Array(Vec<ElementValue>)
+}
+
+/// Annotation on a type used in a class, with the location of the type
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAnnotation {
+  pub target_type: u8,
+  pub target: TypeTarget,
+  pub path: Vec<TypePathEntry>,
+  pub annotation: Annotation
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeTarget {
+  TypeParameter(u8),
+  /// Index of the interface in the class definition, 65535 for the super class
+  SuperType(u16),
+  TypeParameterBound { type_parameter_idx: u8, bound_idx: u8 },
+  /// Type of a field, of the return of a method or of the receiver
+  Empty,
+  FormalParameter(u8),
+  /// Index of the exception in the Exceptions attribute
+  Throws(u16),
+  LocalVariable(Vec<LocalVariableTarget>),
+  /// Index of the handler in the exception table
+  Catch(u16),
+  /// Offset of the instruction using the type
+  Offset(u16),
+  TypeArgument { offset: u16, type_argument_idx: u8 }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalVariableTarget {
+  pub start_pc: u16,
+  pub length: u16,
+  pub index: u16
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypePathEntry {
+  pub kind: u8,
+  pub type_argument_idx: u8
+}
+
+pub fn read_annotation(reader: &mut dyn Reader) -> io::Result<Annotation> {
+  let type_idx = read_u16(reader)?;
+  let elements = read_list(reader, |reader| Ok(ElementValuePair {
+    name_idx: read_u16(reader)?,
+    value: read_element_value(reader)?
+  }))?;
+  Ok(Annotation { type_idx: type_idx, elements: elements })
+}
+
+pub fn read_element_value(reader: &mut dyn Reader) -> io::Result<ElementValue> {
+  let tag = read_u8(reader)?;
+  let value = match tag {
+    b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => ElementValue::Constant {
+      tag: tag,
+      value_idx: read_u16(reader)?
+    },
+    b'e' => ElementValue::Enum {
+      type_name_idx: read_u16(reader)?,
+      const_name_idx: read_u16(reader)?
+    },
+    b'c' => ElementValue::Class(read_u16(reader)?),
+    b'@' => ElementValue::Annotation(read_annotation(reader)?),
+    b'[' => ElementValue::Array(read_list(reader, read_element_value)?),
+    _ => return Err(invalid_data(format!("Unknown element value tag {}", tag)))
+  };
+  Ok(value)
+}
+
+fn read_type_target(reader: &mut dyn Reader, target_type: u8) -> io::Result<TypeTarget> {
+  let target = match target_type {
+    0x00 | 0x01 => TypeTarget::TypeParameter(read_u8(reader)?),
+    0x10 => TypeTarget::SuperType(read_u16(reader)?),
+    0x11 | 0x12 => TypeTarget::TypeParameterBound {
+      type_parameter_idx: read_u8(reader)?,
+      bound_idx: read_u8(reader)?
+    },
+    0x13 ..= 0x15 => TypeTarget::Empty,
+    0x16 => TypeTarget::FormalParameter(read_u8(reader)?),
+    0x17 => TypeTarget::Throws(read_u16(reader)?),
+    0x40 | 0x41 => TypeTarget::LocalVariable(read_list(reader, |reader| Ok(LocalVariableTarget {
+      start_pc: read_u16(reader)?,
+      length: read_u16(reader)?,
+      index: read_u16(reader)?
+    }))?),
+    0x42 => TypeTarget::Catch(read_u16(reader)?),
+    0x43 ..= 0x46 => TypeTarget::Offset(read_u16(reader)?),
+    0x47 ..= 0x4B => TypeTarget::TypeArgument {
+      offset: read_u16(reader)?,
+      type_argument_idx: read_u8(reader)?
+    },
+    _ => return Err(invalid_data(format!("Unknown type annotation target {}", target_type)))
+  };
+  Ok(target)
+}
+
+fn read_type_path(reader: &mut dyn Reader) -> io::Result<Vec<TypePathEntry>> {
+  let length = read_u8(reader)?;
+  let mut path = Vec::with_capacity(length as usize);
+  for _i in 0..length {
+    path.push(TypePathEntry {
+      kind: read_u8(reader)?,
+      type_argument_idx: read_u8(reader)?
+    });
+  }
+  Ok(path)
+}
+
+pub fn read_type_annotation(reader: &mut dyn Reader) -> io::Result<TypeAnnotation> {
+  let target_type = read_u8(reader)?;
+  let target = read_type_target(reader, target_type)?;
+  let path = read_type_path(reader)?;
+  let annotation = read_annotation(reader)?;
+  Ok(TypeAnnotation {
+    target_type: target_type,
+    target: target,
+    path: path,
+    annotation: annotation
+  })
+}
+
+/// Reads the annotations of each parameter of a method, preceded by the number of parameters
+pub fn read_parameter_annotations(reader: &mut dyn Reader) -> io::Result<Vec<Vec<Annotation>>> {
+  let count = read_u8(reader)?;
+  let mut parameters = Vec::with_capacity(count as usize);
+  for _i in 0..count {
+    parameters.push(read_list(reader, read_annotation)?);
+  }
+  Ok(parameters)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use reader::ByteReader;
+
+  #[test]
+  fn test_read_annotation() {
+    let bytes = [
+      0, 3, 0, 2,
+      0, 4, b'I', 0, 5,
+      0, 6, b'[', 0, 2, b'e', 0, 7, 0, 8, b'c', 0, 9
+    ];
+    let annotation = read_annotation(&mut ByteReader::new(&bytes)).unwrap();
+    assert_eq!(annotation, Annotation {
+      type_idx: 3,
+      elements: vec![
+        ElementValuePair { name_idx: 4, value: ElementValue::Constant { tag: b'I', value_idx: 5 } },
+        ElementValuePair {
+          name_idx: 6,
+          value: ElementValue::Array(vec![
+            ElementValue::Enum { type_name_idx: 7, const_name_idx: 8 },
+            ElementValue::Class(9)
+          ])
+        }
+      ]
+    });
+  }
+
+  #[test]
+  fn test_read_type_annotation() {
+    let bytes = [0x40, 0, 1, 0, 2, 0, 5, 0, 1, 1, 3, 1, 0, 4, 0, 0];
+    let annotation = read_type_annotation(&mut ByteReader::new(&bytes)).unwrap();
+    assert_eq!(annotation, TypeAnnotation {
+      target_type: 0x40,
+      target: TypeTarget::LocalVariable(vec![LocalVariableTarget { start_pc: 2, length: 5, index: 1 }]),
+      path: vec![TypePathEntry { kind: 3, type_argument_idx: 1 }],
+      annotation: Annotation { type_idx: 4, elements: vec![] }
+    });
+  }
+}
@@ -1,149 +1,209 @@
-use inheritance::read_class_access;
-use pool::{PoolList, resolve_utf8_value, resolve_class_name};
-use printer::print_bytes;
-use reader::{Reader, ByteReader, ReadResult, to_u16, to_u32};
+use std::io;
+
+use annotations::{read_annotation, read_element_value, read_parameter_annotations, read_type_annotation};
+use class_file::{
+  Attribute, BootstrapMethod, Code, ExceptionHandler, InnerClass, LineNumber, LocalVariable, MethodParameter,
+  RecordComponent};
+use pool::{PoolList, resolve_utf8_value};
+use reader::{Reader, ByteReader, invalid_data, read_list, read_u8, read_u16, read_u32};
+use module;
 use operations;
+use stack_map;
 
-pub fn read(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-	read_u16!(count, reader, indent);
-	println!("Attribute count = {}", count);
-
-	for _i in 0..count {
-		read_attribute(reader, pool, indent)?;
-	}
-	Ok(())
+pub fn read(reader: &mut dyn Reader, pool: &PoolList) -> io::Result<Vec<Attribute>> {
+  read_list(reader, |reader| read_attribute(reader, pool))
 }
 
-pub fn read_attribute(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-  read_u16!(attribute_idx, reader, indent);
+pub fn read_attribute(reader: &mut dyn Reader, pool: &PoolList) -> io::Result<Attribute> {
+  let attribute_idx = read_u16(reader)?;
   let attribute_name = resolve_utf8_value(pool, attribute_idx as usize)
-    .expect(&format!(
+    .ok_or_else(|| invalid_data(format!(
       "No attribute name in constant pool at {}",
-      attribute_idx));
-  println!("Attribute '{}'", attribute_name);
-
-  read_u32!(length, reader, indent + 1);
-  println!("Attribute length = {}", length);
+      attribute_idx)))?;
 
+  let length = read_u32(reader)?;
   let mut bytes = vec![0; length as usize];
   reader.read(&mut bytes[..])?;
 
   let mut attribute_reader = ByteReader::new(&bytes);
-  match attribute_name {
-    "Code" => read_code(&mut attribute_reader, pool, indent + 1),
-    "InnerClasses" => read_inner_classes(&mut attribute_reader, pool, indent + 1),
-    "LineNumberTable" => read_line_number_table(&mut attribute_reader, indent + 1),
-    "SourceFile" => read_source_file(&mut attribute_reader, pool, indent + 1),
-    "LocalVariableTable" => read_local_var_table(&mut attribute_reader, pool, indent + 1),
-    "LocalVariableTypeTable" => read_local_var_type_table(&mut attribute_reader, pool, indent + 1),
-    "RuntimeVisibleAnnotations" => read_annotations(&mut attribute_reader, pool, indent + 1),
-    _ => panic!("Unsupported attribute '{}'", attribute_name)
+  let attribute = {
+    let reader = &mut attribute_reader;
+    match attribute_name {
+      "ConstantValue" => Attribute::ConstantValue(read_u16(reader)?),
+      "Code" => Attribute::Code(read_code(reader, pool)?),
+      "StackMapTable" => Attribute::StackMapTable(stack_map::read(reader)?),
+      "BootstrapMethods" => Attribute::BootstrapMethods(read_list(reader, read_bootstrap_method)?),
+      "NestHost" => Attribute::NestHost(read_u16(reader)?),
+      "NestMembers" => Attribute::NestMembers(read_list(reader, read_u16)?),
+      "PermittedSubclasses" => Attribute::PermittedSubclasses(read_list(reader, read_u16)?),
+      "Exceptions" => Attribute::Exceptions(read_list(reader, read_u16)?),
+      "InnerClasses" => Attribute::InnerClasses(read_list(reader, read_inner_class)?),
+      "EnclosingMethod" => Attribute::EnclosingMethod {
+        class_idx: read_u16(reader)?,
+        method_idx: read_u16(reader)?
+      },
+      "Synthetic" => Attribute::Synthetic,
+      "Deprecated" => Attribute::Deprecated,
+      "Signature" => Attribute::Signature(read_u16(reader)?),
+      "Record" => Attribute::Record(read_list(reader, |reader| read_record_component(reader, pool))?),
+      "SourceFile" => Attribute::SourceFile(read_u16(reader)?),
+      "SourceDebugExtension" => {
+        let length = bytes.len();
+        Attribute::SourceDebugExtension(reader.get_slice(length)?.to_vec())
+      },
+      "LineNumberTable" => Attribute::LineNumberTable(read_list(reader, read_line_number)?),
+      "LocalVariableTable" => Attribute::LocalVariableTable(read_list(reader, read_local_variable)?),
+      "LocalVariableTypeTable" => Attribute::LocalVariableTypeTable(read_list(reader, read_local_variable)?),
+      "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => Attribute::Annotations {
+        visible: attribute_name == "RuntimeVisibleAnnotations",
+        annotations: read_list(reader, read_annotation)?
+      },
+      "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => Attribute::ParameterAnnotations {
+        visible: attribute_name == "RuntimeVisibleParameterAnnotations",
+        parameters: read_parameter_annotations(reader)?
+      },
+      "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => Attribute::TypeAnnotations {
+        visible: attribute_name == "RuntimeVisibleTypeAnnotations",
+        annotations: read_list(reader, read_type_annotation)?
+      },
+      "AnnotationDefault" => Attribute::AnnotationDefault(read_element_value(reader)?),
+      "MethodParameters" => Attribute::MethodParameters(read_method_parameters(reader)?),
+      "Module" => Attribute::Module(module::read(reader)?),
+      "ModulePackages" => Attribute::ModulePackages(read_list(reader, read_u16)?),
+      "ModuleMainClass" => Attribute::ModuleMainClass(read_u16(reader)?),
+      // Attributes from other tools are allowed, and must be ignored by the JVM
+      _ => return Ok(Attribute::Unknown { name: String::from(attribute_name), data: bytes })
+    }
+  };
+  if !attribute_reader.is_empty() {
+    return Err(invalid_data(format!(
+      "Attribute '{}' is longer than its content",
+      attribute_name)));
   }
+  Ok(attribute)
 }
 
-fn read_code(reader: &mut ByteReader, pool: &PoolList, indent: u8) -> ReadResult {
-  read_u16!(max_stack, reader, indent);
-  println!("Max stack = {}", max_stack);
+fn read_code(reader: &mut ByteReader, pool: &PoolList) -> io::Result<Code> {
+  let max_stack = read_u16(reader)?;
+  let max_locals = read_u16(reader)?;
+
+  let code_length = read_u32(reader)?;
+  let code = reader.get_slice(code_length as usize)?;
+  let instructions = operations::read(&mut ByteReader::new(code))?;
+
+  let exception_table = read_list(reader, read_exception_handler)?;
+  let attributes = read(reader, pool)?;
+
+  Ok(Code {
+    max_stack: max_stack,
+    max_locals: max_locals,
+    code: code.to_vec(),
+    instructions: instructions,
+    exception_table: exception_table,
+    attributes: attributes
+  })
+}
 
-  read_u16!(max_locals, reader, indent);
-  println!("Max local vars = {}", max_locals);
+fn read_exception_handler(reader: &mut dyn Reader) -> io::Result<ExceptionHandler> {
+  Ok(ExceptionHandler {
+    start_pc: read_u16(reader)?,
+    end_pc: read_u16(reader)?,
+    handler_pc: read_u16(reader)?,
+    catch_type: read_u16(reader)?
+  })
+}
 
-  read_u32!(code_length, reader, indent);
-  println!("Code length = {}", code_length);
+fn read_inner_class(reader: &mut dyn Reader) -> io::Result<InnerClass> {
+  Ok(InnerClass {
+    inner_class_idx: read_u16(reader)?,
+    outer_class_idx: read_u16(reader)?,
+    name_idx: read_u16(reader)?,
+    access_flags: read_u16(reader)?
+  })
+}
 
-  {
-    let code_bytes = reader.get_slice(code_length as usize)?;
-    let mut code_reader = ByteReader::new(&code_bytes);
-    operations::read(&mut code_reader, pool, indent + 1)?;
-  }
+fn read_line_number(reader: &mut dyn Reader) -> io::Result<LineNumber> {
+  Ok(LineNumber {
+    start_pc: read_u16(reader)?,
+    line_number: read_u16(reader)?
+  })
+}
 
-  read_u16!(exception_length, reader, indent);
-  println!("Exception table length = {}", exception_length);
-  if exception_length > 0 {
-    panic!("No support for exception table");
-  }
+fn read_local_variable(reader: &mut dyn Reader) -> io::Result<LocalVariable> {
+  Ok(LocalVariable {
+    start_pc: read_u16(reader)?,
+    length: read_u16(reader)?,
+    name_idx: read_u16(reader)?,
+    descriptor_idx: read_u16(reader)?,
+    index: read_u16(reader)?
+  })
+}
 
-  read(reader, pool, indent)
+fn read_bootstrap_method(reader: &mut dyn Reader) -> io::Result<BootstrapMethod> {
+  Ok(BootstrapMethod {
+    method_ref_idx: read_u16(reader)?,
+    arguments: read_list(reader, read_u16)?
+  })
 }
 
-fn read_inner_classes(reader: &mut ByteReader, pool: &PoolList, indent: u8) -> ReadResult {
-  read_u16!(count, reader, indent);
-  println!("Number of inners = {}", count);
+fn read_record_component(reader: &mut dyn Reader, pool: &PoolList) -> io::Result<RecordComponent> {
+  Ok(RecordComponent {
+    name_idx: read_u16(reader)?,
+    descriptor_idx: read_u16(reader)?,
+    attributes: read(reader, pool)?
+  })
+}
 
+fn read_method_parameters(reader: &mut dyn Reader) -> io::Result<Vec<MethodParameter>> {
+  let count = read_u8(reader)?;
+  let mut parameters = Vec::with_capacity(count as usize);
   for _i in 0..count {
-    read_u16!(inner_class_idx, reader, indent + 1);
-    let inner_class_name = resolve_class_name(pool, inner_class_idx as usize)
-      .expect(&format!(
-        "No class in the pool at {}", inner_class_idx));
-    println!("Inner class {} -> {}", inner_class_idx, inner_class_name);
-
-    read_u16!(outer_class_idx, reader, indent + 1);
-    let outer_class_name = if outer_class_idx == 0 {
-      "<anonymous>"
-    } else {
-      resolve_class_name(pool, outer_class_idx as usize)
-        .expect(&format!(
-          "No class in the pool at {}", outer_class_idx))
-    };
-    println!("Outer class {} -> {}", outer_class_idx, outer_class_name);
-
-    read_u16!(user_idx, reader, indent + 1);
-    let user_name = if user_idx == 0 {
-      "<anonymous>"
-    } else {
-      resolve_utf8_value(pool, user_idx as usize)
-        .expect(&format!("No string value in the pool at {}", user_idx))
-    };
-    println!("Defined class name {} -> {}", user_idx, user_name);
-
-    read_class_access(reader, indent + 1)?;
+    parameters.push(MethodParameter {
+      name_idx: read_u16(reader)?,
+      access_flags: read_u16(reader)?
+    });
   }
-
-  Ok(())
+  Ok(parameters)
 }
 
-fn read_line_number_table(reader: &mut ByteReader, indent: u8) -> ReadResult {
-  read_u16!(table_length, reader, indent);
-  println!("Table length = {}", table_length);
-
-  for _i in 0..table_length {
-    read_u16!(start_pc, reader, indent + 1);
-    println!("Start in the code array = {}", start_pc);
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pool::PoolElement;
 
-    read_u16!(line_number, reader, indent + 1);
-    println!("Code line = {}", line_number);
+  fn create_pool(names: &[&str]) -> PoolList {
+    let mut pool = vec![None];
+    pool.extend(names.iter().map(|name| Some(PoolElement::Utf8Value(String::from(*name)))));
+    pool
   }
 
-  Ok(())
-}
-
-fn read_source_file(reader: &mut ByteReader, pool: &PoolList, indent: u8) -> ReadResult {
-  read_u16!(file_idx, reader, indent);
-  let file_name = resolve_utf8_value(pool, file_idx as usize)
-    .expect(&format!(
-      "No file name string at index {}", file_idx));
-  println!("Source file: {}", file_name);
-
-  Ok(())
-}
-
-fn read_local_var_table(_reader: &mut ByteReader, _pool: &PoolList, _indent: u8) -> ReadResult {
-  // TODO complete the decoding
-  println!("Local var table. Ignored ...");
-
-  Ok(())
-}
-
-fn read_local_var_type_table(_reader: &mut ByteReader, _pool: &PoolList, _indent: u8) -> ReadResult {
-  // TODO complete the decoding
-  println!("Local var type table. Ignored ...");
-
-  Ok(())
-}
+  #[test]
+  fn test_read_local_variables() {
+    let pool = create_pool(&["LocalVariableTable"]);
+    let bytes = [0, 1, 0, 0, 0, 12, 0, 1, 0, 0, 0, 5, 0, 2, 0, 3, 0, 1];
+    let attribute = read_attribute(&mut ByteReader::new(&bytes), &pool).unwrap();
+    assert_eq!(attribute, Attribute::LocalVariableTable(vec![LocalVariable {
+      start_pc: 0,
+      length: 5,
+      name_idx: 2,
+      descriptor_idx: 3,
+      index: 1
+    }]));
+  }
 
-fn read_annotations(_reader: &mut ByteReader, _pool: &PoolList, _indent: u8) -> ReadResult {
-  // TODO complete the decoding
-  println!("Runtime annotation table ignored. Ignored ...");
+  #[test]
+  fn test_keep_unknown_attributes() {
+    let pool = create_pool(&["Custom"]);
+    let bytes = [0, 1, 0, 0, 0, 2, 0xca, 0xfe];
+    let attribute = read_attribute(&mut ByteReader::new(&bytes), &pool).unwrap();
+    assert_eq!(attribute, Attribute::Unknown { name: String::from("Custom"), data: vec![0xca, 0xfe] });
+    assert_eq!(attribute.name(), "Custom");
+  }
 
-  Ok(())
+  #[test]
+  fn test_reject_inconsistent_lengths() {
+    let pool = create_pool(&["Signature"]);
+    let bytes = [0, 1, 0, 0, 0, 3, 0, 2, 0];
+    assert!(read_attribute(&mut ByteReader::new(&bytes), &pool).is_err());
+  }
 }
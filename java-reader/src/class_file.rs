@@ -0,0 +1,215 @@
+//! Model of a class file, as read from its bytes.
+//!
+//! Elements refer to the constant pool through their indexes, as in the file.
+//! The `resolve_*` functions of the `pool` module give the values behind these indexes.
+
+use annotations::{Annotation, ElementValue, TypeAnnotation};
+use module::Module;
+use operations::Instruction;
+use stack_map::StackMapFrame;
+use pool::{PoolList, resolve_class_name, resolve_utf8_value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassFile {
+  pub minor_version: u16,
+  pub major_version: u16,
+  pub pool: PoolList,
+  pub access_flags: u16,
+  pub this_class: u16,
+  /// Index of the super class, 0 for `java/lang/Object`
+  pub super_class: u16,
+  pub interfaces: Vec<u16>,
+  pub fields: Vec<Member>,
+  pub methods: Vec<Member>,
+  pub attributes: Vec<Attribute>
+}
+
+impl ClassFile {
+  pub fn class_name(&self) -> Option<&str> {
+    resolve_class_name(&self.pool, self.this_class as usize)
+  }
+
+  pub fn super_class_name(&self) -> Option<&str> {
+    resolve_class_name(&self.pool, self.super_class as usize)
+  }
+
+  /// Finds the first method with a given name
+  pub fn find_method(&self, name: &str) -> Option<&Member> {
+    self.methods.iter().find(|method| method.name(&self.pool) == Some(name))
+  }
+}
+
+/// Field or method of a class
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+  pub access_flags: u16,
+  pub name_idx: u16,
+  pub descriptor_idx: u16,
+  pub attributes: Vec<Attribute>
+}
+
+impl Member {
+  pub fn name<'a>(&self, pool: &'a PoolList) -> Option<&'a str> {
+    resolve_utf8_value(pool, self.name_idx as usize)
+  }
+
+  pub fn descriptor<'a>(&self, pool: &'a PoolList) -> Option<&'a str> {
+    resolve_utf8_value(pool, self.descriptor_idx as usize)
+  }
+
+  /// Gets the code of a method, if not abstract or native
+  pub fn code(&self) -> Option<&Code> {
+    self.attributes.iter()
+      .filter_map(|attribute| match *attribute {
+        Attribute::Code(ref code) => Some(code),
+        _ => None
+      })
+      .next()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+  /// Index of the value of a constant field
+  ConstantValue(u16),
+  Code(Code),
+  StackMapTable(Vec<StackMapFrame>),
+  BootstrapMethods(Vec<BootstrapMethod>),
+  /// Index of the class hosting the nest of the class
+  NestHost(u16),
+  NestMembers(Vec<u16>),
+  PermittedSubclasses(Vec<u16>),
+  /// Indexes of the classes of the exceptions thrown by a method
+  Exceptions(Vec<u16>),
+  InnerClasses(Vec<InnerClass>),
+  /// Class and method, 0 if none, enclosing a local or anonymous class
+  EnclosingMethod { class_idx: u16, method_idx: u16 },
+  Synthetic,
+  Deprecated,
+  /// Index of the generic signature of a class, a method or a field
+  Signature(u16),
+  Record(Vec<RecordComponent>),
+  /// Index of the name of the source file
+  SourceFile(u16),
+  SourceDebugExtension(Vec<u8>),
+  LineNumberTable(Vec<LineNumber>),
+  LocalVariableTable(Vec<LocalVariable>),
+  LocalVariableTypeTable(Vec<LocalVariable>),
+  Annotations { visible: bool, annotations: Vec<Annotation> },
+  ParameterAnnotations { visible: bool, parameters: Vec<Vec<Annotation>> },
+  TypeAnnotations { visible: bool, annotations: Vec<TypeAnnotation> },
+  AnnotationDefault(ElementValue),
+  MethodParameters(Vec<MethodParameter>),
+  Module(Module),
+  /// Indexes of all the packages of a module
+  ModulePackages(Vec<u16>),
+  ModuleMainClass(u16),
+  /// Attribute not defined by the JVM specification, kept as bytes
+  Unknown { name: String, data: Vec<u8> }
+}
+
+impl Attribute {
+  /// Gets the name of the attribute, as written in the constant pool
+  pub fn name(&self) -> &str {
+    match *self {
+      Attribute::ConstantValue(_) => "ConstantValue",
+      Attribute::Code(_) => "Code",
+      Attribute::StackMapTable(_) => "StackMapTable",
+      Attribute::BootstrapMethods(_) => "BootstrapMethods",
+      Attribute::NestHost(_) => "NestHost",
+      Attribute::NestMembers(_) => "NestMembers",
+      Attribute::PermittedSubclasses(_) => "PermittedSubclasses",
+      Attribute::Exceptions(_) => "Exceptions",
+      Attribute::InnerClasses(_) => "InnerClasses",
+      Attribute::EnclosingMethod { .. } => "EnclosingMethod",
+      Attribute::Synthetic => "Synthetic",
+      Attribute::Deprecated => "Deprecated",
+      Attribute::Signature(_) => "Signature",
+      Attribute::Record(_) => "Record",
+      Attribute::SourceFile(_) => "SourceFile",
+      Attribute::SourceDebugExtension(_) => "SourceDebugExtension",
+      Attribute::LineNumberTable(_) => "LineNumberTable",
+      Attribute::LocalVariableTable(_) => "LocalVariableTable",
+      Attribute::LocalVariableTypeTable(_) => "LocalVariableTypeTable",
+      Attribute::Annotations { visible: true, .. } => "RuntimeVisibleAnnotations",
+      Attribute::Annotations { visible: false, .. } => "RuntimeInvisibleAnnotations",
+      Attribute::ParameterAnnotations { visible: true, .. } => "RuntimeVisibleParameterAnnotations",
+      Attribute::ParameterAnnotations { visible: false, .. } => "RuntimeInvisibleParameterAnnotations",
+      Attribute::TypeAnnotations { visible: true, .. } => "RuntimeVisibleTypeAnnotations",
+      Attribute::TypeAnnotations { visible: false, .. } => "RuntimeInvisibleTypeAnnotations",
+      Attribute::AnnotationDefault(_) => "AnnotationDefault",
+      Attribute::MethodParameters(_) => "MethodParameters",
+      Attribute::Module(_) => "Module",
+      Attribute::ModulePackages(_) => "ModulePackages",
+      Attribute::ModuleMainClass(_) => "ModuleMainClass",
+      Attribute::Unknown { ref name, .. } => name
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Code {
+  pub max_stack: u16,
+  pub max_locals: u16,
+  /// Bytes of the code, as written in the class
+  pub code: Vec<u8>,
+  pub instructions: Vec<Instruction>,
+  pub exception_table: Vec<ExceptionHandler>,
+  pub attributes: Vec<Attribute>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExceptionHandler {
+  pub start_pc: u16,
+  pub end_pc: u16,
+  pub handler_pc: u16,
+  /// Index of the class of the caught exceptions, 0 to catch all of them
+  pub catch_type: u16
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InnerClass {
+  pub inner_class_idx: u16,
+  /// Index of the outer class, 0 for anonymous or local classes
+  pub outer_class_idx: u16,
+  /// Index of the simple name of the class, 0 for anonymous classes
+  pub name_idx: u16,
+  pub access_flags: u16
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineNumber {
+  pub start_pc: u16,
+  pub line_number: u16
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalVariable {
+  pub start_pc: u16,
+  pub length: u16,
+  pub name_idx: u16,
+  /// Index of the descriptor of the variable, or of its signature in a LocalVariableTypeTable
+  pub descriptor_idx: u16,
+  pub index: u16
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BootstrapMethod {
+  /// Index of the method handle of the bootstrap method
+  pub method_ref_idx: u16,
+  pub arguments: Vec<u16>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordComponent {
+  pub name_idx: u16,
+  pub descriptor_idx: u16,
+  pub attributes: Vec<Attribute>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodParameter {
+  /// Index of the name of the parameter, 0 for unnamed parameters
+  pub name_idx: u16,
+  pub access_flags: u16
+}
@@ -1,68 +1,49 @@
 use std::io;
 
-use flags::to_class_access;
-use pool::{PoolList, resolve_utf8_value};
-use printer::print_bytes;
-use reader::{Reader, ReadResult, to_u16};
-
-pub fn read_class_access(reader: &mut Reader, indent: u8) -> ReadResult {
-	read_u16!(flag_value, reader, indent);
-	let flags = to_class_access(flag_value);
-
-	print!("Flags:");
-	for flag in &flags {
-		print!(" {}", flag);
-	}
-	println!("");
-
-	Ok(())
+use pool::{PoolList, resolve_class_name};
+use reader::{Reader, invalid_data, read_list, read_u16};
+
+/// Description of a class and of its parents
+pub struct Inheritance {
+	pub access_flags: u16,
+	pub this_class: u16,
+	pub super_class: u16,
+	pub interfaces: Vec<u16>
 }
 
-fn read_class_name<'a>(reader: &'a mut Reader, pool: &'a PoolList, indent: u8) -> io::Result<Option<&'a str>> {
-	read_u16!(index, reader, indent);
-	Ok(resolve_utf8_value(pool, index as usize))
-}
-
-fn read_class(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-	let class_name = read_class_name(reader, pool, indent)?;
-	println!(
-		"Class '{}'",
-		class_name.expect("Class name is not present in the constant pool"));
-
-	Ok(())
-}
-
-fn read_super_class(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-	let class_name = read_class_name(reader, pool, indent)?;
-	println!(
-		"Super class '{}'",
-		class_name.expect("Super name is not present in the constant pool"));
-
-	Ok(())
-}
-
-fn read_interfaces(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-	let interface_count: u16;
-	{
-		let bytes = reader.read_2u()?;
-		interface_count = to_u16(bytes);
-		print_bytes(indent, bytes);
-		println!("Interface count: {}", interface_count);
+fn read_class_index(reader: &mut dyn Reader, pool: &PoolList, description: &str) -> io::Result<u16> {
+	let index = read_u16(reader)?;
+	match resolve_class_name(pool, index as usize) {
+		Some(_) => Ok(index),
+		None => Err(invalid_data(format!(
+			"{} is not present in the constant pool at {}",
+			description,
+			index)))
 	}
+}
 
-	for _i in 0..interface_count {
-		let class_name = read_class_name(reader, pool, indent + 1)?;
-		println!(
-			"Interface '{}'",
-			class_name.expect("Interface name is not present in the constant pool"));
+fn read_super_class(reader: &mut dyn Reader, pool: &PoolList) -> io::Result<u16> {
+	let index = read_u16(reader)?;
+	// Only java/lang/Object has no super class
+	if index == 0 || resolve_class_name(pool, index as usize).is_some() {
+		Ok(index)
+	} else {
+		Err(invalid_data(format!(
+			"Super name is not present in the constant pool at {}",
+			index)))
 	}
-
-	Ok(())
 }
 
-pub fn read(reader: &mut Reader, pool: &PoolList) -> ReadResult {
-	read_class_access(reader, 0)?;
-	read_class(reader, pool, 0)?;
-	read_super_class(reader, pool, 1)?;
-	read_interfaces(reader, pool, 1)
-}
\ No newline at end of file
+pub fn read(reader: &mut dyn Reader, pool: &PoolList) -> io::Result<Inheritance> {
+	let access_flags = read_u16(reader)?;
+	let this_class = read_class_index(reader, pool, "Class name")?;
+	let super_class = read_super_class(reader, pool)?;
+	let interfaces = read_list(reader, |reader| read_class_index(reader, pool, "Interface name"))?;
+
+	Ok(Inheritance {
+		access_flags: access_flags,
+		this_class: this_class,
+		super_class: super_class,
+		interfaces: interfaces
+	})
+}
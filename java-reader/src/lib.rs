@@ -0,0 +1,144 @@
+//! Library reading Java class files into a typed model.
+
+#[macro_use]
+extern crate lazy_static;
+
+pub mod annotations;
+pub mod class_file;
+pub mod flags;
+pub mod module;
+pub mod operations;
+pub mod pool;
+pub mod printer;
+pub mod reader;
+pub mod stack_map;
+pub mod types;
+mod attributes;
+mod fields;
+mod inheritance;
+mod methods;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use class_file::ClassFile;
+use reader::{ByteReader, invalid_data, read_u16, read_u32};
+
+const MAGIC_NUMBER: u32 = 0xCAFE_BABE;
+
+/// Parses the bytes of a whole class file
+pub fn parse_class(bytes: &[u8]) -> io::Result<ClassFile> {
+	let mut reader = ByteReader::new(bytes);
+
+	let magic_number = read_u32(&mut reader)?;
+	if magic_number != MAGIC_NUMBER {
+		return Err(invalid_data(format!("Invalid magic number {:X}", magic_number)));
+	}
+	let minor_version = read_u16(&mut reader)?;
+	let major_version = read_u16(&mut reader)?;
+
+	let pool = pool::read_class_pool(&mut reader)?;
+	let inheritance = inheritance::read(&mut reader, &pool)?;
+	let fields = fields::read(&mut reader, &pool)?;
+	let methods = methods::read(&mut reader, &pool)?;
+	let attributes = attributes::read(&mut reader, &pool)?;
+	if !reader.is_empty() {
+		return Err(invalid_data(format!(
+			"Unexpected content after the class, at {}",
+			reader.get_pos())));
+	}
+
+	Ok(ClassFile {
+		minor_version: minor_version,
+		major_version: major_version,
+		pool: pool,
+		access_flags: inheritance.access_flags,
+		this_class: inheritance.this_class,
+		super_class: inheritance.super_class,
+		interfaces: inheritance.interfaces,
+		fields: fields,
+		methods: methods,
+		attributes: attributes
+	})
+}
+
+/// Reads and parses a class file
+pub fn read_class_file<P: AsRef<Path>>(path: P) -> io::Result<ClassFile> {
+	let bytes = fs::read(path)?;
+	parse_class(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use class_file::Attribute;
+	use operations::Operands;
+	use pool::PoolElement;
+
+	fn utf8(bytes: &mut Vec<u8>, value: &str) {
+		bytes.push(1);
+		bytes.extend_from_slice(&[0, value.len() as u8]);
+		bytes.extend_from_slice(value.as_bytes());
+	}
+
+	/// Creates a class `a/B`, extending `java/lang/Object`, with a static method `run`
+	fn create_class() -> Vec<u8> {
+		let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52];
+		bytes.extend_from_slice(&[0, 10]);
+		utf8(&mut bytes, "a/B");
+		bytes.extend_from_slice(&[7, 0, 1]);
+		utf8(&mut bytes, "java/lang/Object");
+		bytes.extend_from_slice(&[7, 0, 3]);
+		utf8(&mut bytes, "run");
+		utf8(&mut bytes, "()V");
+		utf8(&mut bytes, "Code");
+		utf8(&mut bytes, "SourceFile");
+		utf8(&mut bytes, "B.java");
+		// Public super class a/B
+		bytes.extend_from_slice(&[0, 0x21, 0, 2, 0, 4, 0, 0]);
+		// No fields
+		bytes.extend_from_slice(&[0, 0]);
+		// Method run
+		bytes.extend_from_slice(&[0, 1, 0, 9, 0, 5, 0, 6, 0, 1]);
+		bytes.extend_from_slice(&[0, 7, 0, 0, 0, 16, 0, 1, 0, 0, 0, 0, 0, 4, 16, 42, 87, 177, 0, 0, 0, 0]);
+		// Source file
+		bytes.extend_from_slice(&[0, 1, 0, 8, 0, 0, 0, 2, 0, 9]);
+		bytes
+	}
+
+	#[test]
+	fn test_parse_class() {
+		let class = parse_class(&create_class()).unwrap();
+		assert_eq!(class.major_version, 52);
+		assert_eq!(class.pool[1], Some(PoolElement::Utf8Value(String::from("a/B"))));
+		assert_eq!(class.class_name(), Some("a/B"));
+		assert_eq!(class.super_class_name(), Some("java/lang/Object"));
+		assert!(class.interfaces.is_empty());
+		assert!(class.fields.is_empty());
+		assert_eq!(class.attributes, vec![Attribute::SourceFile(9)]);
+
+		let method = class.find_method("run").unwrap();
+		assert_eq!(method.descriptor(&class.pool), Some("()V"));
+		let code = method.code().unwrap();
+		assert_eq!(code.max_stack, 1);
+		assert_eq!(code.code, vec![16, 42, 87, 177]);
+		let names: Vec<&str> = code.instructions.iter().map(|i| i.name()).collect();
+		assert_eq!(names, vec!["bipush", "pop", "return"]);
+		assert_eq!(code.instructions[0].operands, Operands::Value(42));
+	}
+
+	#[test]
+	fn test_reject_invalid_classes() {
+		let mut bytes = create_class();
+		bytes.push(0);
+		assert!(parse_class(&bytes).is_err());
+
+		let bytes = create_class();
+		assert!(parse_class(&bytes[..bytes.len() - 1]).is_err());
+
+		let mut bytes = create_class();
+		bytes[0] = 0;
+		assert!(parse_class(&bytes).is_err());
+	}
+}
@@ -0,0 +1,76 @@
+use std::io;
+
+use reader::{Reader, read_list, read_u16};
+
+/// Content of the Module attribute of a `module-info` class
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+  pub name_idx: u16,
+  pub flags: u16,
+  /// Index of the version of the module, 0 if not set
+  pub version_idx: u16,
+  pub requires: Vec<Requires>,
+  pub exports: Vec<Exports>,
+  pub opens: Vec<Exports>,
+  /// Indexes of the services used by the module
+  pub uses: Vec<u16>,
+  pub provides: Vec<Provides>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requires {
+  pub module_idx: u16,
+  pub flags: u16,
+  pub version_idx: u16
+}
+
+/// Package exported or opened by a module, to all modules if no target is given
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exports {
+  pub package_idx: u16,
+  pub flags: u16,
+  pub to: Vec<u16>
+}
+
+/// Service implemented by a module
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provides {
+  pub class_idx: u16,
+  pub with: Vec<u16>
+}
+
+fn read_requires(reader: &mut dyn Reader) -> io::Result<Requires> {
+  Ok(Requires {
+    module_idx: read_u16(reader)?,
+    flags: read_u16(reader)?,
+    version_idx: read_u16(reader)?
+  })
+}
+
+fn read_exports(reader: &mut dyn Reader) -> io::Result<Exports> {
+  Ok(Exports {
+    package_idx: read_u16(reader)?,
+    flags: read_u16(reader)?,
+    to: read_list(reader, read_u16)?
+  })
+}
+
+fn read_provides(reader: &mut dyn Reader) -> io::Result<Provides> {
+  Ok(Provides {
+    class_idx: read_u16(reader)?,
+    with: read_list(reader, read_u16)?
+  })
+}
+
+pub fn read(reader: &mut dyn Reader) -> io::Result<Module> {
+  Ok(Module {
+    name_idx: read_u16(reader)?,
+    flags: read_u16(reader)?,
+    version_idx: read_u16(reader)?,
+    requires: read_list(reader, read_requires)?,
+    exports: read_list(reader, read_exports)?,
+    opens: read_list(reader, read_exports)?,
+    uses: read_list(reader, read_u16)?,
+    provides: read_list(reader, read_provides)?
+  })
+}
@@ -1,267 +1,302 @@
-use pool::{PoolList, resolve_method_name, resolve_class_name, resolve_field_name};
-use printer::{print_bytes};
-use reader::{Reader, ByteReader, ReadResult, to_u16};
-use types::{ARRAY_TYPES};
-
-fn print_op(name: &str) {
-  println!("[{}]", name);
+use std::io;
+
+use reader::{ByteReader, invalid_data, read_i8, read_i16, read_i32, read_u8, read_u16};
+
+/// Operands of an instruction, following its opcode
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operands {
+  None,
+  /// Index of a local variable
+  Local(u16),
+  /// Integer pushed to the stack
+  Value(i32),
+  /// Index of an entry of the constant pool
+  Constant(u16),
+  /// Code of the type of a primitive array
+  ArrayType(u8),
+  /// Method of an interface, with the number of words of its arguments
+  InterfaceMethod { method_idx: u16, count: u8 },
+  /// Offset of the target of a jump, relative to the instruction
+  Branch(i32),
+  /// Local variable incremented by a constant value
+  Increment { index: u16, value: i16 },
+  /// Jump offsets for the keys from low to high, relative to the instruction
+  TableSwitch { default: i32, low: i32, high: i32, offsets: Vec<i32> },
+  /// Pairs of keys and jump offsets, relative to the instruction
+  LookupSwitch { default: i32, pairs: Vec<(i32, i32)> },
+  /// Class of a multi-dimensional array, with the number of dimensions to create
+  MultiArray { class_idx: u16, dimensions: u8 }
 }
 
-fn are_same_decimal(before: usize, now: usize) -> bool {
-  let b = before / 10;
-  let n = now / 10;
-  b == n
+/// Instruction decoded from the code of a method
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+  /// Offset of the instruction in the code, in bytes
+  pub offset: usize,
+  /// Opcode of the operation, modified by `wide` if the flag is set
+  pub opcode: u8,
+  pub wide: bool,
+  pub operands: Operands
 }
 
-pub fn read(
-    reader: &mut ByteReader,
-    pool: &PoolList,
-    indent: u8) -> ReadResult {
-  let mut last_size: usize = 0;
-  while !reader.is_empty() {
-    read_u8!(operation_code, reader, indent);
-    if !are_same_decimal(last_size, reader.get_pos()) {
-      print!("<{}> ", reader.get_pos());
-    }
-    last_size = reader.get_pos();
-
-    match operation_code {
-      0 => read_nop(),
-      1 => read_aconst_null(),
-      2 ... 8 => read_iconst(operation_code),
-      9 | 10 => read_lconst_n(operation_code),
-      11 ... 13 => read_fconst_n(operation_code),
-      16 => read_bipush(reader, indent)?,
-      17 => read_sipush(reader, indent)?,
-      18 => read_ldc(reader, pool, indent)?,
-      20 => read_ldc2_w(reader, pool, indent)?,
-      25 => read_aload(reader, indent)?,
-      26 ... 29 => read_iload_n(operation_code),
-      42 ... 45 => read_aload_n(operation_code),
-      58 => read_astore(reader, indent)?,
-      75 ... 78 => read_astore_n(operation_code),
-      79 => read_iastore(),
-      80 => read_lastore(),
-      83 => read_aastore(),
-      87 => read_pop(),
-      88 => read_pop2(),
-      89 => read_dup(),
-      176 => read_areturn(),
-      177 => read_return(),
-      178 => read_get_static(reader, pool, indent)?,
-      181 => read_put_field(reader, pool, indent)?,
-      182 => read_invoke_virtual(reader, pool, indent)?,
-      183 => read_invoke_special(reader, pool, indent)?,
-      184 => read_invoke_static(reader, pool, indent)?,
-      185 => read_invoke_interface(reader, pool, indent)?,
-      186 => read_invoke_dynamic(reader, pool, indent)?,
-      187 => read_new(reader, pool, indent)?,
-      188 => read_new_prim_array(reader, indent)?,
-      189 => read_new_object_array(reader, pool, indent)?,
-      _ => panic!("Unsupported operation: {}", operation_code)
-    }
+impl Instruction {
+  pub fn name(&self) -> &'static str {
+    opcode_name(self.opcode).unwrap_or("<unknown>")
   }
-  Ok(())
 }
 
-macro_rules! shortcut_op {
-  ($f: ident, $name: tt, $base: tt, $to: tt) => {
-    fn $f(operation: u8) {
-      let num = operation as i8 - $base;
-      match num {
-        0 ... $to => print_op(&format!("{}_{}", $name, num)),
-        _ => panic!("Invalid {}_n opcode {}", $name, num)
-      }
-    }
-  };
+const WIDE: u8 = 196;
+
+/// Mnemonics of the operations, by opcode
+const OPCODE_NAMES: [&str; 202] = [
+  // 0
+  "nop", "aconst_null", "iconst_m1", "iconst_0", "iconst_1", "iconst_2", "iconst_3", "iconst_4",
+  "iconst_5", "lconst_0", "lconst_1", "fconst_0", "fconst_1", "fconst_2", "dconst_0", "dconst_1",
+  // 16
+  "bipush", "sipush", "ldc", "ldc_w", "ldc2_w", "iload", "lload", "fload",
+  "dload", "aload", "iload_0", "iload_1", "iload_2", "iload_3", "lload_0", "lload_1",
+  // 32
+  "lload_2", "lload_3", "fload_0", "fload_1", "fload_2", "fload_3", "dload_0", "dload_1",
+  "dload_2", "dload_3", "aload_0", "aload_1", "aload_2", "aload_3", "iaload", "laload",
+  // 48
+  "faload", "daload", "aaload", "baload", "caload", "saload", "istore", "lstore",
+  "fstore", "dstore", "astore", "istore_0", "istore_1", "istore_2", "istore_3", "lstore_0",
+  // 64
+  "lstore_1", "lstore_2", "lstore_3", "fstore_0", "fstore_1", "fstore_2", "fstore_3", "dstore_0",
+  "dstore_1", "dstore_2", "dstore_3", "astore_0", "astore_1", "astore_2", "astore_3", "iastore",
+  // 80
+  "lastore", "fastore", "dastore", "aastore", "bastore", "castore", "sastore", "pop",
+  "pop2", "dup", "dup_x1", "dup_x2", "dup2", "dup2_x1", "dup2_x2", "swap",
+  // 96
+  "iadd", "ladd", "fadd", "dadd", "isub", "lsub", "fsub", "dsub",
+  "imul", "lmul", "fmul", "dmul", "idiv", "ldiv", "fdiv", "ddiv",
+  // 112
+  "irem", "lrem", "frem", "drem", "ineg", "lneg", "fneg", "dneg",
+  "ishl", "lshl", "ishr", "lshr", "iushr", "lushr", "iand", "land",
+  // 128
+  "ior", "lor", "ixor", "lxor", "iinc", "i2l", "i2f", "i2d",
+  "l2i", "l2f", "l2d", "f2i", "f2l", "f2d", "d2i", "d2l",
+  // 144
+  "d2f", "i2b", "i2c", "i2s", "lcmp", "fcmpl", "fcmpg", "dcmpl",
+  "dcmpg", "ifeq", "ifne", "iflt", "ifge", "ifgt", "ifle", "if_icmpeq",
+  // 160
+  "if_icmpne", "if_icmplt", "if_icmpge", "if_icmpgt", "if_icmple", "if_acmpeq", "if_acmpne", "goto",
+  "jsr", "ret", "tableswitch", "lookupswitch", "ireturn", "lreturn", "freturn", "dreturn",
+  // 176
+  "areturn", "return", "getstatic", "putstatic", "getfield", "putfield", "invokevirtual", "invokespecial",
+  "invokestatic", "invokeinterface", "invokedynamic", "new", "newarray", "anewarray", "arraylength", "athrow",
+  // 192
+  "checkcast", "instanceof", "monitorenter", "monitorexit", "wide", "multianewarray", "ifnull", "ifnonnull",
+  "goto_w", "jsr_w"
+];
+
+/// Gets the mnemonic of an opcode, if defined
+pub fn opcode_name(opcode: u8) -> Option<&'static str> {
+  OPCODE_NAMES.get(opcode as usize).cloned()
 }
 
-macro_rules! single_op {
-  ($f: ident, $name: tt) => {
-    fn $f() {
-      print_op($name);
-    }
-  }
-}
-
-single_op!(read_aconst_null, "aconst_null");
-
-fn read_aload(reader: &mut Reader, indent: u8) -> ReadResult {
-  print_op("aload");
-  read_u8!(var_idx, reader, indent + 1);
-  println!("load from var#{}", var_idx);
-
-  Ok(())
+/// Tests if an operation reads or writes a local variable, given by its index
+fn is_local_operation(opcode: u8) -> bool {
+  matches!(opcode, 21 ..= 25 | 54 ..= 58 | 169)
 }
-shortcut_op!(read_aload_n, "aload", 42, 3);
-
-fn read_astore(reader: &mut Reader, indent: u8) -> ReadResult {
-  print_op("astore");
-  read_u8!(var_idx, reader, indent + 1);
-  println!("store into var#{}", var_idx);
 
+/// Skips the padding aligning the operands of switches on 4 bytes
+fn skip_padding(reader: &mut ByteReader) -> io::Result<()> {
+  let padding = (4 - reader.get_pos() % 4) % 4;
+  reader.get_slice(padding)?;
   Ok(())
 }
-shortcut_op!(read_astore_n, "astore", 75, 3);
 
-single_op!(read_iastore, "iastore");
-single_op!(read_lastore, "lastore");
-single_op!(read_aastore, "aastore");
-
-fn read_bipush(reader: &mut Reader, indent: u8) -> ReadResult {
-  print_op("bipush");
-  read_u8!(value, reader, indent + 1);
-  let value: i32 = value.into();
-  println!("Push int {} to stack", value);
-  Ok(())
-}
-
-fn read_sipush(reader: &mut Reader, indent: u8) -> ReadResult {
-  print_op("sipush");
-  read_u16!(value, reader, indent + 1);
-  let value: i32 = value.into();
-  println!("Push int {} to stack", value);
-  Ok(())
-}
-
-shortcut_op!(read_fconst_n, "fconst", 11, 2);
-
-fn read_iconst(operation: u8) {
-  let num = operation as i8 - 3;
-  match num {
-    -1 => print_op("iconst_m1"),
-    0 ... 5 => print_op(&format!("iconst_{}", num)),
-    _ => panic!("Invalid integer constant opcode {}", num)
+fn read_table_switch(reader: &mut ByteReader) -> io::Result<Operands> {
+  skip_padding(reader)?;
+  let default = read_i32(reader)?;
+  let low = read_i32(reader)?;
+  let high = read_i32(reader)?;
+  if high < low {
+    return Err(invalid_data(format!("Invalid tableswitch range [{}, {}]", low, high)));
   }
+  let count = (high as i64 - low as i64 + 1) as usize;
+  let mut offsets = Vec::with_capacity(count);
+  for _i in 0..count {
+    offsets.push(read_i32(reader)?);
+  }
+  Ok(Operands::TableSwitch { default: default, low: low, high: high, offsets: offsets })
 }
 
-shortcut_op!(read_iload_n, "iload", 26, 3);
-
-shortcut_op!(read_lconst_n, "lconst", 9, 1);
-
-single_op!(read_areturn, "areturn");
-single_op!(read_return, "return");
-
-single_op!(read_nop, "nop");
-
-fn read_get_static(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-  print_op("getstatic");
-  read_u16!(field_idx, reader, indent + 1);
-  let (ref class_name, ref field_name, ref descriptor) = resolve_field_name(pool, field_idx as usize)
-    .expect(&format!("No field reference in pool at {}", field_idx));
-  println!("Get static field #{} -> {}.{}:{}", field_idx, class_name, field_name, descriptor);
-  Ok(())
+fn read_lookup_switch(reader: &mut ByteReader) -> io::Result<Operands> {
+  skip_padding(reader)?;
+  let default = read_i32(reader)?;
+  let count = read_i32(reader)?;
+  if count < 0 {
+    return Err(invalid_data(format!("Invalid lookupswitch size {}", count)));
+  }
+  let mut pairs = Vec::with_capacity(count as usize);
+  for _i in 0..count {
+    let key = read_i32(reader)?;
+    let offset = read_i32(reader)?;
+    pairs.push((key, offset));
+  }
+  Ok(Operands::LookupSwitch { default: default, pairs: pairs })
 }
 
-fn read_put_field(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-  print_op("putfield");
-  read_u16!(field_idx, reader, indent + 1);
-  let (ref class_name, ref field_name, ref descriptor) = resolve_field_name(pool, field_idx as usize)
-    .expect(&format!("No field reference in pool at {}", field_idx));
-  println!("Put field #{} -> {}.{}:{}", field_idx, class_name, field_name, descriptor);
-  Ok(())
+/// Reads the operands of an operation modified by `wide`, whose indexes take two bytes
+fn read_wide_operands(reader: &mut ByteReader, opcode: u8) -> io::Result<Operands> {
+  match opcode {
+    132 => Ok(Operands::Increment {
+      index: read_u16(reader)?,
+      value: read_i16(reader)?
+    }),
+    _ if is_local_operation(opcode) => Ok(Operands::Local(read_u16(reader)?)),
+    _ => Err(invalid_data(format!("Operation {} cannot be modified by wide", opcode)))
+  }
 }
 
-/// Macro interpreting invoke of standard methods
-macro_rules! read_std_invoke {
-  ($method_name: ident, $name: expr) => {
-    fn $method_name(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-      print_op($name);
-      read_u16!(method_idx, reader, indent + 1);
-      let (ref class_name, ref method_name, ref descriptor) = resolve_method_name(pool, method_idx as usize)
-        .expect(&format!("No method reference in pool at {}", method_idx));
-      println!("Invoke #{} -> {}.{}:{}", method_idx, class_name, method_name, descriptor);
-      Ok(())
-    }
+fn read_operands(reader: &mut ByteReader, opcode: u8) -> io::Result<Operands> {
+  let operands = match opcode {
+    16 => Operands::Value(read_i8(reader)? as i32),
+    17 => Operands::Value(read_i16(reader)? as i32),
+    18 => Operands::Constant(read_u8(reader)? as u16),
+    _ if is_local_operation(opcode) => Operands::Local(read_u8(reader)? as u16),
+    132 => Operands::Increment {
+      index: read_u8(reader)? as u16,
+      value: read_i8(reader)? as i16
+    },
+    153 ..= 168 | 198 | 199 => Operands::Branch(read_i16(reader)? as i32),
+    200 | 201 => Operands::Branch(read_i32(reader)?),
+    170 => read_table_switch(reader)?,
+    171 => read_lookup_switch(reader)?,
+    19 | 20 | 178 ..= 184 | 187 | 189 | 192 | 193 => Operands::Constant(read_u16(reader)?),
+    185 => {
+      let method_idx = read_u16(reader)?;
+      let count = read_u8(reader)?;
+      if read_u8(reader)? != 0 {
+        return Err(invalid_data("Expecting 0 after the arguments of invokeinterface"));
+      }
+      Operands::InterfaceMethod { method_idx: method_idx, count: count }
+    },
+    186 => {
+      let call_site_idx = read_u16(reader)?;
+      if read_u16(reader)? != 0 {
+        return Err(invalid_data("Expecting 0 after the call site of invokedynamic"));
+      }
+      Operands::Constant(call_site_idx)
+    },
+    188 => Operands::ArrayType(read_u8(reader)?),
+    197 => Operands::MultiArray {
+      class_idx: read_u16(reader)?,
+      dimensions: read_u8(reader)?
+    },
+    _ => Operands::None
   };
+  Ok(operands)
 }
-read_std_invoke!(read_invoke_virtual, "invokevirtual");
-read_std_invoke!(read_invoke_special, "invokespecial");
-read_std_invoke!(read_invoke_static, "invokestatic");
-
-fn read_invoke_interface(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-  print_op("invokeinterface");
-  read_u16!(method_idx, reader, indent + 1);
-  let (ref class_name, ref method_name, ref descriptor) = resolve_method_name(pool, method_idx as usize)
-    .expect(&format!("No method reference in pool at {}", method_idx));
-  println!("Invoke #{} -> {}.{}:{}", method_idx, class_name, method_name, descriptor);
-
-  read_u8!(count, reader, indent + 1);
-  println!("Interface arg count: {}", count);
-
-  read_u8!(null_value, reader, indent + 1);
-  println!("Expected 0 value: <{}>", null_value);
-
-  Ok(())
-}
-fn read_invoke_dynamic(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-  print_op("invokeinterface");
-  read_u16!(method_idx, reader, indent + 1);
-  let (ref class_name, ref method_name, ref descriptor) = resolve_method_name(pool, method_idx as usize)
-    .expect(&format!("No method reference in pool at {}", method_idx));
-  println!("Invoke #{} -> {}.{}:{}", method_idx, class_name, method_name, descriptor);
-
-  read_u16!(first_null, reader, indent + 1);
-  read_u8!(second_null, reader, indent + 1);
-  println!("Expected 0 values: <{}> <{}>", first_null, second_null);
-
-  Ok(())
-}
-
-fn read_new(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-  print_op("new");
-  read_u16!(class_idx, reader, indent + 1);
-  let class_name = resolve_class_name(pool, class_idx as usize)
-    .expect(&format!("No class name in pool at {}", class_idx));
-  println!("New {}", class_name);
 
-  Ok(())
+/// Decodes the instructions of the code of a method.
+/// The reader must start at the beginning of the code, for switches to be aligned.
+pub fn read(reader: &mut ByteReader) -> io::Result<Vec<Instruction>> {
+  let mut instructions = vec![];
+  while !reader.is_empty() {
+    let offset = reader.get_pos();
+    let mut opcode = read_u8(reader)?;
+    let wide = opcode == WIDE;
+    if wide {
+      opcode = read_u8(reader)?;
+    }
+    if opcode_name(opcode).is_none() {
+      return Err(invalid_data(format!("Unsupported operation {} at {}", opcode, offset)));
+    }
+    let operands = if wide {
+      read_wide_operands(reader, opcode)?
+    } else {
+      read_operands(reader, opcode)?
+    };
+    instructions.push(Instruction { offset: offset, opcode: opcode, wide: wide, operands: operands });
+  }
+  Ok(instructions)
 }
 
-fn read_new_prim_array(reader: &mut Reader, indent: u8) -> ReadResult {
-  print_op("newarray");
-  read_u8!(array_type, reader, indent + 1);
-  let type_name = ARRAY_TYPES.get(&array_type)
-    .expect(&format!("No array type with code {}", array_type));
-  println!("new array of {}", type_name);
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-  Ok(())
-}
+  fn decode(code: &[u8]) -> io::Result<Vec<Instruction>> {
+    let mut reader = ByteReader::new(code);
+    read(&mut reader)
+  }
 
-fn read_new_object_array(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-  print_op("anewarray");
-  read_u16!(class_idx, reader, indent + 1);
-  let class_name = resolve_class_name(pool, class_idx as usize)
-    .expect(&format!("No class name in pool at {}", class_idx));
-  println!("new array of {}", class_name);
+  #[test]
+  fn test_read_instructions() {
+    let instructions = decode(&[42, 16, 0xfe, 185, 0, 7, 2, 0, 176]).unwrap();
+    assert_eq!(instructions, vec![
+      Instruction { offset: 0, opcode: 42, wide: false, operands: Operands::None },
+      Instruction { offset: 1, opcode: 16, wide: false, operands: Operands::Value(-2) },
+      Instruction {
+        offset: 3,
+        opcode: 185,
+        wide: false,
+        operands: Operands::InterfaceMethod { method_idx: 7, count: 2 }
+      },
+      Instruction { offset: 8, opcode: 176, wide: false, operands: Operands::None }
+    ]);
+    assert_eq!(instructions[0].name(), "aload_0");
+  }
 
-  Ok(())
-}
+  #[test]
+  fn test_read_switches() {
+    let instructions = decode(&[
+      4,
+      170, 0, 0,
+      0, 0, 0, 20, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 28, 0, 0, 0, 29,
+      171, 0, 0, 0,
+      0, 0, 0, 12, 0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 8
+    ]).unwrap();
+    assert_eq!(instructions.len(), 3);
+    assert_eq!(instructions[1].offset, 1);
+    assert_eq!(instructions[1].operands, Operands::TableSwitch {
+      default: 20,
+      low: 1,
+      high: 2,
+      offsets: vec![28, 29]
+    });
+    assert_eq!(instructions[2].offset, 24);
+    assert_eq!(instructions[2].name(), "lookupswitch");
+    assert_eq!(instructions[2].operands, Operands::LookupSwitch {
+      default: 12,
+      pairs: vec![(-1, 8)]
+    });
+  }
 
-fn read_ldc(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-  print_op("ldc");
-  read_u8!(cst_idx, reader, indent + 1);
-  if let &Some(ref entry) = &pool[cst_idx as usize] {
-    // TODO Test that the read value is an int, short, ref, ...
-    println!("load constant #{} -> {:?}", cst_idx, entry);
-  } else {
-    panic!("No constant in the pool at {}", cst_idx);
+  #[test]
+  fn test_read_wide_operations() {
+    let instructions = decode(&[196, 132, 1, 0, 0xff, 0xfe, 196, 21, 0, 5, 132, 2, 3, 167, 0xff, 0xf3]).unwrap();
+    assert_eq!(instructions, vec![
+      Instruction {
+        offset: 0,
+        opcode: 132,
+        wide: true,
+        operands: Operands::Increment { index: 256, value: -2 }
+      },
+      Instruction { offset: 6, opcode: 21, wide: true, operands: Operands::Local(5) },
+      Instruction {
+        offset: 10,
+        opcode: 132,
+        wide: false,
+        operands: Operands::Increment { index: 2, value: 3 }
+      },
+      Instruction { offset: 13, opcode: 167, wide: false, operands: Operands::Branch(-13) }
+    ]);
+    assert_eq!(instructions[1].name(), "iload");
   }
-  Ok(())
-}
 
-fn read_ldc2_w(reader: &mut Reader, pool: &PoolList, indent: u8) -> ReadResult {
-  print_op("ldc2_w");
-  read_u16!(cst_idx, reader, indent + 1);
-  if let &Some(ref entry) = &pool[cst_idx as usize] {
-    // TODO test that the value is either an double or a long
-    println!("load constant #{} -> {:?}", cst_idx, entry);
-  } else {
-    panic!("No constant in the pool at {}", cst_idx);
+  #[test]
+  fn test_reject_invalid_code() {
+    // Unsupported opcode
+    assert!(decode(&[255]).is_err());
+    // Missing operand
+    assert!(decode(&[17, 0]).is_err());
+    // Operation that cannot be wide
+    assert!(decode(&[196, 96]).is_err());
+    // Empty table
+    assert!(decode(&[170, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1]).is_err());
   }
-  Ok(())
 }
-
-single_op!(read_dup, "dup");
-single_op!(read_pop, "pop");
-single_op!(read_pop2, "pop2");
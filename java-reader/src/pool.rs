@@ -1,233 +1,245 @@
 use std::io;
-use reader::{Reader, to_u16, to_i32, to_i64};
-use printer::print_bytes;
-use std::str::from_utf8;
+use reader::{Reader, invalid_data, read_u8, read_u16, read_i32, read_i64};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PoolElement {
   Utf8Value(String),
   ClassInfo(usize),
   Integer(i32),
+  Float(f32),
   Long(i64),
+  Double(f64),
   String(usize),
   NameAndType(usize, usize),
   FieldRef {class_idx: usize, name_and_type_idx: usize },
   MethodRef {class_idx: usize, name_and_type_idx: usize },
-  InterfaceMethodRef {class_idx: usize, name_and_type_idx: usize }
+  InterfaceMethodRef {class_idx: usize, name_and_type_idx: usize },
+  MethodHandle { kind: u8, reference_idx: usize },
+  MethodType(usize),
+  Dynamic { bootstrap_method_idx: usize, name_and_type_idx: usize },
+  InvokeDynamic { bootstrap_method_idx: usize, name_and_type_idx: usize },
+  Module(usize),
+  Package(usize)
 }
 
+/// Entries of the constant pool, by index.
+/// Index 0 and the slots following longs and doubles have no entry.
 pub type PoolList = Vec<Option<PoolElement>>;
 
-fn read_utf8_value(reader: &mut Reader, indent: u8) -> io::Result<PoolElement> {
-  read_u16!(length, reader, indent);
-  println!("length {}", length);
-
-  let value: String;
-  {
-    let mut bytes = vec![0; length as usize];
-    reader.read(&mut bytes)?;
-    print_bytes(indent, &bytes);
-    value = String::from(from_utf8(&bytes).expect("Invalid utf8 content"));
+/// Decodes the modified UTF-8 of the JVM, where NUL takes 2 bytes and characters
+/// outside of the Basic Multilingual Plane are encoded as surrogate pairs.
+/// Unpaired surrogates, allowed in class files, are replaced.
+fn decode_modified_utf8(bytes: &[u8]) -> io::Result<String> {
+  let mut units: Vec<u16> = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let byte = bytes[i] as u16;
+    let (unit, size) = match byte {
+      0x01..=0x7f => (byte, 1),
+      0xc0..=0xdf => (((byte & 0x1f) << 6) | continuation(bytes, i + 1)?, 2),
+      0xe0..=0xef => (
+        ((byte & 0x0f) << 12) | (continuation(bytes, i + 1)? << 6) | continuation(bytes, i + 2)?,
+        3),
+      _ => return Err(invalid_data(format!("Invalid modified utf8 byte {:#x} at {}", byte, i)))
+    };
+    units.push(unit);
+    i += size;
   }
-  // TODO support the full string encoding
-  Ok(PoolElement::Utf8Value(value))
-}
-
-fn read_class_info(reader: &mut Reader, indent: u8) -> io::Result<PoolElement> {
-  read_u16!(idx, reader, indent);
-  Ok(PoolElement::ClassInfo(idx as usize))
-}
-
-fn read_integer(reader: &mut Reader, indent: u8) -> io::Result<PoolElement> {
-  read_i32!(value, reader, indent);
-  Ok(PoolElement::Integer(value))
-}
-
-fn read_long(reader: &mut Reader, indent: u8) -> io::Result<PoolElement> {
-  read_i64!(value, reader, indent);
-  Ok(PoolElement::Long(value))
-}
-
-fn read_string(reader: &mut Reader, indent: u8) -> io::Result<PoolElement> {
-  read_u16!(idx, reader, indent);
-  Ok(PoolElement::String(idx as usize))
-}
-
-fn read_name_and_type(reader: &mut Reader, indent: u8) -> io::Result<PoolElement> {
-  let bytes = reader.read_4u()?;
-  print_bytes(indent, bytes);
-  let name_idx = to_u16(&bytes[0..2]) as usize;
-  let descriptor_idx = to_u16(&bytes[2..4]) as usize;
-
-  Ok(PoolElement::NameAndType(name_idx, descriptor_idx))
+  Ok(String::from_utf16_lossy(&units))
 }
 
-fn read_field_ref(reader: &mut Reader, indent: u8) -> io::Result<PoolElement> {
-  let bytes = reader.read_4u()?;
-  print_bytes(indent, bytes);
-  let class_idx = to_u16(&bytes[0..2]) as usize;
-  let name_and_type_idx = to_u16(&bytes[2..4]) as usize;
-
-  Ok(PoolElement::FieldRef {class_idx: class_idx, name_and_type_idx: name_and_type_idx})
+/// Reads the 6 bits of a continuation byte of a multi-byte character
+fn continuation(bytes: &[u8], i: usize) -> io::Result<u16> {
+  match bytes.get(i) {
+    Some(byte) if byte & 0xc0 == 0x80 => Ok((byte & 0x3f) as u16),
+    _ => Err(invalid_data(format!("Truncated modified utf8 character at {}", i)))
+  }
 }
 
-fn read_method_ref(reader: &mut Reader, indent: u8) -> io::Result<PoolElement> {
-  let bytes = reader.read_4u()?;
-  print_bytes(indent, bytes);
-  let class_idx = to_u16(&bytes[0..2]) as usize;
-  let name_and_type_idx = to_u16(&bytes[2..4]) as usize;
-
-  Ok(PoolElement::MethodRef { class_idx: class_idx, name_and_type_idx: name_and_type_idx })
+fn read_utf8_value(reader: &mut dyn Reader) -> io::Result<PoolElement> {
+  let length = read_u16(reader)?;
+  let mut bytes = vec![0; length as usize];
+  reader.read(&mut bytes)?;
+  Ok(PoolElement::Utf8Value(decode_modified_utf8(&bytes)?))
 }
 
-fn read_interface_method_ref(reader: &mut Reader, indent: u8) -> io::Result<PoolElement> {
-  let bytes = reader.read_4u()?;
-  print_bytes(indent, bytes);
-  let class_idx = to_u16(&bytes[0..2]) as usize;
-  let name_and_type_idx = to_u16(&bytes[2..4]) as usize;
-
-  Ok(PoolElement::InterfaceMethodRef { class_idx: class_idx, name_and_type_idx: name_and_type_idx })
+fn read_index(reader: &mut dyn Reader) -> io::Result<usize> {
+  Ok(read_u16(reader)? as usize)
 }
 
-fn read_entry(reader: &mut Reader, index: &mut u16) -> io::Result<PoolElement> {
-  let pool_code: u8;
-  {
-    let entry_code = reader.read_1u()?;
-    print_bytes(1, entry_code);
-    pool_code = entry_code[0];
-  }
-
-  print!("#{} ", index);
-  let indent = 2;
+fn read_entry(reader: &mut dyn Reader) -> io::Result<PoolElement> {
+  let pool_code = read_u8(reader)?;
   let element = match pool_code {
-    1 => {
-      println!("Utf8 constant");
-      read_utf8_value(reader, indent)?
-    },
-    3 => {
-      println!("Integer constant");
-      read_integer(reader, indent)?
+    1 => read_utf8_value(reader)?,
+    3 => PoolElement::Integer(read_i32(reader)?),
+    4 => PoolElement::Float(f32::from_bits(read_i32(reader)? as u32)),
+    5 => PoolElement::Long(read_i64(reader)?),
+    6 => PoolElement::Double(f64::from_bits(read_i64(reader)? as u64)),
+    7 => PoolElement::ClassInfo(read_index(reader)?),
+    8 => PoolElement::String(read_index(reader)?),
+    9 => PoolElement::FieldRef {
+      class_idx: read_index(reader)?,
+      name_and_type_idx: read_index(reader)?
     },
-    5 => {
-      println!("Long constant");
-      *index += 1;
-      read_long(reader, indent)?
+    10 => PoolElement::MethodRef {
+      class_idx: read_index(reader)?,
+      name_and_type_idx: read_index(reader)?
     },
-    7 => {
-      println!("Class info");
-      read_class_info(reader, indent)?
+    11 => PoolElement::InterfaceMethodRef {
+      class_idx: read_index(reader)?,
+      name_and_type_idx: read_index(reader)?
     },
-    8 => {
-      println!("String constant");
-      read_string(reader, indent)?
+    12 => {
+      let name_idx = read_index(reader)?;
+      let descriptor_idx = read_index(reader)?;
+      PoolElement::NameAndType(name_idx, descriptor_idx)
     },
-    9 => {
-      println!("Field ref");
-      read_field_ref(reader, indent)?
+    15 => PoolElement::MethodHandle {
+      kind: read_u8(reader)?,
+      reference_idx: read_index(reader)?
     },
-    10 => {
-      println!("Method ref");
-      read_method_ref(reader, indent)?
+    16 => PoolElement::MethodType(read_index(reader)?),
+    17 => PoolElement::Dynamic {
+      bootstrap_method_idx: read_index(reader)?,
+      name_and_type_idx: read_index(reader)?
     },
-    11 => {
-      println!("Interface method ref");
-      read_interface_method_ref(reader, indent)?
+    18 => PoolElement::InvokeDynamic {
+      bootstrap_method_idx: read_index(reader)?,
+      name_and_type_idx: read_index(reader)?
     },
-    12 => {
-      println!("Name and type");
-      read_name_and_type(reader, indent)?
-    }
-    _ => panic!("Unsupported pool element. Code = {}", pool_code)
+    19 => PoolElement::Module(read_index(reader)?),
+    20 => PoolElement::Package(read_index(reader)?),
+    _ => return Err(invalid_data(format!("Unsupported pool element. Code = {}", pool_code)))
   };
-  *index += 1;
-  println!("{:?}", element);
   Ok(element)
 }
 
-pub fn read_class_pool(reader: &mut Reader) -> io::Result<PoolList> {
-  read_u16!(count, reader, 0);
-  println!("constant pool size = {}", count);
+pub fn read_class_pool(reader: &mut dyn Reader) -> io::Result<PoolList> {
+  let count = read_u16(reader)?;
 
-	let mut entries = vec![None; count as usize];
+  let mut entries = vec![None; count as usize];
   let mut i = 1;
-	while i < count {
-    let idx = i as usize;
-    let entry = read_entry(reader, &mut i)?;
-    entries[idx] = Some(entry);
-	}
+  while i < count as usize {
+    let entry = read_entry(reader)?;
+    // Longs and doubles take two entries
+    let size = match entry {
+      PoolElement::Long(_) | PoolElement::Double(_) => 2,
+      _ => 1
+    };
+    entries[i] = Some(entry);
+    i += size;
+  }
 
-	Ok(entries)
+  Ok(entries)
 }
 
-pub fn resolve_utf8_value<'a>(pool: &'a PoolList, index: usize) -> Option<&'a str> {
-	if let &Some(ref entry) = &pool[index] {
-		match entry {
-      &PoolElement::Utf8Value(ref value) => Some(value),
-      &PoolElement::ClassInfo(idx) => {
-        let class_entry = &pool[idx];
-        if let &Some(PoolElement::Utf8Value(ref value)) = class_entry {
-          Some(value)
-        } else {
-          panic!("Invalid index into pool: {:?}", class_entry);
-        }
-      },
+fn get_entry(pool: &PoolList, index: usize) -> Option<&PoolElement> {
+  pool.get(index).and_then(|entry| entry.as_ref())
+}
+
+pub fn resolve_utf8_value(pool: &PoolList, index: usize) -> Option<&str> {
+  match get_entry(pool, index) {
+    Some(&PoolElement::Utf8Value(ref value)) => Some(value),
+    Some(&PoolElement::ClassInfo(idx)) => match get_entry(pool, idx) {
+      Some(&PoolElement::Utf8Value(ref value)) => Some(value),
       _ => None
-    }
-	} else {
-		None
-	}
+    },
+    _ => None
+  }
 }
 
-pub fn resolve_class_name<'a>(pool: &'a PoolList, index: usize) -> Option<&'a str> {
-	if let &Some(PoolElement::ClassInfo(ref name_idx)) = &pool[index] {
-    let name = resolve_utf8_value(pool, *name_idx)
-      .expect(&format!("No class name at {}", name_idx));
-    Some(name)
-	} else {
-		None
-	}
+pub fn resolve_class_name(pool: &PoolList, index: usize) -> Option<&str> {
+  match get_entry(pool, index) {
+    Some(&PoolElement::ClassInfo(name_idx)) => resolve_utf8_value(pool, name_idx),
+    _ => None
+  }
 }
 
-fn resolve_name_and_type<'a>(pool: &'a PoolList, index: usize) -> Option<(&'a str, &'a str)> {
-	if let &Some(PoolElement::NameAndType(ref name_idx, ref descriptor_idx)) = &pool[index] {
-    let name = resolve_utf8_value(pool, *name_idx)
-      .expect(&format!("No method name at {}", name_idx));
-    let descriptor = resolve_utf8_value(pool, *descriptor_idx)
-      .expect(&format!("No descriptor string at {}", descriptor_idx));
-    Some((name, descriptor))
-	} else {
-		None
-	}
+pub fn resolve_name_and_type(pool: &PoolList, index: usize) -> Option<(&str, &str)> {
+  match get_entry(pool, index) {
+    Some(&PoolElement::NameAndType(name_idx, descriptor_idx)) => {
+      let name = resolve_utf8_value(pool, name_idx)?;
+      let descriptor = resolve_utf8_value(pool, descriptor_idx)?;
+      Some((name, descriptor))
+    },
+    _ => None
+  }
 }
 
-pub fn resolve_field_name<'a>(pool: &'a PoolList, index: usize) -> Option<(&'a str, &'a str, &'a str)> {
-	if let &Some(PoolElement::FieldRef { ref class_idx, ref name_and_type_idx }) = &pool[index] {
-    let class_name = resolve_utf8_value(pool, *class_idx)
-      .expect(&format!("No field name at {}", class_idx));
-    let name_type = resolve_name_and_type(pool, *name_and_type_idx)
-      .expect(&format!("No name & type at {}", name_and_type_idx));
-    Some((class_name, name_type.0, name_type.1))
-	} else {
-		None
-	}
+fn resolve_member(pool: &PoolList, class_idx: usize, name_and_type_idx: usize) -> Option<(&str, &str, &str)> {
+  let class_name = resolve_class_name(pool, class_idx)?;
+  let (name, descriptor) = resolve_name_and_type(pool, name_and_type_idx)?;
+  Some((class_name, name, descriptor))
 }
 
-pub fn resolve_method_name<'a>(pool: &'a PoolList, index: usize) -> Option<(&'a str, &'a str, &'a str)> {
-  match &pool[index] {
-	  &Some(PoolElement::MethodRef { ref class_idx, ref name_and_type_idx }) => {
-      let class_name = resolve_utf8_value(pool, *class_idx)
-        .expect(&format!("No method name at {}", class_idx));
-      let name_type = resolve_name_and_type(pool, *name_and_type_idx)
-        .expect(&format!("No name & type at {}", name_and_type_idx));
-      Some((class_name, name_type.0, name_type.1))
-    },
-	  &Some(PoolElement::InterfaceMethodRef { ref class_idx, ref name_and_type_idx }) => {
-      let class_name = resolve_utf8_value(pool, *class_idx)
-        .expect(&format!("No method name at {}", class_idx));
-      let name_type = resolve_name_and_type(pool, *name_and_type_idx)
-        .expect(&format!("No name & type at {}", name_and_type_idx));
-      Some((class_name, name_type.0, name_type.1))
-    },
-	  _ => None
+pub fn resolve_field_name(pool: &PoolList, index: usize) -> Option<(&str, &str, &str)> {
+  match get_entry(pool, index) {
+    Some(&PoolElement::FieldRef { class_idx, name_and_type_idx }) =>
+      resolve_member(pool, class_idx, name_and_type_idx),
+    _ => None
+  }
+}
+
+pub fn resolve_method_name(pool: &PoolList, index: usize) -> Option<(&str, &str, &str)> {
+  match get_entry(pool, index) {
+    Some(&PoolElement::MethodRef { class_idx, name_and_type_idx })
+    | Some(&PoolElement::InterfaceMethodRef { class_idx, name_and_type_idx }) =>
+      resolve_member(pool, class_idx, name_and_type_idx),
+    _ => None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use reader::ByteReader;
+
+  #[test]
+  fn test_read_pool() {
+    let bytes = [
+      0, 6,
+      1, 0, 3, b'a', b'/', b'B',
+      7, 0, 1,
+      5, 0, 0, 0, 0, 0, 0, 0, 42,
+      3, 0xff, 0xff, 0xff, 0xfe
+    ];
+    let mut reader = ByteReader::new(&bytes);
+    let pool = read_class_pool(&mut reader).unwrap();
+    assert_eq!(pool, vec![
+      None,
+      Some(PoolElement::Utf8Value(String::from("a/B"))),
+      Some(PoolElement::ClassInfo(1)),
+      Some(PoolElement::Long(42)),
+      None,
+      Some(PoolElement::Integer(-2))
+    ]);
+    assert_eq!(resolve_class_name(&pool, 2), Some("a/B"));
+    assert_eq!(resolve_class_name(&pool, 1), None);
+    assert_eq!(resolve_utf8_value(&pool, 10), None);
+  }
+
+  #[test]
+  fn test_reject_unknown_entries() {
+    let bytes = [0, 2, 2, 0, 0];
+    let mut reader = ByteReader::new(&bytes);
+    assert!(read_class_pool(&mut reader).is_err());
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn test_decode_modified_utf8() {
+    assert_eq!(decode_modified_utf8(b"a/B").unwrap(), "a/B");
+    assert_eq!(decode_modified_utf8(&[b'a', 0xc0, 0x80, b'b']).unwrap(), "a\u{0}b");
+    assert_eq!(decode_modified_utf8(&[0xc3, 0xa9]).unwrap(), "\u{e9}");
+    // U+1F600 as the surrogate pair D83D DE00
+    assert_eq!(
+      decode_modified_utf8(&[0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80]).unwrap(),
+      "\u{1f600}");
+  }
+
+  #[test]
+  fn test_reject_invalid_modified_utf8() {
+    assert!(decode_modified_utf8(&[0]).is_err());
+    assert!(decode_modified_utf8(&[0xf0, 0x9f, 0x98, 0x80]).is_err());
+    assert!(decode_modified_utf8(&[0xe0, 0x80]).is_err());
+  }
+}
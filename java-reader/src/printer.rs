@@ -1,17 +1,377 @@
-pub fn print_indent(indent: u8) {
+//! Human readable dump of a class file.
+
+use std::fmt::Write;
+
+use annotations::{Annotation, ElementValue};
+use class_file::{Attribute, ClassFile, Code, Member};
+use flags::{to_class_access, to_method_access};
+use module::Module;
+use operations::{Instruction, Operands};
+use pool::{PoolElement, PoolList, resolve_class_name, resolve_field_name, resolve_method_name, resolve_utf8_value};
+use stack_map::{StackMapFrame, VerificationType};
+use types::ARRAY_TYPES;
+
+fn print_indent(output: &mut String, indent: u8) {
 	for _i in 0..indent {
-		print!("  ");
+		output.push_str("  ");
+	}
+}
+
+fn print_flags(flags: &[&str]) -> String {
+	flags.join(" ")
+}
+
+fn utf8_or_unknown(pool: &PoolList, index: usize) -> &str {
+	resolve_utf8_value(pool, index).unwrap_or("<unknown>")
+}
+
+fn describe_entry(pool: &PoolList, entry: &PoolElement) -> String {
+	match *entry {
+		PoolElement::Utf8Value(ref value) => format!("Utf8 '{}'", value),
+		PoolElement::ClassInfo(idx) => format!("Class {}", utf8_or_unknown(pool, idx)),
+		PoolElement::Integer(value) => format!("Integer {}", value),
+		PoolElement::Float(value) => format!("Float {}", value),
+		PoolElement::Long(value) => format!("Long {}", value),
+		PoolElement::Double(value) => format!("Double {}", value),
+		PoolElement::String(idx) => format!("String '{}'", utf8_or_unknown(pool, idx)),
+		PoolElement::NameAndType(name_idx, descriptor_idx) => format!(
+			"NameAndType {}:{}",
+			utf8_or_unknown(pool, name_idx),
+			utf8_or_unknown(pool, descriptor_idx)),
+		PoolElement::FieldRef { class_idx, name_and_type_idx } =>
+			format!("FieldRef #{}.#{}", class_idx, name_and_type_idx),
+		PoolElement::MethodRef { class_idx, name_and_type_idx } =>
+			format!("MethodRef #{}.#{}", class_idx, name_and_type_idx),
+		PoolElement::InterfaceMethodRef { class_idx, name_and_type_idx } =>
+			format!("InterfaceMethodRef #{}.#{}", class_idx, name_and_type_idx),
+		PoolElement::MethodHandle { kind, reference_idx } =>
+			format!("MethodHandle {}:#{}", kind, reference_idx),
+		PoolElement::MethodType(idx) => format!("MethodType {}", utf8_or_unknown(pool, idx)),
+		PoolElement::Dynamic { bootstrap_method_idx, name_and_type_idx } =>
+			format!("Dynamic #{}:#{}", bootstrap_method_idx, name_and_type_idx),
+		PoolElement::InvokeDynamic { bootstrap_method_idx, name_and_type_idx } =>
+			format!("InvokeDynamic #{}:#{}", bootstrap_method_idx, name_and_type_idx),
+		PoolElement::Module(idx) => format!("Module {}", utf8_or_unknown(pool, idx)),
+		PoolElement::Package(idx) => format!("Package {}", utf8_or_unknown(pool, idx))
+	}
+}
+
+fn describe_constant(pool: &PoolList, index: usize) -> String {
+	if let Some((class_name, name, descriptor)) = resolve_field_name(pool, index)
+		.or_else(|| resolve_method_name(pool, index)) {
+		return format!("{}.{}:{}", class_name, name, descriptor);
+	}
+	if let Some(class_name) = resolve_class_name(pool, index) {
+		return String::from(class_name);
+	}
+	match pool.get(index).and_then(|entry| entry.as_ref()) {
+		Some(entry) => describe_entry(pool, entry),
+		None => String::from("<unknown>")
+	}
+}
+
+/// Gets the offset in the code of the target of a jump
+fn target(instruction: &Instruction, offset: i32) -> i64 {
+	instruction.offset as i64 + offset as i64
+}
+
+fn describe_instruction(pool: &PoolList, instruction: &Instruction) -> String {
+	let operands = match instruction.operands {
+		Operands::None => String::new(),
+		Operands::Local(idx) => format!(" {}", idx),
+		Operands::Value(value) => format!(" {}", value),
+		Operands::Constant(idx) => format!(" #{} // {}", idx, describe_constant(pool, idx as usize)),
+		Operands::ArrayType(code) => format!(" {}", ARRAY_TYPES.get(&code).unwrap_or(&"<unknown>")),
+		Operands::InterfaceMethod { method_idx, count } => format!(
+			" #{}, {} // {}",
+			method_idx,
+			count,
+			describe_constant(pool, method_idx as usize)),
+		Operands::Branch(offset) => format!(" {}", target(instruction, offset)),
+		Operands::Increment { index, value } => format!(" {}, {}", index, value),
+		Operands::TableSwitch { default, low, ref offsets, .. } => {
+			let cases: Vec<String> = offsets.iter().enumerate()
+				.map(|(idx, offset)| format!("{}: {}", low as i64 + idx as i64, target(instruction, *offset)))
+				.collect();
+			format!(" {{ {}, default: {} }}", cases.join(", "), target(instruction, default))
+		},
+		Operands::LookupSwitch { default, ref pairs } => {
+			let cases: Vec<String> = pairs.iter()
+				.map(|&(key, offset)| format!("{}: {}", key, target(instruction, offset)))
+				.collect();
+			format!(" {{ {}, default: {} }}", cases.join(", "), target(instruction, default))
+		},
+		Operands::MultiArray { class_idx, dimensions } => format!(
+			" #{}, {} // {}",
+			class_idx,
+			dimensions,
+			describe_constant(pool, class_idx as usize))
+	};
+	let prefix = if instruction.wide { "wide " } else { "" };
+	format!("{}: {}{}{}", instruction.offset, prefix, instruction.name(), operands)
+}
+
+fn print_code(output: &mut String, pool: &PoolList, code: &Code, indent: u8) {
+	print_indent(output, indent);
+	writeln!(output, "Max stack = {}, max local vars = {}", code.max_stack, code.max_locals).unwrap();
+	for instruction in &code.instructions {
+		print_indent(output, indent + 1);
+		writeln!(output, "{}", describe_instruction(pool, instruction)).unwrap();
+	}
+	for handler in &code.exception_table {
+		print_indent(output, indent);
+		writeln!(
+			output,
+			"Exception handler [{}, {}) -> {} for {}",
+			handler.start_pc,
+			handler.end_pc,
+			handler.handler_pc,
+			resolve_class_name(pool, handler.catch_type as usize).unwrap_or("any")).unwrap();
+	}
+	print_attributes(output, pool, &code.attributes, indent);
+}
+
+fn class_names(pool: &PoolList, indexes: &[u16]) -> String {
+	let names: Vec<&str> = indexes.iter()
+		.map(|idx| resolve_class_name(pool, *idx as usize).unwrap_or("<unknown>"))
+		.collect();
+	names.join(", ")
+}
+
+fn describe_element_value(pool: &PoolList, value: &ElementValue) -> String {
+	match *value {
+		ElementValue::Constant { value_idx, .. } => describe_constant(pool, value_idx as usize),
+		ElementValue::Enum { type_name_idx, const_name_idx } => format!(
+			"{}.{}",
+			utf8_or_unknown(pool, type_name_idx as usize),
+			utf8_or_unknown(pool, const_name_idx as usize)),
+		ElementValue::Class(idx) => format!("{}.class", utf8_or_unknown(pool, idx as usize)),
+		ElementValue::Annotation(ref annotation) => describe_annotation(pool, annotation),
+		ElementValue::Array(ref values) => {
+			let values: Vec<String> = values.iter().map(|value| describe_element_value(pool, value)).collect();
+			format!("{{{}}}", values.join(", "))
+		}
+	}
+}
+
+fn describe_annotation(pool: &PoolList, annotation: &Annotation) -> String {
+	let elements: Vec<String> = annotation.elements.iter()
+		.map(|pair| format!(
+			"{}={}",
+			utf8_or_unknown(pool, pair.name_idx as usize),
+			describe_element_value(pool, &pair.value)))
+		.collect();
+	format!("@{}({})", utf8_or_unknown(pool, annotation.type_idx as usize), elements.join(", "))
+}
+
+fn describe_verification_type(pool: &PoolList, value: &VerificationType) -> String {
+	match *value {
+		VerificationType::Object(idx) => describe_constant(pool, idx as usize),
+		VerificationType::Uninitialized(offset) => format!("Uninitialized({})", offset),
+		ref value => format!("{:?}", value)
+	}
+}
+
+fn describe_verification_types(pool: &PoolList, values: &[VerificationType]) -> String {
+	let values: Vec<String> = values.iter().map(|value| describe_verification_type(pool, value)).collect();
+	format!("[{}]", values.join(", "))
+}
+
+fn describe_frame(pool: &PoolList, frame: &StackMapFrame) -> String {
+	match *frame {
+		StackMapFrame::Same { offset_delta } => format!("+{} same", offset_delta),
+		StackMapFrame::SameLocals1StackItem { offset_delta, ref stack } => format!(
+			"+{} same locals, stack: {}",
+			offset_delta,
+			describe_verification_type(pool, stack)),
+		StackMapFrame::Chop { offset_delta, count } => format!("+{} chop {}", offset_delta, count),
+		StackMapFrame::Append { offset_delta, ref locals } => format!(
+			"+{} append {}",
+			offset_delta,
+			describe_verification_types(pool, locals)),
+		StackMapFrame::Full { offset_delta, ref locals, ref stack } => format!(
+			"+{} full, locals: {}, stack: {}",
+			offset_delta,
+			describe_verification_types(pool, locals),
+			describe_verification_types(pool, stack))
 	}
 }
 
-pub fn print_bytes(indent: u8, bytes: &[u8]) {
-	print_indent(indent);
-	for b in bytes {
-		match *b {
-			// Small fix as it is not possible to put trailing 0s in front of hexa
-			b @ 0 ... 15 => print!("0{:X} ", b),
-			_ => print!("{:X} ", b)
+fn describe_module(pool: &PoolList, module: &Module) -> Vec<String> {
+	let mut lines = vec![format!("Module {}", describe_constant(pool, module.name_idx as usize))];
+	for requires in &module.requires {
+		lines.push(format!("requires {}", describe_constant(pool, requires.module_idx as usize)));
+	}
+	for &(kind, packages) in &[("exports", &module.exports), ("opens", &module.opens)] {
+		for package in packages {
+			let targets: Vec<String> = package.to.iter()
+				.map(|idx| describe_constant(pool, *idx as usize))
+				.collect();
+			lines.push(format!("{} {} to [{}]", kind, describe_constant(pool, package.package_idx as usize), targets.join(", ")));
 		}
 	}
-	print!("> ")
+	for service in &module.uses {
+		lines.push(format!("uses {}", describe_constant(pool, *service as usize)));
+	}
+	for provides in &module.provides {
+		lines.push(format!(
+			"provides {} with {}",
+			describe_constant(pool, provides.class_idx as usize),
+			class_names(pool, &provides.with)));
+	}
+	lines
+}
+
+/// Describes the content of attributes without nested attributes
+fn describe_attribute(pool: &PoolList, attribute: &Attribute) -> Vec<String> {
+	match *attribute {
+		Attribute::ConstantValue(idx) => vec![format!("Value: {}", describe_constant(pool, idx as usize))],
+		Attribute::StackMapTable(ref frames) => frames.iter().map(|frame| describe_frame(pool, frame)).collect(),
+		Attribute::BootstrapMethods(ref methods) => methods.iter()
+			.map(|method| {
+				let arguments: Vec<String> = method.arguments.iter()
+					.map(|idx| describe_constant(pool, *idx as usize))
+					.collect();
+				format!("#{} ({})", method.method_ref_idx, arguments.join(", "))
+			})
+			.collect(),
+		Attribute::NestHost(idx) => vec![format!("Host: {}", describe_constant(pool, idx as usize))],
+		Attribute::NestMembers(ref classes)
+		| Attribute::PermittedSubclasses(ref classes)
+		| Attribute::Exceptions(ref classes) => vec![class_names(pool, classes)],
+		Attribute::InnerClasses(ref classes) => classes.iter()
+			.map(|inner_class| format!(
+				"Inner class {} in {} as {}, flags: {}",
+				resolve_class_name(pool, inner_class.inner_class_idx as usize).unwrap_or("<unknown>"),
+				resolve_class_name(pool, inner_class.outer_class_idx as usize).unwrap_or("<anonymous>"),
+				resolve_utf8_value(pool, inner_class.name_idx as usize).unwrap_or("<anonymous>"),
+				print_flags(&to_class_access(inner_class.access_flags))))
+			.collect(),
+		Attribute::EnclosingMethod { class_idx, method_idx } => vec![format!(
+			"Enclosed in {}, method {}",
+			describe_constant(pool, class_idx as usize),
+			if method_idx == 0 { String::from("<none>") } else { describe_constant(pool, method_idx as usize) })],
+		Attribute::Synthetic | Attribute::Deprecated | Attribute::Code(_) | Attribute::Record(_) => vec![],
+		Attribute::Signature(idx) => vec![format!("Signature: {}", utf8_or_unknown(pool, idx as usize))],
+		Attribute::SourceFile(idx) => vec![format!("Source file: {}", utf8_or_unknown(pool, idx as usize))],
+		Attribute::SourceDebugExtension(ref data) => vec![format!("{} bytes", data.len())],
+		Attribute::LineNumberTable(ref lines) => lines.iter()
+			.map(|line| format!("Line {} at {}", line.line_number, line.start_pc))
+			.collect(),
+		Attribute::LocalVariableTable(ref variables)
+		| Attribute::LocalVariableTypeTable(ref variables) => variables.iter()
+			.map(|variable| format!(
+				"Local {} '{}' {} in [{}, {})",
+				variable.index,
+				utf8_or_unknown(pool, variable.name_idx as usize),
+				utf8_or_unknown(pool, variable.descriptor_idx as usize),
+				variable.start_pc,
+				variable.start_pc as u32 + variable.length as u32))
+			.collect(),
+		Attribute::Annotations { ref annotations, .. } => annotations.iter()
+			.map(|annotation| describe_annotation(pool, annotation))
+			.collect(),
+		Attribute::ParameterAnnotations { ref parameters, .. } => parameters.iter().enumerate()
+			.map(|(idx, annotations)| {
+				let annotations: Vec<String> = annotations.iter()
+					.map(|annotation| describe_annotation(pool, annotation))
+					.collect();
+				format!("Parameter {}: {}", idx, annotations.join(" "))
+			})
+			.collect(),
+		Attribute::TypeAnnotations { ref annotations, .. } => annotations.iter()
+			.map(|annotation| format!(
+				"{} on {:?}",
+				describe_annotation(pool, &annotation.annotation),
+				annotation.target))
+			.collect(),
+		Attribute::AnnotationDefault(ref value) => vec![describe_element_value(pool, value)],
+		Attribute::MethodParameters(ref parameters) => parameters.iter()
+			.map(|parameter| format!(
+				"Parameter {}, flags: {:#06X}",
+				resolve_utf8_value(pool, parameter.name_idx as usize).unwrap_or("<unnamed>"),
+				parameter.access_flags))
+			.collect(),
+		Attribute::Module(ref module) => describe_module(pool, module),
+		Attribute::ModulePackages(ref packages) => packages.iter()
+			.map(|idx| describe_constant(pool, *idx as usize))
+			.collect(),
+		Attribute::ModuleMainClass(idx) => vec![format!("Main class: {}", describe_constant(pool, idx as usize))],
+		Attribute::Unknown { ref data, .. } => vec![format!("Unknown attribute, {} bytes skipped", data.len())]
+	}
+}
+
+fn print_attributes(output: &mut String, pool: &PoolList, attributes: &[Attribute], indent: u8) {
+	for attribute in attributes {
+		print_indent(output, indent);
+		writeln!(output, "Attribute '{}'", attribute.name()).unwrap();
+		match *attribute {
+			Attribute::Code(ref code) => print_code(output, pool, code, indent + 1),
+			Attribute::Record(ref components) => for component in components {
+				print_indent(output, indent + 1);
+				writeln!(
+					output,
+					"Component '{}' {}",
+					utf8_or_unknown(pool, component.name_idx as usize),
+					utf8_or_unknown(pool, component.descriptor_idx as usize)).unwrap();
+				print_attributes(output, pool, &component.attributes, indent + 2);
+			},
+			_ => for line in describe_attribute(pool, attribute) {
+				print_indent(output, indent + 1);
+				writeln!(output, "{}", line).unwrap();
+			}
+		}
+	}
+}
+
+fn print_member(output: &mut String, pool: &PoolList, member: &Member, kind: &str) {
+	print_indent(output, 1);
+	writeln!(
+		output,
+		"{} '{}' {}, flags: {}",
+		kind,
+		member.name(pool).unwrap_or("<unknown>"),
+		member.descriptor(pool).unwrap_or("<unknown>"),
+		print_flags(&to_method_access(member.access_flags))).unwrap();
+	print_attributes(output, pool, &member.attributes, 2);
+}
+
+/// Dumps the content of a class, resolving references to the constant pool
+pub fn dump_class(class: &ClassFile) -> String {
+	let mut output = String::new();
+	writeln!(output, "version: {}.{}", class.major_version, class.minor_version).unwrap();
+
+	writeln!(output, "Constant pool:").unwrap();
+	for (idx, entry) in class.pool.iter().enumerate() {
+		if let Some(ref entry) = *entry {
+			print_indent(&mut output, 1);
+			writeln!(output, "#{} = {}", idx, describe_entry(&class.pool, entry)).unwrap();
+		}
+	}
+
+	writeln!(
+		output,
+		"Class '{}', flags: {}",
+		class.class_name().unwrap_or("<unknown>"),
+		print_flags(&to_class_access(class.access_flags))).unwrap();
+	writeln!(output, "Super class '{}'", class.super_class_name().unwrap_or("<none>")).unwrap();
+	for interface in &class.interfaces {
+		print_indent(&mut output, 1);
+		writeln!(
+			output,
+			"Interface '{}'",
+			resolve_class_name(&class.pool, *interface as usize).unwrap_or("<unknown>")).unwrap();
+	}
+
+	writeln!(output, "Field count = {}", class.fields.len()).unwrap();
+	for field in &class.fields {
+		print_member(&mut output, &class.pool, field, "Field");
+	}
+	writeln!(output, "Method count = {}", class.methods.len()).unwrap();
+	for method in &class.methods {
+		print_member(&mut output, &class.pool, method, "Method");
+	}
+	print_attributes(&mut output, &class.pool, &class.attributes, 0);
+
+	output
 }
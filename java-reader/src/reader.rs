@@ -1,104 +1,30 @@
 use std::io;
-use std::io::{BufReader, Read, Error, ErrorKind};
-use std::fs::File;
-use std::mem::transmute;
+use std::io::{Error, ErrorKind};
 
 pub type ReadResult = io::Result<()>;
 
 pub trait Reader {
 
-	fn read_1u(&mut self) -> io::Result<&[u8]>;
+  fn read_1u(&mut self) -> io::Result<&[u8]>;
 
   fn read_2u(&mut self) -> io::Result<&[u8]>;
 
-	fn read_4u(&mut self) -> io::Result<&[u8]>;
+  fn read_4u(&mut self) -> io::Result<&[u8]>;
 
-	fn read_8u(&mut self) -> io::Result<&[u8]>;
+  fn read_8u(&mut self) -> io::Result<&[u8]>;
 
-	fn read(&mut self, buffer: &mut [u8]) -> ReadResult;
+  fn read(&mut self, buffer: &mut [u8]) -> ReadResult;
 
   fn read_up_to_u16(&mut self, length: u16) -> io::Result<&[u8]>;
 }
 
-pub struct FileReader {
-	buffer: BufReader<File>,
-	data_buffer: [u8; 100]
-}
-
-impl FileReader {
-	pub fn new(file: File) -> FileReader {
-		FileReader {
-			buffer: BufReader::new(file),
-			data_buffer: [0; 100]
-		}
-	}
-
-  fn read_buffer(reader: &mut BufReader<File>, buffer: &mut [u8]) -> ReadResult {
-    // reader.read_exact(buffer)
-    let mut remaining = buffer.len();
-    let mut start = 0;
-    while remaining > 0 {
-      let read = reader.read(&mut buffer[start..])?;
-      if read > 0 {
-        if read <= remaining {
-          remaining -= read;
-          start += read;
-        } else {
-          panic!("Too much bytes read.");
-        }
-      } else {
-        panic!("Cannot read more than {} bytes, but asking for {}", start, buffer.len());
-      }
-    }
-    Ok(())
-  }
-}
-
-impl Reader for FileReader {
-
-	fn read_1u(&mut self) -> io::Result<&[u8]> {
-		self.buffer.read_exact(&mut self.data_buffer[0..1])?;
-		Ok(&self.data_buffer[0..1])
-	}
-
-	fn read_2u(&mut self) -> io::Result<&[u8]> {
-		self.buffer.read_exact(&mut self.data_buffer[0..2])?;
-		Ok(&self.data_buffer[0..2])
-	}
-
-	fn read_4u(&mut self) -> io::Result<&[u8]> {
-		self.buffer.read_exact(&mut self.data_buffer[0..4])?;
-		Ok(&self.data_buffer[0..4])
-	}
-
-	fn read_8u(&mut self) -> io::Result<&[u8]> {
-		self.buffer.read_exact(&mut self.data_buffer[0..8])?;
-		Ok(&self.data_buffer[0..8])
-	}
-
-	fn read(&mut self, buffer: &mut [u8]) -> ReadResult {
-		Self::read_buffer(&mut self.buffer, buffer)
-	}
-
-  fn read_up_to_u16(&mut self, length: u16) -> io::Result<&[u8]> {
-    if length <= 100 {
-      let end = length as usize;
-      Self::read_buffer(&mut self.buffer, &mut self.data_buffer[0..end])?;
-      Ok(&self.data_buffer[0..end])
-    } else {
-      panic!("Not supporting read > 100 chars yet. Asked: {}", length);
-    }
-  }
-
-}
-
 pub struct ByteReader<'a> {
   bytes: &'a[u8],
   position: usize
 }
 
 impl <'a> ByteReader<'a> {
-  pub fn new(bytes: &'a[u8]) -> ByteReader {
+  pub fn new(bytes: &'a[u8]) -> ByteReader<'a> {
     ByteReader { bytes: bytes, position: 0 }
   }
 
@@ -106,7 +32,7 @@ impl <'a> ByteReader<'a> {
     self.bytes.len() <= self.position
   }
 
-  pub fn get_slice(&mut self, length: usize) -> io::Result<&[u8]> {
+  pub fn get_slice(&mut self, length: usize) -> io::Result<&'a [u8]> {
     let start = self.position;
     let end = start + length;
     if end <= self.bytes.len() {
@@ -124,27 +50,27 @@ impl <'a> ByteReader<'a> {
 
 impl <'a> Reader for ByteReader<'a> {
 
-	fn read_1u(&mut self) -> io::Result<&[u8]> {
-		self.get_slice(1)
-	}
+  fn read_1u(&mut self) -> io::Result<&[u8]> {
+    self.get_slice(1)
+  }
 
-	fn read_2u(&mut self) -> io::Result<&[u8]> {
-		self.get_slice(2)
-	}
+  fn read_2u(&mut self) -> io::Result<&[u8]> {
+    self.get_slice(2)
+  }
 
-	fn read_4u(&mut self) -> io::Result<&[u8]> {
-		self.get_slice(4)
-	}
+  fn read_4u(&mut self) -> io::Result<&[u8]> {
+    self.get_slice(4)
+  }
 
-	fn read_8u(&mut self) -> io::Result<&[u8]> {
-		self.get_slice(8)
-	}
+  fn read_8u(&mut self) -> io::Result<&[u8]> {
+    self.get_slice(8)
+  }
 
-	fn read(&mut self, buffer: &mut [u8]) -> ReadResult {
+  fn read(&mut self, buffer: &mut [u8]) -> ReadResult {
     let content = self.get_slice(buffer.len())?;
-		buffer.clone_from_slice(content);
+    buffer.clone_from_slice(content);
     Ok(())
-	}
+  }
 
   fn read_up_to_u16(&mut self, length: u16) -> io::Result<&[u8]> {
     self.get_slice(length as usize)
@@ -152,114 +78,100 @@ impl <'a> Reader for ByteReader<'a> {
 
 }
 
+/// Creates the error reported for content not following the class file format
+pub fn invalid_data<S: Into<String>>(message: S) -> Error {
+  Error::new(ErrorKind::InvalidData, message.into())
+}
+
 pub fn to_u16(bytes: &[u8]) -> u16 {
-	((bytes[0] as u16) << 8) | (bytes[1] as u16)
+  ((bytes[0] as u16) << 8) | (bytes[1] as u16)
 }
 
 pub fn to_u32(bytes: &[u8]) -> u32 {
-	((bytes[0] as u32) << 24)
-	  | ((bytes[1] as u32) << 16)
-	  | ((bytes[2] as u32) << 8)
+  ((bytes[0] as u32) << 24)
+    | ((bytes[1] as u32) << 16)
+    | ((bytes[2] as u32) << 8)
     | (bytes[3] as u32)
 }
 
 pub fn to_u64(bytes: &[u8]) -> u64 {
-	((bytes[0] as u64) << 56)
-	  | ((bytes[1] as u64) << 48)
-	  | ((bytes[2] as u64) << 40)
+  ((bytes[0] as u64) << 56)
+    | ((bytes[1] as u64) << 48)
+    | ((bytes[2] as u64) << 40)
     | ((bytes[3] as u64) << 32)
-	  | ((bytes[4] as u64) << 24)
-	  | ((bytes[5] as u64) << 16)
-	  | ((bytes[6] as u64) << 8)
+    | ((bytes[4] as u64) << 24)
+    | ((bytes[5] as u64) << 16)
+    | ((bytes[6] as u64) << 8)
     | (bytes[7] as u64)
 }
 
 pub fn to_i32(bytes: &[u8]) -> i32 {
-  // let b: [u8; 4] = [
-  //   bytes[0],
-  //   bytes[1],
-  //   bytes[2],
-  //   bytes[3]
-  // ];
-  // unsafe {
-  //   transmute::<[u8; 4], i32>(b)
-  // }
   to_u32(bytes) as i32
 }
 
 pub fn to_i64(bytes: &[u8]) -> i64 {
-  // let b: [u8; 8] = [
-  //   bytes[0],
-  //   bytes[1],
-  //   bytes[2],
-  //   bytes[3],
-  //   bytes[4],
-  //   bytes[5],
-  //   bytes[6],
-  //   bytes[7]
-  // ];
-  // unsafe {
-  //   transmute::<[u8; 8], i64>(b)
-  // }
   to_u64(bytes) as i64
 }
 
-macro_rules! read_u8 {
-	($result: ident, $reader: tt, $indent:expr) => {
-		let $result: u8;
-    {
-      let bytes = $reader.read_1u()?;
-      print_bytes($indent, bytes);
-
-      $result = bytes[0];
-    }
-	};
+pub fn read_u8(reader: &mut dyn Reader) -> io::Result<u8> {
+  Ok(reader.read_1u()?[0])
 }
 
-macro_rules! read_u16 {
-	($result: ident, $reader: tt, $indent:expr) => {
-		let $result: u16;
-    {
-      let bytes = $reader.read_2u()?;
-      print_bytes($indent, bytes);
+pub fn read_i8(reader: &mut dyn Reader) -> io::Result<i8> {
+  Ok(reader.read_1u()?[0] as i8)
+}
 
-      $result = to_u16(bytes);
-    }
-	};
+pub fn read_u16(reader: &mut dyn Reader) -> io::Result<u16> {
+  Ok(to_u16(reader.read_2u()?))
 }
 
-macro_rules! read_u32 {
-	($result: ident, $reader: tt, $indent:expr) => {
-		let $result: u32;
-    {
-      let bytes = $reader.read_4u()?;
-      print_bytes($indent, bytes);
+pub fn read_i16(reader: &mut dyn Reader) -> io::Result<i16> {
+  Ok(to_u16(reader.read_2u()?) as i16)
+}
 
-      $result = to_u32(bytes);
-    }
-	};
+pub fn read_u32(reader: &mut dyn Reader) -> io::Result<u32> {
+  Ok(to_u32(reader.read_4u()?))
 }
 
-macro_rules! read_i32 {
-	($result: ident, $reader: tt, $indent:expr) => {
-		let $result: i32;
-    {
-      let bytes = $reader.read_4u()?;
-      print_bytes($indent, bytes);
+pub fn read_i32(reader: &mut dyn Reader) -> io::Result<i32> {
+  Ok(to_i32(reader.read_4u()?))
+}
 
-      $result = to_i32(bytes);
-    }
-	};
+pub fn read_i64(reader: &mut dyn Reader) -> io::Result<i64> {
+  Ok(to_i64(reader.read_8u()?))
 }
 
-macro_rules! read_i64 {
-	($result: ident, $reader: tt, $indent:expr) => {
-		let $result: i64;
-    {
-      let bytes = $reader.read_8u()?;
-      print_bytes($indent, bytes);
+/// Reads a list of elements, preceded by their count as an u16
+pub fn read_list<T, F>(reader: &mut dyn Reader, mut read_element: F) -> io::Result<Vec<T>>
+  where F: FnMut(&mut dyn Reader) -> io::Result<T> {
+  let count = read_u16(reader)?;
+  let mut elements = Vec::with_capacity(count as usize);
+  for _i in 0..count {
+    elements.push(read_element(reader)?);
+  }
+  Ok(elements)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_read_numbers() {
+    let bytes = [0xff, 0xca, 0xfe, 0xff, 0xff, 0xff, 0xfe];
+    let mut reader = ByteReader::new(&bytes);
+    assert_eq!(read_i8(&mut reader).unwrap(), -1);
+    assert_eq!(read_u16(&mut reader).unwrap(), 0xcafe);
+    assert_eq!(read_i32(&mut reader).unwrap(), -2);
+    assert!(reader.is_empty());
+    assert_eq!(read_u8(&mut reader).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+  }
 
-      $result = to_i64(bytes);
-    }
-	};
+  #[test]
+  fn test_read_list() {
+    let bytes = [0, 2, 0, 5, 0, 7];
+    let mut reader = ByteReader::new(&bytes);
+    let values = read_list(&mut reader, read_u16).unwrap();
+    assert_eq!(values, vec![5, 7]);
+  }
 }
@@ -0,0 +1,141 @@
+use std::io;
+
+use reader::{Reader, invalid_data, read_list, read_u8, read_u16};
+
+/// Type of a local variable or of a stack element, as checked by the verifier
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationType {
+  Top,
+  Integer,
+  Float,
+  Double,
+  Long,
+  Null,
+  UninitializedThis,
+  /// Index of the class of the object
+  Object(u16),
+  /// Offset of the `new` instruction creating the object
+  Uninitialized(u16)
+}
+
+/// Frame of a StackMapTable, describing the state at an offset from the previous frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackMapFrame {
+  /// Same locals as the previous frame, with an empty stack
+  Same { offset_delta: u16 },
+  /// Same locals as the previous frame, with one element on the stack
+  SameLocals1StackItem { offset_delta: u16, stack: VerificationType },
+  /// Previous locals without the last ones, with an empty stack
+  Chop { offset_delta: u16, count: u8 },
+  /// Previous locals with additional ones, with an empty stack
+  Append { offset_delta: u16, locals: Vec<VerificationType> },
+  Full { offset_delta: u16, locals: Vec<VerificationType>, stack: Vec<VerificationType> }
+}
+
+impl StackMapFrame {
+  pub fn offset_delta(&self) -> u16 {
+    match *self {
+      StackMapFrame::Same { offset_delta }
+      | StackMapFrame::SameLocals1StackItem { offset_delta, .. }
+      | StackMapFrame::Chop { offset_delta, .. }
+      | StackMapFrame::Append { offset_delta, .. }
+      | StackMapFrame::Full { offset_delta, .. } => offset_delta
+    }
+  }
+}
+
+fn read_verification_type(reader: &mut dyn Reader) -> io::Result<VerificationType> {
+  let tag = read_u8(reader)?;
+  let value = match tag {
+    0 => VerificationType::Top,
+    1 => VerificationType::Integer,
+    2 => VerificationType::Float,
+    3 => VerificationType::Double,
+    4 => VerificationType::Long,
+    5 => VerificationType::Null,
+    6 => VerificationType::UninitializedThis,
+    7 => VerificationType::Object(read_u16(reader)?),
+    8 => VerificationType::Uninitialized(read_u16(reader)?),
+    _ => return Err(invalid_data(format!("Unknown verification type {}", tag)))
+  };
+  Ok(value)
+}
+
+fn read_frame(reader: &mut dyn Reader) -> io::Result<StackMapFrame> {
+  let frame_type = read_u8(reader)?;
+  let frame = match frame_type {
+    0 ..= 63 => StackMapFrame::Same { offset_delta: frame_type as u16 },
+    64 ..= 127 => StackMapFrame::SameLocals1StackItem {
+      offset_delta: (frame_type - 64) as u16,
+      stack: read_verification_type(reader)?
+    },
+    247 => StackMapFrame::SameLocals1StackItem {
+      offset_delta: read_u16(reader)?,
+      stack: read_verification_type(reader)?
+    },
+    248 ..= 250 => StackMapFrame::Chop {
+      offset_delta: read_u16(reader)?,
+      count: 251 - frame_type
+    },
+    251 => StackMapFrame::Same { offset_delta: read_u16(reader)? },
+    252 ..= 254 => {
+      let offset_delta = read_u16(reader)?;
+      let mut locals = vec![];
+      for _i in 251..frame_type {
+        locals.push(read_verification_type(reader)?);
+      }
+      StackMapFrame::Append { offset_delta: offset_delta, locals: locals }
+    },
+    255 => StackMapFrame::Full {
+      offset_delta: read_u16(reader)?,
+      locals: read_list(reader, read_verification_type)?,
+      stack: read_list(reader, read_verification_type)?
+    },
+    _ => return Err(invalid_data(format!("Reserved frame type {}", frame_type)))
+  };
+  Ok(frame)
+}
+
+pub fn read(reader: &mut dyn Reader) -> io::Result<Vec<StackMapFrame>> {
+  read_list(reader, read_frame)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use reader::ByteReader;
+
+  #[test]
+  fn test_read_frames() {
+    let bytes = [
+      0, 5,
+      3,
+      65, 7, 0, 4,
+      249, 0, 10,
+      253, 0, 2, 1, 8, 0, 9,
+      255, 0, 20, 0, 1, 6, 0, 0
+    ];
+    let frames = read(&mut ByteReader::new(&bytes)).unwrap();
+    assert_eq!(frames, vec![
+      StackMapFrame::Same { offset_delta: 3 },
+      StackMapFrame::SameLocals1StackItem { offset_delta: 1, stack: VerificationType::Object(4) },
+      StackMapFrame::Chop { offset_delta: 10, count: 2 },
+      StackMapFrame::Append {
+        offset_delta: 2,
+        locals: vec![VerificationType::Integer, VerificationType::Uninitialized(9)]
+      },
+      StackMapFrame::Full {
+        offset_delta: 20,
+        locals: vec![VerificationType::UninitializedThis],
+        stack: vec![]
+      }
+    ]);
+    assert_eq!(frames[4].offset_delta(), 20);
+  }
+
+  #[test]
+  fn test_reject_reserved_frames() {
+    let bytes = [0, 1, 128];
+    assert!(read(&mut ByteReader::new(&bytes)).is_err());
+  }
+}
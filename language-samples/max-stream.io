@@ -1,12 +1,40 @@
-// Returns the max value of the provided stream
+// Returns the max value of the provided stream, as seen so far
 />> 1: [1 3 -4 7 2]
-/<< 1: [7]
+/<< 1: [1 3 3 7 7]
 
 Node #main
 ============
-IN:1 -> 1
+IN:1 -> 1, #echo:1 -> 2
 -----------
-MOV <1, >1
+// The first value is the max
+MOV <1, ACC
+MOV ACC, >1
+LOOP:
+// Compare the next value to the max, kept in ACC
+SAV
+MOV <1, >2
+NEG
+ADD <2
+JGZ GREATER
+MOV <2, NIL
+SWP
+JMP PRINT
+GREATER:
+MOV <2, ACC
+PRINT:
+MOV ACC, >1
+JMP LOOP
 ----------
-1 -> OUT:1
+1 -> OUT:1, 2 -> #echo:1
+===========
+
+Node #echo
+===========
+#main:2 -> 1
+-------------
+MOV <1, ACC
+MOV ACC, >1
+MOV ACC, >1
+-----------
+1 -> #main:2
 ===========
@@ -12,7 +12,8 @@ MOV <1, >2
 MOV <2, >3
 MOV <3, ACC
 SUB <4
-JLZ LEFT
+// Output the left value when it is the greatest
+JGZ LEFT
 MOV <4, >1
 MOV <3, NIL
 JMP START
@@ -0,0 +1,310 @@
+//! Module parsing the command line of the compiler.
+
+use checker::{Code, Severity};
+
+use crate::report::MessageFormat;
+
+pub const USAGE: &str = "Usage:
+  app <command> [options] <file>...
+
+Commands:
+  build   Compiles programs
+  check   Checks programs without generating any file
+  run     Simulates a program, reading `;`-separated inputs from stdin
+  test    Runs the test case embedded in programs
+  fmt     Formats the source of programs
+
+Options:
+  --out-dir <dir>           Directory receiving compiled files (default: out)
+  --package <name>          Name of the compiled program, naming its output files
+                            and its package for java (default: file name)
+  --target <target>         Platform to compile for, java or llvm (default: java)
+  -C <key>=<value>          Sets an option of the target, like mode=compiled for java
+                            or max-ticks for llvm. output=jar packages java programs
+                            as executable JARs, their libraries set with class-path.
+                            class-name=<a.b.C> sets the main class of java programs,
+                            instead of the class in the package of the program
+  -W <lint>                 Reports a lint as a warning
+  -D <lint>                 Reports a lint as an error, `warnings` denying all lints
+  --message-format <format> Format of the diagnostics: human, json or sarif
+  --check                   With fmt, only checks that files are formatted
+  -q, --quiet               Only prints errors
+  -h, --help                Prints this help message
+
+Exit codes:
+  0 on success, 1 for invalid programs, failed tests or unformatted files,
+  2 for invalid command lines or unreadable files";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Build,
+    Check,
+    Run,
+    Test,
+    Fmt,
+}
+
+impl Command {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "build" => Ok(Command::Build),
+            "check" => Ok(Command::Check),
+            "run" => Ok(Command::Run),
+            "test" => Ok(Command::Test),
+            "fmt" => Ok(Command::Fmt),
+            _ => Err(format!("Unknown command {}", value)),
+        }
+    }
+}
+
+/// Options deciding how diagnostics are reported
+#[derive(Debug, PartialEq)]
+pub struct ReportOptions {
+    pub message_format: MessageFormat,
+    pub quiet: bool,
+    /// Severity of the lints, applied in order. `None` stands for all lints.
+    pub lints: Vec<(Option<Code>, Severity)>,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        ReportOptions {
+            message_format: MessageFormat::Human,
+            quiet: false,
+            lints: vec![],
+        }
+    }
+}
+
+impl ReportOptions {
+    /// Whether progress messages are printed
+    pub fn is_verbose(&self) -> bool {
+        !self.quiet && self.message_format.is_human()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub files: Vec<String>,
+    pub out_dir: String,
+    pub package: Option<String>,
+    pub target: String,
+    /// Options of the target backend, as key and value
+    pub target_options: Vec<(String, String)>,
+    /// Whether fmt only checks the files
+    pub check: bool,
+    pub report: ReportOptions,
+}
+
+/// Parsed command line
+#[derive(Debug, PartialEq)]
+pub enum Invocation {
+    Help,
+    Command(Options),
+}
+
+fn parse_lint(value: &str) -> Result<Option<Code>, String> {
+    if value == "warnings" {
+        return Ok(None);
+    }
+    match Code::from_name(value) {
+        Some(code) if code.is_lint() => Ok(Some(code)),
+        Some(_) => Err(format!("{} is an error and not a lint", value)),
+        None => Err(format!("Unknown lint {}", value)),
+    }
+}
+
+/// Reads the value of an option, given as `--option=value`, `-Ovalue` or in the next argument
+fn option_value<'a, I: Iterator<Item = &'a String>>(
+    arg: &str,
+    name: &str,
+    args: &mut I,
+) -> Result<Option<String>, String> {
+    if arg == name {
+        args.next()
+            .cloned()
+            .map(Some)
+            .ok_or_else(|| format!("Missing value for option {}", name))
+    } else if name.starts_with("--") {
+        Ok(arg
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('='))
+            .map(String::from))
+    } else {
+        Ok(arg.strip_prefix(name).map(String::from))
+    }
+}
+
+/// Parses the arguments of the program, without the program name
+pub fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    let mut args = args.iter();
+    let command = match args.next().map(String::as_str) {
+        None => return Err(String::from("Missing command")),
+        Some("-h") | Some("--help") | Some("help") => return Ok(Invocation::Help),
+        Some(value) => Command::parse(value)?,
+    };
+
+    let mut options = Options {
+        command,
+        files: vec![],
+        out_dir: String::from("out"),
+        package: None,
+        target: String::from("java"),
+        target_options: vec![],
+        check: false,
+        report: ReportOptions::default(),
+    };
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Invocation::Help);
+        } else if arg == "-q" || arg == "--quiet" {
+            options.report.quiet = true;
+        } else if arg == "--check" {
+            options.check = true;
+        } else if let Some(value) = option_value(arg, "--out-dir", &mut args)? {
+            options.out_dir = value;
+        } else if let Some(value) = option_value(arg, "--package", &mut args)? {
+            options.package = Some(value);
+        } else if let Some(value) = option_value(arg, "--target", &mut args)? {
+            let targets = generator::targets();
+            if !targets.contains(&value.as_str()) {
+                return Err(format!(
+                    "Unsupported target {}, expected one of {}",
+                    value,
+                    targets.join(", ")
+                ));
+            }
+            options.target = value;
+        } else if let Some(value) = option_value(arg, "-C", &mut args)? {
+            let mut parts = value.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(option)) if !key.is_empty() => options
+                    .target_options
+                    .push((String::from(key), String::from(option))),
+                _ => {
+                    return Err(format!(
+                        "Invalid target option {}, expected <key>=<value>",
+                        value
+                    ))
+                }
+            }
+        } else if let Some(value) = option_value(arg, "--message-format", &mut args)? {
+            options.report.message_format = MessageFormat::parse(&value)?;
+        } else if let Some(value) = option_value(arg, "-W", &mut args)? {
+            let lint = parse_lint(&value)?;
+            options.report.lints.push((lint, Severity::Warning));
+        } else if let Some(value) = option_value(arg, "-D", &mut args)? {
+            let lint = parse_lint(&value)?;
+            options.report.lints.push((lint, Severity::Error));
+        } else if arg.starts_with('-') {
+            return Err(format!("Unknown option {}", arg));
+        } else {
+            options.files.push(arg.clone());
+        }
+    }
+
+    if options.files.is_empty() {
+        return Err(String::from("Missing file to process"));
+    }
+    if options.files.len() > 1 && (options.command == Command::Run || options.package.is_some()) {
+        return Err(String::from("Only one file can be run or given a package"));
+    }
+    if options.check && options.command != Command::Fmt {
+        return Err(String::from("--check is only supported by fmt"));
+    }
+    Ok(Invocation::Command(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args).map(|invocation| match invocation {
+            Invocation::Command(options) => options,
+            Invocation::Help => panic!("Unexpected help"),
+        })
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        let options = parse(&["build", "a.io"]).unwrap();
+        assert_eq!(options.command, Command::Build);
+        assert_eq!(options.files, vec!["a.io"]);
+        assert_eq!(options.out_dir, "out");
+        assert_eq!(options.package, None);
+        assert_eq!(options.target, "java");
+        assert!(options.target_options.is_empty());
+        assert_eq!(options.report, ReportOptions::default());
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse(&[
+            "build",
+            "--out-dir",
+            "target",
+            "--package=sum",
+            "-q",
+            "--message-format",
+            "json",
+            "a.io",
+        ])
+        .unwrap();
+        assert_eq!(options.out_dir, "target");
+        assert_eq!(options.package, Some(String::from("sum")));
+        assert!(options.report.quiet);
+        assert_eq!(options.report.message_format, MessageFormat::Json);
+    }
+
+    #[test]
+    fn test_parse_target_options() {
+        let options = parse(&["build", "--target=llvm", "-C", "max-ticks=10", "a.io"]).unwrap();
+        assert_eq!(options.target, "llvm");
+        assert_eq!(
+            options.target_options,
+            vec![(String::from("max-ticks"), String::from("10"))]
+        );
+
+        assert!(parse(&["build", "-C", "max-ticks", "a.io"]).is_err());
+        assert!(parse(&["build", "-C=1", "a.io"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_lint_levels() {
+        let options = parse(&["check", "-D", "warnings", "-Wunused-label", "a.io"]).unwrap();
+        assert_eq!(
+            options.report.lints,
+            vec![
+                (None, Severity::Error),
+                (Some(Code::UnusedLabel), Severity::Warning)
+            ]
+        );
+
+        assert!(parse(&["check", "-D", "undefined-label", "a.io"]).is_err());
+        assert!(parse(&["check", "-D", "unknown", "a.io"]).is_err());
+    }
+
+    #[test]
+    fn test_reject_invalid_command_lines() {
+        assert!(parse(&["compile", "a.io"]).is_err());
+        assert!(parse(&["build"]).is_err());
+        assert!(parse(&["build", "--out-dir"]).is_err());
+        assert!(parse(&["build", "--unknown", "a.io"]).is_err());
+        assert!(parse(&["build", "--target", "wasm", "a.io"]).is_err());
+        assert!(parse(&["run", "a.io", "b.io"]).is_err());
+        assert!(parse(&["check", "--check", "a.io"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_help() {
+        assert_eq!(parse_args(&[String::from("help")]), Ok(Invocation::Help));
+        assert!(parse_args(&[]).is_err());
+        assert_eq!(
+            parse_args(&[String::from("build"), String::from("--help")]),
+            Ok(Invocation::Help)
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Module formatting the source of programs.
+//!
+//! Formatting only changes the layout of the lines: indentation, spaces between
+//! tokens, blank lines and the width of the node separators. Comments are kept.
+//! The formatted source is parsed again to make sure the program is unchanged.
+
+use language::syntax::{NodeBlock, Program};
+use parser::parse;
+
+/// Splits a line between its code and its comment, if any
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    match line.find("//") {
+        Some(idx) => (&line[..idx], Some(&line[idx..])),
+        None => (line, None),
+    }
+}
+
+/// Separates tokens with single spaces, with a space after commas and around arrows
+fn normalize_code(code: &str) -> String {
+    let spaced = code.replace("->", " -> ").replace(',', ", ");
+    spaced
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(" ,", ",")
+}
+
+/// Detects the `===` and `---` lines, giving their character
+fn separator_char(code: &str) -> Option<char> {
+    let code = code.trim();
+    ['=', '-']
+        .iter()
+        .cloned()
+        .find(|c| code.len() >= 3 && code.chars().all(|x| x == *c))
+}
+
+/// Formats the lines of a source, without checking the result
+fn format_lines(source: &str) -> String {
+    let mut lines: Vec<String> = vec![];
+    // Separators are as wide as the header of their node
+    let mut width = 3;
+    for line in source.lines() {
+        let (code, comment) = split_comment(line);
+        let formatted = match separator_char(code) {
+            Some(c) if comment.is_none() => c.to_string().repeat(width),
+            _ => {
+                let code = normalize_code(code);
+                if code.starts_with("Node ") {
+                    width = code.len().max(3);
+                }
+                match comment {
+                    Some(comment) if code.is_empty() => comment.trim_end().to_string(),
+                    Some(comment) => format!("{} {}", code, comment.trim_end()),
+                    None => code,
+                }
+            }
+        };
+        let is_blank = formatted.is_empty();
+        let after_blank = lines.last().map(String::is_empty).unwrap_or(true);
+        if !(is_blank && after_blank) {
+            lines.push(formatted);
+        }
+    }
+    while lines.last().map(String::as_str) == Some("") {
+        lines.pop();
+    }
+
+    let mut formatted = lines.join("\n");
+    formatted.push('\n');
+    formatted
+}
+
+fn same_node(a: &NodeBlock, b: &NodeBlock) -> bool {
+    a.node == b.node
+        && a.instructions == b.instructions
+        && a.inputs.len() == b.inputs.len()
+        && a.inputs
+            .iter()
+            .zip(&b.inputs)
+            .all(|(x, y)| x.from == y.from && x.to == y.to)
+        && a.outputs.len() == b.outputs.len()
+        && a.outputs
+            .iter()
+            .zip(&b.outputs)
+            .all(|(x, y)| x.from == y.from && x.to == y.to)
+}
+
+/// Compares two programs, ignoring the locations of their elements
+fn same_program(a: &Program, b: &Program) -> bool {
+    let same_tests = match (&a.tests, &b.tests) {
+        (Some(x), Some(y)) => x.ins == y.ins && x.outs == y.outs,
+        (None, None) => true,
+        _ => false,
+    };
+    same_tests
+        && a.nodes.len() == b.nodes.len()
+        && a.nodes.iter().zip(&b.nodes).all(|(x, y)| same_node(x, y))
+}
+
+/// Formats the source of a program.
+///
+/// This fails if the source cannot be parsed.
+pub fn format_source(source: &str) -> Result<String, String> {
+    let program = parse(source.as_bytes()).map_err(|failure| {
+        format!(
+            "Cannot format a program with syntax errors, starting at {}",
+            failure.errors[0].span
+        )
+    })?;
+    let formatted = format_lines(source);
+    match parse(formatted.as_bytes()) {
+        Ok(ref result) if same_program(&program, result) => Ok(formatted),
+        _ => Err(String::from("Formatting would change the program")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_code() {
+        assert_eq!(normalize_code("  MOV  <1 ,>2 "), "MOV <1, >2");
+        assert_eq!(normalize_code("IN:1->1,#a:2 -> 2"), "IN:1 -> 1, #a:2 -> 2");
+        assert_eq!(normalize_code("/>> 1: [1  -3]"), "/>> 1: [1 -3]");
+    }
+
+    #[test]
+    fn test_format_program() {
+        let source = "
+
+/>> 1:  [1 -3]
+/<< 1: [2 -6]
+Node  #double
+=====
+IN:1->1
+-------------
+  // Double the value
+  START:  MOV <1,ACC   // read
+ADD ACC
+
+
+MOV ACC , >1
+---
+1 -> OUT:1
+=====
+
+
+";
+        let expected = "/>> 1: [1 -3]
+/<< 1: [2 -6]
+Node #double
+============
+IN:1 -> 1
+------------
+// Double the value
+START: MOV <1, ACC // read
+ADD ACC
+
+MOV ACC, >1
+------------
+1 -> OUT:1
+============
+";
+        assert_eq!(format_source(source), Ok(String::from(expected)));
+    }
+
+    #[test]
+    fn test_keep_formatted_samples() {
+        for sample in &["sum", "increment", "double", "diffs"] {
+            let filename = format!("../../language-samples/{}.io", sample);
+            let source = std::fs::read_to_string(&filename).unwrap();
+            let formatted = format_source(&source).unwrap();
+            assert_eq!(format_source(&formatted), Ok(formatted), "{}", sample);
+        }
+    }
+
+    #[test]
+    fn test_reject_invalid_programs() {
+        assert!(format_source("Node #a\n===\nMOV\n===\n").is_err());
+    }
+}
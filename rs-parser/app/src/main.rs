@@ -1,95 +1,383 @@
+mod cli;
+mod formatting;
+mod report;
+mod run;
+mod testing;
+
 use std::env;
-use std::fs::File;
-use std::io::prelude::*;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::process;
 
+use checker::CheckResult;
+use cli::{Command, Invocation, Options, ReportOptions};
+use generator::{Backend, GenerateError};
 use language::syntax::Program;
-use parser::{parse, ParsingResult};
+use parser::{parse, ParseFailure};
+use report::DiagnosticLog;
+
+/// Failure of a command, deciding the exit code of the compiler
+#[derive(Debug, PartialEq)]
+enum Failure {
+    /// Invalid program, failed test or unformatted file
+    Program(String),
+    /// Unreadable input or unwritable output
+    Usage(String),
+}
 
-fn parse_file(filename: &str) -> ParsingResult {
-    println!("Compiling {}", filename);
-    let mut f = File::open(filename).expect("file not found");
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Program(_) => 1,
+            Failure::Usage(_) => 2,
+        }
+    }
 
-    let mut contents = String::new();
-    // Could use buffering to read larger files
-    // let mut buf_reader = BufReader::new(file);
-    // let mut contents = String::new();
-    // buf_reader.read_to_string(&mut contents)?;
-    f.read_to_string(&mut contents)
-        .expect("something went wrong reading the file");
+    fn message(&self) -> &str {
+        match self {
+            Failure::Program(ref message) | Failure::Usage(ref message) => message,
+        }
+    }
+}
 
-    parse(contents.as_bytes())
+fn read_source(filename: &str) -> Result<String, Failure> {
+    fs::read_to_string(filename)
+        .map_err(|error| Failure::Usage(format!("Cannot read file: {}", error)))
 }
 
-fn check_file(result: Program) -> Result<Program, String> {
-    let check_result = checker::check(&result);
-    check_result.print_report();
-    if check_result.has_errors() {
-        Err(String::from("Exit after errors"))
+fn apply_lint_levels(check_result: &mut CheckResult, options: &ReportOptions) {
+    for (code, severity) in &options.lints {
+        check_result.set_lint_level(*code, *severity);
+    }
+}
+
+fn print_report(
+    check_result: &CheckResult,
+    filename: &str,
+    options: &ReportOptions,
+    log: &mut DiagnosticLog,
+) {
+    if options.message_format.is_human() {
+        if !options.quiet || check_result.has_errors() {
+            check_result.print_report(filename);
+        }
     } else {
-        Ok(result)
+        log.add(filename, check_result.diagnostics());
     }
 }
 
-fn process_input(filename: &str) -> Result<Program, String> {
-    parse_file(filename)
-        .map_err(|_| String::from("Failed parsing"))
-        .map(postprocessor::process)
-        .and_then(check_file)
+/// Completes the mappings of a program before checking it.
+/// The program is not returned when mappings reference undefined nodes.
+fn analyse_program(program: Program) -> (Option<Program>, CheckResult) {
+    match postprocessor::process(program) {
+        Ok(program) => {
+            let check_result = checker::check(&program);
+            (Some(program), check_result)
+        }
+        Err(diagnostics) => {
+            let mut check_result = CheckResult::default();
+            for diagnostic in diagnostics {
+                check_result.add(diagnostic);
+            }
+            (None, check_result)
+        }
+    }
 }
 
-fn create_output(result: Program, filename: &str, target_dir: &str) -> Result<(), String> {
-    generator::generate(result, filename, target_dir)
+fn check_program(
+    program: Program,
+    filename: &str,
+    options: &ReportOptions,
+    log: &mut DiagnosticLog,
+) -> Result<Program, String> {
+    let (program, mut check_result) = analyse_program(program);
+    apply_lint_levels(&mut check_result, options);
+    print_report(&check_result, filename, options, log);
+    match program {
+        Some(program) if !check_result.has_errors() => Ok(program),
+        _ => Err(format!(
+            "{} errors in the program",
+            check_result.error_count()
+        )),
+    }
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    let filename = &args[1];
-    let target_dir = &args[2];
-    let result =
-        process_input(filename).and_then(|result| create_output(result, filename, target_dir));
-    if result.is_ok() {
-        println!("File {} compiled with success to {}", filename, target_dir);
+/// Checks the nodes of a program that could be parsed.
+/// Nodes that failed to parse are missing, so the mappings to them are not checked.
+fn check_parsed_nodes(program: Program) -> CheckResult {
+    let program = postprocessor::process_partial(program);
+    checker::check_partial(&program)
+}
+
+/// Reports the syntax errors, still checking the nodes that could be parsed
+fn report_parse_failure(
+    failure: ParseFailure,
+    filename: &str,
+    options: &ReportOptions,
+    log: &mut DiagnosticLog,
+) -> String {
+    let mut check_result = check_parsed_nodes(*failure.program);
+    apply_lint_levels(&mut check_result, options);
+    if options.message_format.is_human() {
+        for error in &failure.errors {
+            eprintln!("{}:{}", filename, error);
+        }
+        print_report(&check_result, filename, options, log);
     } else {
-        panic!(
-            "Error in file {} compilation: {}",
+        let diagnostics: Vec<_> = failure
+            .errors
+            .iter()
+            .map(report::syntax_diagnostic)
+            .chain(check_result.diagnostics().iter().cloned())
+            .collect();
+        log.add(filename, &diagnostics);
+    }
+    format!("{} syntax errors", failure.errors.len())
+}
+
+fn process_input(
+    filename: &str,
+    options: &ReportOptions,
+    log: &mut DiagnosticLog,
+) -> Result<Program, Failure> {
+    let source = read_source(filename)?;
+    if options.is_verbose() {
+        println!("Compiling {}", filename);
+    }
+    parse(source.as_bytes())
+        .map_err(|failure| report_parse_failure(failure, filename, options, log))
+        .and_then(|program| check_program(program, filename, options, log))
+        .map_err(Failure::Program)
+}
+
+/// Creates the backend of the target, configured with the options of the command line
+fn create_backend(options: &Options) -> Result<Box<dyn Backend>, String> {
+    let mut backend = generator::create_backend(&options.target)?;
+    for (key, value) in &options.target_options {
+        backend.set_option(key, value)?;
+    }
+    Ok(backend)
+}
+
+fn build_file(
+    filename: &str,
+    options: &Options,
+    log: &mut DiagnosticLog,
+    backend: &dyn Backend,
+) -> Result<(), Failure> {
+    let program = process_input(filename, &options.report, log)?;
+    let name = options
+        .package
+        .clone()
+        .unwrap_or_else(|| generator::program_name(filename));
+    let output_file = backend
+        .generate(&program, &name, filename, Path::new(&options.out_dir))
+        .map_err(|error| match error {
+            GenerateError::Program(message) => Failure::Program(message),
+            GenerateError::Output(message) => Failure::Usage(message),
+        })?;
+    // Scripts read the location of the program from this message
+    if options.report.is_verbose() {
+        println!(
+            "File {} compiled with success to {}",
             filename,
-            result.unwrap_err()
+            output_file.display()
         );
     }
+    Ok(())
+}
+
+fn run_file(filename: &str, options: &Options, log: &mut DiagnosticLog) -> Result<(), Failure> {
+    let program = process_input(filename, &options.report, log)?;
+    let stdin = io::stdin();
+    let lines = stdin
+        .lock()
+        .lines()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| Failure::Usage(format!("Cannot read inputs: {}", error)))?;
+    let outputs =
+        run::run_program(&program, &lines, testing::MAX_CYCLES).map_err(Failure::Program)?;
+    for output in outputs {
+        println!("{}", output);
+    }
+    Ok(())
+}
+
+fn test_file(
+    filename: &str,
+    options: &ReportOptions,
+    log: &mut DiagnosticLog,
+) -> Result<testing::TestReport, Failure> {
+    process_input(filename, options, log).and_then(|program| {
+        testing::run_test(&program, testing::MAX_CYCLES).map_err(Failure::Program)
+    })
+}
+
+fn format_file(filename: &str, options: &Options) -> Result<(), Failure> {
+    let source = read_source(filename)?;
+    let formatted = formatting::format_source(&source).map_err(Failure::Program)?;
+    if formatted == source {
+        return Ok(());
+    }
+    if options.check {
+        return Err(Failure::Program(String::from("File is not formatted")));
+    }
+    fs::write(filename, formatted)
+        .map_err(|error| Failure::Usage(format!("Cannot write file: {}", error)))?;
+    if options.report.is_verbose() {
+        println!("Formatted {}", filename);
+    }
+    Ok(())
+}
+
+fn execute(
+    filename: &str,
+    options: &Options,
+    log: &mut DiagnosticLog,
+    backend: &dyn Backend,
+) -> Result<(), Failure> {
+    match options.command {
+        Command::Build => build_file(filename, options, log, backend),
+        Command::Check => process_input(filename, &options.report, log).map(|_| ()),
+        Command::Run => run_file(filename, options, log),
+        Command::Test => {
+            let report = test_file(filename, &options.report, log)?;
+            if !report.is_success() {
+                return Err(Failure::Program(report.to_string()));
+            }
+            // Machine-readable formats keep stdout for the diagnostics
+            if options.report.is_verbose() {
+                println!("{}", report);
+            }
+            Ok(())
+        }
+        Command::Fmt => format_file(filename, options),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match cli::parse_args(&args) {
+        Ok(Invocation::Command(options)) => options,
+        Ok(Invocation::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, cli::USAGE);
+            process::exit(2);
+        }
+    };
+    let backend = create_backend(&options).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(2);
+    });
+
+    let mut log = DiagnosticLog::new(options.report.message_format);
+    let mut exit_code = 0;
+    for filename in &options.files {
+        if let Err(failure) = execute(filename, &options, &mut log, backend.as_ref()) {
+            eprintln!("Error in file {}: {}", filename, failure.message());
+            exit_code = exit_code.max(failure.exit_code());
+        }
+    }
+    log.finish();
+    process::exit(exit_code);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use report::MessageFormat;
+
+    fn human_log() -> DiagnosticLog {
+        DiagnosticLog::new(MessageFormat::Human)
+    }
+
+    fn process_input_file(filename: &str) -> Result<Program, Failure> {
+        process_input(filename, &ReportOptions::default(), &mut human_log())
+    }
+
     #[test]
     fn test_sample_sum() {
-        let res = process_input("../../language-samples/sum.io");
+        let res = process_input_file("../../language-samples/sum.io");
         assert_eq!(res.is_ok(), true);
     }
 
     #[test]
     fn test_sample_increment() {
-        let res = process_input("../../language-samples/increment.io");
+        let res = process_input_file("../../language-samples/increment.io");
         assert_eq!(res.is_ok(), true);
     }
 
     #[test]
     fn test_sample_max() {
-        let res = process_input("../../language-samples/max.io");
+        let res = process_input_file("../../language-samples/max.io");
         assert_eq!(res.is_ok(), true);
     }
 
     #[test]
     fn test_sample_double() {
-        let res = process_input("../../language-samples/double.io");
+        let res = process_input_file("../../language-samples/double.io");
         assert_eq!(res.is_ok(), true);
     }
 
     #[test]
     fn test_sample_diffs() {
-        let res = process_input("../../language-samples/diffs.io");
+        let res = process_input_file("../../language-samples/diffs.io");
         assert_eq!(res.is_ok(), true);
     }
+
+    #[test]
+    fn test_report_broken_referenced_nodes() {
+        let source = "/>> 1: [1]\n/<< 1: [1]\n\n\
+            Node #a\n=======\nIN:1 -> 1\n-------\nMOV <1, >1\n-------\n1 -> #b:1\n=======\n\n\
+            Node #b\n=======\n#a:1 -> 1\n-------\nMOV <1, >1 >2\n-------\n1 -> OUT:1\n=======\n";
+        let failure = parse(source.as_bytes()).unwrap_err();
+        assert_eq!(failure.program.nodes.len(), 1);
+        // Mappings of #a to the broken node #b, and outputs of #b in the test, are not reported
+        let check_result = check_parsed_nodes(*failure.program);
+        assert_eq!(check_result.diagnostics(), &[]);
+
+        let failure = parse(source.as_bytes()).unwrap_err();
+        let message = report_parse_failure(
+            failure,
+            "broken.io",
+            &ReportOptions::default(),
+            &mut human_log(),
+        );
+        assert_eq!(message, "1 syntax errors");
+    }
+
+    #[test]
+    fn test_report_unknown_nodes() {
+        let source =
+            "Node #a\n=======\nIN:1 -> 1\n-------\nMOV <1, >1\n-------\n1 -> #zz:1\n=======\n";
+        let program = parse(source.as_bytes()).unwrap();
+        let error = check_program(
+            program,
+            "unknown.io",
+            &ReportOptions::default(),
+            &mut human_log(),
+        )
+        .unwrap_err();
+        assert_eq!(error, "1 errors in the program");
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let failure = process_input_file("../../language-samples/missing.io").unwrap_err();
+        assert_eq!(failure.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_run_sample_tests() {
+        for sample in &["sum", "increment", "max", "max-stream", "double", "diffs"] {
+            let filename = format!("../../language-samples/{}.io", sample);
+            let report = test_file(&filename, &ReportOptions::default(), &mut human_log()).unwrap();
+            assert!(report.is_success(), "{}: {}", sample, report);
+        }
+    }
 }
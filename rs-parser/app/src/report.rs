@@ -0,0 +1,245 @@
+//! Module printing the diagnostics of a compilation for other tools.
+//!
+//! JSON output prints one object per line, while SARIF output prints
+//! a single SARIF 2.1.0 log, as consumed by CI annotations.
+
+use checker::{Code, Diagnostic};
+use parser::ParseError;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageFormat {
+    /// Readable report, as printed by the checker
+    Human,
+    Json,
+    Sarif,
+}
+
+impl MessageFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            "sarif" => Ok(MessageFormat::Sarif),
+            _ => Err(format!(
+                "Unknown message format {}, expected human, json or sarif",
+                value
+            )),
+        }
+    }
+
+    pub fn is_human(self) -> bool {
+        self == MessageFormat::Human
+    }
+}
+
+/// Converts a syntax error into a diagnostic
+pub fn syntax_diagnostic(error: &ParseError) -> Diagnostic {
+    Diagnostic::error(Code::Syntax, error.message()).at(Some(error.span))
+}
+
+/// Escapes a value as a JSON string, quotes included
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn json_option<T, F: Fn(T) -> String>(value: Option<T>, f: F) -> String {
+    value.map(f).unwrap_or_else(|| String::from("null"))
+}
+
+/// Formats a diagnostic as a single-line JSON object
+pub fn to_json(filename: &str, diagnostic: &Diagnostic) -> String {
+    format!(
+        "{{\"file\":{},\"code\":{},\"name\":{},\"severity\":{},\"message\":{},\"node\":{},\"line\":{},\"column\":{}}}",
+        json_string(filename),
+        json_string(diagnostic.code.id()),
+        json_string(diagnostic.code.name()),
+        json_string(&diagnostic.severity.to_string()),
+        json_string(&diagnostic.message),
+        json_option(diagnostic.node.as_ref(), |node| json_string(node)),
+        json_option(diagnostic.span, |span| span.line.to_string()),
+        json_option(diagnostic.span, |span| span.column.to_string()),
+    )
+}
+
+fn sarif_rule(code: Code) -> String {
+    format!(
+        "{{\"id\":{},\"name\":{}}}",
+        json_string(code.id()),
+        json_string(code.name())
+    )
+}
+
+fn sarif_result(filename: &str, diagnostic: &Diagnostic) -> String {
+    let region = diagnostic
+        .span
+        .map(|span| {
+            format!(
+                ",\"region\":{{\"startLine\":{},\"startColumn\":{}}}",
+                span.line, span.column
+            )
+        })
+        .unwrap_or_default();
+    format!(
+        "{{\"ruleId\":{},\"level\":{},\"message\":{{\"text\":{}}},\"locations\":[{{\"physicalLocation\":{{\"artifactLocation\":{{\"uri\":{}}}{}}}}}]}}",
+        json_string(diagnostic.code.id()),
+        json_string(&diagnostic.severity.to_string()),
+        json_string(&diagnostic.message),
+        json_string(filename),
+        region,
+    )
+}
+
+/// Formats the diagnostics of all files as a single SARIF log
+pub fn to_sarif(diagnostics: &[(String, Diagnostic)]) -> String {
+    let rules: Vec<String> = Code::ALL.iter().map(|code| sarif_rule(*code)).collect();
+    let results: Vec<String> = diagnostics
+        .iter()
+        .map(|(filename, diagnostic)| sarif_result(filename, diagnostic))
+        .collect();
+    format!(
+        "{{\"$schema\":{},\"version\":\"2.1.0\",\"runs\":[{{\"tool\":{{\"driver\":{{\"name\":\"tzio\",\"version\":{},\"rules\":[{}]}}}},\"results\":[{}]}}]}}",
+        json_string(SARIF_SCHEMA),
+        json_string(env!("CARGO_PKG_VERSION")),
+        rules.join(","),
+        results.join(",")
+    )
+}
+
+/// Diagnostics of a run, printed in a machine-readable format.
+/// JSON objects are printed as files are checked, the SARIF log once all files are checked.
+pub struct DiagnosticLog {
+    format: MessageFormat,
+    sarif_diagnostics: Vec<(String, Diagnostic)>,
+}
+
+impl DiagnosticLog {
+    pub fn new(format: MessageFormat) -> Self {
+        DiagnosticLog {
+            format,
+            sarif_diagnostics: vec![],
+        }
+    }
+
+    /// Reports the diagnostics of a file
+    pub fn add(&mut self, filename: &str, diagnostics: &[Diagnostic]) {
+        match self.format {
+            MessageFormat::Human => {}
+            MessageFormat::Json => {
+                for diagnostic in diagnostics {
+                    println!("{}", to_json(filename, diagnostic));
+                }
+            }
+            MessageFormat::Sarif => self.sarif_diagnostics.extend(
+                diagnostics
+                    .iter()
+                    .map(|diagnostic| (String::from(filename), diagnostic.clone())),
+            ),
+        }
+    }
+
+    /// Prints the diagnostics waiting for the end of the run
+    pub fn finish(self) {
+        if self.format == MessageFormat::Sarif {
+            println!("{}", to_sarif(&self.sarif_diagnostics));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use language::address::Node;
+    use language::span::Span;
+
+    #[test]
+    fn test_parse_message_format() {
+        assert_eq!(MessageFormat::parse("json"), Ok(MessageFormat::Json));
+        assert_eq!(MessageFormat::parse("sarif"), Ok(MessageFormat::Sarif));
+        assert!(MessageFormat::parse("xml").is_err());
+    }
+
+    #[test]
+    fn test_escape_json_strings() {
+        assert_eq!(json_string("a \"b\"\n\\"), "\"a \\\"b\\\"\\n\\\\\"");
+        assert_eq!(json_string("\u{1}"), "\"\\u0001\"");
+    }
+
+    #[test]
+    fn test_format_json_diagnostic() {
+        let diagnostic = Diagnostic::error(Code::UndefinedLabel, String::from("Label L"))
+            .at(Some(Span::new(10, 2, 3)))
+            .in_node(&Node::new_node("a"));
+        assert_eq!(
+            to_json("a.io", &diagnostic),
+            "{\"file\":\"a.io\",\"code\":\"TZ0401\",\"name\":\"undefined-label\",\"severity\":\"error\",\"message\":\"Label L\",\"node\":\"a\",\"line\":2,\"column\":3}"
+        );
+
+        let diagnostic = Diagnostic::warning(Code::UnusedIoPort, String::from("w"));
+        assert_eq!(
+            to_json("a.io", &diagnostic),
+            "{\"file\":\"a.io\",\"code\":\"TZ0502\",\"name\":\"unused-io-port\",\"severity\":\"warning\",\"message\":\"w\",\"node\":null,\"line\":null,\"column\":null}"
+        );
+    }
+
+    #[test]
+    fn test_format_sarif_log() {
+        let diagnostics = vec![
+            (
+                String::from("a.io"),
+                Diagnostic::warning(Code::Deadlock, String::from("w")).at(Some(Span::new(0, 1, 1))),
+            ),
+            (
+                String::from("b.io"),
+                Diagnostic::error(Code::InvalidTestCase, String::from("e")),
+            ),
+        ];
+        let log = to_sarif(&diagnostics);
+        assert!(log.starts_with("{\"$schema\":"));
+        assert!(log.contains("\"version\":\"2.1.0\""));
+        assert_eq!(log.matches("\"runs\"").count(), 1);
+        assert!(log.contains("{\"id\":\"TZ0601\",\"name\":\"deadlock\"}"));
+        assert!(log.contains(
+            "{\"ruleId\":\"TZ0601\",\"level\":\"warning\",\"message\":{\"text\":\"w\"},\"locations\":[{\"physicalLocation\":{\"artifactLocation\":{\"uri\":\"a.io\"},\"region\":{\"startLine\":1,\"startColumn\":1}}}]}"
+        ));
+        assert!(log.contains(
+            "{\"ruleId\":\"TZ0701\",\"level\":\"error\",\"message\":{\"text\":\"e\"},\"locations\":[{\"physicalLocation\":{\"artifactLocation\":{\"uri\":\"b.io\"}}}]}"
+        ));
+    }
+
+    #[test]
+    fn test_collect_sarif_diagnostics() {
+        let mut log = DiagnosticLog::new(MessageFormat::Sarif);
+        log.add(
+            "a.io",
+            &[Diagnostic::error(Code::Syntax, String::from("e"))],
+        );
+        log.add("b.io", &[]);
+        log.add(
+            "c.io",
+            &[Diagnostic::warning(Code::Deadlock, String::from("w"))],
+        );
+        let files: Vec<&str> = log
+            .sarif_diagnostics
+            .iter()
+            .map(|(filename, _)| filename.as_str())
+            .collect();
+        assert_eq!(files, vec!["a.io", "c.io"]);
+    }
+}
@@ -0,0 +1,107 @@
+//! Module running programs on the simulator, with the protocol of the Java core.
+//!
+//! Each line of input gives the values of the program inputs, separated by `;`.
+//! A blank value leaves its input empty. Outputs are printed the same way,
+//! prefixed by `> `.
+
+use language::syntax::Program;
+use simulator::Simulator;
+
+const SEPARATOR: char = ';';
+
+fn parse_inputs(line: &str) -> Result<Vec<Option<i32>>, String> {
+    line.split(SEPARATOR)
+        .map(str::trim)
+        .map(|value| {
+            if value.is_empty() {
+                Ok(None)
+            } else {
+                value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("Invalid input value {}", value))
+            }
+        })
+        .collect()
+}
+
+fn format_outputs(outputs: &[Option<i32>]) -> String {
+    let values: Vec<String> = outputs
+        .iter()
+        .map(|value| value.map(|v| v.to_string()).unwrap_or_default())
+        .collect();
+    format!("> {}", values.join(&SEPARATOR.to_string()))
+}
+
+/// Feeds all lines of inputs to a program, then runs it for `cycles` ticks.
+///
+/// This returns the formatted outputs produced by the program.
+pub fn run_program(
+    program: &Program,
+    lines: &[String],
+    cycles: usize,
+) -> Result<Vec<String>, String> {
+    let mut simulator = Simulator::new(program)?;
+    let ports = simulator.input_ports();
+    for line in lines {
+        let values = parse_inputs(line)?;
+        if values.len() > ports.len() {
+            return Err(format!(
+                "Too many inputs in `{}`, the program has {} inputs",
+                line,
+                ports.len()
+            ));
+        }
+        for (port, value) in ports.iter().zip(values) {
+            if let Some(value) = value {
+                simulator.consume(*port, &[value])?;
+            }
+        }
+    }
+    Ok(simulator
+        .run(cycles)
+        .iter()
+        .map(|outputs| format_outputs(outputs))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_program(content: &str) -> Program {
+        postprocessor::process(parser::parse(content.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_inputs() {
+        assert_eq!(parse_inputs("1; -2"), Ok(vec![Some(1), Some(-2)]));
+        assert_eq!(parse_inputs(";3"), Ok(vec![None, Some(3)]));
+        assert!(parse_inputs("a").is_err());
+    }
+
+    #[test]
+    fn test_run_program() {
+        let program = parse_program(
+            "Node #1
+==========
+IN:1 -> 1, IN:2 -> 2
+----------
+MOV <1, ACC
+ADD <2
+MOV ACC, >1
+----------
+1 -> OUT:1
+==========
+",
+        );
+        let lines = vec![String::from("1;2"), String::from("3;-5")];
+        assert_eq!(
+            run_program(&program, &lines, 100),
+            Ok(vec![String::from("> 3"), String::from("> -2")])
+        );
+
+        let lines = vec![String::from("1;2;3")];
+        assert!(run_program(&program, &lines, 100).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use language::address::InputSlot;
+use language::syntax::Program;
+use simulator::Simulator;
+
+/// Maximal number of cycles to run a test before giving up
+pub const MAX_CYCLES: usize = 10_000;
+
+/// Values produced on an output of the program, compared to the expected ones
+#[derive(Debug, PartialEq)]
+pub struct PortResult {
+    pub port: InputSlot,
+    pub expected: Vec<i32>,
+    pub actual: Vec<i32>,
+}
+
+impl PortResult {
+    pub fn is_success(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+/// Outcome of the test case of a program
+#[derive(Debug, PartialEq)]
+pub struct TestReport {
+    pub cycles: usize,
+    pub ports: Vec<PortResult>,
+}
+
+impl TestReport {
+    pub fn is_success(&self) -> bool {
+        self.ports.iter().all(PortResult::is_success)
+    }
+}
+
+fn format_values(values: &[i32]) -> String {
+    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(" "))
+}
+
+impl fmt::Display for TestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_success() {
+            write!(f, "Test passed in {} cycles", self.cycles)
+        } else {
+            write!(f, "Test failed after {} cycles", self.cycles)?;
+            for result in self.ports.iter().filter(|r| !r.is_success()) {
+                write!(
+                    f,
+                    "\n  OUT:{}: expected {}, got {}",
+                    result.port,
+                    format_values(&result.expected),
+                    format_values(&result.actual)
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Runs the test case embedded in a program.
+///
+/// The program is run until all expected outputs are produced, or until `max_cycles`
+/// is reached. This fails if the program has no test case or if the test case does not
+/// match the inputs and outputs of the program.
+pub fn run_test(program: &Program, max_cycles: usize) -> Result<TestReport, String> {
+    let test = program
+        .tests
+        .as_ref()
+        .ok_or_else(|| String::from("No test case defined in the program"))?;
+    let mut simulator = Simulator::new(program)?;
+
+    for (port, values) in &test.ins {
+        let values: Vec<i32> = values.iter().map(|v| *v as i32).collect();
+        simulator.consume(*port, &values)?;
+    }
+
+    let output_ports = simulator.output_ports();
+    let mut produced: BTreeMap<InputSlot, Vec<i32>> = BTreeMap::new();
+    for port in test.outs.keys() {
+        if !output_ports.contains(port) {
+            return Err(format!("No output port {} in the program", port));
+        }
+        produced.insert(*port, Vec::new());
+    }
+
+    let is_complete = |produced: &BTreeMap<InputSlot, Vec<i32>>| {
+        produced
+            .iter()
+            .all(|(port, values)| values.len() >= test.outs[port].len())
+    };
+    while !is_complete(&produced) && simulator.cycle_count() < max_cycles {
+        if let Some(outputs) = simulator.tick() {
+            for (port, value) in output_ports.iter().zip(outputs) {
+                if let (Some(values), Some(value)) = (produced.get_mut(port), value) {
+                    values.push(value);
+                }
+            }
+        }
+    }
+
+    let ports = produced
+        .into_iter()
+        .map(|(port, actual)| PortResult {
+            port,
+            expected: test.outs[&port].iter().map(|v| *v as i32).collect(),
+            actual,
+        })
+        .collect();
+    Ok(TestReport {
+        cycles: simulator.cycle_count(),
+        ports,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_program(content: &str) -> Program {
+        postprocessor::process(parser::parse(content.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_passing_test_case() {
+        let program = parse_program(
+            "/>> 1: [1 -3]
+/<< 1: [2 -6]
+Node #1
+==========
+IN:1 -> 1
+----------
+MOV <1, ACC
+ADD ACC
+MOV ACC, >1
+----------
+1 -> OUT:1
+==========
+",
+        );
+        let report = run_test(&program, MAX_CYCLES).unwrap();
+        assert!(report.is_success());
+        assert!(report.cycles < MAX_CYCLES);
+    }
+
+    #[test]
+    fn test_failing_test_case() {
+        let program = parse_program(
+            "/>> 1: [1 -3]
+/<< 1: [2 -5 4]
+Node #1
+==========
+IN:1 -> 1
+----------
+MOV <1, >1
+----------
+1 -> OUT:1
+==========
+",
+        );
+        let report = run_test(&program, 50).unwrap();
+        assert!(!report.is_success());
+        assert_eq!(report.cycles, 50);
+        assert_eq!(
+            report.ports,
+            vec![PortResult {
+                port: 1.into(),
+                expected: vec![2, -5, 4],
+                actual: vec![1, -3],
+            }]
+        );
+        assert_eq!(
+            report.to_string(),
+            "Test failed after 50 cycles\n  OUT:1: expected [2 -5 4], got [1 -3]"
+        );
+    }
+
+    #[test]
+    fn test_missing_test_case() {
+        let program = parse_program(
+            "Node #1
+==========
+IN:1 -> 1
+----------
+MOV <1, >1
+----------
+1 -> OUT:1
+==========
+",
+        );
+        assert!(run_test(&program, MAX_CYCLES).is_err());
+    }
+}
@@ -0,0 +1,328 @@
+//! Module detecting nodes that wait on each other before writing any value.
+//!
+//! Reads block on empty slots, so nodes reading from each other before
+//! producing anything never start. Each node is abstracted into the channel
+//! events it meets from its first instruction: reads of values written by
+//! other nodes, and writes to other nodes. This only generates warnings.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::{CheckResult, Code, Diagnostic};
+use language::address::{InputSlot, Node, OutputSlot};
+use language::instruction::{Operation, ValuePointer};
+use language::syntax::NodeBlock;
+use language::syntax::Program;
+
+/// Channel events reached by a node before producing anything
+#[derive(Debug, Default, PartialEq)]
+struct Behaviour {
+    /// Whether a path writes to another node before waiting on one
+    may_produce: bool,
+    /// Nodes that may be waited on before producing
+    waits_on: BTreeSet<usize>,
+}
+
+impl Behaviour {
+    fn is_blocked(&self) -> bool {
+        !self.may_produce && !self.waits_on.is_empty()
+    }
+}
+
+/// Node writing into each input slot of a node, when it is another node
+fn input_sources(node: &NodeBlock, index: &HashMap<&String, usize>) -> HashMap<InputSlot, usize> {
+    node.inputs
+        .iter()
+        .filter_map(|input| match input.from.node {
+            Node::Node(ref id) => index.get(id).map(|idx| (input.to, *idx)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Output slots of a node read by other nodes
+fn node_outputs(node: &NodeBlock) -> HashSet<OutputSlot> {
+    node.outputs
+        .iter()
+        .filter(|output| matches!(output.to.node, Node::Node(_)))
+        .map(|output| output.from)
+        .collect()
+}
+
+fn label_positions(operations: &[Operation]) -> HashMap<&String, usize> {
+    operations
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, op)| match op {
+            Operation::LABEL(ref label) => Some((label, idx)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Explores the paths of a node from its first instruction, stopping at the first
+/// read from another node or write to another node.
+/// Paths looping without channel events neither produce nor wait.
+fn abstract_node(node: &NodeBlock, index: &HashMap<&String, usize>) -> Behaviour {
+    let sources = input_sources(node, index);
+    let outputs = node_outputs(node);
+    let labels = label_positions(&node.instructions);
+    let count = node.instructions.len();
+
+    let reads_from = |pointer: &ValuePointer| match pointer {
+        ValuePointer::INPUT(ref slot) => sources.get(slot).cloned(),
+        _ => None,
+    };
+
+    let mut behaviour = Behaviour::default();
+    let mut visited = HashSet::new();
+    let mut pending = vec![0];
+    while let Some(idx) = pending.pop() {
+        if count == 0 || !visited.insert(idx) {
+            continue;
+        }
+        let next = (idx + 1) % count;
+        match &node.instructions[idx] {
+            Operation::MOV(ref from, ref to) => {
+                if let Some(src) = reads_from(from) {
+                    behaviour.waits_on.insert(src);
+                } else if matches!(to, ValuePointer::OUTPUT(ref slot) if outputs.contains(slot)) {
+                    behaviour.may_produce = true;
+                } else {
+                    pending.push(next);
+                }
+            }
+            Operation::ADD(ref value) | Operation::SUB(ref value) => match reads_from(value) {
+                Some(src) => {
+                    behaviour.waits_on.insert(src);
+                }
+                None => pending.push(next),
+            },
+            Operation::JMP(ref label) => {
+                pending.extend(labels.get(label));
+            }
+            Operation::JEZ(ref label)
+            | Operation::JNZ(ref label)
+            | Operation::JLZ(ref label)
+            | Operation::JGZ(ref label) => {
+                pending.extend(labels.get(label));
+                pending.push(next);
+            }
+            Operation::JRO(ref value) => match reads_from(value) {
+                Some(src) => {
+                    behaviour.waits_on.insert(src);
+                }
+                // Relative jumps cannot be followed, assume the node may go on
+                None => behaviour.may_produce = true,
+            },
+            Operation::SAV(_) | Operation::SWP(_) | Operation::NEG | Operation::LABEL(_) => {
+                pending.push(next)
+            }
+        }
+    }
+    behaviour
+}
+
+/// Finds the nodes that can never produce, because they only wait on such nodes
+fn find_blocked_nodes(behaviours: &[Behaviour]) -> HashSet<usize> {
+    let mut blocked: HashSet<usize> = (0..behaviours.len())
+        .filter(|idx| behaviours[*idx].is_blocked())
+        .collect();
+    loop {
+        let released: Vec<usize> = blocked
+            .iter()
+            .filter(|idx| {
+                !behaviours[**idx]
+                    .waits_on
+                    .iter()
+                    .all(|n| blocked.contains(n))
+            })
+            .cloned()
+            .collect();
+        if released.is_empty() {
+            return blocked;
+        }
+        for idx in released {
+            blocked.remove(&idx);
+        }
+    }
+}
+
+/// Nodes reachable from a node by following what it waits on
+fn reachable(start: usize, behaviours: &[Behaviour]) -> HashSet<usize> {
+    let mut reached = HashSet::new();
+    let mut pending: Vec<usize> = behaviours[start].waits_on.iter().cloned().collect();
+    while let Some(idx) = pending.pop() {
+        if reached.insert(idx) {
+            pending.extend(behaviours[idx].waits_on.iter());
+        }
+    }
+    reached
+}
+
+/// Groups the blocked nodes into the cycles of nodes waiting on each other.
+/// Blocked nodes only waiting on a cycle are not reported.
+fn find_cycles(behaviours: &[Behaviour], blocked: &HashSet<usize>) -> Vec<Vec<usize>> {
+    let reached: HashMap<usize, HashSet<usize>> = blocked
+        .iter()
+        .map(|idx| (*idx, reachable(*idx, behaviours)))
+        .collect();
+    let mut cycles = vec![];
+    let mut reported = HashSet::new();
+    for idx in (0..behaviours.len()).filter(|idx| blocked.contains(idx)) {
+        if reported.contains(&idx) || !reached[&idx].contains(&idx) {
+            continue;
+        }
+        let cycle: Vec<usize> = (0..behaviours.len())
+            .filter(|other| reached[&idx].contains(other) && reached[other].contains(&idx))
+            .collect();
+        reported.extend(cycle.iter().cloned());
+        cycles.push(cycle);
+    }
+    cycles
+}
+
+pub fn check(tree: &Program, result: &mut CheckResult) -> bool {
+    let initial_count = result.error_count();
+    let index: HashMap<&String, usize> = tree
+        .nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, node)| match node.node {
+            Node::Node(ref id) => Some((id, idx)),
+            _ => None,
+        })
+        .collect();
+    let behaviours: Vec<Behaviour> = tree
+        .nodes
+        .iter()
+        .map(|node| abstract_node(node, &index))
+        .collect();
+
+    let blocked = find_blocked_nodes(&behaviours);
+    for cycle in find_cycles(&behaviours, &blocked) {
+        let names: Vec<String> = cycle
+            .iter()
+            .map(|idx| tree.nodes[*idx].node.to_string())
+            .collect();
+        result.add(
+            Diagnostic::warning(
+                Code::Deadlock,
+                format!(
+                    "Possible deadlock: {} wait on each other before writing any value",
+                    names.join(", ")
+                ),
+            )
+            .at(tree.nodes[cycle[0]].spans.node)
+            .in_node(&tree.nodes[cycle[0]].node),
+        );
+    }
+
+    initial_count == result.error_count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use language::address::Port;
+
+    /// Creates a node reading from `src` and writing to `dst`, with the given instructions
+    fn node(name: &str, src: &str, dst: &str, instructions: Vec<Operation>) -> NodeBlock {
+        instructions.into_iter().fold(
+            NodeBlock::new(Node::new_node(name))
+                .input(Port::named_port(src, 1.into()), 1.into())
+                .output(1.into(), Port::named_port(dst, 1.into())),
+            NodeBlock::instruction,
+        )
+    }
+
+    fn forward() -> Vec<Operation> {
+        vec![Operation::MOV(
+            ValuePointer::INPUT(1.into()),
+            ValuePointer::OUTPUT(1.into()),
+        )]
+    }
+
+    fn check_program(program: &Program) -> CheckResult {
+        let mut checks = CheckResult::default();
+        assert!(check(program, &mut checks));
+        checks
+    }
+
+    #[test]
+    fn test_warn_about_nodes_waiting_on_each_other() {
+        let program = Program::default()
+            .node(node("a", "b", "b", forward()))
+            .node(node("b", "a", "a", forward()));
+        let checks = check_program(&program);
+        assert_eq!(checks.warning_count(), 1);
+
+        let mut msgs = vec![];
+        checks.print_report_into("a.io", |msg| msgs.push(String::from(msg)));
+        assert_eq!(
+            msgs[2],
+            "Possible deadlock: Node#a, Node#b wait on each other before writing any value"
+        );
+    }
+
+    #[test]
+    fn test_accept_node_producing_first() {
+        let mut produce_first = vec![Operation::MOV(
+            ValuePointer::VALUE(1),
+            ValuePointer::OUTPUT(1.into()),
+        )];
+        produce_first.append(&mut forward());
+        let program = Program::default()
+            .node(node("a", "b", "b", produce_first))
+            .node(node("b", "a", "a", forward()));
+        assert!(!check_program(&program).has_warnings());
+    }
+
+    #[test]
+    fn test_follow_jumps_to_the_first_event() {
+        // Skipping the production, node a waits on b
+        let skip_production = vec![
+            Operation::JMP(String::from("READ")),
+            Operation::MOV(ValuePointer::VALUE(1), ValuePointer::OUTPUT(1.into())),
+            Operation::LABEL(String::from("READ")),
+            Operation::MOV(ValuePointer::INPUT(1.into()), ValuePointer::ACC),
+        ];
+        let program = Program::default()
+            .node(node("a", "b", "b", skip_production))
+            .node(node("b", "a", "a", forward()));
+        assert_eq!(check_program(&program).warning_count(), 1);
+
+        // A conditional jump may produce
+        let maybe_produce = vec![
+            Operation::JEZ(String::from("READ")),
+            Operation::MOV(ValuePointer::VALUE(1), ValuePointer::OUTPUT(1.into())),
+            Operation::LABEL(String::from("READ")),
+            Operation::MOV(ValuePointer::INPUT(1.into()), ValuePointer::ACC),
+        ];
+        let program = Program::default()
+            .node(node("a", "b", "b", maybe_produce))
+            .node(node("b", "a", "a", forward()));
+        assert!(!check_program(&program).has_warnings());
+    }
+
+    #[test]
+    fn test_ignore_nodes_waiting_on_a_cycle() {
+        let program = Program::default()
+            .node(node("a", "b", "b", forward()))
+            .node(node("b", "a", "a", forward()))
+            .node(node("c", "a", "d", forward()));
+        let checks = check_program(&program);
+        assert_eq!(checks.warning_count(), 1);
+    }
+
+    #[test]
+    fn test_accept_external_inputs() {
+        let program = Program::default().node(
+            NodeBlock::new(Node::new_node("a"))
+                .input(Port::new(Node::In, 1.into()), 1.into())
+                .output(1.into(), Port::new(Node::Out, 1.into()))
+                .instruction(forward().remove(0)),
+        );
+        assert!(!check_program(&program).has_warnings());
+    }
+}
@@ -1,8 +1,9 @@
 use std::collections::HashSet;
 
-use crate::CheckResult;
+use crate::{CheckResult, Code, Diagnostic};
 use language::address::{InputSlot, Node, OutputSlot};
-use language::instruction::{Operation, ValuePointer};
+use language::instruction::{MemoryPointer, Operation, ValuePointer};
+use language::span::Span;
 use language::syntax::Program;
 use language::syntax::{InputMapping, NodeBlock, OutputMapping};
 
@@ -29,14 +30,22 @@ fn test_input(
     inputs: &HashSet<InputSlot>,
     node: &Node,
     op: &Operation,
+    span: Option<Span>,
     pointer: &ValuePointer,
 ) {
     if let ValuePointer::INPUT(ref port) = pointer {
         if !inputs.contains(port) {
-            result.add_error(format!(
-                "Port {} from {} is not defined in node {} inputs",
-                port, op, node
-            ));
+            result.add(
+                Diagnostic::error(
+                    Code::UndefinedPort,
+                    format!(
+                        "Port {} from {} is not defined in node {} inputs",
+                        port, op, node
+                    ),
+                )
+                .at(span)
+                .in_node(node),
+            );
         }
     }
 }
@@ -46,36 +55,86 @@ fn test_output(
     outputs: &HashSet<OutputSlot>,
     node: &Node,
     op: &Operation,
+    span: Option<Span>,
     pointer: &ValuePointer,
 ) {
     if let ValuePointer::OUTPUT(ref port) = pointer {
         if !outputs.contains(port) {
-            result.add_error(format!(
-                "Port {} from {} is not defined in node {} outputs",
-                port, op, node
-            ));
+            result.add(
+                Diagnostic::error(
+                    Code::UndefinedPort,
+                    format!(
+                        "Port {} from {} is not defined in node {} outputs",
+                        port, op, node
+                    ),
+                )
+                .at(span)
+                .in_node(node),
+            );
         }
     }
 }
 
+/// Highest memory slot, the generator pushing slot indexes as bytes
+const MAX_MEMORY_SLOT: u8 = 127;
+
+fn test_memory(
+    result: &mut CheckResult,
+    node: &Node,
+    op: &Operation,
+    span: Option<Span>,
+    pointer: &MemoryPointer,
+) {
+    let &MemoryPointer::BAK(slot) = pointer;
+    if slot == 0 {
+        result.add(
+            Diagnostic::error(
+                Code::InvalidMemorySlot,
+                format!(
+                    "Memory slot 0 from {} does not exist in node {}, slots start at 1",
+                    op, node
+                ),
+            )
+            .at(span)
+            .in_node(node),
+        );
+    } else if slot > MAX_MEMORY_SLOT {
+        result.add(
+            Diagnostic::error(
+                Code::InvalidMemorySlot,
+                format!(
+                    "Memory slot {} from {} exceeds the {} slots of node {}",
+                    slot, op, MAX_MEMORY_SLOT, node
+                ),
+            )
+            .at(span)
+            .in_node(node),
+        );
+    }
+}
+
 fn check_node(node: &NodeBlock, result: &mut CheckResult) {
-    let inputs = collect_input_ports(&node.1);
-    let outputs = collect_output_ports(&node.2);
+    let inputs = collect_input_ports(&node.inputs);
+    let outputs = collect_output_ports(&node.outputs);
 
-    for op in &node.3 {
+    for (idx, op) in node.instructions.iter().enumerate() {
+        let span = node.spans.operation(idx);
         match op {
             Operation::MOV(ref from, ref to) => {
-                test_input(result, &inputs, &node.0, op, from);
-                test_output(result, &outputs, &node.0, op, to);
+                test_input(result, &inputs, &node.node, op, span, from);
+                test_output(result, &outputs, &node.node, op, span, to);
             }
             Operation::ADD(ref value) => {
-                test_input(result, &inputs, &node.0, op, value);
+                test_input(result, &inputs, &node.node, op, span, value);
             }
             Operation::SUB(ref value) => {
-                test_input(result, &inputs, &node.0, op, value);
+                test_input(result, &inputs, &node.node, op, span, value);
             }
             Operation::JRO(ref value) => {
-                test_input(result, &inputs, &node.0, op, value);
+                test_input(result, &inputs, &node.node, op, span, value);
+            }
+            Operation::SAV(ref pointer) | Operation::SWP(ref pointer) => {
+                test_memory(result, &node.node, op, span, pointer);
             }
             _ => {}
         }
@@ -96,75 +155,112 @@ mod tests {
     use super::*;
 
     use language::address::Port;
+    use language::span::NodeSpans;
 
     #[test]
     fn test_check_node_on_jro() {
         let mut check = Default::default();
 
-        let node_ok = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let node_ok = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port::new(Node::In, 3.into()),
                 to: 1.into(),
+                span: None,
             }],
-            vec![],
-            vec![
+            outputs: vec![],
+            instructions: vec![
                 Operation::JRO(ValuePointer::INPUT(1.into())),
                 Operation::JRO(ValuePointer::ACC),
                 Operation::JRO(ValuePointer::VALUE(2)),
             ],
-        );
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ok, &mut check);
         assert_eq!(check.has_errors(), false);
 
-        let node_ko = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let node_ko = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port::new(Node::In, 3.into()),
                 to: 1.into(),
+                span: None,
             }],
-            vec![OutputMapping {
+            outputs: vec![OutputMapping {
                 from: 2.into(),
                 to: Port::new(Node::Out, 3.into()),
+                span: None,
             }],
-            vec![Operation::JRO(ValuePointer::INPUT(2.into()))],
-        );
+            instructions: vec![Operation::JRO(ValuePointer::INPUT(2.into()))],
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ko, &mut check);
         assert_eq!(check.has_errors(), true);
     }
 
+    #[test]
+    fn test_locate_errors_on_operations() {
+        let mut check = CheckResult::default();
+        let node = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![],
+            outputs: vec![],
+            instructions: vec![
+                Operation::NEG,
+                Operation::ADD(ValuePointer::INPUT(1.into())),
+            ],
+            spans: NodeSpans {
+                node: Some(Span::new(0, 1, 1)),
+                operations: vec![Span::new(20, 3, 1), Span::new(24, 4, 3)],
+            },
+        };
+        check_node(&node, &mut check);
+
+        let mut msgs = vec![];
+        check.print_report_into("a.io", |msg| msgs.push(String::from(msg)));
+        assert_eq!(
+            msgs[2],
+            "a.io:4:3: Port 1 from ADD Input(1) is not defined in node Node#a inputs"
+        );
+    }
+
     #[test]
     fn test_check_node_on_add() {
         let mut check = Default::default();
 
-        let node_ok = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let node_ok = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port::new(Node::In, 3.into()),
                 to: 1.into(),
+                span: None,
             }],
-            vec![],
-            vec![
+            outputs: vec![],
+            instructions: vec![
                 Operation::ADD(ValuePointer::INPUT(1.into())),
                 Operation::ADD(ValuePointer::ACC),
                 Operation::ADD(ValuePointer::VALUE(2)),
             ],
-        );
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ok, &mut check);
         assert_eq!(check.has_errors(), false);
 
-        let node_ko = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let node_ko = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port::new(Node::In, 3.into()),
                 to: 1.into(),
+                span: None,
             }],
-            vec![OutputMapping {
+            outputs: vec![OutputMapping {
                 from: 2.into(),
                 to: Port::new(Node::Out, 3.into()),
+                span: None,
             }],
-            vec![Operation::ADD(ValuePointer::INPUT(2.into()))],
-        );
+            instructions: vec![Operation::ADD(ValuePointer::INPUT(2.into()))],
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ko, &mut check);
         assert_eq!(check.has_errors(), true);
     }
@@ -173,34 +269,39 @@ mod tests {
     fn test_check_node_on_sub() {
         let mut check = Default::default();
 
-        let node_ok = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let node_ok = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port::new(Node::In, 3.into()),
                 to: 1.into(),
+                span: None,
             }],
-            vec![],
-            vec![
+            outputs: vec![],
+            instructions: vec![
                 Operation::SUB(ValuePointer::INPUT(1.into())),
                 Operation::SUB(ValuePointer::ACC),
                 Operation::SUB(ValuePointer::VALUE(2)),
             ],
-        );
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ok, &mut check);
         assert_eq!(check.has_errors(), false);
 
-        let node_ko = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let node_ko = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port::new(Node::In, 3.into()),
                 to: 1.into(),
+                span: None,
             }],
-            vec![OutputMapping {
+            outputs: vec![OutputMapping {
                 from: 2.into(),
                 to: Port::new(Node::Out, 3.into()),
+                span: None,
             }],
-            vec![Operation::SUB(ValuePointer::INPUT(2.into()))],
-        );
+            instructions: vec![Operation::SUB(ValuePointer::INPUT(2.into()))],
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ko, &mut check);
         assert_eq!(check.has_errors(), true);
     }
@@ -209,17 +310,19 @@ mod tests {
     fn test_check_node_on_mov() {
         let mut check = Default::default();
 
-        let node_ok = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let node_ok = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port::new(Node::In, 3.into()),
                 to: 1.into(),
+                span: None,
             }],
-            vec![OutputMapping {
+            outputs: vec![OutputMapping {
                 from: 2.into(),
                 to: Port::new(Node::Out, 3.into()),
+                span: None,
             }],
-            vec![
+            instructions: vec![
                 Operation::MOV(
                     ValuePointer::INPUT(1.into()),
                     ValuePointer::OUTPUT(2.into()),
@@ -227,25 +330,45 @@ mod tests {
                 Operation::MOV(ValuePointer::INPUT(1.into()), ValuePointer::ACC),
                 Operation::MOV(ValuePointer::ACC, ValuePointer::VALUE(2)),
             ],
-        );
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ok, &mut check);
         assert_eq!(check.has_errors(), false);
 
-        let node_ko = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let node_ko = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port::new(Node::In, 1.into()),
                 to: 1.into(),
+                span: None,
             }],
-            vec![OutputMapping {
+            outputs: vec![OutputMapping {
                 from: 2.into(),
                 to: Port::new(Node::Out, 1.into()),
+                span: None,
             }],
-            vec![
+            instructions: vec![
                 Operation::MOV(ValuePointer::INPUT(2.into()), ValuePointer::ACC),
                 Operation::MOV(ValuePointer::ACC, ValuePointer::OUTPUT(1.into())),
             ],
-        );
+            spans: NodeSpans::default(),
+        };
+        check_node(&node_ko, &mut check);
+        assert_eq!(check.error_count(), 2);
+    }
+
+    #[test]
+    fn test_check_node_on_memory() {
+        let mut check = CheckResult::default();
+        let node_ok = NodeBlock::new(Node::new_node("a"))
+            .instruction(Operation::SAV(MemoryPointer::BAK(1)))
+            .instruction(Operation::SWP(MemoryPointer::BAK(MAX_MEMORY_SLOT)));
+        check_node(&node_ok, &mut check);
+        assert!(!check.has_errors());
+
+        let node_ko = NodeBlock::new(Node::new_node("a"))
+            .instruction(Operation::SAV(MemoryPointer::BAK(0)))
+            .instruction(Operation::SWP(MemoryPointer::BAK(200)));
         check_node(&node_ko, &mut check);
         assert_eq!(check.error_count(), 2);
     }
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use crate::CheckResult;
+use crate::{CheckResult, Code, Diagnostic};
 use language::syntax::NodeBlock;
 use language::syntax::Program;
 
@@ -30,22 +30,36 @@ fn dups_to_str<T: std::fmt::Display>(duplicates: HashSet<T>) -> String {
 }
 
 fn check_node(node: &NodeBlock, result: &mut CheckResult) {
-    let input_duplicates = check_ports(&node.1, |ref input| input.to);
+    let input_duplicates = check_ports(&node.inputs, |ref input| input.to);
     if !input_duplicates.is_empty() {
-        result.add_error(format!(
-            "Duplicated input ports referenced in {}: {}",
-            &node.0,
-            dups_to_str(input_duplicates)
-        ));
+        result.add(
+            Diagnostic::error(
+                Code::DuplicatedNodePort,
+                format!(
+                    "Duplicated input ports referenced in {}: {}",
+                    &node.node,
+                    dups_to_str(input_duplicates)
+                ),
+            )
+            .at(node.spans.node)
+            .in_node(&node.node),
+        );
     }
 
-    let output_duplicates = check_ports(&node.2, |ref output| output.from);
+    let output_duplicates = check_ports(&node.outputs, |ref output| output.from);
     if !output_duplicates.is_empty() {
-        result.add_error(format!(
-            "Duplicated output ports referenced in {}: {}",
-            &node.0,
-            dups_to_str(output_duplicates)
-        ));
+        result.add(
+            Diagnostic::error(
+                Code::DuplicatedNodePort,
+                format!(
+                    "Duplicated output ports referenced in {}: {}",
+                    &node.node,
+                    dups_to_str(output_duplicates)
+                ),
+            )
+            .at(node.spans.node)
+            .in_node(&node.node),
+        );
     }
 }
 
@@ -63,12 +77,14 @@ mod tests {
     use super::*;
 
     use language::address::{Node, Port};
+    use language::span::NodeSpans;
     use language::syntax::{InputMapping, OutputMapping};
 
     fn fake_input(i: u8) -> InputMapping {
         InputMapping {
             from: Port::new(Node::In, i.into()),
             to: i.into(),
+            span: None,
         }
     }
 
@@ -76,27 +92,29 @@ mod tests {
     fn test_check_input_duplicates() {
         let mut check = Default::default();
 
-        let node_ok = (
-            Node::new_node(&"a"),
-            vec![fake_input(1), fake_input(2), fake_input(3)],
-            vec![],
-            vec![],
-        );
+        let node_ok = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![fake_input(1), fake_input(2), fake_input(3)],
+            outputs: vec![],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ok, &mut check);
         assert_eq!(check.has_errors(), false);
 
-        let node_ko = (
-            Node::new_node(&"a"),
-            vec![
+        let node_ko = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![
                 fake_input(1),
                 fake_input(2),
                 fake_input(3),
                 fake_input(2),
                 fake_input(3),
             ],
-            vec![],
-            vec![],
-        );
+            outputs: vec![],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ko, &mut check);
         assert_eq!(check.has_errors(), true);
     }
@@ -105,6 +123,7 @@ mod tests {
         OutputMapping {
             from: i.into(),
             to: Port::new(Node::Out, i.into()),
+            span: None,
         }
     }
 
@@ -112,27 +131,29 @@ mod tests {
     fn test_check_output_duplicates() {
         let mut check = Default::default();
 
-        let node_ok = (
-            Node::new_node(&"a"),
-            vec![],
-            vec![fake_output(1), fake_output(2), fake_output(3)],
-            vec![],
-        );
+        let node_ok = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![],
+            outputs: vec![fake_output(1), fake_output(2), fake_output(3)],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ok, &mut check);
         assert_eq!(check.has_errors(), false);
 
-        let node_ko = (
-            Node::new_node(&"a"),
-            vec![],
-            vec![
+        let node_ko = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![],
+            outputs: vec![
                 fake_output(1),
                 fake_output(2),
                 fake_output(3),
                 fake_output(2),
                 fake_output(3),
             ],
-            vec![],
-        );
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         check_node(&node_ko, &mut check);
         assert_eq!(check.has_errors(), true);
     }
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use crate::CheckResult;
+use crate::{CheckResult, Code, Diagnostic};
 use language::address::Node;
 use language::syntax::NodeBlock;
 use language::syntax::Program;
@@ -38,58 +38,91 @@ fn check_ranges<T: std::hash::Hash + Eq + Ord + From<u8>>(
 fn check_inputs(nodes: &[NodeBlock], result: &mut CheckResult) {
     let mut input_ports = HashSet::new();
     let mut duplicates = HashSet::new();
+    // Locations of the first duplicated use and of the use of the highest port
+    let mut duplicate_span = None;
+    let mut max_port_span = None;
     for node in nodes {
-        let inputs = &node.1;
+        let inputs = &node.inputs;
         for input in inputs {
             let node = &input.from.node;
             let port = input.from.port;
-            if node == &Node::In && !input_ports.insert(port) {
-                duplicates.insert(port);
+            if node != &Node::In {
+                continue;
+            }
+            if input_ports.iter().all(|p| *p < port) {
+                max_port_span = input.span;
+            }
+            if !input_ports.insert(port) && duplicates.insert(port) && duplicate_span.is_none() {
+                duplicate_span = input.span;
             }
         }
     }
 
     if !duplicates.is_empty() {
-        result.add_error(format!(
-            "Duplicated uses of input ports {}",
-            dups_to_str(duplicates)
-        ));
+        result.add(
+            Diagnostic::error(
+                Code::DuplicatedIoPort,
+                format!("Duplicated uses of input ports {}", dups_to_str(duplicates)),
+            )
+            .at(duplicate_span),
+        );
     }
 
     if let Some(ununsed_ports) = check_ranges(&input_ports) {
-        result.add_warning(format!(
-            "Unused ports in the input: {}",
-            dups_to_str(ununsed_ports)
-        ));
+        result.add(
+            Diagnostic::warning(
+                Code::UnusedIoPort,
+                format!("Unused ports in the input: {}", dups_to_str(ununsed_ports)),
+            )
+            .at(max_port_span),
+        );
     }
 }
 
 fn check_outputs(nodes: &[NodeBlock], result: &mut CheckResult) {
     let mut output_ports = HashSet::new();
     let mut duplicates = HashSet::new();
+    // Locations of the first duplicated use and of the use of the highest port
+    let mut duplicate_span = None;
+    let mut max_port_span = None;
     for node in nodes {
-        let outputs = &node.2;
+        let outputs = &node.outputs;
         for output in outputs {
             let node = &output.to.node;
             let port = output.to.port;
-            if node == &Node::Out && !output_ports.insert(port) {
-                duplicates.insert(port);
+            if node != &Node::Out {
+                continue;
+            }
+            if output_ports.iter().all(|p| *p < port) {
+                max_port_span = output.span;
+            }
+            if !output_ports.insert(port) && duplicates.insert(port) && duplicate_span.is_none() {
+                duplicate_span = output.span;
             }
         }
     }
 
     if !duplicates.is_empty() {
-        result.add_error(format!(
-            "Duplicated uses of output ports {}",
-            dups_to_str(duplicates)
-        ));
+        result.add(
+            Diagnostic::error(
+                Code::DuplicatedIoPort,
+                format!(
+                    "Duplicated uses of output ports {}",
+                    dups_to_str(duplicates)
+                ),
+            )
+            .at(duplicate_span),
+        );
     }
 
     if let Some(ununsed_ports) = check_ranges(&output_ports) {
-        result.add_warning(format!(
-            "Unused ports in the output: {}",
-            dups_to_str(ununsed_ports)
-        ));
+        result.add(
+            Diagnostic::warning(
+                Code::UnusedIoPort,
+                format!("Unused ports in the output: {}", dups_to_str(ununsed_ports)),
+            )
+            .at(max_port_span),
+        );
     }
 }
 
@@ -106,36 +139,41 @@ mod tests {
     use super::*;
 
     use language::address::Port;
+    use language::span::NodeSpans;
     use language::syntax::{InputMapping, OutputMapping};
 
     #[test]
     fn test_check_in_ok() {
         let mut checks = Default::default();
         let nodes = vec![
-            (
-                Node::new_node(&"a"),
-                vec![InputMapping {
+            NodeBlock {
+                node: Node::new_node(&"a"),
+                inputs: vec![InputMapping {
                     from: Port {
                         node: Node::In,
                         port: 1.into(),
                     },
                     to: 1.into(),
+                    span: None,
                 }],
-                vec![],
-                vec![],
-            ),
-            (
-                Node::new_node(&"b"),
-                vec![InputMapping {
+                outputs: vec![],
+                instructions: vec![],
+                spans: NodeSpans::default(),
+            },
+            NodeBlock {
+                node: Node::new_node(&"b"),
+                inputs: vec![InputMapping {
                     from: Port {
                         node: Node::In,
                         port: 2.into(),
                     },
                     to: 2.into(),
+                    span: None,
                 }],
-                vec![],
-                vec![],
-            ),
+                outputs: vec![],
+                instructions: vec![],
+                spans: NodeSpans::default(),
+            },
         ];
         check_inputs(&nodes, &mut checks);
         assert_eq!(checks.has_errors(), false);
@@ -145,30 +183,34 @@ mod tests {
     fn test_check_in_ko() {
         let mut checks = Default::default();
         let nodes = vec![
-            (
-                Node::new_node(&"a"),
-                vec![InputMapping {
+            NodeBlock {
+                node: Node::new_node(&"a"),
+                inputs: vec![InputMapping {
                     from: Port {
                         node: Node::In,
                         port: 3.into(),
                     },
                     to: 1.into(),
+                    span: None,
                 }],
-                vec![],
-                vec![],
-            ),
-            (
-                Node::new_node(&"b"),
-                vec![InputMapping {
+                outputs: vec![],
+                instructions: vec![],
+                spans: NodeSpans::default(),
+            },
+            NodeBlock {
+                node: Node::new_node(&"b"),
+                inputs: vec![InputMapping {
                     from: Port {
                         node: Node::In,
                         port: 3.into(),
                     },
                     to: 2.into(),
+                    span: None,
                 }],
-                vec![],
-                vec![],
-            ),
+                outputs: vec![],
+                instructions: vec![],
+                spans: NodeSpans::default(),
+            },
         ];
         check_inputs(&nodes, &mut checks);
         assert_eq!(checks.has_errors(), true);
@@ -178,30 +220,34 @@ mod tests {
     fn test_check_out_ok() {
         let mut checks = Default::default();
         let nodes = vec![
-            (
-                Node::new_node(&"a"),
-                vec![],
-                vec![OutputMapping {
+            NodeBlock {
+                node: Node::new_node(&"a"),
+                inputs: vec![],
+                outputs: vec![OutputMapping {
                     from: 1.into(),
                     to: Port {
                         node: Node::Out,
                         port: 1.into(),
                     },
+                    span: None,
                 }],
-                vec![],
-            ),
-            (
-                Node::new_node(&"b"),
-                vec![],
-                vec![OutputMapping {
+                instructions: vec![],
+                spans: NodeSpans::default(),
+            },
+            NodeBlock {
+                node: Node::new_node(&"b"),
+                inputs: vec![],
+                outputs: vec![OutputMapping {
                     from: 2.into(),
                     to: Port {
                         node: Node::Out,
                         port: 2.into(),
                     },
+                    span: None,
                 }],
-                vec![],
-            ),
+                instructions: vec![],
+                spans: NodeSpans::default(),
+            },
         ];
         check_outputs(&nodes, &mut checks);
         assert_eq!(checks.has_errors(), false);
@@ -211,30 +257,34 @@ mod tests {
     fn test_check_out_ko() {
         let mut checks = Default::default();
         let nodes = vec![
-            (
-                Node::new_node(&"a"),
-                vec![],
-                vec![OutputMapping {
+            NodeBlock {
+                node: Node::new_node(&"a"),
+                inputs: vec![],
+                outputs: vec![OutputMapping {
                     from: 1.into(),
                     to: Port {
                         node: Node::Out,
                         port: 3.into(),
                     },
+                    span: None,
                 }],
-                vec![],
-            ),
-            (
-                Node::new_node(&"b"),
-                vec![],
-                vec![OutputMapping {
+                instructions: vec![],
+                spans: NodeSpans::default(),
+            },
+            NodeBlock {
+                node: Node::new_node(&"b"),
+                inputs: vec![],
+                outputs: vec![OutputMapping {
                     from: 2.into(),
                     to: Port {
                         node: Node::Out,
                         port: 3.into(),
                     },
+                    span: None,
                 }],
-                vec![],
-            ),
+                instructions: vec![],
+                spans: NodeSpans::default(),
+            },
         ];
         check_outputs(&nodes, &mut checks);
         assert_eq!(checks.has_errors(), true);
@@ -244,42 +294,48 @@ mod tests {
     fn test_check_complete() {
         let mut checks = Default::default();
         let nodes = vec![
-            (
-                Node::new_node(&"a"),
-                vec![InputMapping {
+            NodeBlock {
+                node: Node::new_node(&"a"),
+                inputs: vec![InputMapping {
                     from: Port {
                         node: Node::In,
                         port: 3.into(),
                     },
                     to: 1.into(),
+                    span: None,
                 }],
-                vec![OutputMapping {
+                outputs: vec![OutputMapping {
                     from: 1.into(),
                     to: Port {
                         node: Node::Out,
                         port: 3.into(),
                     },
+                    span: None,
                 }],
-                vec![],
-            ),
-            (
-                Node::new_node(&"b"),
-                vec![InputMapping {
+                instructions: vec![],
+                spans: NodeSpans::default(),
+            },
+            NodeBlock {
+                node: Node::new_node(&"b"),
+                inputs: vec![InputMapping {
                     from: Port {
                         node: Node::In,
                         port: 3.into(),
                     },
                     to: 2.into(),
+                    span: None,
                 }],
-                vec![OutputMapping {
+                outputs: vec![OutputMapping {
                     from: 2.into(),
                     to: Port {
                         node: Node::Out,
                         port: 3.into(),
                     },
+                    span: None,
                 }],
-                vec![],
-            ),
+                instructions: vec![],
+                spans: NodeSpans::default(),
+            },
         ];
         let tree = Program { nodes, tests: None };
         check(&tree, &mut checks);
@@ -290,18 +346,20 @@ mod tests {
     #[test]
     fn test_warnings_about_unused_inputs() {
         let mut checks = Default::default();
-        let nodes = vec![(
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let nodes = vec![NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port {
                     node: Node::In,
                     port: 3.into(),
                 },
                 to: 1.into(),
+                span: None,
             }],
-            vec![],
-            vec![],
-        )];
+            outputs: vec![],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        }];
         let tree = Program { nodes, tests: None };
         check(&tree, &mut checks);
         assert_eq!(checks.has_warnings(), true);
@@ -310,18 +368,20 @@ mod tests {
     #[test]
     fn test_warnings_about_unused_outputs() {
         let mut checks = Default::default();
-        let nodes = vec![(
-            Node::new_node(&"a"),
-            vec![],
-            vec![OutputMapping {
+        let nodes = vec![NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![],
+            outputs: vec![OutputMapping {
                 from: 1.into(),
                 to: Port {
                     node: Node::Out,
                     port: 3.into(),
                 },
+                span: None,
             }],
-            vec![],
-        )];
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        }];
         let tree = Program { nodes, tests: None };
         check(&tree, &mut checks);
         assert_eq!(checks.has_warnings(), true);
@@ -0,0 +1,172 @@
+//! Module checking that jumps target labels defined once in their node.
+//! Labels never jumped to are reported as warnings.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{CheckResult, Code, Diagnostic};
+use language::instruction::Operation;
+use language::span::Span;
+use language::syntax::NodeBlock;
+use language::syntax::Program;
+
+fn jump_target(op: &Operation) -> Option<&String> {
+    match op {
+        Operation::JMP(ref label)
+        | Operation::JEZ(ref label)
+        | Operation::JNZ(ref label)
+        | Operation::JLZ(ref label)
+        | Operation::JGZ(ref label) => Some(label),
+        _ => None,
+    }
+}
+
+fn collect_labels<'a>(
+    node: &'a NodeBlock,
+    result: &mut CheckResult,
+) -> HashMap<&'a String, Option<Span>> {
+    let mut labels = HashMap::new();
+    for (idx, op) in node.instructions.iter().enumerate() {
+        if let Operation::LABEL(ref label) = op {
+            let span = node.spans.operation(idx);
+            if labels.insert(label, span).is_some() {
+                result.add(
+                    Diagnostic::error(
+                        Code::DuplicatedLabel,
+                        format!(
+                            "Label {} is defined multiple times in node {}",
+                            label, node.node
+                        ),
+                    )
+                    .at(span)
+                    .in_node(&node.node),
+                );
+            }
+        }
+    }
+    labels
+}
+
+fn check_node(node: &NodeBlock, result: &mut CheckResult) {
+    let labels = collect_labels(node, result);
+
+    let mut used = HashSet::new();
+    for (idx, op) in node.instructions.iter().enumerate() {
+        if let Some(label) = jump_target(op) {
+            used.insert(label);
+            if !labels.contains_key(label) {
+                result.add(
+                    Diagnostic::error(
+                        Code::UndefinedLabel,
+                        format!(
+                            "Label {} from {} is not defined in node {}",
+                            label, op, node.node
+                        ),
+                    )
+                    .at(node.spans.operation(idx))
+                    .in_node(&node.node),
+                );
+            }
+        }
+    }
+
+    // Report in the order of definition
+    let mut reported = HashSet::new();
+    for op in &node.instructions {
+        if let Operation::LABEL(ref label) = op {
+            if !used.contains(label) && reported.insert(label) {
+                result.add(
+                    Diagnostic::warning(
+                        Code::UnusedLabel,
+                        format!("Label {} is never used in node {}", label, node.node),
+                    )
+                    .at(labels[label])
+                    .in_node(&node.node),
+                );
+            }
+        }
+    }
+}
+
+pub fn check(tree: &Program, result: &mut CheckResult) -> bool {
+    let initial_count = result.error_count();
+    for node in &tree.nodes {
+        check_node(node, result);
+    }
+
+    initial_count == result.error_count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use language::address::Node;
+    use language::span::NodeSpans;
+
+    fn label(name: &str) -> Operation {
+        Operation::LABEL(String::from(name))
+    }
+
+    fn jump(name: &str) -> Operation {
+        Operation::JEZ(String::from(name))
+    }
+
+    #[test]
+    fn test_check_valid_labels() {
+        let mut check = CheckResult::default();
+        let node = NodeBlock::new(Node::new_node("a"))
+            .instruction(label("start"))
+            .instruction(Operation::NEG)
+            .instruction(jump("start"));
+        check_node(&node, &mut check);
+        assert!(!check.has_errors());
+        assert!(!check.has_warnings());
+    }
+
+    #[test]
+    fn test_check_undefined_label() {
+        let mut check = CheckResult::default();
+        let node = NodeBlock {
+            spans: NodeSpans {
+                node: None,
+                operations: vec![Span::new(20, 3, 1), Span::new(24, 4, 3)],
+            },
+            ..NodeBlock::new(Node::new_node("a"))
+                .instruction(label("start"))
+                .instruction(Operation::JMP(String::from("strat")))
+        };
+        check_node(&node, &mut check);
+        assert_eq!(check.error_count(), 1);
+
+        let mut msgs = vec![];
+        check.print_report_into("a.io", |msg| msgs.push(String::from(msg)));
+        assert_eq!(
+            msgs[4],
+            "a.io:4:3: Label strat from JMP strat is not defined in node Node#a"
+        );
+    }
+
+    #[test]
+    fn test_check_duplicated_labels() {
+        let mut check = CheckResult::default();
+        let node = NodeBlock::new(Node::new_node("a"))
+            .instruction(label("start"))
+            .instruction(Operation::NEG)
+            .instruction(label("start"))
+            .instruction(jump("start"));
+        check_node(&node, &mut check);
+        assert_eq!(check.error_count(), 1);
+        assert!(!check.has_warnings());
+    }
+
+    #[test]
+    fn test_warn_about_unused_labels() {
+        let mut checks = CheckResult::default();
+        let node = NodeBlock::new(Node::new_node("a"))
+            .instruction(label("start"))
+            .instruction(Operation::NEG);
+        let tree = Program::default().node(node);
+        assert!(check(&tree, &mut checks));
+        assert_eq!(checks.warning_count(), 1);
+    }
+}
@@ -1,31 +1,38 @@
+mod deadlock;
 mod instruction;
 mod interface;
 mod io;
+mod labels;
 mod mapping;
 mod result;
 mod test;
 
-use crate::result::CheckResult;
+pub use crate::result::CheckResult;
+pub use language::diagnostic::{Code, Diagnostic, Severity};
 use language::syntax::Program;
 
 pub fn check(tree: &Program) -> CheckResult {
     let mut checks = Default::default();
-    // println!("{:?}", res);
-    if !mapping::check(tree, &mut checks) {
-        checks.add_error(String::from(" -> Mapping errors ..."));
-    }
-    if !interface::check(tree, &mut checks) {
-        checks.add_error(String::from(" -> Node interface errors ..."));
-    }
-    if !instruction::check(tree, &mut checks) {
-        checks.add_error(String::from(" -> Instruction errors ..."));
-    }
-    if !io::check(tree, &mut checks) {
-        checks.add_error(String::from(" -> IOs errors ..."));
-    }
-    if !test::check(tree, &mut checks) {
-        checks.add_error(String::from(" -> Tests errors ..."));
-    }
+    mapping::check(tree, &mut checks);
+    interface::check(tree, &mut checks);
+    instruction::check(tree, &mut checks);
+    labels::check(tree, &mut checks);
+    io::check(tree, &mut checks);
+    deadlock::check(tree, &mut checks);
+    test::check(tree, &mut checks);
+    checks
+}
+
+/// Checks a program missing some nodes, like nodes that could not be parsed.
+/// The checks needing the missing nodes are skipped: the mappings to them,
+/// as well as the inputs and outputs of the program and its test case.
+pub fn check_partial(tree: &Program) -> CheckResult {
+    let mut checks = Default::default();
+    mapping::check_known_nodes(tree, &mut checks);
+    interface::check(tree, &mut checks);
+    instruction::check(tree, &mut checks);
+    labels::check(tree, &mut checks);
+    deadlock::check(tree, &mut checks);
     checks
 }
 
@@ -34,52 +41,59 @@ mod tests {
     use super::*;
     use language::address::{Node, Port};
     use language::instruction::{Operation, ValuePointer};
-    use language::syntax::{InputMapping, OutputMapping};
+    use language::span::{NodeSpans, Span};
+    use language::syntax::{InputMapping, NodeBlock, OutputMapping};
 
     #[test]
     fn test_complete_check_stack() {
-        let src = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let src = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port {
                     node: Node::In,
                     port: 1.into(),
                 },
                 to: 1.into(),
+                span: None,
             }],
-            vec![OutputMapping {
+            outputs: vec![OutputMapping {
                 from: 1.into(),
                 to: Port {
                     node: Node::new_node(&"b"),
                     port: 2.into(),
                 },
+                span: None,
             }],
-            vec![Operation::MOV(
+            instructions: vec![Operation::MOV(
                 ValuePointer::INPUT(1.into()),
                 ValuePointer::OUTPUT(1.into()),
             )],
-        );
-        let dst = (
-            Node::new_node(&"b"),
-            vec![InputMapping {
+            spans: NodeSpans::default(),
+        };
+        let dst = NodeBlock {
+            node: Node::new_node(&"b"),
+            inputs: vec![InputMapping {
                 from: Port {
                     node: Node::new_node(&"a"),
                     port: 1.into(),
                 },
                 to: 2.into(),
+                span: None,
             }],
-            vec![OutputMapping {
+            outputs: vec![OutputMapping {
                 from: 2.into(),
                 to: Port {
                     node: Node::Out,
                     port: 3.into(),
                 },
+                span: None,
             }],
-            vec![Operation::MOV(
+            instructions: vec![Operation::MOV(
                 ValuePointer::INPUT(2.into()),
                 ValuePointer::OUTPUT(2.into()),
             )],
-        );
+            spans: NodeSpans::default(),
+        };
         let tree = vec![src, dst];
         let result = check(&Program {
             nodes: tree,
@@ -96,27 +110,43 @@ mod tests {
         assert_eq!(checks.error_count(), 0);
         assert_eq!(checks.warning_count(), 0);
 
-        checks.add_error(String::from("e"));
+        checks.add(Diagnostic::error(Code::Syntax, String::from("e")));
         assert_eq!(checks.has_errors(), true);
         assert_eq!(checks.has_warnings(), false);
         assert_eq!(checks.error_count(), 1);
         assert_eq!(checks.warning_count(), 0);
 
-        checks.add_warning(String::from("w1"));
-        checks.add_warning(String::from("w2"));
+        checks.add(Diagnostic::warning(Code::Deadlock, String::from("w1")));
+        checks.add(Diagnostic::warning(Code::Deadlock, String::from("w2")));
         assert_eq!(checks.has_errors(), true);
         assert_eq!(checks.has_warnings(), true);
         assert_eq!(checks.error_count(), 1);
         assert_eq!(checks.warning_count(), 2);
     }
 
+    #[test]
+    fn test_set_lint_levels() {
+        let mut checks = CheckResult::default();
+        checks.add(Diagnostic::error(Code::UndefinedLabel, String::from("e")));
+        checks.add(Diagnostic::warning(Code::UnusedLabel, String::from("w1")));
+        checks.add(Diagnostic::warning(Code::Deadlock, String::from("w2")));
+
+        checks.set_lint_level(None, Severity::Error);
+        assert_eq!(checks.error_count(), 3);
+        checks.set_lint_level(Some(Code::Deadlock), Severity::Warning);
+        assert_eq!(checks.error_count(), 2);
+        // Errors are not lints, and are never lowered
+        checks.set_lint_level(Some(Code::UndefinedLabel), Severity::Warning);
+        assert_eq!(checks.error_count(), 2);
+    }
+
     #[test]
     fn test_printing_report() {
         let mut checks = CheckResult::default();
-        checks.add_error(String::from("e"));
-        checks.add_warning(String::from("w"));
+        checks.add(Diagnostic::error(Code::Syntax, String::from("e")));
+        checks.add(Diagnostic::warning(Code::Deadlock, String::from("w")));
         let mut msgs = vec![];
-        checks.print_report_into(|msg| msgs.push(String::from(msg)));
+        checks.print_report_into("file.io", |msg| msgs.push(String::from(msg)));
 
         assert_eq!(
             msgs,
@@ -129,4 +159,27 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_printing_located_messages() {
+        let mut checks = CheckResult::default();
+        checks
+            .add(Diagnostic::error(Code::Syntax, String::from("e")).at(Some(Span::new(12, 2, 5))));
+        checks.add(
+            Diagnostic::warning(Code::Deadlock, String::from("w")).at(Some(Span::new(3, 1, 4))),
+        );
+        let mut msgs = vec![];
+        checks.print_report_into("file.io", |msg| msgs.push(String::from(msg)));
+
+        assert_eq!(
+            msgs,
+            vec![
+                " == TZIO compiler == ",
+                "1 Warnings in your project",
+                "file.io:1:4: w",
+                "1 Errors in your project",
+                "file.io:2:5: e"
+            ]
+        );
+    }
 }
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::result::CheckResult;
+use crate::{CheckResult, Code, Diagnostic};
 use language::address::Node;
 use language::syntax::NodeBlock;
 use language::syntax::Program;
@@ -16,7 +16,7 @@ use language::syntax::Program;
 type Index<'a> = HashMap<&'a String, usize>;
 // TODO move this method to some utility module
 fn map_node_to_idx<'a>(nodes: &'a [NodeBlock], index: &mut Index<'a>) {
-    for (i, &(ref node, _, _, _)) in nodes.iter().enumerate() {
+    for (i, NodeBlock { node, .. }) in nodes.iter().enumerate() {
         if let Node::Node(ref node_id) = node {
             index.insert(node_id, i);
         }
@@ -28,19 +28,20 @@ fn check_node_inputs(
     node: &NodeBlock,
     nodes: &[NodeBlock],
     index: &Index,
+    check_unknown: bool,
 ) {
-    let this_id = match &node.0 {
+    let this_id = match &node.node {
         Node::Node(ref id) => id,
         _ => panic!("Node of incorrect type"),
     };
-    let inputs = &node.1;
+    let inputs = &node.inputs;
     for input in inputs.iter() {
         if let Node::Node(ref src_id) = &input.from.node {
             let is_match = index
                 .get(src_id)
                 .map(|node_idx| &nodes[*node_idx])
                 .map(|ref src_node| {
-                    src_node.2.iter().any(|ref output|
+                    src_node.outputs.iter().any(|ref output|
             // Output m: i -> n:j <=> Input n: m:i -> j
             match &output.to.node {
               Node::Node(ref id) =>
@@ -50,13 +51,20 @@ fn check_node_inputs(
               _ => false
             })
                 })
-                .unwrap_or(false);
+                .unwrap_or(!check_unknown);
             if !is_match {
                 // TODO code display for input
-                result.add_error(format!(
-                    "No corresponding output for input {} of node {}",
-                    "<in>", /*input*/ this_id
-                ));
+                result.add(
+                    Diagnostic::error(
+                        Code::UnmatchedMapping,
+                        format!(
+                            "No corresponding output for input {} of node {}",
+                            "<in>", /*input*/ this_id
+                        ),
+                    )
+                    .at(input.span)
+                    .in_node(&node.node),
+                );
             }
         }
     }
@@ -67,19 +75,20 @@ fn check_node_outputs(
     node: &NodeBlock,
     nodes: &[NodeBlock],
     index: &Index,
+    check_unknown: bool,
 ) {
-    let this_id = match &node.0 {
+    let this_id = match &node.node {
         Node::Node(ref id) => id,
         _ => panic!("Node of incorrect type"),
     };
-    let outputs = &node.2;
+    let outputs = &node.outputs;
     for output in outputs.iter() {
         if let Node::Node(ref src_id) = &output.to.node {
             let is_match = index
                 .get(src_id)
                 .map(|node_idx| &nodes[*node_idx])
                 .map(|ref dst_node| {
-                    dst_node.1.iter().any(|ref input|
+                    dst_node.inputs.iter().any(|ref input|
             // Output m: i -> n:j <=> Input n: m:i -> j
             match &input.from.node {
               Node::Node(ref id) =>
@@ -89,19 +98,36 @@ fn check_node_outputs(
               _ => false
             })
                 })
-                .unwrap_or(false);
+                .unwrap_or(!check_unknown);
             if !is_match {
                 // TODO code display for input
-                result.add_error(format!(
-                    "No corresponding output for input {} of node {}",
-                    "<in>", /*input*/ this_id
-                ));
+                result.add(
+                    Diagnostic::error(
+                        Code::UnmatchedMapping,
+                        format!(
+                            "No corresponding output for input {} of node {}",
+                            "<in>", /*input*/ this_id
+                        ),
+                    )
+                    .at(output.span)
+                    .in_node(&node.node),
+                );
             }
         }
     }
 }
 
 pub fn check(tree: &Program, result: &mut CheckResult) -> bool {
+    check_mappings(tree, result, true)
+}
+
+/// Checks the mappings between the nodes of a program missing some nodes,
+/// like nodes that could not be parsed. Mappings to absent nodes are not checked.
+pub fn check_known_nodes(tree: &Program, result: &mut CheckResult) -> bool {
+    check_mappings(tree, result, false)
+}
+
+fn check_mappings(tree: &Program, result: &mut CheckResult, check_unknown: bool) -> bool {
     let mut index = HashMap::new();
     {
         map_node_to_idx(&tree.nodes, &mut index);
@@ -109,8 +135,8 @@ pub fn check(tree: &Program, result: &mut CheckResult) -> bool {
 
     let initial_count = result.error_count();
     for node in tree.nodes.iter() {
-        check_node_inputs(result, node, &tree.nodes, &index);
-        check_node_outputs(result, node, &tree.nodes, &index);
+        check_node_inputs(result, node, &tree.nodes, &index, check_unknown);
+        check_node_outputs(result, node, &tree.nodes, &index, check_unknown);
     }
 
     result.error_count() == initial_count
@@ -121,36 +147,41 @@ mod tests {
     use super::*;
 
     use language::address::Port;
+    use language::span::NodeSpans;
     use language::syntax::{InputMapping, OutputMapping};
 
     #[test]
     fn test_check_valid_mappings() {
         let mut check_result = Default::default();
 
-        let src = (
-            Node::new_node(&"a"),
-            vec![],
-            vec![OutputMapping {
+        let src = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![],
+            outputs: vec![OutputMapping {
                 from: 1.into(),
                 to: Port {
                     node: Node::new_node(&"b"),
                     port: 2.into(),
                 },
+                span: None,
             }],
-            vec![],
-        );
-        let dst = (
-            Node::new_node(&"b"),
-            vec![InputMapping {
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
+        let dst = NodeBlock {
+            node: Node::new_node(&"b"),
+            inputs: vec![InputMapping {
                 from: Port {
                     node: Node::new_node(&"a"),
                     port: 1.into(),
                 },
                 to: 2.into(),
+                span: None,
             }],
-            vec![],
-            vec![],
-        );
+            outputs: vec![],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         let nodes = vec![src, dst];
         let tree = Program { nodes, tests: None };
         let result = check(&tree, &mut check_result);
@@ -158,30 +189,34 @@ mod tests {
         assert_eq!(check_result.has_errors(), false);
         let mut check_result = Default::default();
 
-        let src = (
-            Node::new_node(&"a"),
-            vec![],
-            vec![OutputMapping {
+        let src = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![],
+            outputs: vec![OutputMapping {
                 from: 1.into(),
                 to: Port {
                     node: Node::new_node(&"b"),
                     port: 2.into(),
                 },
+                span: None,
             }],
-            vec![],
-        );
-        let dst = (
-            Node::new_node(&"b"),
-            vec![InputMapping {
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
+        let dst = NodeBlock {
+            node: Node::new_node(&"b"),
+            inputs: vec![InputMapping {
                 from: Port {
                     node: Node::new_node(&"a"),
                     port: 1.into(),
                 },
                 to: 2.into(),
+                span: None,
             }],
-            vec![],
-            vec![],
-        );
+            outputs: vec![],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         let nodes = vec![src, dst];
         let tree = Program { nodes, tests: None };
         let result = check(&tree, &mut check_result);
@@ -193,22 +228,24 @@ mod tests {
     fn test_check_invalid_mappings() {
         let mut check_result = Default::default();
 
-        let src = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let src = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port {
                     node: Node::In,
                     port: 1.into(),
                 },
                 to: 1.into(),
+                span: None,
             }],
-            vec![
+            outputs: vec![
                 OutputMapping {
                     from: 1.into(),
                     to: Port {
                         node: Node::new_node(&"b"),
                         port: 3.into(), // Incorrect port
                     },
+                    span: None,
                 },
                 OutputMapping {
                     from: 4.into(), // Incorrect port
@@ -216,6 +253,7 @@ mod tests {
                         node: Node::new_node(&"b"),
                         port: 2.into(),
                     },
+                    span: None,
                 },
                 OutputMapping {
                     from: 1.into(),
@@ -223,28 +261,33 @@ mod tests {
                         node: Node::new_node(&"c"), // Incorrect name
                         port: 2.into(),
                     },
+                    span: None,
                 },
             ],
-            vec![],
-        );
-        let dst = (
-            Node::new_node(&"b"),
-            vec![InputMapping {
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
+        let dst = NodeBlock {
+            node: Node::new_node(&"b"),
+            inputs: vec![InputMapping {
                 from: Port {
                     node: Node::new_node(&"a"),
                     port: 1.into(),
                 },
                 to: 2.into(),
+                span: None,
             }],
-            vec![OutputMapping {
+            outputs: vec![OutputMapping {
                 from: 1.into(),
                 to: Port {
                     node: Node::Out,
                     port: 1.into(),
                 },
+                span: None,
             }],
-            vec![],
-        );
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         let tree = Program {
             nodes: vec![src, dst],
             tests: None,
@@ -253,4 +296,33 @@ mod tests {
         assert_eq!(result, false);
         assert_eq!(check_result.error_count(), 4);
     }
+    #[test]
+    fn test_check_known_nodes() {
+        let mut check_result = Default::default();
+
+        // Node b is missing, like when it could not be parsed
+        let src = NodeBlock::new(Node::new_node("a"))
+            .input(
+                Port {
+                    node: Node::new_node("b"),
+                    port: 1.into(),
+                },
+                1.into(),
+            )
+            .output(
+                1.into(),
+                Port {
+                    node: Node::new_node("b"),
+                    port: 1.into(),
+                },
+            );
+        let tree = Program::default().node(src);
+        let result = check_known_nodes(&tree, &mut check_result);
+        assert!(result);
+        assert!(!check_result.has_errors());
+
+        let result = check(&tree, &mut check_result);
+        assert!(!result);
+        assert_eq!(check_result.error_count(), 2);
+    }
 }
@@ -1,61 +1,71 @@
+use language::diagnostic::{Code, Diagnostic, Severity};
+
+#[derive(Default)]
 pub struct CheckResult {
-    warnings: Vec<String>,
-    errors: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
 }
 
-impl Default for CheckResult {
-    fn default() -> Self {
-        CheckResult {
-            warnings: Vec::new(),
-            errors: Vec::new(),
-        }
+impl CheckResult {
+    pub fn add(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
     }
-}
 
-impl CheckResult {
-    pub fn add_error(&mut self, message: String) {
-        self.errors.push(message);
+    /// All diagnostics, in the order they were reported
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Changes the severity of the lints with a given code, or of all lints
+    pub fn set_lint_level(&mut self, code: Option<Code>, severity: Severity) {
+        for diagnostic in self.diagnostics.iter_mut() {
+            if diagnostic.code.is_lint() && code.iter().all(|c| *c == diagnostic.code) {
+                diagnostic.severity = severity;
+            }
+        }
     }
 
-    pub fn add_warning(&mut self, message: String) {
-        self.warnings.push(message);
+    fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(move |diagnostic| diagnostic.severity == severity)
     }
 
     pub fn has_errors(&self) -> bool {
-        !self.errors.is_empty()
+        self.error_count() > 0
     }
 
     pub fn has_warnings(&self) -> bool {
-        !self.warnings.is_empty()
+        self.warning_count() > 0
     }
 
     pub fn error_count(&self) -> usize {
-        self.errors.len()
+        self.with_severity(Severity::Error).count()
     }
 
     pub fn warning_count(&self) -> usize {
-        self.warnings.len()
+        self.with_severity(Severity::Warning).count()
     }
 
-    pub fn print_report(&self) {
-        self.print_report_into(|msg| println!("{}", msg));
+    /// Prints the report, locating messages as `source:line:col`
+    pub fn print_report(&self, source: &str) {
+        self.print_report_into(source, |msg| println!("{}", msg));
     }
 
-    pub(crate) fn print_report_into<F: FnMut(&str)>(&self, mut out: F) {
-        out(&" == TZIO compiler == ");
+    pub(crate) fn print_report_into<F: FnMut(&str)>(&self, source: &str, mut out: F) {
+        out(" == TZIO compiler == ");
         if self.has_warnings() {
             out(&format!(
                 "{} Warnings in your project",
                 self.warning_count()
             ));
-            for warning in &self.warnings {
-                out(&warning);
+            for warning in self.with_severity(Severity::Warning) {
+                out(&warning.format(source));
             }
         }
         if self.has_errors() {
             out(&format!("{} Errors in your project", self.error_count()));
-            for error in &self.errors {
-                out(&error);
+            for error in self.with_severity(Severity::Error) {
+                out(&error.format(source));
             }
         }
     }
@@ -1,29 +1,34 @@
-use crate::result::CheckResult;
+use crate::{CheckResult, Code, Diagnostic};
 use language::address::{Node, Port};
 use language::syntax::Program;
 use language::syntax::{InputMapping, NodeBlock, OutputMapping};
 use language::test::TestCase;
 
 fn is_reading_in(mapping: &InputMapping) -> bool {
-    matches!(mapping, InputMapping {
-            from:
-                Port {
-                    node: Node::In,
-                    port: _,
-                },
-            to: _
-    })
+    matches!(
+        mapping,
+        InputMapping {
+            from: Port {
+                node: Node::In,
+                port: _,
+            },
+            ..
+        }
+    )
 }
 
 fn is_writing_out(mapping: &&OutputMapping) -> bool {
-    matches!(mapping, OutputMapping {
-        from: _,
-        to:
-            Port {
+    matches!(
+        mapping,
+        OutputMapping {
+            from: _,
+            to: Port {
                 node: Node::Out,
                 port: _,
             },
-    })
+            ..
+        }
+    )
 }
 
 /// Module checking that the tests are correctly formed.
@@ -33,8 +38,8 @@ fn count_ios(nodes: &[NodeBlock]) -> Counts {
     let mut ins = 0;
     let mut outs = 0;
     for node in nodes {
-        ins += node.1.iter().filter(|i| is_reading_in(i)).count();
-        outs += node.2.iter().filter(|o| is_writing_out(o)).count();
+        ins += node.inputs.iter().filter(|i| is_reading_in(i)).count();
+        outs += node.outputs.iter().filter(|o| is_writing_out(o)).count();
     }
 
     (ins, outs)
@@ -43,29 +48,47 @@ fn count_ios(nodes: &[NodeBlock]) -> Counts {
 fn check_test_inputs(test: &TestCase, result: &mut CheckResult, input_count: usize) {
     for input_slot in test.ins.keys() {
         if *input_slot == 0.into() || *input_slot > (input_count as u8).into() {
-            result.add_error(format!(
-                "Test case {:?} has values for input {} that does not exist",
-                test, *input_slot,
-            ));
+            result.add(
+                Diagnostic::error(
+                    Code::InvalidTestCase,
+                    format!(
+                        "Test case {:?} has values for input {} that does not exist",
+                        test, *input_slot,
+                    ),
+                )
+                .at(test.span),
+            );
         }
     }
 
     match test.ins.len().cmp(&input_count) {
         std::cmp::Ordering::Greater => {
-            result.add_error(format!(
-                "Test case {:?} has too many inputs ({} / {})",
-                test,
-                test.ins.len(),
-                input_count
-            ));
+            result.add(
+                Diagnostic::error(
+                    Code::InvalidTestCase,
+                    format!(
+                        "Test case {:?} has too many inputs ({} / {})",
+                        test,
+                        test.ins.len(),
+                        input_count
+                    ),
+                )
+                .at(test.span),
+            );
         }
         std::cmp::Ordering::Less => {
-            result.add_error(format!(
-                "Test case {:?} does not have enough inputs ({} / {}).",
-                test,
-                test.ins.len(),
-                input_count
-            ));
+            result.add(
+                Diagnostic::error(
+                    Code::InvalidTestCase,
+                    format!(
+                        "Test case {:?} does not have enough inputs ({} / {}).",
+                        test,
+                        test.ins.len(),
+                        input_count
+                    ),
+                )
+                .at(test.span),
+            );
         }
         _ => {}
     };
@@ -74,29 +97,47 @@ fn check_test_inputs(test: &TestCase, result: &mut CheckResult, input_count: usi
 fn check_test_outputs(test: &TestCase, result: &mut CheckResult, output_count: usize) {
     for output_slot in test.outs.keys() {
         if *output_slot == 0.into() || *output_slot > (output_count as u8).into() {
-            result.add_error(format!(
-                "Test case {:?} has values for output {} that does not exist",
-                test, *output_slot,
-            ));
+            result.add(
+                Diagnostic::error(
+                    Code::InvalidTestCase,
+                    format!(
+                        "Test case {:?} has values for output {} that does not exist",
+                        test, *output_slot,
+                    ),
+                )
+                .at(test.span),
+            );
         }
     }
 
     match test.outs.len().cmp(&output_count) {
         std::cmp::Ordering::Greater => {
-            result.add_error(format!(
-                "Test case {:?} has too many outputs ({} / {})",
-                test,
-                test.ins.len(),
-                output_count
-            ));
+            result.add(
+                Diagnostic::error(
+                    Code::InvalidTestCase,
+                    format!(
+                        "Test case {:?} has too many outputs ({} / {})",
+                        test,
+                        test.ins.len(),
+                        output_count
+                    ),
+                )
+                .at(test.span),
+            );
         }
         std::cmp::Ordering::Less => {
-            result.add_error(format!(
-                "Test case {:?} does not have enough outputs ({} / {}).",
-                test,
-                test.ins.len(),
-                output_count
-            ));
+            result.add(
+                Diagnostic::error(
+                    Code::InvalidTestCase,
+                    format!(
+                        "Test case {:?} does not have enough outputs ({} / {}).",
+                        test,
+                        test.ins.len(),
+                        output_count
+                    ),
+                )
+                .at(test.span),
+            );
         }
         _ => {}
     }
@@ -123,39 +164,45 @@ pub fn check(tree: &Program, result: &mut CheckResult) -> bool {
 mod tests {
     use super::*;
     use language::address::Port;
+    use language::span::NodeSpans;
     use language::syntax::{InputMapping, OutputMapping};
 
     fn create_nodes() -> Vec<NodeBlock> {
-        let src = (
-            Node::new_node(&"a"),
-            vec![InputMapping {
+        let src = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![InputMapping {
                 from: Port {
                     node: Node::In,
                     port: 1.into(),
                 },
                 to: 2.into(),
+                span: None,
             }],
-            vec![OutputMapping {
+            outputs: vec![OutputMapping {
                 from: 1.into(),
                 to: Port {
                     node: Node::Out,
                     port: 2.into(),
                 },
+                span: None,
             }],
-            vec![],
-        );
-        let dst = (
-            Node::new_node(&"b"),
-            vec![InputMapping {
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
+        let dst = NodeBlock {
+            node: Node::new_node(&"b"),
+            inputs: vec![InputMapping {
                 from: Port {
                     node: Node::In,
                     port: 1.into(),
                 },
                 to: 2.into(),
+                span: None,
             }],
-            vec![],
-            vec![],
-        );
+            outputs: vec![],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         vec![src, dst]
     }
 
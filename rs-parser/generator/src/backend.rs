@@ -0,0 +1,204 @@
+//! Module defining the platforms programs can be generated for.
+
+use std::path::{Path, PathBuf};
+
+use language::syntax::Program;
+
+use crate::java::JavaBackend;
+use crate::llvm::LlvmBackend;
+
+type BackendFactory = fn() -> Box<dyn Backend>;
+
+/// Backends the generator can write programs with, by name of their target
+const BACKENDS: [(&str, BackendFactory); 2] = [
+    ("java", || Box::new(JavaBackend::default())),
+    ("llvm", || Box::new(LlvmBackend::default())),
+];
+
+/// Names of the targets the generator can write programs for
+pub fn targets() -> Vec<&'static str> {
+    BACKENDS.iter().map(|(name, _)| *name).collect()
+}
+
+/// Failure to generate a program
+#[derive(Debug, PartialEq)]
+pub enum GenerateError {
+    /// Program that cannot be represented on the target
+    Program(String),
+    /// Generated files that cannot be written
+    Output(String),
+}
+
+/// Platform receiving generated programs.
+///
+/// A backend decides the layout of its output and the naming of its files.
+pub trait Backend {
+    /// Name of the target, as given on the command line
+    fn name(&self) -> &'static str;
+
+    /// Sets an option specific to this backend
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let _ = value;
+        Err(format!("Unknown option {} for target {}", key, self.name()))
+    }
+
+    /// Generates a program under the output directory.
+    /// This returns the path to the main generated file, like the main class of java programs.
+    ///
+    /// # Arguments
+    ///  - `program` - definition of the program to write
+    ///  - `name` - name of the program, usually the stem of its source file
+    ///  - `source_file` - path to the source file of the program, for debug information
+    ///  - `output_dir` - directory receiving the generated files
+    fn generate(
+        &self,
+        program: &Program,
+        name: &str,
+        source_file: &str,
+        output_dir: &Path,
+    ) -> Result<PathBuf, GenerateError>;
+}
+
+/// Creates the backend of a target, with its default options
+pub fn create_backend(target: &str) -> Result<Box<dyn Backend>, String> {
+    BACKENDS
+        .iter()
+        .find(|(name, _)| *name == target)
+        .map(|(_, create)| create())
+        .ok_or_else(|| {
+            format!(
+                "Unsupported target {}, expected one of {}",
+                target,
+                targets().join(", ")
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use language::address::{Node, Port};
+    use language::instruction::{Operation, ValuePointer};
+    use language::syntax::NodeBlock;
+
+    #[test]
+    fn test_create_backends() {
+        for target in targets() {
+            assert_eq!(create_backend(target).unwrap().name(), target);
+        }
+        assert!(create_backend("wasm").is_err());
+    }
+
+    #[test]
+    fn test_set_options() {
+        let mut backend = create_backend("llvm").unwrap();
+        assert!(backend.set_option("max-ticks", "100").is_ok());
+        assert!(backend.set_option("max-ticks", "many").is_err());
+        assert!(backend.set_option("unknown", "1").is_err());
+
+        let mut backend = create_backend("java").unwrap();
+        assert!(backend.set_option("mode", "compiled").is_ok());
+        assert!(backend.set_option("mode", "jit").is_err());
+        assert!(backend.set_option("output", "jar").is_ok());
+        assert!(backend.set_option("output", "war").is_err());
+        assert!(backend
+            .set_option("class-path", "lib/a.jar lib/b.jar")
+            .is_ok());
+        assert!(backend
+            .set_option("class-name", "org.example.Program")
+            .is_ok());
+        assert!(backend
+            .set_option("class-name", "org.max-stream.Main")
+            .is_err());
+        assert!(backend.set_option("max-ticks", "100").is_err());
+    }
+
+    #[test]
+    fn test_report_invalid_programs() {
+        let mut node = NodeBlock::new(Node::new_node("a"))
+            .input(Port::new(Node::In, 1.into()), 1.into())
+            .output(1.into(), Port::new(Node::Out, 1.into()));
+        for _ in 0..3000 {
+            node = node.instruction(Operation::MOV(
+                ValuePointer::INPUT(1.into()),
+                ValuePointer::OUTPUT(1.into()),
+            ));
+        }
+        let program = Program::default().node(node);
+
+        let mut backend = create_backend("java").unwrap();
+        backend.set_option("mode", "compiled").unwrap();
+        let output_dir = std::env::temp_dir().join("tzio-invalid-program");
+        match backend.generate(&program, "large", "large.io", &output_dir) {
+            Err(GenerateError::Program(_)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    /// Creates a program copying its input to its output
+    fn copy_program() -> Program {
+        let node = NodeBlock::new(Node::new_node("a"))
+            .input(Port::new(Node::In, 1.into()), 1.into())
+            .output(1.into(), Port::new(Node::Out, 1.into()))
+            .instruction(Operation::MOV(
+                ValuePointer::INPUT(1.into()),
+                ValuePointer::OUTPUT(1.into()),
+            ));
+        Program::default().node(node)
+    }
+
+    #[test]
+    fn test_locate_generated_programs() {
+        let program = copy_program();
+        let output_dir = std::env::temp_dir().join("tzio-generated-programs");
+
+        let backend = create_backend("java").unwrap();
+        let main_file = backend
+            .generate(&program, "max-stream", "max-stream.io", &output_dir)
+            .unwrap();
+        assert_eq!(
+            main_file,
+            output_dir.join("com/kineolyan/tzio/max_stream/Main.class")
+        );
+
+        // The configured class name wins over the name of the program
+        let mut backend = create_backend("java").unwrap();
+        backend
+            .set_option("class-name", "org.example.Program")
+            .unwrap();
+        let main_file = backend
+            .generate(&program, "max-stream", "max-stream.io", &output_dir)
+            .unwrap();
+        assert_eq!(main_file, output_dir.join("org/example/Program.class"));
+        assert!(main_file.is_file());
+
+        // The name of the program still names the JAR
+        backend.set_option("output", "jar").unwrap();
+        let main_file = backend
+            .generate(&program, "max-stream", "max-stream.io", &output_dir)
+            .unwrap();
+        assert_eq!(main_file, output_dir.join("max-stream.jar"));
+
+        let backend = create_backend("llvm").unwrap();
+        let main_file = backend
+            .generate(&program, "max-stream", "max-stream.io", &output_dir)
+            .unwrap();
+        assert_eq!(main_file, output_dir.join("max-stream.ll"));
+    }
+
+    #[test]
+    fn test_report_stale_classes() {
+        let program = copy_program();
+
+        let mut backend = create_backend("java").unwrap();
+        backend.set_option("class-name", "Stale").unwrap();
+        // A directory named like a nested class of a previous build cannot be deleted
+        let output_dir = std::env::temp_dir().join("tzio-stale-classes");
+        std::fs::create_dir_all(output_dir.join("Stale$Old.class")).unwrap();
+        match backend.generate(&program, "stale", "stale.io", &output_dir) {
+            Err(GenerateError::Output(message)) => assert!(message.contains("Stale$Old.class")),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
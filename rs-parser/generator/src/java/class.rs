@@ -1,6 +1,7 @@
-use crate::java::constants::{ArrayType, Type};
-use crate::java::constructs::{Attribute, Method, Signature};
+use crate::java::constants::{ArrayType, MethodAccess, Type};
+use crate::java::constructs::{Attribute, Field, Method, Operation, Signature};
 use crate::java::dictionary::{Dictionary, DictionaryIter};
+use crate::java::frames;
 use std::cmp::Eq;
 
 pub type PoolIdx = u16;
@@ -21,6 +22,13 @@ pub enum PoolElement {
     ///  1. Index to the UTF8 value
     /// ```
     String(PoolIdx),
+    /// Info refering to a field
+    /// Structure
+    /// ```md
+    ///  1. Index to class info
+    ///  2. Index to a name & type info
+    /// ```
+    FieldRef(PoolIdx, PoolIdx),
     /// Info refering to a method
     /// Structure
     /// ```md
@@ -55,7 +63,10 @@ pub struct JavaClass {
     pub class_id: PoolIdx,
     pub super_class_id: PoolIdx,
     pub interfaces: Vec<PoolIdx>,
+    pub fields: Vec<Field>,
     pub methods: Vec<Method>,
+    /// Name of the source file of the class, written as its SourceFile attribute
+    pub source_file: Option<PoolIdx>,
 }
 
 impl JavaClass {
@@ -65,7 +76,9 @@ impl JavaClass {
             class_id: 0,
             super_class_id: 0,
             interfaces: Vec::new(),
+            fields: Vec::new(),
             methods: Vec::new(),
+            source_file: None,
         }
     }
 
@@ -77,6 +90,12 @@ impl JavaClass {
         self.super_class_id = self.map_class(classname);
     }
 
+    /// Sets the name of the file the class is compiled from, without its directories
+    pub fn set_source_file(&mut self, filename: &str) {
+        self.map_utf8_value(SOURCE_FILE);
+        self.source_file = Some(self.map_utf8_value(filename));
+    }
+
     pub fn get_class_name(&self) -> Option<String> {
         self.class_pool
             .get(&self.class_id)
@@ -95,6 +114,28 @@ impl JavaClass {
         self.class_pool.map(PoolElement::Integer(value))
     }
 
+    /// Gets the operation pushing an integer to the operand stack.
+    ///
+    /// Only values too large for sipush are mapped in the constant pool.
+    pub fn push_integer(&mut self, value: i32) -> Operation {
+        match value {
+            -1 => Operation::iconst_m1,
+            0 => Operation::iconst_0,
+            1 => Operation::iconst_1,
+            2 => Operation::iconst_2,
+            3 => Operation::iconst_3,
+            4 => Operation::iconst_4,
+            5 => Operation::iconst_5,
+            _ if value >= i8::MIN as i32 && value <= i8::MAX as i32 => {
+                Operation::bipush(value as i8)
+            }
+            _ if value >= i16::MIN as i32 && value <= i16::MAX as i32 => {
+                Operation::sipush(value as i16)
+            }
+            _ => Operation::ldc(self.map_integer(value as u32)),
+        }
+    }
+
     pub fn map_string(&mut self, value: &str) -> PoolIdx {
         let value_idx = self.map_utf8_value(value);
         self.class_pool.map(PoolElement::String(value_idx))
@@ -105,19 +146,46 @@ impl JavaClass {
     /// Unlike #map_method that creates a reference to an existing
     /// method, this method creates a new method for the class.
     /// The declaration includes the operations executed by the method
-    /// as well as its metadata
+    /// as well as its metadata.
+    /// This fails if the operations are not valid for the signature of the method.
     pub fn create_method(
         &mut self,
         access: u16,
         method_name: &str,
         signature: Signature,
-        attributes: Vec<Attribute>,
-    ) -> PoolIdx {
+        mut attributes: Vec<Attribute>,
+    ) -> Result<PoolIdx, String> {
         let name_idx = self.map_utf8_value(method_name);
         let descriptor = create_descriptor(&signature);
         let descriptor_idx = self.map_utf8_value(&descriptor);
         let attr_idx = self.map_utf8_value(&"Code");
 
+        let is_static = access & (MethodAccess::STATIC as u16) != 0;
+        let mut stack_map = None;
+        for attribute in attributes.iter_mut() {
+            let Attribute::Code {
+                ref operations,
+                ref mut max_stack,
+                ..
+            } = attribute;
+            let analysis =
+                frames::analyse_code(self, is_static, method_name, &signature, operations)
+                    .map_err(|e| format!("Invalid code for method {}: {}", method_name, e))?;
+            *max_stack = analysis.max_stack;
+            if !analysis.stack_map.frames.is_empty() {
+                // Frames are written after the code
+                self.map_utf8_value(STACK_MAP_TABLE);
+                stack_map = Some(analysis.stack_map);
+            }
+            if operations
+                .iter()
+                .any(|operation| matches!(operation, Operation::line(_)))
+            {
+                // Lines are written after the code
+                self.map_utf8_value(LINE_NUMBER_TABLE);
+            }
+        }
+
         self.methods.push(Method {
             access,
             name_index: name_idx,
@@ -126,9 +194,38 @@ impl JavaClass {
                 .into_iter()
                 .map(|attr| (attr_idx, attr))
                 .collect(),
+            stack_map,
         });
 
-        self.map_self_method(method_name, &signature)
+        Ok(self.map_self_method(method_name, &signature))
+    }
+
+    /// Creates a new field in the class
+    ///
+    /// This returns the reference to the field, usable with getfield and putfield
+    pub fn create_field(&mut self, access: u16, field_name: &str, field_type: &Type) -> PoolIdx {
+        let name_idx = self.map_utf8_value(field_name);
+        let descriptor_idx = self.map_utf8_value(&field_descriptor(field_type));
+        self.fields.push(Field {
+            access,
+            name_index: name_idx,
+            descriptor_index: descriptor_idx,
+        });
+
+        let class_name = self.get_class_name().expect("Class name not defined yet");
+        self.map_field(&class_name, field_name, field_type)
+    }
+
+    /// Maps a field existing in this or another class.
+    pub fn map_field(&mut self, class_name: &str, field_name: &str, field_type: &Type) -> PoolIdx {
+        let class_idx = self.map_class(class_name);
+        let name_idx = self.map_utf8_value(field_name);
+        let descr_idx = self.map_utf8_value(&field_descriptor(field_type));
+        let name_and_type = self
+            .class_pool
+            .map(PoolElement::NameAndType(name_idx, descr_idx));
+        self.class_pool
+            .map(PoolElement::FieldRef(class_idx, name_and_type))
     }
 
     fn map_name_and_type(&mut self, method_name: &str, signature: &Signature) -> PoolIdx {
@@ -191,6 +288,17 @@ impl JavaClass {
         self.class_pool.map(info)
     }
 
+    /// Gets the index of an UTF8 value already in the class pool
+    pub fn find_utf8_value(&self, value: &str) -> Option<PoolIdx> {
+        self.class_pool
+            .find(&PoolElement::Utf8Value(String::from(value)))
+    }
+
+    /// Gets an element of the class pool
+    pub fn pool_element(&self, idx: PoolIdx) -> Option<&PoolElement> {
+        self.class_pool.get(&idx)
+    }
+
     /// Gets an iterator on all elements of the class pool
     ///
     /// Elements are enumrated by increasing pool idx.
@@ -202,6 +310,25 @@ impl JavaClass {
     pub fn pool_size(&self) -> PoolIdx {
         self.class_pool.size()
     }
+
+    /// Tells if the class has more constants than a class pool can hold
+    pub fn is_pool_overflowed(&self) -> bool {
+        self.class_pool.is_overflowed()
+    }
+}
+
+/// Name of the attribute describing the frames of a method
+pub const STACK_MAP_TABLE: &str = "StackMapTable";
+/// Name of the attribute mapping the code of a method to source lines
+pub const LINE_NUMBER_TABLE: &str = "LineNumberTable";
+/// Name of the attribute naming the source file of a class
+pub const SOURCE_FILE: &str = "SourceFile";
+
+/// Gets the descriptor of the type of a field
+pub fn field_descriptor(field_type: &Type) -> String {
+    let mut descriptor = String::new();
+    type_to_str(&mut descriptor, field_type);
+    descriptor
 }
 
 fn create_descriptor(signature: &Signature) -> String {
@@ -301,6 +428,21 @@ mod tests {
             let e2 = pool.get(&i2).unwrap();
             assert_eq!(e2, &PoolElement::NameAndType(2, 3));
         }
+
+        #[test]
+        fn test_detect_overflow() {
+            let mut pool = ClassPool::new();
+            for i in 0..(u16::MAX as u32 - 1) {
+                pool.map(PoolElement::Integer(i));
+            }
+            assert!(!pool.is_overflowed());
+            assert_eq!(pool.size(), u16::MAX);
+
+            let idx = pool.map(PoolElement::Integer(u32::MAX));
+            assert!(pool.is_overflowed());
+            assert_eq!(idx, u16::MAX);
+            assert_eq!(pool.size(), u16::MAX);
+        }
     }
 
     mod base {
@@ -374,6 +516,82 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_push_integer() {
+            let mut c = JavaClass::new();
+            assert_eq!(c.push_integer(-1), Operation::iconst_m1);
+            assert_eq!(c.push_integer(0), Operation::iconst_0);
+            assert_eq!(c.push_integer(5), Operation::iconst_5);
+            assert_eq!(c.push_integer(6), Operation::bipush(6));
+            assert_eq!(c.push_integer(-128), Operation::bipush(-128));
+            assert_eq!(c.push_integer(128), Operation::sipush(128));
+            assert_eq!(c.push_integer(-32768), Operation::sipush(-32768));
+            assert_eq!(c.pool_size(), 1);
+
+            let op = c.push_integer(32768);
+            assert_eq!(op, Operation::ldc(1));
+            assert_eq!(
+                c.pool_iter().next().unwrap().1,
+                &PoolElement::Integer(32768)
+            );
+        }
+
+        #[test]
+        fn test_create_field() {
+            let mut c = JavaClass::new();
+            c.set_class("the/Class");
+            let field_idx = c.create_field(0, "acc", &Type::Integer);
+            assert_eq!(c.fields.len(), 1);
+            assert_eq!(c.map_field("the/Class", "acc", &Type::Integer), field_idx);
+
+            let elements: Vec<&PoolElement> = c.pool_iter().map(|elt| elt.1).collect();
+            assert!(elements.contains(&&PoolElement::Utf8Value(String::from("acc"))));
+            assert!(elements.contains(&&PoolElement::Utf8Value(String::from("I"))));
+            assert!(matches!(
+                elements[(field_idx - 1) as usize],
+                PoolElement::FieldRef(_, _)
+            ));
+        }
+
+        #[test]
+        fn test_map_frames_for_labels() {
+            let mut c = JavaClass::new();
+            c.set_class("the/Class");
+            let signature = Signature {
+                return_type: Type::Void,
+                parameter_types: vec![],
+            };
+            c.create_method(
+                0,
+                "linear",
+                signature.clone(),
+                vec![Attribute::Code {
+                    max_stack: 0,
+                    operations: vec![Operation::return_void],
+                    locals: 0,
+                }],
+            )
+            .unwrap();
+            assert_eq!(c.find_utf8_value(STACK_MAP_TABLE), None);
+
+            c.create_method(
+                0,
+                "branching",
+                signature,
+                vec![Attribute::Code {
+                    max_stack: 0,
+                    operations: vec![
+                        Operation::goto(1),
+                        Operation::label(1),
+                        Operation::return_void,
+                    ],
+                    locals: 0,
+                }],
+            )
+            .unwrap();
+            assert!(c.find_utf8_value(STACK_MAP_TABLE).is_some());
+        }
+
         #[test]
         fn test_map_method() {
             let mut c = JavaClass::new();
@@ -453,19 +671,41 @@ mod tests {
         }
 
         #[test]
-        fn test_create_method() {
+        fn test_reject_invalid_method_code() {
             let mut c = JavaClass::new();
             c.set_class("the/Class");
-            let access: u16 = (MethodAccess::FINAL as u16) | (MethodAccess::PROTECTED as u16);
-            let return_idx = c.create_method(
-                access,
-                "aMethod",
+            let result = c.create_method(
+                0,
+                "broken",
                 Signature {
-                    return_type: Type::Integer,
+                    return_type: Type::Void,
                     parameter_types: vec![],
                 },
-                vec![],
+                vec![Attribute::Code {
+                    max_stack: 1,
+                    operations: vec![Operation::iadd, Operation::return_void],
+                    locals: 1,
+                }],
             );
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn test_create_method() {
+            let mut c = JavaClass::new();
+            c.set_class("the/Class");
+            let access: u16 = (MethodAccess::FINAL as u16) | (MethodAccess::PROTECTED as u16);
+            let return_idx = c
+                .create_method(
+                    access,
+                    "aMethod",
+                    Signature {
+                        return_type: Type::Integer,
+                        parameter_types: vec![],
+                    },
+                    vec![],
+                )
+                .unwrap();
 
             let pool_size = c.pool_size();
             {
@@ -0,0 +1,565 @@
+//! Module compiling the operations of a node into the `step` method of a dedicated class.
+//!
+//! The class extends `CompiledNode`, keeping the node value, memory and current operation
+//! in fields. `step` switches on the current operation, checks that its inputs can be read
+//! and its outputs written, then runs it and moves to the next operation.
+
+use std::collections::HashMap;
+
+use language::instruction::{MemoryPointer, Operation, ValuePointer};
+use language::syntax::NodeBlock;
+
+use crate::java::class::{JavaClass, PoolIdx};
+use crate::java::constants::{ArrayType, MethodAccess, Type};
+use crate::java::constructs::{self, Label, Signature};
+
+pub const COMPILED_NODE_CLASS_NAME: &str = "com/kineolyan/tzio/v1/api/nodes/CompiledNode";
+const INPUT_PORT_CLASS_NAME: &str = "com/kineolyan/tzio/v1/api/nodes/InputPort";
+const OUTPUT_PORT_CLASS_NAME: &str = "com/kineolyan/tzio/v1/api/nodes/OutputPort";
+
+/// Pool references used by the step method
+struct NodeReferences {
+    acc: PoolIdx,
+    step: PoolIdx,
+    memory: PoolIdx,
+    inputs: PoolIdx,
+    outputs: PoolIdx,
+    can_read: PoolIdx,
+    read: PoolIdx,
+    can_write: PoolIdx,
+    write: PoolIdx,
+    floor_mod: PoolIdx,
+}
+
+/// Writer of the step method, allocating labels
+struct StepWriter<'a> {
+    class: &'a mut JavaClass,
+    refs: NodeReferences,
+    node: &'a NodeBlock,
+    operations: Vec<constructs::Operation>,
+    next_label: Label,
+}
+
+impl<'a> StepWriter<'a> {
+    fn new_label(&mut self) -> Label {
+        self.next_label += 1;
+        self.next_label
+    }
+
+    fn push(&mut self, operation: constructs::Operation) {
+        self.operations.push(operation);
+    }
+
+    fn push_int(&mut self, value: i32) {
+        let operation = self.class.push_integer(value);
+        self.push(operation);
+    }
+
+    fn input_index(&self, port: &language::address::InputSlot) -> Result<i32, String> {
+        self.node
+            .inputs
+            .iter()
+            .position(|input| input.to == *port)
+            .map(|idx| idx as i32)
+            .ok_or_else(|| {
+                format!(
+                    "Port {} is not defined in node {} inputs",
+                    port, self.node.node
+                )
+            })
+    }
+
+    fn output_index(&self, port: &language::address::OutputSlot) -> Result<i32, String> {
+        self.node
+            .outputs
+            .iter()
+            .position(|output| output.from == *port)
+            .map(|idx| idx as i32)
+            .ok_or_else(|| {
+                format!(
+                    "Port {} is not defined in node {} outputs",
+                    port, self.node.node
+                )
+            })
+    }
+
+    /// Pushes the port of an input or an output on the stack
+    fn load_port(&mut self, array_field: PoolIdx, idx: i32) {
+        self.push(constructs::Operation::aload(0));
+        self.push(constructs::Operation::getfield(array_field));
+        self.push_int(idx);
+        self.push(constructs::Operation::aaload);
+    }
+
+    /// Leaves the method when a value cannot be read
+    fn check_readable(&mut self, pointer: &ValuePointer, blocked: Label) -> Result<(), String> {
+        if let ValuePointer::INPUT(ref port) = pointer {
+            let idx = self.input_index(port)?;
+            self.load_port(self.refs.inputs, idx);
+            self.push(constructs::Operation::invokeinterface(
+                self.refs.can_read,
+                1,
+            ));
+            self.push(constructs::Operation::ifeq(blocked));
+        }
+        Ok(())
+    }
+
+    /// Leaves the method when a value cannot be written
+    fn check_writable(&mut self, pointer: &ValuePointer, blocked: Label) -> Result<(), String> {
+        if let ValuePointer::OUTPUT(ref port) = pointer {
+            let idx = self.output_index(port)?;
+            self.load_port(self.refs.outputs, idx);
+            self.push(constructs::Operation::invokeinterface(
+                self.refs.can_write,
+                1,
+            ));
+            self.push(constructs::Operation::ifeq(blocked));
+        }
+        Ok(())
+    }
+
+    /// Pushes a value on the stack
+    fn read(&mut self, pointer: &ValuePointer) -> Result<(), String> {
+        match pointer {
+            ValuePointer::VALUE(value) => self.push_int(*value as i32),
+            ValuePointer::NIL => self.push_int(0),
+            ValuePointer::ACC => self.load_acc(),
+            ValuePointer::INPUT(ref port) => {
+                let idx = self.input_index(port)?;
+                self.load_port(self.refs.inputs, idx);
+                self.push(constructs::Operation::invokeinterface(self.refs.read, 1));
+            }
+            ValuePointer::OUTPUT(_) => {
+                return Err(format!(
+                    "Cannot read from {} in {}",
+                    pointer, self.node.node
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn load_acc(&mut self) {
+        self.push(constructs::Operation::aload(0));
+        self.push(constructs::Operation::getfield(self.refs.acc));
+    }
+
+    /// Pushes the memory and the index of a memory slot on the stack
+    fn load_memory_slot(&mut self, pointer: &MemoryPointer) -> Result<(), String> {
+        let &MemoryPointer::BAK(slot) = pointer;
+        if slot == 0 {
+            return Err(format!("Memory slots start at 1 in {}", self.node.node));
+        }
+        self.push(constructs::Operation::aload(0));
+        self.push(constructs::Operation::getfield(self.refs.memory));
+        self.push_int(slot as i32 - 1);
+        Ok(())
+    }
+
+    /// Moves to the given operation and leaves the method
+    fn finish(&mut self, step: i32) {
+        self.push(constructs::Operation::aload(0));
+        self.push_int(step);
+        self.push(constructs::Operation::putfield(self.refs.step));
+        self.push(constructs::Operation::return_void);
+    }
+
+    fn write_mov(
+        &mut self,
+        from: &ValuePointer,
+        to: &ValuePointer,
+        blocked: Label,
+    ) -> Result<(), String> {
+        self.check_readable(from, blocked)?;
+        self.check_writable(to, blocked)?;
+        match to {
+            ValuePointer::ACC => {
+                self.push(constructs::Operation::aload(0));
+                self.read(from)?;
+                self.push(constructs::Operation::putfield(self.refs.acc));
+            }
+            ValuePointer::NIL => {
+                self.read(from)?;
+                self.push(constructs::Operation::pop);
+            }
+            ValuePointer::OUTPUT(ref port) => {
+                let idx = self.output_index(port)?;
+                self.load_port(self.refs.outputs, idx);
+                self.read(from)?;
+                self.push(constructs::Operation::invokeinterface(self.refs.write, 2));
+            }
+            _ => return Err(format!("Cannot write into {} in {}", to, self.node.node)),
+        }
+        Ok(())
+    }
+
+    /// Updates the node value with an operation on the value and another value
+    fn write_math(
+        &mut self,
+        value: &ValuePointer,
+        operation: constructs::Operation,
+        blocked: Label,
+    ) -> Result<(), String> {
+        self.check_readable(value, blocked)?;
+        self.push(constructs::Operation::aload(0));
+        self.load_acc();
+        self.read(value)?;
+        self.push(operation);
+        self.push(constructs::Operation::putfield(self.refs.acc));
+        Ok(())
+    }
+
+    /// Jumps to the target when the node value matches the condition
+    fn write_conditional_jump(
+        &mut self,
+        condition: fn(Label) -> constructs::Operation,
+        target: i32,
+        next: i32,
+    ) {
+        let jump = self.new_label();
+        self.load_acc();
+        self.push(condition(jump));
+        self.finish(next);
+        self.push(constructs::Operation::label(jump));
+        self.finish(target);
+    }
+}
+
+/// Removes the labels from the operations, giving the index of the operation following each label.
+/// Executed operations are listed with their index in the node.
+fn split_labels(operations: &[Operation]) -> (Vec<(usize, &Operation)>, HashMap<&String, usize>) {
+    let mut executed = vec![];
+    let mut labels = HashMap::new();
+    for (idx, operation) in operations.iter().enumerate() {
+        if let Operation::LABEL(ref label) = operation {
+            labels.insert(label, executed.len());
+        } else {
+            executed.push((idx, operation));
+        }
+    }
+    (executed, labels)
+}
+
+fn map_references(class: &mut JavaClass) -> NodeReferences {
+    let boolean_method = Signature {
+        return_type: Type::Boolean,
+        parameter_types: vec![],
+    };
+    NodeReferences {
+        acc: class.create_field(MethodAccess::PRIVATE as u16, "acc", &Type::Integer),
+        step: class.create_field(MethodAccess::PRIVATE as u16, "step", &Type::Integer),
+        memory: class.create_field(
+            (MethodAccess::PRIVATE as u16) | (MethodAccess::FINAL as u16),
+            "memory",
+            &Type::PrimitiveArray(1, ArrayType::INT),
+        ),
+        inputs: class.map_field(
+            COMPILED_NODE_CLASS_NAME,
+            "inputs",
+            &Type::ObjectArray(1, String::from(INPUT_PORT_CLASS_NAME)),
+        ),
+        outputs: class.map_field(
+            COMPILED_NODE_CLASS_NAME,
+            "outputs",
+            &Type::ObjectArray(1, String::from(OUTPUT_PORT_CLASS_NAME)),
+        ),
+        can_read: class.map_interface_method(INPUT_PORT_CLASS_NAME, "canRead", &boolean_method),
+        read: class.map_interface_method(
+            INPUT_PORT_CLASS_NAME,
+            "read",
+            &Signature {
+                return_type: Type::Integer,
+                parameter_types: vec![],
+            },
+        ),
+        can_write: class.map_interface_method(OUTPUT_PORT_CLASS_NAME, "canWrite", &boolean_method),
+        write: class.map_interface_method(
+            OUTPUT_PORT_CLASS_NAME,
+            "write",
+            &Signature {
+                return_type: Type::Void,
+                parameter_types: vec![Type::Integer],
+            },
+        ),
+        floor_mod: class.map_method(
+            "java/lang/Math",
+            "floorMod",
+            &Signature {
+                return_type: Type::Integer,
+                parameter_types: vec![Type::Integer, Type::Integer],
+            },
+        ),
+    }
+}
+
+/// Creates the constructor, allocating the memory of the node
+fn create_constructor(
+    class: &mut JavaClass,
+    node: &NodeBlock,
+    memory: PoolIdx,
+) -> Result<(), String> {
+    let signature = Signature {
+        return_type: Type::Void,
+        parameter_types: vec![],
+    };
+    let super_constructor = class.map_method(COMPILED_NODE_CLASS_NAME, "<init>", &signature);
+    let memory_size = class.push_integer(node.memory_size().into());
+    let operations = vec![
+        constructs::Operation::aload(0),
+        constructs::Operation::invokespecial(super_constructor),
+        constructs::Operation::aload(0),
+        memory_size,
+        constructs::Operation::newarray(ArrayType::INT),
+        constructs::Operation::putfield(memory),
+        constructs::Operation::return_void,
+    ];
+    let code = constructs::create_code(None, operations)?;
+    class.create_method(MethodAccess::PUBLIC as u16, "<init>", signature, vec![code])?;
+    Ok(())
+}
+
+/// Creates the step method, running the current operation of the node
+fn create_step(
+    class: &mut JavaClass,
+    node: &NodeBlock,
+    refs: NodeReferences,
+) -> Result<(), String> {
+    let (operations, labels) = split_labels(&node.instructions);
+    let count = operations.len();
+    let mut writer = StepWriter {
+        class,
+        refs,
+        node,
+        operations: vec![],
+        next_label: 0,
+    };
+    let blocked = writer.new_label();
+    if count > 0 {
+        let targets: Vec<Label> = (0..count).map(|_| writer.new_label()).collect();
+        writer.push(constructs::Operation::aload(0));
+        writer.push(constructs::Operation::getfield(writer.refs.step));
+        writer.push(constructs::Operation::tableswitch {
+            default: blocked,
+            low: 0,
+            targets: targets.clone(),
+        });
+
+        let target_of = |label: &String| {
+            labels
+                .get(label)
+                .map(|idx| if *idx < count { *idx as i32 } else { 0 })
+                .ok_or_else(|| format!("Label {} is not defined in {}", label, node.node))
+        };
+        for (idx, (source_idx, operation)) in operations.iter().enumerate() {
+            writer.push(constructs::Operation::label(targets[idx]));
+            if let Some(line) = constructs::source_line(node.spans.operation(*source_idx)) {
+                writer.push(line);
+            }
+            let next = ((idx + 1) % count) as i32;
+            match operation {
+                Operation::MOV(ref from, ref to) => {
+                    writer.write_mov(from, to, blocked)?;
+                    writer.finish(next);
+                }
+                Operation::ADD(ref value) => {
+                    writer.write_math(value, constructs::Operation::iadd, blocked)?;
+                    writer.finish(next);
+                }
+                Operation::SUB(ref value) => {
+                    writer.write_math(value, constructs::Operation::isub, blocked)?;
+                    writer.finish(next);
+                }
+                Operation::NEG => {
+                    writer.push(constructs::Operation::aload(0));
+                    writer.load_acc();
+                    writer.push(constructs::Operation::ineg);
+                    writer.push(constructs::Operation::putfield(writer.refs.acc));
+                    writer.finish(next);
+                }
+                Operation::SAV(ref pointer) => {
+                    writer.load_memory_slot(pointer)?;
+                    writer.load_acc();
+                    writer.push(constructs::Operation::iastore);
+                    writer.finish(next);
+                }
+                Operation::SWP(ref pointer) => {
+                    // Keep the saved value on the stack, to set it as the node value at the end
+                    writer.push(constructs::Operation::aload(0));
+                    writer.load_memory_slot(pointer)?;
+                    writer.push(constructs::Operation::iaload);
+                    writer.load_memory_slot(pointer)?;
+                    writer.load_acc();
+                    writer.push(constructs::Operation::iastore);
+                    writer.push(constructs::Operation::putfield(writer.refs.acc));
+                    writer.finish(next);
+                }
+                Operation::JMP(ref label) => writer.finish(target_of(label)?),
+                Operation::JEZ(ref label) => writer.write_conditional_jump(
+                    constructs::Operation::ifeq,
+                    target_of(label)?,
+                    next,
+                ),
+                Operation::JNZ(ref label) => writer.write_conditional_jump(
+                    constructs::Operation::ifne,
+                    target_of(label)?,
+                    next,
+                ),
+                Operation::JLZ(ref label) => writer.write_conditional_jump(
+                    constructs::Operation::iflt,
+                    target_of(label)?,
+                    next,
+                ),
+                Operation::JGZ(ref label) => writer.write_conditional_jump(
+                    constructs::Operation::ifgt,
+                    target_of(label)?,
+                    next,
+                ),
+                Operation::JRO(ref value) => {
+                    // Offsets wrap around the operations
+                    writer.check_readable(value, blocked)?;
+                    writer.push(constructs::Operation::aload(0));
+                    writer.push_int(idx as i32);
+                    writer.read(value)?;
+                    writer.push(constructs::Operation::iadd);
+                    writer.push_int(count as i32);
+                    writer.push(constructs::Operation::invokestatic(writer.refs.floor_mod));
+                    writer.push(constructs::Operation::putfield(writer.refs.step));
+                    writer.push(constructs::Operation::return_void);
+                }
+                Operation::LABEL(_) => return Err(String::from("Labels are not executed")),
+            }
+        }
+    }
+    writer.push(constructs::Operation::label(blocked));
+    writer.push(constructs::Operation::return_void);
+
+    let code = constructs::create_code(None, writer.operations)?;
+    writer.class.create_method(
+        MethodAccess::PUBLIC as u16,
+        "step",
+        Signature {
+            return_type: Type::Void,
+            parameter_types: vec![],
+        },
+        vec![code],
+    )?;
+    Ok(())
+}
+
+/// Creates the class of a node, with its operations compiled into its step method
+pub fn create_node_class(node: &NodeBlock, class_name: &str) -> Result<JavaClass, String> {
+    let mut class = JavaClass::new();
+    class.set_class(class_name);
+    class.set_super_class(COMPILED_NODE_CLASS_NAME);
+
+    let refs = map_references(&mut class);
+    create_constructor(&mut class, node, refs.memory)?;
+    create_step(&mut class, node, refs)?;
+    Ok(class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::java::constructs::Attribute;
+    use language::address::{Node, Port};
+    use language::span::Span;
+
+    fn create_node() -> NodeBlock {
+        NodeBlock::new(Node::new_node("a"))
+            .input(Port::new(Node::In, 1.into()), 1.into())
+            .output(1.into(), Port::new(Node::Out, 1.into()))
+    }
+
+    fn step_operations(class: &JavaClass) -> &Vec<constructs::Operation> {
+        let Attribute::Code { ref operations, .. } = class.methods[1].attributes[0].1;
+        operations
+    }
+
+    #[test]
+    fn test_create_node_fields() {
+        let class = create_node_class(&create_node(), "a/Main$Node0").unwrap();
+        assert_eq!(class.fields.len(), 3);
+        assert_eq!(class.methods.len(), 2);
+    }
+
+    #[test]
+    fn test_switch_on_operations() {
+        let node = create_node()
+            .instruction(Operation::MOV(
+                ValuePointer::INPUT(1.into()),
+                ValuePointer::ACC,
+            ))
+            .instruction(Operation::NEG);
+        let class = create_node_class(&node, "a/Main$Node0").unwrap();
+        let operations = step_operations(&class);
+        assert!(operations.contains(&constructs::Operation::tableswitch {
+            default: 1,
+            low: 0,
+            targets: vec![2, 3],
+        }));
+        assert_eq!(
+            operations
+                .iter()
+                .filter(|op| matches!(op, constructs::Operation::label(_)))
+                .count(),
+            3
+        );
+        assert!(operations.contains(&constructs::Operation::ineg));
+    }
+
+    #[test]
+    fn test_map_operations_to_lines() {
+        let mut node = create_node()
+            .instruction(Operation::LABEL(String::from("START")))
+            .instruction(Operation::NEG)
+            .instruction(Operation::JMP(String::from("START")));
+        node.spans.operations = vec![Span::new(0, 3, 1), Span::new(7, 4, 1), Span::new(11, 5, 1)];
+        let class = create_node_class(&node, "a/Main$Node0").unwrap();
+        let lines: Vec<&constructs::Operation> = step_operations(&class)
+            .iter()
+            .filter(|op| matches!(op, constructs::Operation::line(_)))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                &constructs::Operation::line(4),
+                &constructs::Operation::line(5)
+            ]
+        );
+
+        let class =
+            create_node_class(&create_node().instruction(Operation::NEG), "a/Main$Node0").unwrap();
+        assert!(!step_operations(&class)
+            .iter()
+            .any(|op| matches!(op, constructs::Operation::line(_))));
+    }
+
+    #[test]
+    fn test_block_on_unavailable_ports() {
+        let node = create_node().instruction(Operation::MOV(
+            ValuePointer::INPUT(1.into()),
+            ValuePointer::OUTPUT(1.into()),
+        ));
+        let class = create_node_class(&node, "a/Main$Node0").unwrap();
+        let operations = step_operations(&class);
+        assert_eq!(
+            operations
+                .iter()
+                .filter(|op| **op == constructs::Operation::ifeq(1))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_reject_undefined_references() {
+        let node = create_node().instruction(Operation::JMP(String::from("NOWHERE")));
+        assert!(create_node_class(&node, "a/Main$Node0").is_err());
+
+        let node = create_node().instruction(Operation::ADD(ValuePointer::INPUT(2.into())));
+        assert!(create_node_class(&node, "a/Main$Node0").is_err());
+    }
+}
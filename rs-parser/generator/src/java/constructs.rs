@@ -1,4 +1,7 @@
 use crate::java::constants::{ArrayType, Type};
+use crate::java::frames::StackMap;
+use crate::java::writer;
+use language::span::Span;
 use std::cmp;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -7,11 +10,14 @@ pub struct Signature {
     pub parameter_types: Vec<Type>,
 }
 
+/// Identifier of a position in the code of a method, resolved to a byte offset when writing
+pub type Label = u16;
+
 #[derive(Debug, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum Operation {
-    /// Load a value from an array
-    // aaload,
+    /// Load a reference from an array
+    aaload,
     /// Push the value into an array at a given index
     // aastore,
     /// Push null onto the stack
@@ -42,12 +48,79 @@ pub enum Operation {
     bipush(i8),
     /// Duplicates the top value of the operand stack
     dup,
+    /// Fetches a field from an object
+    ///
+    /// Structure
+    /// ```md
+    ///  1. Index of the field ref
+    /// ```
+    getfield(u16),
+    /// Branches unconditionally
+    ///
+    /// Structure
+    /// ```md
+    ///  1. Label of the target
+    /// ```
+    #[allow(dead_code)]
+    goto(Label),
+    /// Adds two integers
+    iadd,
+    /// Load an integer from an array
+    iaload,
     /// Store an integer into an array
     iastore,
+    /// Push the constant 0 to the operand stack
+    iconst_0,
     /// Push the constant 1 to the operand stack
     iconst_1,
+    /// Push the constant 2 to the operand stack
+    iconst_2,
+    /// Push the constant 3 to the operand stack
+    iconst_3,
+    /// Push the constant 4 to the operand stack
+    iconst_4,
+    /// Push the constant 5 to the operand stack
+    iconst_5,
     /// Push the constant -1 to the operand stack
     iconst_m1,
+    /// Branches if the integer on the stack is 0
+    ///
+    /// Structure
+    /// ```md
+    ///  1. Label of the target
+    /// ```
+    ifeq(Label),
+    /// Branches if the integer on the stack is strictly greater than 0
+    ///
+    /// Structure
+    /// ```md
+    ///  1. Label of the target
+    /// ```
+    ifgt(Label),
+    /// Branches if the integer on the stack is strictly less than 0
+    ///
+    /// Structure
+    /// ```md
+    ///  1. Label of the target
+    /// ```
+    iflt(Label),
+    /// Branches if the integer on the stack is not 0
+    ///
+    /// Structure
+    /// ```md
+    ///  1. Label of the target
+    /// ```
+    ifne(Label),
+    /// Loads an integer of a local variable into the stack
+    ///
+    /// Structure
+    /// ```md
+    ///  1. Index of the local variable
+    /// ```
+    #[allow(dead_code)]
+    iload(u8),
+    /// Negates an integer
+    ineg,
     /// Invoke an instance method.
     ///
     /// Special handling is provided for superclass, private, and instance
@@ -88,12 +161,83 @@ pub enum Operation {
     ///  2. Arg count of the method
     /// ```
     invokeinterface(u16, u8),
+    /// Stores an integer into a local variable
+    ///
+    /// Structure
+    /// ```md
+    ///  1. Index of the local variable
+    /// ```
+    #[allow(dead_code)]
+    istore(u8),
+    /// Subtracts two integers
+    isub,
+    /// Marks the position of a label, writing no bytecode.
+    ///
+    /// The frame at this position is computed when creating the method.
+    label(Label),
     ldc(u16),
+    /// Marks the start of the code of a source line, writing no bytecode.
+    ///
+    /// Lines are written in the line number table of the method.
+    line(u16),
     new(u16),
     newarray(ArrayType),
     /// Do nothing
     nop,
+    /// Discards the top value of the operand stack
+    pop,
+    /// Sets a field of an object
+    ///
+    /// Structure
+    /// ```md
+    ///  1. Index of the field ref
+    /// ```
+    putfield(u16),
     return_void,
+    /// Pushes a short into the operand stack
+    ///
+    /// Structure
+    /// ```md
+    ///  1. Value to push
+    /// ```
+    sipush(i16),
+    /// Branches to the label at the index of the integer on the stack
+    ///
+    /// Structure
+    /// ```md
+    ///  - default: label of the target when the index is out of bounds
+    ///  - low: index of the first target
+    ///  - targets: labels of the targets, for the consecutive indexes
+    /// ```
+    tableswitch {
+        default: Label,
+        low: i32,
+        targets: Vec<Label>,
+    },
+}
+
+impl Operation {
+    /// Gets the labels the operation branches to, and whether it continues with the next one
+    pub fn successors(&self) -> (Vec<Label>, bool) {
+        match self {
+            Operation::goto(ref label) => (vec![*label], false),
+            Operation::ifeq(ref label)
+            | Operation::ifgt(ref label)
+            | Operation::iflt(ref label)
+            | Operation::ifne(ref label) => (vec![*label], true),
+            Operation::tableswitch {
+                ref default,
+                ref targets,
+                ..
+            } => {
+                let mut labels = targets.clone();
+                labels.push(*default);
+                (labels, false)
+            }
+            Operation::areturn | Operation::return_void => (vec![], false),
+            _ => (vec![], true),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -112,12 +256,21 @@ pub enum Attribute {
     },
 }
 
+#[derive(Debug)]
+pub struct Field {
+    pub access: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+}
+
 #[derive(Debug)]
 pub struct Method {
     pub access: u16,
     pub name_index: u16,
     pub descriptor_index: u16,
     pub attributes: Vec<(u16, Attribute)>,
+    /// Frames of the code of the method, if it has any
+    pub stack_map: Option<StackMap>,
 }
 
 pub fn count_local_vars(signature: Option<&Signature>, operations: &[Operation]) -> u16 {
@@ -129,6 +282,8 @@ pub fn count_local_vars(signature: Option<&Signature>, operations: &[Operation])
         .map(|op| match op {
             Operation::aload(ref idx) => *idx as u16 + 1,
             Operation::astore(ref idx) => *idx as u16 + 1,
+            Operation::iload(ref idx) => *idx as u16 + 1,
+            Operation::istore(ref idx) => *idx as u16 + 1,
             _ => 0u16,
         })
         .max()
@@ -137,40 +292,32 @@ pub fn count_local_vars(signature: Option<&Signature>, operations: &[Operation])
     cmp::max(op_count, arg_count)
 }
 
-pub fn merge_codes(opt_signature: Option<&Signature>, mut codes: Vec<Attribute>) -> Attribute {
-    let initial_locals: u16 = opt_signature
-        .map(|s| s.parameter_types.len() as u16)
-        .unwrap_or(0);
-    codes.drain(0..).fold(
-        Attribute::Code {
-            max_stack: 0,
-            operations: vec![],
-            locals: initial_locals,
-        },
-        |mut r, mut e| {
-            {
-                let Attribute::Code {
-                    max_stack: max,
-                    operations: ref mut ops,
-                    locals,
-                } = e;
-                let &mut Attribute::Code {
-                    max_stack: ref mut result_max,
-                    operations: ref mut result_ops,
-                    locals: ref mut result_locals,
-                } = &mut r;
-                *result_max += max;
-                *result_locals = cmp::max(locals, *result_locals);
+/// Creates the marker of the source line of an element, for located elements
+pub fn source_line(span: Option<Span>) -> Option<Operation> {
+    span.map(|span| Operation::line(cmp::min(span.line, u16::MAX as usize) as u16))
+}
 
-                ops.drain(0..).fold(result_ops, |o, e| {
-                    o.push(e);
-                    o
-                });
-            }
+/// Maximal size in bytes of the code of a method
+pub const MAX_CODE_LENGTH: u32 = 65535;
 
-            r
-        },
-    )
+/// Creates the Code attribute of a method, computing its local variables.
+/// Its stack size is computed along with its frames, when the method is created.
+pub fn create_code(
+    signature: Option<&Signature>,
+    operations: Vec<Operation>,
+) -> Result<Attribute, String> {
+    let length = writer::code_length(&operations)?;
+    if length > MAX_CODE_LENGTH {
+        return Err(format!(
+            "Code of {} bytes exceeds the limit of {} bytes of a method",
+            length, MAX_CODE_LENGTH
+        ));
+    }
+    Ok(Attribute::Code {
+        max_stack: 0,
+        locals: count_local_vars(signature, &operations),
+        operations,
+    })
 }
 
 #[cfg(test)]
@@ -209,6 +356,12 @@ mod tests {
         assert_eq!(count, 11);
     }
 
+    #[test]
+    fn test_count_local_vars_from_integers() {
+        let count = count_local_vars(None, &[Operation::iload(2), Operation::istore(1)]);
+        assert_eq!(count, 3);
+    }
+
     #[test]
     fn test_count_local_vars() {
         let count = count_local_vars(None, &[Operation::aload(3), Operation::astore(7)]);
@@ -255,91 +408,71 @@ mod tests {
     }
 
     #[test]
-    fn test_merge_codes() {
-        let code1 = Attribute::Code {
-            max_stack: 2,
-            operations: vec![Operation::bipush(12), Operation::dup],
-            locals: 3,
-        };
-        let code2 = Attribute::Code {
-            max_stack: 3,
-            operations: vec![Operation::ldc(8), Operation::newarray(ArrayType::BOOLEAN)],
-            locals: 2,
-        };
-        let code3 = Attribute::Code {
-            max_stack: 4,
-            operations: vec![
-                Operation::invokespecial(4),
-                Operation::invokestatic(5),
+    fn test_create_code() {
+        let code = create_code(
+            None,
+            vec![
+                Operation::bipush(12),
+                Operation::dup,
+                Operation::astore(2),
+                Operation::ldc(8),
+                Operation::newarray(ArrayType::BOOLEAN),
                 Operation::return_void,
             ],
-            locals: 0,
-        };
-        let merged_code = merge_codes(None, vec![code1, code2, code3]);
+        );
 
         assert_eq!(
-            merged_code,
-            Attribute::Code {
-                max_stack: 9,
+            code,
+            Ok(Attribute::Code {
+                max_stack: 0,
                 operations: vec![
                     Operation::bipush(12),
                     Operation::dup,
+                    Operation::astore(2),
                     Operation::ldc(8),
                     Operation::newarray(ArrayType::BOOLEAN),
-                    Operation::invokespecial(4),
-                    Operation::invokestatic(5),
                     Operation::return_void
                 ],
                 locals: 3
-            }
+            })
         );
     }
 
     #[test]
-    fn test_merge_codes_with_signature() {
-        let code1 = Attribute::Code {
-            max_stack: 2,
-            operations: vec![Operation::bipush(12), Operation::dup],
-            locals: 1,
-        };
-        let code2 = Attribute::Code {
-            max_stack: 3,
-            operations: vec![Operation::ldc(8), Operation::newarray(ArrayType::BOOLEAN)],
-            locals: 1,
+    fn test_create_code_with_signature() {
+        let signature = Signature {
+            return_type: Type::Void,
+            parameter_types: vec![Type::Integer, Type::Integer, Type::Integer],
         };
-        let code3 = Attribute::Code {
-            max_stack: 4,
-            operations: vec![
-                Operation::invokespecial(4),
-                Operation::invokestatic(5),
+        let Attribute::Code { locals, .. } = create_code(
+            Some(&signature),
+            vec![
+                Operation::iconst_1,
+                Operation::istore(1),
                 Operation::return_void,
             ],
-            locals: 5,
-        };
-        let Attribute::Code {
-            locals: locals1,
-            max_stack: _,
-            operations: _,
-        } = merge_codes(
-            Some(&Signature {
-                return_type: Type::Void,
-                parameter_types: vec![Type::Integer, Type::Integer, Type::Integer],
-            }),
-            vec![code1, code2],
-        );
-        assert_eq!(locals1, 3);
+        )
+        .unwrap();
+        assert_eq!(locals, 3);
 
-        let Attribute::Code {
-            locals: locals2,
-            max_stack: _,
-            operations: _,
-        } = merge_codes(
-            Some(&Signature {
-                return_type: Type::Void,
-                parameter_types: vec![Type::Integer, Type::Integer, Type::Integer],
-            }),
-            vec![code3],
-        );
-        assert_eq!(locals2, 5);
+        let Attribute::Code { locals, .. } = create_code(
+            Some(&signature),
+            vec![
+                Operation::aconst_null,
+                Operation::astore(4),
+                Operation::return_void,
+            ],
+        )
+        .unwrap();
+        assert_eq!(locals, 5);
+    }
+
+    #[test]
+    fn test_reject_too_long_code() {
+        let half = MAX_CODE_LENGTH as usize / 2 + 1;
+        let mut operations: Vec<Operation> = (0..half).map(|_| Operation::iconst_0).collect();
+        operations.extend((0..half).map(|_| Operation::pop));
+        operations.push(Operation::return_void);
+        assert!(create_code(None, operations).is_err());
     }
 }
@@ -6,6 +6,7 @@ use std::hash::Hash;
 pub struct Dictionary<K: Eq + Hash> {
     pool: HashMap<K, u16>,
     next_idx: u16,
+    overflowed: bool,
 }
 
 pub struct DictionaryIter<'a, K: 'a> {
@@ -32,6 +33,7 @@ impl<K: Eq + Hash> Dictionary<K> {
         Dictionary {
             pool: HashMap::new(),
             next_idx: 1,
+            overflowed: false,
         }
     }
 
@@ -44,17 +46,29 @@ impl<K: Eq + Hash> Dictionary<K> {
         None
     }
 
+    /// Gets the index of an element, without mapping it
+    pub fn find(&self, element: &K) -> Option<u16> {
+        self.pool.get(element).cloned()
+    }
+
     pub fn map(&mut self, element: K) -> u16 {
-        let mut new_idx = Some(self.next_idx);
-        let entry = self.pool.entry(element).or_insert_with(|| {
-            let idx = new_idx.unwrap();
-            new_idx = None;
-            idx
-        });
-        if new_idx.is_none() {
-            self.next_idx += 1;
+        if let Some(idx) = self.pool.get(&element) {
+            return *idx;
+        }
+        if self.next_idx == u16::MAX {
+            // The size of the dictionary cannot be represented anymore
+            self.overflowed = true;
+            return u16::MAX;
         }
-        *entry
+        let idx = self.next_idx;
+        self.pool.insert(element, idx);
+        self.next_idx += 1;
+        idx
+    }
+
+    /// Tells if elements could not be mapped, the dictionary being full
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed
     }
 
     pub fn size(&self) -> u16 {
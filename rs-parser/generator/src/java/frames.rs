@@ -0,0 +1,908 @@
+//! Analysis of the code of methods, inferring the types of the local variables and of the
+//! operand stack at each operation.
+//!
+//! The JVM requires the frames at each branch target for class files of version 50+.
+//! The analysis also gives the maximal size of the operand stack.
+
+use std::collections::HashMap;
+
+use crate::java::class::{field_descriptor, JavaClass, PoolElement, PoolIdx};
+use crate::java::constants::Type;
+use crate::java::constructs::{Label, Operation, Signature};
+
+/// Type of a value in a frame, as written in the StackMapTable attribute
+#[derive(Debug, PartialEq, Clone)]
+pub enum VerificationType {
+    Top,
+    Integer,
+    Null,
+    UninitializedThis,
+    /// Initialized object
+    /// Structure
+    /// ```md
+    ///  1. Index to the class info
+    /// ```
+    Object(PoolIdx),
+}
+
+/// State of the local variables and the operand stack at a position in the code
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    pub locals: Vec<VerificationType>,
+    pub stack: Vec<VerificationType>,
+}
+
+/// Frames of a method, at the start of the code and at each label
+#[derive(Debug, PartialEq)]
+pub struct StackMap {
+    pub initial: Frame,
+    /// Frames of the labels, in the order of the operations
+    pub frames: Vec<(Label, Frame)>,
+}
+
+#[derive(Debug)]
+pub struct CodeAnalysis {
+    pub max_stack: u16,
+    pub stack_map: StackMap,
+}
+
+/// Type of a value during the analysis, with the class names not mapped yet
+#[derive(Debug, PartialEq, Clone)]
+enum Value {
+    Top,
+    Integer,
+    Null,
+    UninitializedThis,
+    /// Object created at an operation, not initialized yet
+    Uninitialized(usize, String),
+    Reference(String),
+}
+
+impl Value {
+    fn is_reference(&self) -> bool {
+        !matches!(self, Value::Top | Value::Integer)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct State {
+    locals: Vec<Value>,
+    stack: Vec<Value>,
+}
+
+impl State {
+    fn pop(&mut self) -> Result<Value, String> {
+        self.stack
+            .pop()
+            .ok_or_else(|| String::from("Stack underflow"))
+    }
+
+    fn pop_int(&mut self) -> Result<(), String> {
+        match self.pop()? {
+            Value::Integer => Ok(()),
+            value => Err(format!("Expected an integer on the stack, got {:?}", value)),
+        }
+    }
+
+    fn pop_reference(&mut self) -> Result<Value, String> {
+        let value = self.pop()?;
+        if value.is_reference() {
+            Ok(value)
+        } else {
+            Err(format!(
+                "Expected a reference on the stack, got {:?}",
+                value
+            ))
+        }
+    }
+
+    fn load(&self, idx: u8) -> Result<Value, String> {
+        self.locals
+            .get(idx as usize)
+            .cloned()
+            .ok_or_else(|| format!("Local variable {} is not defined", idx))
+    }
+
+    fn store(&mut self, idx: u8, value: Value) {
+        let idx = idx as usize;
+        if self.locals.len() <= idx {
+            self.locals.resize(idx + 1, Value::Top);
+        }
+        self.locals[idx] = value;
+    }
+
+    /// Marks all references to an uninitialized object as initialized
+    fn initialize(&mut self, object: &Value, class_name: &str) {
+        let initialized = Value::Reference(String::from(class_name));
+        for value in self.locals.iter_mut().chain(self.stack.iter_mut()) {
+            if value == object {
+                *value = initialized.clone();
+            }
+        }
+    }
+}
+
+/// Member of a class, referenced by a field or method ref
+struct Member<'a> {
+    name: &'a str,
+    descriptor: &'a str,
+}
+
+fn get_utf8(class: &JavaClass, idx: PoolIdx) -> Result<&str, String> {
+    match class.pool_element(idx) {
+        Some(PoolElement::Utf8Value(ref value)) => Ok(value),
+        element => Err(format!(
+            "Expected an UTF8 value at {}, got {:?}",
+            idx, element
+        )),
+    }
+}
+
+fn get_class_name(class: &JavaClass, idx: PoolIdx) -> Result<&str, String> {
+    match class.pool_element(idx) {
+        Some(PoolElement::ClassInfo(ref name_idx)) => get_utf8(class, *name_idx),
+        element => Err(format!(
+            "Expected a class info at {}, got {:?}",
+            idx, element
+        )),
+    }
+}
+
+fn get_member(class: &JavaClass, idx: PoolIdx) -> Result<Member<'_>, String> {
+    let nnt_idx = match class.pool_element(idx) {
+        Some(PoolElement::FieldRef(_, ref n))
+        | Some(PoolElement::MethodRef(_, ref n))
+        | Some(PoolElement::InterfaceMethodRef(_, ref n)) => *n,
+        element => {
+            return Err(format!(
+                "Expected a member ref at {}, got {:?}",
+                idx, element
+            ))
+        }
+    };
+    match class.pool_element(nnt_idx) {
+        Some(PoolElement::NameAndType(ref name_idx, ref descriptor_idx)) => Ok(Member {
+            name: get_utf8(class, *name_idx)?,
+            descriptor: get_utf8(class, *descriptor_idx)?,
+        }),
+        element => Err(format!(
+            "Expected a name and type at {}, got {:?}",
+            nnt_idx, element
+        )),
+    }
+}
+
+/// Reads the type starting a descriptor, returning the rest of the descriptor
+fn parse_type(descriptor: &str) -> Result<(Value, &str), String> {
+    let invalid = || format!("Invalid descriptor {}", descriptor);
+    let first = descriptor.chars().next().ok_or_else(invalid)?;
+    match first {
+        'Z' | 'B' | 'C' | 'S' | 'I' => Ok((Value::Integer, &descriptor[1..])),
+        'L' => {
+            let end = descriptor.find(';').ok_or_else(invalid)?;
+            Ok((
+                Value::Reference(String::from(&descriptor[1..end])),
+                &descriptor[end + 1..],
+            ))
+        }
+        '[' => {
+            let (_, rest) = parse_type(&descriptor[1..])?;
+            let length = descriptor.len() - rest.len();
+            Ok((Value::Reference(String::from(&descriptor[..length])), rest))
+        }
+        // Long, float and double values are not used by the generator
+        _ => Err(format!("Unsupported type in descriptor {}", descriptor)),
+    }
+}
+
+/// Reads the types of the parameters and of the returned value of a method descriptor
+fn parse_method_descriptor(descriptor: &str) -> Result<(Vec<Value>, Option<Value>), String> {
+    let mut rest = descriptor
+        .strip_prefix('(')
+        .ok_or_else(|| format!("Invalid method descriptor {}", descriptor))?;
+    let mut parameters = Vec::new();
+    while !rest.starts_with(')') {
+        let (parameter, next) = parse_type(rest)?;
+        parameters.push(parameter);
+        rest = next;
+    }
+    let returned = &rest[1..];
+    if returned == "V" {
+        Ok((parameters, None))
+    } else {
+        parse_type(returned).map(|(value, _)| (parameters, Some(value)))
+    }
+}
+
+/// Computes the state after an operation, before it possibly branches
+fn execute(
+    class: &JavaClass,
+    index: usize,
+    operation: &Operation,
+    state: &mut State,
+) -> Result<(), String> {
+    match operation {
+        Operation::aaload => {
+            state.pop_int()?;
+            let component = match state.pop_reference()? {
+                Value::Null => Value::Null,
+                Value::Reference(ref name) if name.starts_with("[L") || name.starts_with("[[") => {
+                    parse_type(&name[1..])?.0
+                }
+                value => return Err(format!("Expected an array of references, got {:?}", value)),
+            };
+            state.stack.push(component);
+        }
+        Operation::aconst_null => state.stack.push(Value::Null),
+        Operation::aload(ref idx) => {
+            let value = state.load(*idx)?;
+            if !value.is_reference() {
+                return Err(format!("Local variable {} is not a reference", idx));
+            }
+            state.stack.push(value);
+        }
+        Operation::astore(ref idx) => {
+            let value = state.pop_reference()?;
+            state.store(*idx, value);
+        }
+        Operation::bipush(_)
+        | Operation::iconst_m1
+        | Operation::iconst_0
+        | Operation::iconst_1
+        | Operation::iconst_2
+        | Operation::iconst_3
+        | Operation::iconst_4
+        | Operation::iconst_5
+        | Operation::sipush(_) => state.stack.push(Value::Integer),
+        Operation::dup => {
+            let value = state.pop()?;
+            state.stack.push(value.clone());
+            state.stack.push(value);
+        }
+        Operation::getfield(ref idx) => {
+            let field = get_member(class, *idx)?;
+            state.pop_reference()?;
+            state.stack.push(parse_type(field.descriptor)?.0);
+        }
+        Operation::putfield(ref idx) => {
+            get_member(class, *idx)?;
+            state.pop()?;
+            state.pop_reference()?;
+        }
+        Operation::iadd | Operation::isub => {
+            state.pop_int()?;
+            state.pop_int()?;
+            state.stack.push(Value::Integer);
+        }
+        Operation::ineg => {
+            state.pop_int()?;
+            state.stack.push(Value::Integer);
+        }
+        Operation::iaload => {
+            state.pop_int()?;
+            state.pop_reference()?;
+            state.stack.push(Value::Integer);
+        }
+        Operation::iastore => {
+            state.pop_int()?;
+            state.pop_int()?;
+            state.pop_reference()?;
+        }
+        Operation::iload(ref idx) => {
+            if state.load(*idx)? != Value::Integer {
+                return Err(format!("Local variable {} is not an integer", idx));
+            }
+            state.stack.push(Value::Integer);
+        }
+        Operation::istore(ref idx) => {
+            state.pop_int()?;
+            state.store(*idx, Value::Integer);
+        }
+        Operation::invokespecial(ref idx)
+        | Operation::invokevirtual(ref idx)
+        | Operation::invokestatic(ref idx)
+        | Operation::invokeinterface(ref idx, _) => {
+            let method = get_member(class, *idx)?;
+            let (parameters, returned) = parse_method_descriptor(method.descriptor)?;
+            for _ in &parameters {
+                state.pop()?;
+            }
+            if !matches!(operation, Operation::invokestatic(_)) {
+                let receiver = state.pop_reference()?;
+                if method.name == "<init>" {
+                    match receiver {
+                        Value::UninitializedThis => {
+                            let class_name = class
+                                .get_class_name()
+                                .ok_or_else(|| String::from("Class name not defined"))?;
+                            state.initialize(&receiver, &class_name);
+                        }
+                        Value::Uninitialized(_, ref class_name) => {
+                            state.initialize(&receiver, class_name)
+                        }
+                        _ => return Err(String::from("Object already initialized")),
+                    }
+                }
+            }
+            if let Some(value) = returned {
+                state.stack.push(value);
+            }
+        }
+        Operation::invokedynamic(_) => {
+            return Err(String::from("Dynamic invocations are not supported"))
+        }
+        Operation::ldc(ref idx) => {
+            let value = match class.pool_element(*idx) {
+                Some(PoolElement::Integer(_)) => Value::Integer,
+                Some(PoolElement::String(_)) => Value::Reference(String::from("java/lang/String")),
+                Some(PoolElement::ClassInfo(_)) => {
+                    Value::Reference(String::from("java/lang/Class"))
+                }
+                element => return Err(format!("Cannot load constant {:?}", element)),
+            };
+            state.stack.push(value);
+        }
+        Operation::new(ref idx) => {
+            let class_name = get_class_name(class, *idx)?;
+            state
+                .stack
+                .push(Value::Uninitialized(index, String::from(class_name)));
+        }
+        Operation::newarray(ref array_type) => {
+            state.pop_int()?;
+            let descriptor = field_descriptor(&Type::PrimitiveArray(1, array_type.clone()));
+            state.stack.push(Value::Reference(descriptor));
+        }
+        Operation::pop => {
+            state.pop()?;
+        }
+        Operation::nop | Operation::label(_) | Operation::line(_) => {}
+        Operation::areturn => {
+            state.pop_reference()?;
+        }
+        Operation::return_void => {}
+        Operation::goto(_) => {}
+        Operation::ifeq(_) | Operation::ifgt(_) | Operation::iflt(_) | Operation::ifne(_) => {
+            state.pop_int()?
+        }
+        Operation::tableswitch { .. } => state.pop_int()?,
+    }
+    Ok(())
+}
+
+fn merge_value(current: &Value, incoming: &Value) -> Option<Value> {
+    if current == incoming {
+        Some(current.clone())
+    } else {
+        match (current, incoming) {
+            (Value::Null, Value::Reference(_)) => Some(incoming.clone()),
+            (Value::Reference(_), Value::Null) => Some(current.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Merges the state reaching an operation into the known one, telling if it changed
+fn merge_state(current: &mut Option<State>, incoming: &State) -> Result<bool, String> {
+    let state = match current {
+        Some(ref mut state) => state,
+        None => {
+            *current = Some(incoming.clone());
+            return Ok(true);
+        }
+    };
+    if state.stack.len() != incoming.stack.len() {
+        return Err(format!(
+            "Inconsistent stack sizes {} and {}",
+            state.stack.len(),
+            incoming.stack.len()
+        ));
+    }
+    let mut changed = false;
+    for (value, other) in state.stack.iter_mut().zip(incoming.stack.iter()) {
+        let merged = merge_value(value, other)
+            .ok_or_else(|| format!("Inconsistent stack values {:?} and {:?}", value, other))?;
+        if *value != merged {
+            *value = merged;
+            changed = true;
+        }
+    }
+    // Locals only known by one of the states become unusable
+    if state.locals.len() > incoming.locals.len() {
+        state.locals.truncate(incoming.locals.len());
+        changed = true;
+    }
+    for (value, other) in state.locals.iter_mut().zip(incoming.locals.iter()) {
+        let merged = merge_value(value, other).unwrap_or(Value::Top);
+        if *value != merged {
+            *value = merged;
+            changed = true;
+        }
+    }
+    Ok(changed)
+}
+
+fn to_verification_type(class: &mut JavaClass, value: &Value) -> Result<VerificationType, String> {
+    match value {
+        Value::Top => Ok(VerificationType::Top),
+        Value::Integer => Ok(VerificationType::Integer),
+        Value::Null => Ok(VerificationType::Null),
+        Value::UninitializedThis => Ok(VerificationType::UninitializedThis),
+        Value::Reference(ref name) => Ok(VerificationType::Object(class.map_class(name))),
+        Value::Uninitialized(_, ref name) => Err(format!(
+            "Uninitialized {} cannot cross a branch target",
+            name
+        )),
+    }
+}
+
+fn to_frame(class: &mut JavaClass, state: &State) -> Result<Frame, String> {
+    // Trailing unusable locals are implicit
+    let used = state
+        .locals
+        .iter()
+        .rposition(|value| *value != Value::Top)
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+    let locals = state.locals[..used]
+        .iter()
+        .map(|value| to_verification_type(class, value))
+        .collect::<Result<Vec<_>, _>>()?;
+    let stack = state
+        .stack
+        .iter()
+        .map(|value| to_verification_type(class, value))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Frame { locals, stack })
+}
+
+/// States of the code of a method, found by following all its paths
+struct Simulation {
+    initial: State,
+    /// State before each operation, if reached
+    states: Vec<Option<State>>,
+    /// Maximal number of values on the operand stack
+    max_stack: usize,
+}
+
+/// Creates the state at the start of a method, with its parameters as local variables
+fn initial_state(
+    class: &JavaClass,
+    is_static: bool,
+    method_name: &str,
+    parameters: Vec<Value>,
+) -> Result<State, String> {
+    let mut locals = Vec::new();
+    if !is_static {
+        if method_name == "<init>" {
+            locals.push(Value::UninitializedThis);
+        } else {
+            let class_name = class
+                .get_class_name()
+                .ok_or_else(|| String::from("Class name not defined"))?;
+            locals.push(Value::Reference(class_name));
+        }
+    }
+    locals.extend(parameters);
+    Ok(State {
+        locals,
+        stack: Vec::new(),
+    })
+}
+
+/// Follows all paths of the code, checking that operations get values of the expected types
+fn simulate(
+    class: &JavaClass,
+    initial: State,
+    operations: &[Operation],
+) -> Result<Simulation, String> {
+    let mut label_indexes = HashMap::new();
+    for (idx, operation) in operations.iter().enumerate() {
+        if let Operation::label(ref label) = operation {
+            if label_indexes.insert(*label, idx).is_some() {
+                return Err(format!("Label {} defined twice", label));
+            }
+        }
+    }
+
+    let mut states: Vec<Option<State>> = vec![None; operations.len()];
+    let mut pending = vec![];
+    if !operations.is_empty() {
+        states[0] = Some(initial.clone());
+        pending.push(0);
+    }
+    let mut max_stack = 0;
+    while let Some(idx) = pending.pop() {
+        let operation = &operations[idx];
+        let mut state = states[idx]
+            .clone()
+            .expect("Pending operation without state");
+        execute(class, idx, operation, &mut state)
+            .map_err(|e| format!("{} at operation {} ({:?})", e, idx, operation))?;
+        max_stack = max_stack.max(state.stack.len());
+
+        let (labels, continues) = operation.successors();
+        let mut successors = Vec::with_capacity(labels.len() + 1);
+        for label in labels {
+            let target = label_indexes
+                .get(&label)
+                .ok_or_else(|| format!("Label {} is not defined", label))?;
+            successors.push(*target);
+        }
+        if continues {
+            if idx + 1 == operations.len() {
+                return Err(String::from("Execution falls off the end of the code"));
+            }
+            successors.push(idx + 1);
+        }
+        for successor in successors {
+            let changed = merge_state(&mut states[successor], &state)
+                .map_err(|e| format!("{} at operation {}", e, successor))?;
+            if changed {
+                pending.push(successor);
+            }
+        }
+    }
+    let unreachable = states
+        .iter()
+        .zip(operations)
+        .position(|(state, operation)| state.is_none() && !matches!(operation, Operation::line(_)));
+    if let Some(idx) = unreachable {
+        return Err(format!(
+            "Operation {} ({:?}) is never executed",
+            idx, operations[idx]
+        ));
+    }
+
+    Ok(Simulation {
+        initial,
+        states,
+        max_stack,
+    })
+}
+
+/// Analyses the code of a method of the class.
+///
+/// This follows all paths of the code, checking that operations get values of the
+/// expected types, and computes the frames at each label as well as the stack size.
+/// The class of the objects in the frames are mapped in the class pool.
+pub fn analyse_code(
+    class: &mut JavaClass,
+    is_static: bool,
+    method_name: &str,
+    signature: &Signature,
+    operations: &[Operation],
+) -> Result<CodeAnalysis, String> {
+    let parameters = signature
+        .parameter_types
+        .iter()
+        .map(|parameter| parse_type(&field_descriptor(parameter)).map(|(value, _)| value))
+        .collect::<Result<Vec<_>, _>>()?;
+    let initial = initial_state(class, is_static, method_name, parameters)?;
+    let simulation = simulate(class, initial, operations)?;
+
+    let mut frames = Vec::new();
+    for (operation, state) in operations.iter().zip(simulation.states.iter()) {
+        if let (Operation::label(ref label), Some(ref state)) = (operation, state) {
+            frames.push((*label, to_frame(class, state)?));
+        }
+    }
+    if simulation.max_stack > u16::MAX as usize {
+        return Err(format!(
+            "Stack of {} values exceeds the limit of a method",
+            simulation.max_stack
+        ));
+    }
+    Ok(CodeAnalysis {
+        max_stack: simulation.max_stack as u16,
+        stack_map: StackMap {
+            initial: to_frame(class, &simulation.initial)?,
+            frames,
+        },
+    })
+}
+
+/// Checks the types of the values used by the code of a method, described by its descriptor.
+/// This returns the maximal number of values on the operand stack.
+pub fn check_code(
+    class: &JavaClass,
+    is_static: bool,
+    method_name: &str,
+    descriptor: &str,
+    operations: &[Operation],
+) -> Result<usize, String> {
+    let (parameters, _) = parse_method_descriptor(descriptor)?;
+    let initial = initial_state(class, is_static, method_name, parameters)?;
+    simulate(class, initial, operations).map(|simulation| simulation.max_stack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_class() -> JavaClass {
+        let mut class = JavaClass::new();
+        class.set_class("the/Class");
+        class
+    }
+
+    fn no_args() -> Signature {
+        Signature {
+            return_type: Type::Void,
+            parameter_types: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse_method_descriptor() {
+        let (parameters, returned) =
+            parse_method_descriptor("(I[ILjava/lang/String;[[La/B;)Ljava/lang/Object;").unwrap();
+        assert_eq!(
+            parameters,
+            vec![
+                Value::Integer,
+                Value::Reference(String::from("[I")),
+                Value::Reference(String::from("java/lang/String")),
+                Value::Reference(String::from("[[La/B;")),
+            ]
+        );
+        assert_eq!(
+            returned,
+            Some(Value::Reference(String::from("java/lang/Object")))
+        );
+        assert_eq!(parse_method_descriptor("()V").unwrap(), (vec![], None));
+        assert!(parse_method_descriptor("(J)V").is_err());
+    }
+
+    #[test]
+    fn test_compute_frames_at_labels() {
+        let mut class = create_class();
+        let signature = Signature {
+            return_type: Type::Integer,
+            parameter_types: vec![Type::Integer],
+        };
+        let stack_map = analyse_code(
+            &mut class,
+            false,
+            "run",
+            &signature,
+            &[
+                Operation::iload(1),
+                Operation::ifeq(1),
+                Operation::iconst_1,
+                Operation::goto(2),
+                Operation::label(1),
+                Operation::iconst_m1,
+                Operation::label(2),
+                Operation::istore(2),
+                Operation::return_void,
+            ],
+        )
+        .unwrap()
+        .stack_map;
+        let this = VerificationType::Object(class.map_class("the/Class"));
+        assert_eq!(
+            stack_map.initial,
+            Frame {
+                locals: vec![this.clone(), VerificationType::Integer],
+                stack: vec![],
+            }
+        );
+        assert_eq!(
+            stack_map.frames,
+            vec![
+                (
+                    1,
+                    Frame {
+                        locals: vec![this.clone(), VerificationType::Integer],
+                        stack: vec![],
+                    }
+                ),
+                (
+                    2,
+                    Frame {
+                        locals: vec![this, VerificationType::Integer],
+                        stack: vec![VerificationType::Integer],
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forget_locals_set_on_one_branch() {
+        let mut class = create_class();
+        let stack_map = analyse_code(
+            &mut class,
+            true,
+            "run",
+            &no_args(),
+            &[
+                Operation::iconst_0,
+                Operation::ifeq(1),
+                Operation::iconst_1,
+                Operation::istore(0),
+                Operation::label(1),
+                Operation::return_void,
+            ],
+        )
+        .unwrap()
+        .stack_map;
+        assert_eq!(
+            stack_map.frames,
+            vec![(
+                1,
+                Frame {
+                    locals: vec![],
+                    stack: vec![],
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_initialize_objects() {
+        let mut class = create_class();
+        let constructor = class.map_method("a/B", "<init>", &no_args());
+        let created_class = class.map_class("a/B");
+        let super_constructor = class.map_method("java/lang/Object", "<init>", &no_args());
+        let stack_map = analyse_code(
+            &mut class,
+            false,
+            "<init>",
+            &no_args(),
+            &[
+                Operation::aload(0),
+                Operation::invokespecial(super_constructor),
+                Operation::new(created_class),
+                Operation::dup,
+                Operation::invokespecial(constructor),
+                Operation::goto(1),
+                Operation::label(1),
+                Operation::astore(1),
+                Operation::return_void,
+            ],
+        )
+        .unwrap()
+        .stack_map;
+        assert_eq!(
+            stack_map.initial.locals,
+            vec![VerificationType::UninitializedThis]
+        );
+        let (_, ref frame) = stack_map.frames[0];
+        assert_eq!(
+            frame.locals,
+            vec![VerificationType::Object(class.map_class("the/Class"))]
+        );
+        assert_eq!(
+            frame.stack,
+            vec![VerificationType::Object(class.map_class("a/B"))]
+        );
+    }
+
+    #[test]
+    fn test_count_max_stack() {
+        let mut class = create_class();
+        let method = class.map_method(
+            "a/B",
+            "run",
+            &Signature {
+                return_type: Type::Integer,
+                parameter_types: vec![Type::Integer, Type::Integer],
+            },
+        );
+        let analysis = analyse_code(
+            &mut class,
+            true,
+            "run",
+            &Signature {
+                return_type: Type::Void,
+                parameter_types: vec![Type::Integer, Type::Integer],
+            },
+            &[
+                Operation::bipush(12),
+                Operation::dup,
+                Operation::aconst_null,
+                Operation::pop,
+                Operation::iload(1),
+                Operation::invokestatic(method),
+                Operation::iadd,
+                Operation::pop,
+                Operation::return_void,
+            ],
+        )
+        .unwrap();
+        assert_eq!(analysis.max_stack, 3);
+    }
+
+    #[test]
+    fn test_count_max_stack_with_receivers() {
+        let mut class = create_class();
+        let method = class.map_interface_method(
+            "a/B",
+            "run",
+            &Signature {
+                return_type: Type::Void,
+                parameter_types: vec![Type::Integer],
+            },
+        );
+        let analysis = analyse_code(
+            &mut class,
+            false,
+            "run",
+            &no_args(),
+            &[
+                Operation::aload(0),
+                Operation::iconst_1,
+                Operation::invokeinterface(method, 2),
+                Operation::return_void,
+            ],
+        )
+        .unwrap();
+        assert_eq!(analysis.max_stack, 2);
+    }
+
+    #[test]
+    fn test_count_max_stack_across_branches() {
+        let mut class = create_class();
+        let analysis = analyse_code(
+            &mut class,
+            true,
+            "run",
+            &no_args(),
+            &[
+                Operation::iconst_0,
+                Operation::iconst_1,
+                Operation::ifeq(1),
+                Operation::iconst_2,
+                Operation::iadd,
+                Operation::goto(1),
+                Operation::label(1),
+                Operation::istore(1),
+                Operation::return_void,
+            ],
+        )
+        .unwrap();
+        assert_eq!(analysis.max_stack, 2);
+    }
+
+    #[test]
+    fn test_reject_invalid_code() {
+        let mut class = create_class();
+        let mut check = |operations: &[Operation]| {
+            analyse_code(&mut class, true, "run", &no_args(), operations)
+        };
+        // Underflow
+        assert!(check(&[Operation::pop, Operation::return_void]).is_err());
+        assert!(check(&[Operation::areturn]).is_err());
+        // Methods must be mapped in the class pool
+        assert!(check(&[Operation::invokestatic(4), Operation::return_void]).is_err());
+        // Wrong type
+        assert!(check(&[
+            Operation::aconst_null,
+            Operation::ineg,
+            Operation::return_void
+        ])
+        .is_err());
+        // No return
+        assert!(check(&[Operation::nop]).is_err());
+        // Unknown label
+        assert!(check(&[Operation::goto(3)]).is_err());
+        // Unreachable code
+        assert!(check(&[
+            Operation::return_void,
+            Operation::nop,
+            Operation::return_void
+        ])
+        .is_err());
+        // Different stacks at a label
+        assert!(check(&[
+            Operation::iconst_0,
+            Operation::ifeq(1),
+            Operation::iconst_0,
+            Operation::label(1),
+            Operation::return_void,
+        ])
+        .is_err());
+    }
+}
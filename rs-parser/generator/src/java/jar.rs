@@ -0,0 +1,264 @@
+//! Module packaging classes into a JAR archive.
+//!
+//! Entries are stored without compression, with a fixed date so that the same program always
+//! produces the same archive.
+
+/// Path of the manifest in the archive
+pub const MANIFEST_PATH: &str = "META-INF/MANIFEST.MF";
+
+/// Maximal length of a manifest line, in bytes, line ending excluded
+const MAX_MANIFEST_LINE: usize = 72;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+/// Version 1.0 of the zip specification, enough for stored entries
+const ZIP_VERSION: u16 = 10;
+/// Date of the entries, 1980-01-01 in the MS-DOS format
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+struct Entry {
+    name: String,
+    data: Vec<u8>,
+}
+
+/// Archive of files, written as a JAR
+pub struct Jar {
+    entries: Vec<Entry>,
+}
+
+/// Computes the CRC-32 checksum of data, as defined for zip files
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Writes an attribute of the manifest, splitting it into continuation lines when too long
+fn write_manifest_attribute(manifest: &mut String, name: &str, value: &str) {
+    let line = format!("{}: {}", name, value);
+    let mut remaining = line.as_str();
+    let mut limit = MAX_MANIFEST_LINE;
+    while remaining.len() > limit {
+        // Cut on a char boundary, as the limit is expressed in bytes
+        let mut cut = limit;
+        while !remaining.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        manifest.push_str(&remaining[..cut]);
+        manifest.push_str("\r\n ");
+        remaining = &remaining[cut..];
+        // Continuation lines start with a space
+        limit = MAX_MANIFEST_LINE - 1;
+    }
+    manifest.push_str(remaining);
+    manifest.push_str("\r\n");
+}
+
+/// Creates the manifest of an executable JAR
+///
+/// # Arguments
+///  - `main_class` - binary name of the class to run, like `a.b.Main`
+///  - `class_path` - paths to the libraries of the program, relative to the JAR
+pub fn create_manifest(main_class: &str, class_path: &[String]) -> String {
+    let mut manifest = String::new();
+    write_manifest_attribute(&mut manifest, "Manifest-Version", "1.0");
+    write_manifest_attribute(&mut manifest, "Created-By", "tzio");
+    write_manifest_attribute(&mut manifest, "Main-Class", main_class);
+    if !class_path.is_empty() {
+        write_manifest_attribute(&mut manifest, "Class-Path", &class_path.join(" "));
+    }
+    manifest.push_str("\r\n");
+    manifest
+}
+
+fn push_u16(data: &mut Vec<u8>, value: u16) {
+    data.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(data: &mut Vec<u8>, value: u32) {
+    data.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Writes the fields shared by the local and central headers of an entry
+fn push_entry_description(data: &mut Vec<u8>, entry: &Entry, size: u32) {
+    push_u16(data, ZIP_VERSION);
+    push_u16(data, 0); // Flags
+    push_u16(data, 0); // Stored, without compression
+    push_u16(data, 0); // Time
+    push_u16(data, DOS_DATE);
+    push_u32(data, crc32(&entry.data));
+    push_u32(data, size); // Compressed size
+    push_u32(data, size);
+    push_u16(data, entry.name.len() as u16);
+    push_u16(data, 0); // Extra field length
+}
+
+impl Jar {
+    /// Creates an executable archive, starting with its manifest
+    pub fn new(main_class: &str, class_path: &[String]) -> Self {
+        let mut jar = Jar { entries: vec![] };
+        jar.add_file(
+            MANIFEST_PATH,
+            create_manifest(main_class, class_path).into_bytes(),
+        );
+        jar
+    }
+
+    /// Adds a file to the archive, under a `/`-separated path
+    pub fn add_file(&mut self, name: &str, data: Vec<u8>) {
+        self.entries.push(Entry {
+            name: String::from(name),
+            data,
+        });
+    }
+
+    /// Writes the archive in the zip format
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        if self.entries.len() > u16::MAX as usize {
+            return Err(format!(
+                "Cannot store {} files in an archive",
+                self.entries.len()
+            ));
+        }
+
+        let mut data = vec![];
+        let mut offsets = vec![];
+        for entry in &self.entries {
+            if entry.name.len() > u16::MAX as usize {
+                return Err(format!("Name of file {} is too long", entry.name));
+            }
+            let offset = data.len();
+            let size = entry.data.len();
+            if offset + size > u32::MAX as usize {
+                return Err(format!("Archive is too large to store {}", entry.name));
+            }
+            offsets.push(offset as u32);
+            push_u32(&mut data, LOCAL_HEADER_SIGNATURE);
+            push_entry_description(&mut data, entry, size as u32);
+            data.extend_from_slice(entry.name.as_bytes());
+            data.extend_from_slice(&entry.data);
+        }
+
+        let directory_offset = data.len();
+        for (entry, offset) in self.entries.iter().zip(offsets) {
+            push_u32(&mut data, CENTRAL_HEADER_SIGNATURE);
+            push_u16(&mut data, ZIP_VERSION); // Version made by
+            push_entry_description(&mut data, entry, entry.data.len() as u32);
+            push_u16(&mut data, 0); // Comment length
+            push_u16(&mut data, 0); // Disk number
+            push_u16(&mut data, 0); // Internal attributes
+            push_u32(&mut data, 0); // External attributes
+            push_u32(&mut data, offset);
+            data.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = data.len() - directory_offset;
+        if data.len() > u32::MAX as usize {
+            return Err(String::from("Archive is too large"));
+        }
+
+        push_u32(&mut data, END_OF_DIRECTORY_SIGNATURE);
+        push_u16(&mut data, 0); // Disk number
+        push_u16(&mut data, 0); // Disk of the central directory
+        push_u16(&mut data, self.entries.len() as u16);
+        push_u16(&mut data, self.entries.len() as u16);
+        push_u32(&mut data, directory_size as u32);
+        push_u32(&mut data, directory_offset as u32);
+        push_u16(&mut data, 0); // Comment length
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u16(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([data[offset], data[offset + 1]])
+    }
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_create_manifest() {
+        let manifest = create_manifest("a.b.Main", &[String::from("lib/core.jar")]);
+        assert_eq!(
+            manifest,
+            "Manifest-Version: 1.0\r\nCreated-By: tzio\r\nMain-Class: a.b.Main\r\nClass-Path: lib/core.jar\r\n\r\n"
+        );
+
+        let manifest = create_manifest("a.b.Main", &[]);
+        assert!(!manifest.contains("Class-Path"));
+    }
+
+    #[test]
+    fn test_split_long_manifest_lines() {
+        let libraries: Vec<String> = (0..20).map(|i| format!("lib/library-{}.jar", i)).collect();
+        let manifest = create_manifest("a.b.Main", &libraries);
+        for line in manifest.split("\r\n") {
+            assert!(line.len() <= MAX_MANIFEST_LINE);
+        }
+        let joined = manifest.replace("\r\n ", "");
+        assert!(joined.contains(&format!("Class-Path: {}\r\n", libraries.join(" "))));
+    }
+
+    #[test]
+    fn test_write_archive() {
+        let mut jar = Jar::new("a.b.Main", &[]);
+        jar.add_file("a/b/Main.class", vec![0xca, 0xfe, 0xba, 0xbe]);
+        let data = jar.to_bytes().unwrap();
+
+        // The manifest comes first
+        assert_eq!(read_u32(&data, 0), LOCAL_HEADER_SIGNATURE);
+        assert_eq!(read_u16(&data, 26) as usize, MANIFEST_PATH.len());
+        assert_eq!(
+            &data[30..30 + MANIFEST_PATH.len()],
+            MANIFEST_PATH.as_bytes()
+        );
+
+        // The end of the archive locates the central directory
+        let end = data.len() - 22;
+        assert_eq!(read_u32(&data, end), END_OF_DIRECTORY_SIGNATURE);
+        assert_eq!(read_u16(&data, end + 10), 2);
+        let directory_offset = read_u32(&data, end + 16) as usize;
+        assert_eq!(read_u32(&data, directory_offset), CENTRAL_HEADER_SIGNATURE);
+        assert_eq!(directory_offset + read_u32(&data, end + 12) as usize, end);
+
+        // The second entry points to the class, stored as is
+        let second = directory_offset + 46 + MANIFEST_PATH.len();
+        assert_eq!(read_u32(&data, second), CENTRAL_HEADER_SIGNATURE);
+        assert_eq!(
+            read_u32(&data, second + 16),
+            crc32(&[0xca, 0xfe, 0xba, 0xbe])
+        );
+        let offset = read_u32(&data, second + 42) as usize;
+        let name_length = read_u16(&data, offset + 26) as usize;
+        assert_eq!(
+            &data[offset + 30..offset + 30 + name_length],
+            b"a/b/Main.class"
+        );
+        assert_eq!(
+            &data[offset + 30 + name_length..offset + 34 + name_length],
+            &[0xca, 0xfe, 0xba, 0xbe]
+        );
+    }
+}
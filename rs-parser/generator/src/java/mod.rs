@@ -1,13 +1,23 @@
 mod class;
+mod compiled;
 mod constants;
 mod constructs;
 mod dictionary;
+mod frames;
+mod jar;
+mod names;
+#[cfg(test)]
+mod roundtrip;
+mod verifier;
 mod writer;
 
 use std::cmp::Eq;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
+use crate::backend::{Backend, GenerateError};
 use crate::java::dictionary::Dictionary;
 use language::address::Node;
 use language::instruction::{MemoryPointer, Operation, ValuePointer};
@@ -40,14 +50,6 @@ struct SlotStructure {
     output_indexes: Vec<u32>,
 }
 
-fn u8_to_i8(value: u8) -> i8 {
-    if value < 128 {
-        value as i8
-    } else {
-        panic!("Memory pointer index out of range: {}", value);
-    }
-}
-
 fn create_reference_instructions(
     class: &mut class::JavaClass,
     value_pointer: &ValuePointer,
@@ -86,7 +88,7 @@ fn create_reference_instructions(
             instructions.push(constructs::Operation::invokestatic(nil_method_idx));
         }
         ValuePointer::VALUE(ref value) => {
-            let cst_idx = class.map_integer(*value);
+            let value_operation = class.push_integer(*value as i32);
             let value_method_idx = class.map_method(
                 REFERENCES_CLASS_NAME,
                 "value",
@@ -95,12 +97,12 @@ fn create_reference_instructions(
                     parameter_types: vec![constants::Type::Integer],
                 },
             );
-            instructions.push(constructs::Operation::ldc(cst_idx));
+            instructions.push(value_operation);
             instructions.push(constructs::Operation::invokestatic(value_method_idx));
         }
         ValuePointer::INPUT(id) => {
             assert!(input, "Must be an input slot");
-            let cst_idx = class.map_integer(id.value().into());
+            let slot_operation = class.push_integer(id.value().into());
             let value_method_idx = class.map_method(
                 REFERENCES_CLASS_NAME,
                 "inSlot",
@@ -109,12 +111,12 @@ fn create_reference_instructions(
                     parameter_types: vec![constants::Type::Integer],
                 },
             );
-            instructions.push(constructs::Operation::ldc(cst_idx));
+            instructions.push(slot_operation);
             instructions.push(constructs::Operation::invokestatic(value_method_idx));
         }
         ValuePointer::OUTPUT(id) => {
             assert!(!input, "Must be an output slot");
-            let cst_idx = class.map_integer(id.value().into());
+            let slot_operation = class.push_integer(id.value().into());
             let value_method_idx = class.map_method(
                 REFERENCES_CLASS_NAME,
                 "outSlot",
@@ -123,7 +125,7 @@ fn create_reference_instructions(
                     parameter_types: vec![constants::Type::Integer],
                 },
             );
-            instructions.push(constructs::Operation::ldc(cst_idx));
+            instructions.push(slot_operation);
             instructions.push(constructs::Operation::invokestatic(value_method_idx));
         }
     }
@@ -140,9 +142,9 @@ fn create_slot_indexes(tree: &Program) -> SlotStructure {
     let mut slots: Dictionary<NodeSlot> = Dictionary::new();
 
     for (i, node) in tree.nodes.iter().enumerate() {
-        let node_name = node.0.get_id();
+        let node_name = node.node.get_id();
         let mut ins = Vec::new();
-        for input in &node.1 {
+        for input in &node.inputs {
             let input_name = match &input.from.node {
                 Node::Node(ref id) => id,
                 Node::In => "<IN>",
@@ -163,7 +165,7 @@ fn create_slot_indexes(tree: &Program) -> SlotStructure {
         s.node_inputs.insert(i, ins);
 
         let mut outs = Vec::new();
-        for output in &node.2 {
+        for output in &node.outputs {
             let output_name = match &output.to.node {
                 Node::Node(ref id) => id,
                 Node::Out => "<OUT>",
@@ -188,40 +190,42 @@ fn create_slot_indexes(tree: &Program) -> SlotStructure {
     s
 }
 
+/// Creates the operations storing an array of integers into a local variable
 fn create_int_array(
     class: &mut class::JavaClass,
     values: &[u32],
     var_idx: u8,
-) -> constructs::Attribute {
-    let array_size = class.map_integer(values.len() as u32);
+) -> Vec<constructs::Operation> {
     let mut operations = vec![
-        constructs::Operation::ldc(array_size),
+        class.push_integer(values.len() as i32),
         constructs::Operation::newarray(constants::ArrayType::INT),
         constructs::Operation::astore(var_idx),
     ];
     for (i, value) in values.iter().enumerate() {
-        let value_idx = class.map_integer(*value);
-        let index_idx = class.map_integer(i as u32);
-
         // Add value to array
         operations.push(constructs::Operation::aload(var_idx));
-        operations.push(constructs::Operation::ldc(index_idx));
-        operations.push(constructs::Operation::ldc(value_idx));
+        operations.push(class.push_integer(i as i32));
+        operations.push(class.push_integer(*value as i32));
         operations.push(constructs::Operation::iastore);
     }
 
-    constructs::Attribute::Code {
-        max_stack: 3,
-        operations,
-        locals: (var_idx as u16) + 1,
-    }
+    operations
 }
 
+/// Maximal size of the code adding the operations of a node to their list, before moving
+/// it to helper methods
+const OPERATION_CODE_BUDGET: u32 = constructs::MAX_CODE_LENGTH / 2;
+
+/// Creates the operations storing the list of the operations of a node into a local variable.
+///
+/// When the code is too large to fit in the method of the node, operations are added to the
+/// list by helper methods.
 fn create_operation_array(
     class: &mut class::JavaClass,
-    operations: &[Operation],
+    node_idx: usize,
+    node: &NodeBlock,
     var_idx: u8,
-) -> constructs::Attribute {
+) -> Result<Vec<constructs::Operation>, String> {
     // Create the array for the operations and store it as the var
     let arraylist_class_idx = class.map_class(ARRAY_LIST_CLASS_NAME);
     let list_cstr_idx = class.map_method(
@@ -248,70 +252,123 @@ fn create_operation_array(
         },
     );
 
-    // Create each operation
-    for operation in operations {
-        // Load the array
-        instructions.push(constructs::Operation::aload(var_idx));
-        // Construct the operation object
-        match operation {
-            Operation::MOV(ref from_pointer, ref to_pointer) => {
-                create_mov_operation(class, from_pointer, to_pointer, &mut instructions);
-            }
-            Operation::SAV(ref mem_pointer) => {
-                create_memory_operation(class, "SAV", mem_pointer, &mut instructions);
-            }
-            Operation::SWP(ref mem_pointer) => {
-                create_memory_operation(class, "SWP", mem_pointer, &mut instructions);
-            }
-            Operation::ADD(ref value_pointer) => {
-                create_math_operation(class, "ADD", value_pointer, &mut instructions);
-            }
-            Operation::SUB(ref value_pointer) => {
-                create_math_operation(class, "SUB", value_pointer, &mut instructions);
-            }
-            Operation::NEG => {
-                let method_idx = class.map_method(
-                    OPERATION_FACADE_CLASS_NAME,
-                    "NEG",
-                    &constructs::Signature {
-                        return_type: constants::Type::Object(String::from(OPERATION_CLASS_NAME)),
-                        parameter_types: vec![],
-                    },
-                );
-                instructions.push(constructs::Operation::invokestatic(method_idx));
-            }
-            Operation::LABEL(ref name) => {
-                create_labeled_operation(class, "LABEL", name, &mut instructions);
-            }
-            Operation::JMP(ref name) => {
-                create_labeled_operation(class, "JMP", name, &mut instructions);
-            }
-            Operation::JEZ(ref name) => {
-                create_labeled_operation(class, "JEZ", name, &mut instructions);
-            }
-            Operation::JNZ(ref name) => {
-                create_labeled_operation(class, "JNZ", name, &mut instructions);
-            }
-            Operation::JLZ(ref name) => {
-                create_labeled_operation(class, "JLZ", name, &mut instructions);
-            }
-            Operation::JGZ(ref name) => {
-                create_labeled_operation(class, "JGZ", name, &mut instructions);
-            }
-            Operation::JRO(ref value_pointer) => {
-                create_jro_operation(class, value_pointer, &mut instructions);
+    // Create each operation, then add it to the list, dropping the result of the addition
+    let additions: Vec<Vec<constructs::Operation>> = node
+        .instructions
+        .iter()
+        .enumerate()
+        .map(|(idx, operation)| {
+            let mut addition: Vec<constructs::Operation> =
+                constructs::source_line(node.spans.operation(idx))
+                    .into_iter()
+                    .collect();
+            addition.extend(create_operation_construction(class, operation));
+            addition.push(constructs::Operation::invokevirtual(add_to_list_idx));
+            addition.push(constructs::Operation::pop);
+            addition
+        })
+        .collect();
+    let mut lengths = Vec::with_capacity(additions.len());
+    for addition in &additions {
+        // Count the loading of the list
+        lengths.push(writer::code_length(addition)? + 2);
+    }
+
+    if lengths.iter().sum::<u32>() <= OPERATION_CODE_BUDGET {
+        for addition in additions {
+            instructions.push(constructs::Operation::aload(var_idx));
+            instructions.extend(addition);
+        }
+    } else {
+        let helper_signature = constructs::Signature {
+            return_type: constants::Type::Void,
+            parameter_types: vec![constants::Type::Object(String::from(ARRAY_LIST_CLASS_NAME))],
+        };
+        let access: u16 =
+            (constants::MethodAccess::STATIC as u16) | (constants::MethodAccess::PRIVATE as u16);
+        // Group the additions by helper, keeping room for the final return
+        let mut groups: Vec<Vec<constructs::Operation>> = vec![];
+        let mut group_length = constructs::MAX_CODE_LENGTH;
+        for (addition, length) in additions.into_iter().zip(lengths) {
+            if group_length + length >= constructs::MAX_CODE_LENGTH {
+                groups.push(vec![]);
+                group_length = 0;
             }
+            let group = groups.last_mut().expect("No group of operations");
+            group.push(constructs::Operation::aload(0));
+            group.extend(addition);
+            group_length += length;
+        }
+        for (helper_idx, mut group) in groups.into_iter().enumerate() {
+            group.push(constructs::Operation::return_void);
+            let method_name = format!("createNode{}Operations{}", node_idx, helper_idx);
+            let code = constructs::create_code(Some(&helper_signature), group)?;
+            let method_idx =
+                class.create_method(access, &method_name, helper_signature.clone(), vec![code])?;
+            instructions.push(constructs::Operation::aload(var_idx));
+            instructions.push(constructs::Operation::invokestatic(method_idx));
         }
-        // Add the operation to the list
-        instructions.push(constructs::Operation::invokevirtual(add_to_list_idx));
     }
 
-    let locals = constructs::count_local_vars(None, &instructions);
-    constructs::Attribute::Code {
-        max_stack: 10,
-        operations: instructions,
-        locals,
+    Ok(instructions)
+}
+
+/// Creates the operations constructing an operation object, putting it on the stack
+fn create_operation_construction(
+    class: &mut class::JavaClass,
+    operation: &Operation,
+) -> Vec<constructs::Operation> {
+    let mut instructions = vec![];
+    match operation {
+        Operation::MOV(ref from_pointer, ref to_pointer) => {
+            create_mov_operation(class, from_pointer, to_pointer, &mut instructions);
+        }
+        Operation::SAV(ref mem_pointer) => {
+            create_memory_operation(class, "SAV", mem_pointer, &mut instructions);
+        }
+        Operation::SWP(ref mem_pointer) => {
+            create_memory_operation(class, "SWP", mem_pointer, &mut instructions);
+        }
+        Operation::ADD(ref value_pointer) => {
+            create_math_operation(class, "ADD", value_pointer, &mut instructions);
+        }
+        Operation::SUB(ref value_pointer) => {
+            create_math_operation(class, "SUB", value_pointer, &mut instructions);
+        }
+        Operation::NEG => {
+            let method_idx = class.map_method(
+                OPERATION_FACADE_CLASS_NAME,
+                "NEG",
+                &constructs::Signature {
+                    return_type: constants::Type::Object(String::from(OPERATION_CLASS_NAME)),
+                    parameter_types: vec![],
+                },
+            );
+            instructions.push(constructs::Operation::invokestatic(method_idx));
+        }
+        Operation::LABEL(ref name) => {
+            create_labeled_operation(class, "LABEL", name, &mut instructions);
+        }
+        Operation::JMP(ref name) => {
+            create_labeled_operation(class, "JMP", name, &mut instructions);
+        }
+        Operation::JEZ(ref name) => {
+            create_labeled_operation(class, "JEZ", name, &mut instructions);
+        }
+        Operation::JNZ(ref name) => {
+            create_labeled_operation(class, "JNZ", name, &mut instructions);
+        }
+        Operation::JLZ(ref name) => {
+            create_labeled_operation(class, "JLZ", name, &mut instructions);
+        }
+        Operation::JGZ(ref name) => {
+            create_labeled_operation(class, "JGZ", name, &mut instructions);
+        }
+        Operation::JRO(ref value_pointer) => {
+            create_jro_operation(class, value_pointer, &mut instructions);
+        }
     }
+    instructions
 }
 
 fn create_mov_operation(
@@ -351,8 +408,7 @@ fn create_memory_operation(
         },
     );
     let &MemoryPointer::BAK(i) = mem_pointer;
-    let memory_slot: i8 = u8_to_i8(i);
-    instructions.push(constructs::Operation::bipush(memory_slot));
+    instructions.push(class.push_integer(i.into()));
     instructions.push(constructs::Operation::invokestatic(method_idx));
 }
 
@@ -414,31 +470,252 @@ fn create_jro_operation(
     instructions.push(constructs::Operation::invokestatic(method_idx));
 }
 
-pub fn create_main_file(tree: &Program, package: &str, output_dir: &PathBuf) -> Result<(), String> {
+/// How the operations of the nodes are run
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    /// Operations are created as objects, interpreted by the Java core
+    Interpreted,
+    /// Operations are compiled into the step method of a class per node
+    Compiled,
+}
+
+/// Format of the files written for a program
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Output {
+    /// Class files, in the directories of their package
+    Classes,
+    /// Executable JAR containing all classes
+    Jar,
+}
+
+/// Default path to the Java core, relative to generated JARs
+const DEFAULT_CLASS_PATH: &str = "tzio-java-full-1.0-SNAPSHOT.jar";
+
+/// Backend writing programs as Java classes, run by the Java core
+pub struct JavaBackend {
+    mode: Mode,
+    output: Output,
+    /// Libraries referenced by the manifest of JARs
+    class_path: Vec<String>,
+    /// Main class of programs, in its internal form. Derived from the name of programs if not set.
+    class_name: Option<String>,
+}
+
+impl Default for JavaBackend {
+    fn default() -> Self {
+        JavaBackend {
+            mode: Mode::Interpreted,
+            output: Output::Classes,
+            class_path: vec![String::from(DEFAULT_CLASS_PATH)],
+            class_name: None,
+        }
+    }
+}
+
+/// Generates the output directory for a given program.
+/// To comply with Java module naming, this creates a series of parent directories for the final
+/// program, after the package of its main class. The directory receives the main class
+/// as well as any additional classes.
+fn prepare_output(classname: &str, target_dir: &Path) -> Result<PathBuf, String> {
+    let mut output_dir = target_dir.to_path_buf();
+    output_dir.extend(classname.split('/'));
+    output_dir.pop();
+    fs::create_dir_all(&output_dir).map_err(|error| {
+        format!(
+            "Could not create output directory {} due to error {}",
+            output_dir.display(),
+            error
+        )
+    })?;
+    // Clean the classes of a previous build, as the package may be shared with other programs
+    let simple_name = names::simple_name(classname);
+    let nested_prefix = format!("{}$", simple_name);
+    let entries = fs::read_dir(&output_dir).map_err(|error| {
+        format!(
+            "Could not read output directory {} due to error {}",
+            output_dir.display(),
+            error
+        )
+    })?;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let filename = entry.file_name().to_string_lossy().into_owned();
+        if let Some(stem) = filename.strip_suffix(".class") {
+            if stem == simple_name || stem.starts_with(&nested_prefix) {
+                let path = entry.path();
+                match fs::remove_file(&path) {
+                    // Already removed, like by a concurrent build
+                    Err(ref error) if error.kind() == io::ErrorKind::NotFound => (),
+                    Err(error) => {
+                        return Err(format!(
+                            "Could not delete previous class {} due to error {}",
+                            path.display(),
+                            error
+                        ))
+                    }
+                    Ok(()) => (),
+                }
+            }
+        }
+    }
+    Ok(output_dir)
+}
+
+impl Backend for JavaBackend {
+    fn name(&self) -> &'static str {
+        "java"
+    }
+
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match (key, value) {
+            ("mode", "interpreted") => self.mode = Mode::Interpreted,
+            ("mode", "compiled") => self.mode = Mode::Compiled,
+            ("mode", _) => {
+                return Err(format!(
+                    "Invalid mode {}, expected interpreted or compiled",
+                    value
+                ))
+            }
+            ("output", "classes") => self.output = Output::Classes,
+            ("output", "jar") => self.output = Output::Jar,
+            ("output", _) => {
+                return Err(format!("Invalid output {}, expected classes or jar", value))
+            }
+            ("class-path", _) => {
+                self.class_path = value.split_whitespace().map(String::from).collect()
+            }
+            ("class-name", _) => self.class_name = Some(names::parse_class_name(value)?),
+            _ => return Err(format!("Unknown option {} for target java", key)),
+        }
+        Ok(())
+    }
+
+    fn generate(
+        &self,
+        program: &Program,
+        name: &str,
+        source_file: &str,
+        output_dir: &Path,
+    ) -> Result<PathBuf, GenerateError> {
+        let classname = self
+            .class_name
+            .clone()
+            .unwrap_or_else(|| names::default_class_name(name));
+        let source_name = names::source_name(source_file);
+        let classes = create_classes(program, classname, source_name.as_deref(), self.mode)
+            .map_err(GenerateError::Program)?;
+        for (classname, class) in &classes {
+            verifier::verify_class(class).map_err(|e| {
+                GenerateError::Program(format!("Invalid class {}: {}", classname, e))
+            })?;
+        }
+        match self.output {
+            Output::Classes => {
+                prepare_output(&classes[0].0, output_dir).map_err(GenerateError::Output)?;
+                for (classname, class) in &classes {
+                    write_class(class, output_dir, classname).map_err(GenerateError::Output)?;
+                }
+                Ok(output_dir.join(format!("{}.class", classes[0].0)))
+            }
+            Output::Jar => write_jar(&classes, name, output_dir, &self.class_path),
+        }
+    }
+}
+
+/// Writes a class under the root directory of the classes
+fn write_class(class: &class::JavaClass, output_dir: &Path, classname: &str) -> Result<(), String> {
+    let mut output_file = output_dir.to_path_buf();
+    output_file.push(format!("{}.class", classname));
+    writer::write(class, output_file.as_path())
+        .map_err(|e| format!("Failed to write into file. Caused by {}", e))
+}
+
+/// Writes the classes of a program into an executable JAR named after the program.
+/// The first class is the main class of the program.
+fn write_jar(
+    classes: &[(String, class::JavaClass)],
+    name: &str,
+    output_dir: &Path,
+    class_path: &[String],
+) -> Result<PathBuf, GenerateError> {
+    let main_class = names::binary_name(&classes[0].0);
+    let mut jar = jar::Jar::new(&main_class, class_path);
+    for (classname, class) in classes {
+        let data = writer::to_bytes(class).map_err(|e| {
+            GenerateError::Program(format!(
+                "Failed to write class {}. Caused by {}",
+                classname, e
+            ))
+        })?;
+        jar.add_file(&format!("{}.class", classname), data);
+    }
+    let data = jar.to_bytes().map_err(GenerateError::Program)?;
+
+    fs::create_dir_all(output_dir).map_err(|error| {
+        GenerateError::Output(format!(
+            "Could not create output directory {} due to error {}",
+            output_dir.display(),
+            error
+        ))
+    })?;
+    let mut output_file = output_dir.to_path_buf();
+    output_file.push(format!("{}.jar", name));
+    fs::write(&output_file, data).map_err(|e| {
+        GenerateError::Output(format!(
+            "Failed to write into file {}. Caused by {}",
+            output_file.display(),
+            e
+        ))
+    })?;
+    Ok(output_file)
+}
+
+/// Creates the classes of a program, with their names.
+/// The main class comes first, followed by the classes it uses.
+fn create_classes(
+    tree: &Program,
+    classname: String,
+    source_name: Option<&str>,
+    mode: Mode,
+) -> Result<Vec<(String, class::JavaClass)>, String> {
     let slots = create_slot_indexes(tree);
     let mut class = class::JavaClass::new();
-
-    let mut classname = String::from("com/kineolyan/tzio/");
-    classname.push_str(package);
-    classname.push_str("/Main");
     class.set_class(&classname);
 
     class.set_super_class(&OBJECT_CLASS_NAME);
+    if let Some(filename) = source_name {
+        class.set_source_file(filename);
+    }
 
+    let mut node_classes = vec![];
     let mut definition_methods: Vec<class::PoolIdx> = vec![];
     for (i, node) in tree.nodes.iter().enumerate() {
-        let pool_idx = create_node_definition_method(i, node, &mut class, &slots);
+        let pool_idx = match mode {
+            Mode::Interpreted => create_node_definition_method(i, node, &mut class, &slots)?,
+            Mode::Compiled => {
+                let node_class_name = format!("{}$Node{}", classname, i);
+                let mut node_class = compiled::create_node_class(node, &node_class_name)?;
+                if let Some(filename) = source_name {
+                    node_class.set_source_file(filename);
+                }
+                node_classes.push((node_class_name.clone(), node_class));
+                create_compiled_node_definition_method(
+                    i,
+                    node,
+                    &mut class,
+                    &slots,
+                    &node_class_name,
+                )?
+            }
+        };
         definition_methods.push(pool_idx);
     }
 
-    let create_idx = create_construction(&mut class, &definition_methods, &slots);
-    create_main(&mut class, create_idx);
+    let create_idx = create_construction(&mut class, &definition_methods, &slots)?;
+    create_main(&mut class, create_idx)?;
 
-    let mut output_file = output_dir.clone();
-    output_file.push("Main");
-    output_file.set_extension("class");
-    writer::write(&class, output_file.as_path())
-        .map_err(|e| format!("Failed to write into file. Caused by {}", e))
+    let mut classes = vec![(classname, class)];
+    classes.extend(node_classes);
+    Ok(classes)
 }
 
 /// Create a static method
@@ -447,7 +724,7 @@ fn create_node_definition_method(
     node: &NodeBlock,
     class: &mut class::JavaClass,
     slots: &SlotStructure,
-) -> class::PoolIdx {
+) -> Result<class::PoolIdx, String> {
     let add_node_idx = class.map_interface_method(
         &TZ_ENV_CLASS_NAME,
         "addNode",
@@ -468,7 +745,7 @@ fn create_node_definition_method(
         parameter_types: vec![constants::Type::Object(String::from(TZ_ENV_CLASS_NAME))],
     };
 
-    let node_name = class.map_string(&node.0.get_id());
+    let node_name = class.map_string(&node.node.get_id());
     let input_array_var_idx = 1;
     let create_input_array = create_int_array(
         class,
@@ -488,11 +765,13 @@ fn create_node_definition_method(
         output_array_var_idx,
     );
     let operation_array_var_idx = 3;
-    let create_op_array = create_operation_array(class, &node.3, operation_array_var_idx);
+    let create_op_array = create_operation_array(class, i, node, operation_array_var_idx)?;
+    let memory_size = class.push_integer(node.memory_size().into());
+    let node_line = constructs::source_line(node.spans.node);
     let call_to_add_node = vec![
         constructs::Operation::aload(0),                       // first arg
         constructs::Operation::ldc(node_name),                 // node name
-        constructs::Operation::iconst_1,                       // node memory size
+        memory_size,                                           // node memory size
         constructs::Operation::aload(input_array_var_idx),     // input array
         constructs::Operation::aload(output_array_var_idx),    // output array
         constructs::Operation::aload(operation_array_var_idx), // operation array
@@ -506,19 +785,110 @@ fn create_node_definition_method(
     let mut method_name = String::from("createNode");
     method_name.push_str(&(i as u32).to_string());
 
-    let method_code = constructs::merge_codes(
-        Some(&signature),
-        vec![
-            create_input_array,
-            create_output_array,
-            create_op_array,
-            constructs::Attribute::Code {
-                max_stack: 6,
-                locals: constructs::count_local_vars(None, &call_to_add_node),
-                operations: call_to_add_node,
-            },
-        ],
+    // The node is added at the line of its declaration, after the lines of its operations
+    let operations: Vec<constructs::Operation> = vec![
+        constructs::source_line(node.spans.node)
+            .into_iter()
+            .collect(),
+        create_input_array,
+        create_output_array,
+        create_op_array,
+        node_line.into_iter().collect(),
+        call_to_add_node,
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let method_code = constructs::create_code(Some(&signature), operations)?;
+
+    class.create_method(access, &method_name, signature, vec![method_code])
+}
+
+/// Create a static method adding a compiled node to the environment
+fn create_compiled_node_definition_method(
+    i: usize,
+    node: &NodeBlock,
+    class: &mut class::JavaClass,
+    slots: &SlotStructure,
+    node_class_name: &str,
+) -> Result<class::PoolIdx, String> {
+    let add_node_idx = class.map_interface_method(
+        TZ_ENV_CLASS_NAME,
+        "addCompiledNode",
+        &constructs::Signature {
+            return_type: constants::Type::Object(String::from(TZ_ENV_CLASS_NAME)),
+            parameter_types: vec![
+                constants::Type::Object(String::from(STRING_CLASS_NAME)),
+                constants::Type::PrimitiveArray(1, constants::ArrayType::INT),
+                constants::Type::PrimitiveArray(1, constants::ArrayType::INT),
+                constants::Type::Object(String::from(compiled::COMPILED_NODE_CLASS_NAME)),
+            ],
+        },
+    );
+    let node_class_idx = class.map_class(node_class_name);
+    let node_constructor_idx = class.map_method(
+        node_class_name,
+        "<init>",
+        &constructs::Signature {
+            return_type: constants::Type::Void,
+            parameter_types: vec![],
+        },
+    );
+
+    let signature = constructs::Signature {
+        return_type: constants::Type::Object(String::from(TZ_ENV_CLASS_NAME)),
+        parameter_types: vec![constants::Type::Object(String::from(TZ_ENV_CLASS_NAME))],
+    };
+
+    let node_name = class.map_string(node.node.get_id());
+    let input_array_var_idx = 1;
+    let create_input_array = create_int_array(
+        class,
+        slots
+            .node_inputs
+            .get(&i)
+            .unwrap_or_else(|| panic!("No inputs for node {}", i)),
+        input_array_var_idx,
     );
+    let output_array_var_idx = 2;
+    let create_output_array = create_int_array(
+        class,
+        slots
+            .node_outputs
+            .get(&i)
+            .unwrap_or_else(|| panic!("No outputs for node {}", i)),
+        output_array_var_idx,
+    );
+    let call_to_add_node = vec![
+        constructs::Operation::aload(0),                    // first arg
+        constructs::Operation::ldc(node_name),              // node name
+        constructs::Operation::aload(input_array_var_idx),  // input array
+        constructs::Operation::aload(output_array_var_idx), // output array
+        constructs::Operation::new(node_class_idx),         // compiled node
+        constructs::Operation::dup,
+        constructs::Operation::invokespecial(node_constructor_idx),
+        constructs::Operation::invokeinterface(add_node_idx, 5),
+        constructs::Operation::areturn,
+    ];
+
+    let access: u16 =
+        (constants::MethodAccess::STATIC as u16) | (constants::MethodAccess::PRIVATE as u16);
+
+    let mut method_name = String::from("createNode");
+    method_name.push_str(&(i as u32).to_string());
+
+    let operations: Vec<constructs::Operation> = vec![
+        constructs::source_line(node.spans.node)
+            .into_iter()
+            .collect(),
+        create_input_array,
+        create_output_array,
+        call_to_add_node,
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let method_code = constructs::create_code(Some(&signature), operations)?;
 
     class.create_method(access, &method_name, signature, vec![method_code])
 }
@@ -527,7 +897,7 @@ fn create_construction(
     class: &mut class::JavaClass,
     definition_methods: &[class::PoolIdx],
     slots: &SlotStructure,
-) -> class::PoolIdx {
+) -> Result<class::PoolIdx, String> {
     let get_instance_idx = class.map_interface_method(
         TZ_SYSTEM_CLASS_NAME,
         &"getInstance",
@@ -548,22 +918,17 @@ fn create_construction(
         constructs::Operation::invokestatic(get_instance_idx),
         constructs::Operation::invokeinterface(create_env_idx, 1),
     ];
-    let create_env_code = constructs::Attribute::Code {
-        max_stack: 1,
-        locals: constructs::count_local_vars(None, &create_env_ops),
-        operations: create_env_ops,
-    };
 
     let create_input_array_op = create_int_array(class, &slots.input_indexes, 1);
     let create_output_array_op = create_int_array(class, &slots.output_indexes, 2);
 
-    let slot_count_cst = class.map_integer(slots.count);
+    let slot_count = class.push_integer(slots.count as i32);
     let with_slots_idx = get_with_slots_idx(class);
     let with_slots_op = vec![
         // TzEnv already on the top of the stack
-        constructs::Operation::ldc(slot_count_cst), // slot count
-        constructs::Operation::aload(1),            // inputs array
-        constructs::Operation::aload(2),            // output array
+        slot_count,                                                // slot count
+        constructs::Operation::aload(1),                           // inputs array
+        constructs::Operation::aload(2),                           // output array
         constructs::Operation::invokeinterface(with_slots_idx, 4), // Method returning the TzEnv instance
     ];
 
@@ -586,29 +951,25 @@ fn create_construction(
     let access: u16 =
         constants::MethodAccess::PRIVATE as u16 | constants::MethodAccess::STATIC as u16;
 
-    let method_code = constructs::merge_codes(
-        Some(&this_signature),
-        vec![
-            create_env_code,
-            create_input_array_op,
-            create_output_array_op,
-            constructs::Attribute::Code {
-                max_stack: 5,
-                locals: constructs::count_local_vars(None, &with_slots_op),
-                operations: with_slots_op,
-            },
-            constructs::Attribute::Code {
-                max_stack: 2,
-                locals: constructs::count_local_vars(None, &create_nodes_op),
-                operations: create_nodes_op,
-            },
-        ],
-    );
+    let operations: Vec<constructs::Operation> = vec![
+        create_env_ops,
+        create_input_array_op,
+        create_output_array_op,
+        with_slots_op,
+        create_nodes_op,
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let method_code = constructs::create_code(Some(&this_signature), operations)?;
 
     class.create_method(access, &"create", this_signature, vec![method_code])
 }
 
-fn create_main(class: &mut class::JavaClass, creator_idx: class::PoolIdx) -> class::PoolIdx {
+fn create_main(
+    class: &mut class::JavaClass,
+    creator_idx: class::PoolIdx,
+) -> Result<class::PoolIdx, String> {
     let signature = constructs::Signature {
         return_type: constants::Type::Void,
         parameter_types: vec![constants::Type::ObjectArray(
@@ -631,17 +992,8 @@ fn create_main(class: &mut class::JavaClass, creator_idx: class::PoolIdx) -> cla
     let access: u16 =
         (constants::MethodAccess::STATIC as u16) | (constants::MethodAccess::PUBLIC as u16);
 
-    let local_count = constructs::count_local_vars(Some(&signature), &operations);
-    class.create_method(
-        access,
-        &"main",
-        signature,
-        vec![constructs::Attribute::Code {
-            max_stack: 3,
-            operations,
-            locals: local_count,
-        }],
-    )
+    let method_code = constructs::create_code(Some(&signature), operations)?;
+    class.create_method(access, &"main", signature, vec![method_code])
 }
 
 fn get_with_slots_idx(class: &mut class::JavaClass) -> class::PoolIdx {
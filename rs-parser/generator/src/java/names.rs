@@ -0,0 +1,212 @@
+//! Module naming the classes generated for programs.
+//!
+//! Classes are named in the internal form of the JVM, with packages separated by `/`.
+
+use std::path::Path;
+
+/// Package of the programs without a configured class name
+const DEFAULT_PACKAGE: &str = "com/kineolyan/tzio";
+/// Simple name of the main class of programs without a configured class name
+const DEFAULT_CLASS: &str = "Main";
+
+/// Words that cannot be used as Java identifiers
+const RESERVED_WORDS: [&str; 54] = [
+    "_",
+    "abstract",
+    "assert",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "false",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "strictfp",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "true",
+    "try",
+    "void",
+    "volatile",
+    "while",
+];
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_identifier_part(c: char) -> bool {
+    is_identifier_start(c) || c.is_ascii_digit()
+}
+
+/// Checks that a name is a valid Java identifier.
+/// Only ASCII identifiers are accepted, as class files are written with ASCII strings.
+fn check_identifier(identifier: &str) -> Result<(), String> {
+    let mut chars = identifier.chars();
+    match chars.next() {
+        None => Err(String::from("Empty identifier")),
+        Some(c) if !is_identifier_start(c) => {
+            Err(format!("Identifier {} cannot start with {}", identifier, c))
+        }
+        _ => match chars.find(|c| !is_identifier_part(*c)) {
+            Some(c) => Err(format!("Identifier {} cannot contain {}", identifier, c)),
+            None if RESERVED_WORDS.contains(&identifier) => {
+                Err(format!("Identifier {} is a reserved word", identifier))
+            }
+            None => Ok(()),
+        },
+    }
+}
+
+/// Turns any name into a valid Java identifier, replacing invalid characters by `_`
+pub fn sanitise_identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .chars()
+        .map(|c| if is_identifier_part(c) { c } else { '_' })
+        .collect();
+    let starts_correctly = identifier.chars().next().map(is_identifier_start);
+    if starts_correctly != Some(true) || RESERVED_WORDS.contains(&identifier.as_str()) {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+/// Gets the class name of a program with no configured class name.
+/// The program name becomes a package, under the package of the project.
+pub fn default_class_name(program_name: &str) -> String {
+    format!(
+        "{}/{}/{}",
+        DEFAULT_PACKAGE,
+        sanitise_identifier(program_name),
+        DEFAULT_CLASS
+    )
+}
+
+/// Parses a fully qualified class name, like `org.example.Program`, into its internal form
+pub fn parse_class_name(name: &str) -> Result<String, String> {
+    name.split('.')
+        .map(|identifier| {
+            check_identifier(identifier)
+                .map(|_| identifier)
+                .map_err(|error| format!("Invalid class name {}: {}", name, error))
+        })
+        .collect::<Result<Vec<&str>, String>>()
+        .map(|identifiers| identifiers.join("/"))
+}
+
+/// Gets the binary name of a class, like `org.example.Program`, from its internal form
+pub fn binary_name(classname: &str) -> String {
+    classname.replace('/', ".")
+}
+
+/// Gets the simple name of a class, without its package, from its internal form
+pub fn simple_name(classname: &str) -> &str {
+    classname.rsplit('/').next().unwrap_or(classname)
+}
+
+/// Gets the name of a source file, as written in classes.
+/// Names that cannot be written as ASCII strings are dropped.
+pub fn source_name(source_file: &str) -> Option<String> {
+    Path::new(source_file)
+        .file_name()
+        .and_then(|filename| filename.to_str())
+        .filter(|filename| filename.is_ascii())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitise_identifiers() {
+        assert_eq!(sanitise_identifier("sum"), "sum");
+        assert_eq!(sanitise_identifier("max-stream"), "max_stream");
+        assert_eq!(sanitise_identifier("2-inputs"), "_2_inputs");
+        assert_eq!(sanitise_identifier("int"), "_int");
+        assert_eq!(sanitise_identifier("été"), "_t_");
+        assert_eq!(sanitise_identifier(""), "_");
+    }
+
+    #[test]
+    fn test_default_class_name() {
+        assert_eq!(default_class_name("sum"), "com/kineolyan/tzio/sum/Main");
+        assert_eq!(
+            default_class_name("max-stream"),
+            "com/kineolyan/tzio/max_stream/Main"
+        );
+    }
+
+    #[test]
+    fn test_parse_class_names() {
+        assert_eq!(
+            parse_class_name("org.example.Program"),
+            Ok(String::from("org/example/Program"))
+        );
+        assert_eq!(parse_class_name("Program"), Ok(String::from("Program")));
+        assert_eq!(parse_class_name("$a._b.C1"), Ok(String::from("$a/_b/C1")));
+    }
+
+    #[test]
+    fn test_reject_invalid_class_names() {
+        assert!(parse_class_name("").is_err());
+        assert!(parse_class_name("org..Program").is_err());
+        assert!(parse_class_name("org.example.").is_err());
+        assert!(parse_class_name("org.max-stream.Main").is_err());
+        assert!(parse_class_name("org.2d.Main").is_err());
+        assert!(parse_class_name("org.new.Main").is_err());
+        assert!(parse_class_name("org/example/Main").is_err());
+        assert!(parse_class_name("org.été.Main").is_err());
+    }
+
+    #[test]
+    fn test_names_of_classes() {
+        assert_eq!(binary_name("org/example/Program"), "org.example.Program");
+        assert_eq!(simple_name("org/example/Program"), "Program");
+        assert_eq!(simple_name("Program"), "Program");
+    }
+
+    #[test]
+    fn test_source_names() {
+        assert_eq!(source_name("samples/sum.io"), Some(String::from("sum.io")));
+        assert_eq!(source_name("sum.io"), Some(String::from("sum.io")));
+        assert_eq!(source_name("samples/été.io"), None);
+        assert_eq!(source_name(""), None);
+    }
+}
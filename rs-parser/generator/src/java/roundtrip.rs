@@ -0,0 +1,386 @@
+//! Cross-checks of the classes written for the samples, read back with java-reader.
+//!
+//! The writer and java-reader implement the class file format independently. Each class is
+//! written, parsed again and compared to the model it was written from.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use java_reader::class_file::{Attribute, ClassFile};
+use java_reader::operations::Operands;
+use java_reader::pool::{PoolElement as ReadElement, PoolList};
+
+use crate::java::class::{JavaClass, PoolElement};
+use crate::java::constructs::{self, Operation};
+use crate::java::verifier::{check_field_descriptor, check_method_descriptor, verify_class};
+use crate::java::{create_classes, names, writer, Mode};
+
+const SAMPLES_DIR: &str = "../../language-samples";
+
+fn sample_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(SAMPLES_DIR)
+        .expect("Cannot list the samples")
+        .map(|entry| entry.expect("Cannot read sample entry").path())
+        .filter(|path| path.extension().map(|ext| ext == "io").unwrap_or(false))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Compiles a sample into its classes, with their names
+fn compile_sample(path: &Path, mode: Mode) -> Vec<(String, JavaClass)> {
+    let source = fs::read(path).expect("Cannot read sample");
+    let program =
+        parser::parse(&source).unwrap_or_else(|_| panic!("Cannot parse {}", path.display()));
+    let program = postprocessor::process(program)
+        .unwrap_or_else(|_| panic!("Invalid mappings in {}", path.display()));
+    let filename = path.to_str().expect("Sample path is not valid utf8");
+    let classname = names::default_class_name(&crate::program_name(filename));
+    create_classes(
+        &program,
+        classname,
+        names::source_name(filename).as_deref(),
+        mode,
+    )
+    .unwrap_or_else(|e| panic!("Cannot compile {}: {}", path.display(), e))
+}
+
+fn entry(pool: &PoolList, idx: usize) -> Option<&ReadElement> {
+    pool.get(idx).and_then(|element| element.as_ref())
+}
+
+fn is_utf8(pool: &PoolList, idx: usize) -> bool {
+    matches!(entry(pool, idx), Some(ReadElement::Utf8Value(_)))
+}
+
+fn is_class(pool: &PoolList, idx: usize) -> bool {
+    matches!(entry(pool, idx), Some(ReadElement::ClassInfo(_)))
+}
+
+fn is_name_and_type(pool: &PoolList, idx: usize) -> bool {
+    matches!(entry(pool, idx), Some(ReadElement::NameAndType(_, _)))
+}
+
+fn resolve_descriptor(pool: &PoolList, name_and_type_idx: usize) -> &str {
+    match entry(pool, name_and_type_idx) {
+        Some(ReadElement::NameAndType(_, descriptor_idx)) => match entry(pool, *descriptor_idx) {
+            Some(ReadElement::Utf8Value(ref value)) => value,
+            _ => "",
+        },
+        _ => "",
+    }
+}
+
+/// Checks that all references of the pool are in range and of the right tag
+fn check_pool(pool: &PoolList) -> Result<(), String> {
+    for (idx, element) in pool.iter().enumerate() {
+        let valid = match element {
+            None => idx == 0,
+            Some(ReadElement::ClassInfo(name_idx)) | Some(ReadElement::String(name_idx)) => {
+                is_utf8(pool, *name_idx)
+            }
+            Some(ReadElement::NameAndType(name_idx, descriptor_idx)) => {
+                is_utf8(pool, *name_idx) && is_utf8(pool, *descriptor_idx)
+            }
+            Some(ReadElement::FieldRef {
+                class_idx,
+                name_and_type_idx,
+            }) => {
+                is_class(pool, *class_idx)
+                    && is_name_and_type(pool, *name_and_type_idx)
+                    && check_field_descriptor(resolve_descriptor(pool, *name_and_type_idx)).is_ok()
+            }
+            Some(ReadElement::MethodRef {
+                class_idx,
+                name_and_type_idx,
+            })
+            | Some(ReadElement::InterfaceMethodRef {
+                class_idx,
+                name_and_type_idx,
+            }) => {
+                is_class(pool, *class_idx)
+                    && is_name_and_type(pool, *name_and_type_idx)
+                    && check_method_descriptor(resolve_descriptor(pool, *name_and_type_idx)).is_ok()
+            }
+            Some(ReadElement::Utf8Value(_)) | Some(ReadElement::Integer(_)) => true,
+            Some(element) => return Err(format!("Unexpected entry {:?} at {}", element, idx)),
+        };
+        if !valid {
+            return Err(format!("Invalid entry {:?} at {}", element, idx));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the constants used by instructions have the expected tags
+fn check_code_references(class: &ClassFile) -> Result<(), String> {
+    let pool = &class.pool;
+    for method in &class.methods {
+        let code = match method.code() {
+            Some(code) => code,
+            None => continue,
+        };
+        for instruction in &code.instructions {
+            let valid = match instruction.operands {
+                Operands::Constant(idx) => {
+                    let element = entry(pool, idx as usize);
+                    match instruction.opcode {
+                        // ldc
+                        18 => matches!(
+                            element,
+                            Some(ReadElement::Integer(_)) | Some(ReadElement::String(_))
+                        ),
+                        // getstatic, putstatic, getfield, putfield
+                        178..=181 => matches!(element, Some(ReadElement::FieldRef { .. })),
+                        // invokevirtual
+                        182 => matches!(element, Some(ReadElement::MethodRef { .. })),
+                        // invokespecial and invokestatic also call interface methods
+                        183 | 184 => matches!(
+                            element,
+                            Some(ReadElement::MethodRef { .. })
+                                | Some(ReadElement::InterfaceMethodRef { .. })
+                        ),
+                        // new, anewarray, checkcast, instanceof
+                        187 | 189 | 192 | 193 => is_class(pool, idx as usize),
+                        _ => false,
+                    }
+                }
+                Operands::InterfaceMethod { method_idx, count } => {
+                    count > 0
+                        && matches!(
+                            entry(pool, method_idx as usize),
+                            Some(ReadElement::InterfaceMethodRef { .. })
+                        )
+                }
+                _ => true,
+            };
+            if !valid {
+                return Err(format!(
+                    "Invalid reference of {} at {}",
+                    instruction.name(),
+                    instruction.offset
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the parsed pool holds the entries of the model, at the same indexes
+fn compare_pool(expected: &JavaClass, actual: &PoolList) -> Result<(), String> {
+    if actual.len() != expected.pool_size() as usize {
+        return Err(format!(
+            "Pool of {} entries instead of {}",
+            actual.len(),
+            expected.pool_size()
+        ));
+    }
+    for (idx, element) in expected.pool_iter() {
+        let idx = *idx as usize;
+        let same = match (element, entry(actual, idx)) {
+            (PoolElement::Utf8Value(a), Some(ReadElement::Utf8Value(b))) => a == b,
+            (PoolElement::ClassInfo(a), Some(ReadElement::ClassInfo(b)))
+            | (PoolElement::String(a), Some(ReadElement::String(b))) => *a as usize == *b,
+            (PoolElement::Integer(a), Some(ReadElement::Integer(b))) => *a as i32 == *b,
+            (PoolElement::NameAndType(a, b), Some(ReadElement::NameAndType(c, d))) => {
+                (*a as usize, *b as usize) == (*c, *d)
+            }
+            (
+                PoolElement::FieldRef(a, b),
+                Some(ReadElement::FieldRef {
+                    class_idx,
+                    name_and_type_idx,
+                }),
+            )
+            | (
+                PoolElement::MethodRef(a, b),
+                Some(ReadElement::MethodRef {
+                    class_idx,
+                    name_and_type_idx,
+                }),
+            )
+            | (
+                PoolElement::InterfaceMethodRef(a, b),
+                Some(ReadElement::InterfaceMethodRef {
+                    class_idx,
+                    name_and_type_idx,
+                }),
+            ) => (*a as usize, *b as usize) == (*class_idx, *name_and_type_idx),
+            _ => false,
+        };
+        if !same {
+            return Err(format!(
+                "Entry {} read as {:?} instead of {:?}",
+                idx,
+                entry(actual, idx),
+                element
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn has_attribute(attributes: &[Attribute], name: &str) -> bool {
+    attributes.iter().any(|attribute| attribute.name() == name)
+}
+
+/// Compares a parsed class to the model it was written from
+fn compare_class(expected: &JavaClass, actual: &ClassFile) -> Result<(), String> {
+    compare_pool(expected, &actual.pool)?;
+    if (actual.this_class, actual.super_class) != (expected.class_id, expected.super_class_id) {
+        return Err(String::from("Wrong class or super class"));
+    }
+    if actual.interfaces != expected.interfaces {
+        return Err(String::from("Wrong interfaces"));
+    }
+    let source_file = actual
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::SourceFile(idx) => Some(*idx),
+            _ => None,
+        });
+    if source_file != expected.source_file {
+        return Err(format!("Wrong source file {:?}", source_file));
+    }
+
+    let fields: Vec<(u16, u16, u16)> = actual
+        .fields
+        .iter()
+        .map(|field| (field.access_flags, field.name_idx, field.descriptor_idx))
+        .collect();
+    let expected_fields: Vec<(u16, u16, u16)> = expected
+        .fields
+        .iter()
+        .map(|field| (field.access, field.name_index, field.descriptor_index))
+        .collect();
+    if fields != expected_fields {
+        return Err(format!(
+            "Fields {:?} instead of {:?}",
+            fields, expected_fields
+        ));
+    }
+    for field in &actual.fields {
+        let descriptor = field.descriptor(&actual.pool).unwrap_or("");
+        check_field_descriptor(descriptor)
+            .map_err(|e| format!("Invalid field descriptor {}: {}", descriptor, e))?;
+    }
+
+    if actual.methods.len() != expected.methods.len() {
+        return Err(format!(
+            "{} methods instead of {}",
+            actual.methods.len(),
+            expected.methods.len()
+        ));
+    }
+    for (method, expected_method) in actual.methods.iter().zip(&expected.methods) {
+        let name = method.name(&actual.pool).unwrap_or("<unknown>");
+        compare_method(expected_method, method, &actual.pool)
+            .map_err(|e| format!("Method {}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+fn compare_method(
+    expected: &constructs::Method,
+    actual: &java_reader::class_file::Member,
+    pool: &PoolList,
+) -> Result<(), String> {
+    if (actual.access_flags, actual.name_idx, actual.descriptor_idx)
+        != (
+            expected.access,
+            expected.name_index,
+            expected.descriptor_index,
+        )
+    {
+        return Err(String::from("Wrong access, name or descriptor"));
+    }
+    let descriptor = actual.descriptor(pool).unwrap_or("");
+    check_method_descriptor(descriptor)
+        .map_err(|e| format!("Invalid descriptor {}: {}", descriptor, e))?;
+
+    let (max_stack, operations, locals) = match expected.attributes.first() {
+        Some((
+            _,
+            constructs::Attribute::Code {
+                max_stack,
+                operations,
+                locals,
+            },
+        )) => (*max_stack, operations, *locals),
+        None => return Ok(()),
+    };
+    let code = actual.code().ok_or("Missing code")?;
+    if (code.max_stack, code.max_locals) != (max_stack, locals) {
+        return Err(format!(
+            "Max stack and locals ({}, {}) instead of ({}, {})",
+            code.max_stack, code.max_locals, max_stack, locals
+        ));
+    }
+    let length = writer::code_length(operations)?;
+    if code.code.len() != length as usize {
+        return Err(format!(
+            "Code of {} bytes instead of {}",
+            code.code.len(),
+            length
+        ));
+    }
+    let instruction_count = operations
+        .iter()
+        .filter(|operation| !matches!(operation, Operation::label(_) | Operation::line(_)))
+        .count();
+    if code.instructions.len() != instruction_count {
+        return Err(format!(
+            "{} instructions instead of {}",
+            code.instructions.len(),
+            instruction_count
+        ));
+    }
+    if has_attribute(&code.attributes, "StackMapTable") != expected.stack_map.is_some() {
+        return Err(String::from("Stack map not written as expected"));
+    }
+    let has_lines = operations
+        .iter()
+        .any(|operation| matches!(operation, Operation::line(_)));
+    if has_attribute(&code.attributes, "LineNumberTable") != has_lines {
+        return Err(String::from("Line numbers not written as expected"));
+    }
+    Ok(())
+}
+
+fn check_sample(path: &Path, mode: Mode) {
+    for (classname, class) in compile_sample(path, mode) {
+        let context = format!("{} ({:?}), class {}", path.display(), mode, classname);
+        if let Err(error) = verify_class(&class) {
+            panic!("{}: invalid class: {}", context, error);
+        }
+        let bytes = writer::to_bytes(&class)
+            .unwrap_or_else(|e| panic!("{}: cannot write class: {}", context, e));
+        let parsed = java_reader::parse_class(&bytes)
+            .unwrap_or_else(|e| panic!("{}: cannot read class: {}", context, e));
+
+        assert_eq!(parsed.class_name(), Some(classname.as_str()), "{}", context);
+        let result = check_pool(&parsed.pool)
+            .and_then(|_| check_code_references(&parsed))
+            .and_then(|_| compare_class(&class, &parsed));
+        if let Err(error) = result {
+            panic!("{}: {}", context, error);
+        }
+    }
+}
+
+#[test]
+fn test_read_interpreted_samples() {
+    let files = sample_files();
+    assert!(!files.is_empty());
+    for path in files {
+        check_sample(&path, Mode::Interpreted);
+    }
+}
+
+#[test]
+fn test_read_compiled_samples() {
+    for path in sample_files() {
+        check_sample(&path, Mode::Compiled);
+    }
+}
@@ -0,0 +1,486 @@
+//! Verification of the generated classes, before writing them.
+//!
+//! This reports the errors the JVM would raise as a `VerifyError` when loading the classes:
+//! invalid references to the class pool, malformed descriptors, values of the wrong type on
+//! the operand stack, or stack and local variables exceeding the sizes declared by the code.
+
+use crate::java::class::{JavaClass, PoolElement, PoolIdx};
+use crate::java::constants::MethodAccess;
+use crate::java::constructs::{Attribute, Method, Operation};
+use crate::java::frames;
+
+/// Checks that a descriptor is a single field type, starting at a position.
+/// This returns the position after the type.
+fn read_field_type(descriptor: &[u8], start: usize) -> Result<usize, String> {
+    match descriptor.get(start) {
+        Some(b'B') | Some(b'C') | Some(b'D') | Some(b'F') | Some(b'I') | Some(b'J')
+        | Some(b'S') | Some(b'Z') => Ok(start + 1),
+        Some(b'L') => descriptor[start..]
+            .iter()
+            .position(|c| *c == b';')
+            .filter(|end| *end > 1)
+            .map(|end| start + end + 1)
+            .ok_or_else(|| String::from("Unterminated class type")),
+        Some(b'[') => read_field_type(descriptor, start + 1),
+        Some(c) => Err(format!("Unexpected character {}", *c as char)),
+        None => Err(String::from("Missing type")),
+    }
+}
+
+/// Checks that a descriptor is the type of a field
+pub fn check_field_descriptor(descriptor: &str) -> Result<(), String> {
+    match read_field_type(descriptor.as_bytes(), 0)? {
+        end if end == descriptor.len() => Ok(()),
+        end => Err(format!("Unexpected content after {}", end)),
+    }
+}
+
+/// Splits a method descriptor into the types of its parameters and its returned type
+fn split_method_descriptor(descriptor: &str) -> Result<(Vec<&str>, &str), String> {
+    let bytes = descriptor.as_bytes();
+    if bytes.first() != Some(&b'(') {
+        return Err(String::from("Missing parameters"));
+    }
+    let mut parameters = Vec::new();
+    let mut position = 1;
+    while bytes.get(position) != Some(&b')') {
+        let end = read_field_type(bytes, position)?;
+        parameters.push(&descriptor[position..end]);
+        position = end;
+    }
+    let returned = &descriptor[position + 1..];
+    if returned != "V" {
+        check_field_descriptor(returned)?;
+    }
+    Ok((parameters, returned))
+}
+
+/// Checks that a descriptor is the type of a method
+pub fn check_method_descriptor(descriptor: &str) -> Result<(), String> {
+    split_method_descriptor(descriptor).map(|_| ())
+}
+
+/// Counts the local variables or the words taken by parameters, longs and doubles taking two
+fn count_slots(parameters: &[&str]) -> u16 {
+    parameters
+        .iter()
+        .map(|parameter| match parameter.as_bytes()[0] {
+            b'J' | b'D' => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+fn get_utf8(class: &JavaClass, idx: PoolIdx) -> Result<&str, String> {
+    match class.pool_element(idx) {
+        Some(PoolElement::Utf8Value(ref value)) => Ok(value),
+        element => Err(format!(
+            "Expected an UTF8 value at {}, got {:?}",
+            idx, element
+        )),
+    }
+}
+
+fn check_class_name(name: &str) -> Result<(), String> {
+    if name.starts_with('[') {
+        check_field_descriptor(name)
+    } else if name.is_empty() || name.contains(&['.', ';', '['][..]) {
+        Err(format!("Invalid class name {}", name))
+    } else {
+        Ok(())
+    }
+}
+
+fn get_class_name(class: &JavaClass, idx: PoolIdx) -> Result<&str, String> {
+    match class.pool_element(idx) {
+        Some(PoolElement::ClassInfo(ref name_idx)) => get_utf8(class, *name_idx),
+        element => Err(format!(
+            "Expected a class info at {}, got {:?}",
+            idx, element
+        )),
+    }
+}
+
+/// Gets the descriptor of the name and type of a field or a method ref
+fn get_descriptor(class: &JavaClass, nnt_idx: PoolIdx) -> Result<&str, String> {
+    match class.pool_element(nnt_idx) {
+        Some(PoolElement::NameAndType(ref name_idx, ref descriptor_idx)) => {
+            get_utf8(class, *name_idx)?;
+            get_utf8(class, *descriptor_idx)
+        }
+        element => Err(format!(
+            "Expected a name and type at {}, got {:?}",
+            nnt_idx, element
+        )),
+    }
+}
+
+fn check_pool_element(class: &JavaClass, element: &PoolElement) -> Result<(), String> {
+    match element {
+        PoolElement::Utf8Value(_) | PoolElement::Integer(_) => Ok(()),
+        PoolElement::ClassInfo(ref name_idx) => check_class_name(get_utf8(class, *name_idx)?),
+        PoolElement::String(ref value_idx) => get_utf8(class, *value_idx).map(|_| ()),
+        PoolElement::NameAndType(ref name_idx, ref descriptor_idx) => {
+            get_utf8(class, *name_idx)?;
+            get_utf8(class, *descriptor_idx).map(|_| ())
+        }
+        PoolElement::FieldRef(ref class_idx, ref nnt_idx) => {
+            get_class_name(class, *class_idx)?;
+            check_field_descriptor(get_descriptor(class, *nnt_idx)?)
+        }
+        PoolElement::MethodRef(ref class_idx, ref nnt_idx)
+        | PoolElement::InterfaceMethodRef(ref class_idx, ref nnt_idx) => {
+            get_class_name(class, *class_idx)?;
+            check_method_descriptor(get_descriptor(class, *nnt_idx)?)
+        }
+    }
+}
+
+/// Gets the descriptor of the method called by an invocation, checking the kind of its ref
+fn get_called_method(
+    class: &JavaClass,
+    idx: PoolIdx,
+    allow_methods: bool,
+    allow_interface_methods: bool,
+) -> Result<&str, String> {
+    match class.pool_element(idx) {
+        Some(PoolElement::MethodRef(_, ref nnt_idx)) if allow_methods => {
+            get_descriptor(class, *nnt_idx)
+        }
+        Some(PoolElement::InterfaceMethodRef(_, ref nnt_idx)) if allow_interface_methods => {
+            get_descriptor(class, *nnt_idx)
+        }
+        element => Err(format!("Cannot invoke {:?} at {}", element, idx)),
+    }
+}
+
+/// Checks the constants used by an operation and the local variables it accesses
+fn check_operation(
+    class: &JavaClass,
+    operation: &Operation,
+    max_locals: u16,
+) -> Result<(), String> {
+    match operation {
+        Operation::aload(ref idx)
+        | Operation::astore(ref idx)
+        | Operation::iload(ref idx)
+        | Operation::istore(ref idx)
+            if *idx as u16 >= max_locals =>
+        {
+            Err(format!(
+                "Local variable {} out of the {} locals",
+                idx, max_locals
+            ))
+        }
+        Operation::getfield(ref idx) | Operation::putfield(ref idx) => {
+            match class.pool_element(*idx) {
+                Some(PoolElement::FieldRef(_, _)) => Ok(()),
+                _ => Err(format!("Expected a field ref at {}", idx)),
+            }
+        }
+        Operation::invokevirtual(ref idx) => {
+            get_called_method(class, *idx, true, false).map(|_| ())
+        }
+        Operation::invokespecial(ref idx) | Operation::invokestatic(ref idx) => {
+            get_called_method(class, *idx, true, true).map(|_| ())
+        }
+        Operation::invokeinterface(ref idx, ref count) => {
+            let descriptor = get_called_method(class, *idx, false, true)?;
+            let (parameters, _) = split_method_descriptor(descriptor)?;
+            let expected = 1 + count_slots(&parameters);
+            if *count as u16 == expected {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Interface call with {} argument words instead of {}",
+                    count, expected
+                ))
+            }
+        }
+        Operation::invokedynamic(_) => Err(String::from("No bootstrap methods for dynamic calls")),
+        Operation::ldc(ref idx) => match class.pool_element(*idx) {
+            Some(PoolElement::Integer(_))
+            | Some(PoolElement::String(_))
+            | Some(PoolElement::ClassInfo(_)) => Ok(()),
+            element => Err(format!("Cannot load constant {:?} at {}", element, idx)),
+        },
+        Operation::new(ref idx) => match get_class_name(class, *idx)? {
+            name if name.starts_with('[') => Err(String::from("Cannot create an array with new")),
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+fn verify_method(
+    class: &JavaClass,
+    method: &Method,
+    name: &str,
+    descriptor: &str,
+) -> Result<(), String> {
+    let (parameters, returned) = split_method_descriptor(descriptor)?;
+    let is_static = method.access & (MethodAccess::STATIC as u16) != 0;
+    let parameter_slots = count_slots(&parameters) + if is_static { 0 } else { 1 };
+
+    for (attribute_idx, attribute) in &method.attributes {
+        let attribute_name = get_utf8(class, *attribute_idx)?;
+        let Attribute::Code {
+            max_stack,
+            operations,
+            locals,
+        } = attribute;
+        if attribute_name != "Code" {
+            return Err(format!("Code written as attribute {}", attribute_name));
+        }
+        if parameter_slots > *locals {
+            return Err(format!(
+                "Parameters take {} locals out of {}",
+                parameter_slots, locals
+            ));
+        }
+        for (idx, operation) in operations.iter().enumerate() {
+            check_operation(class, operation, *locals)
+                .map_err(|e| format!("{} at operation {} ({:?})", e, idx, operation))?;
+            let returns_correctly = match operation {
+                Operation::return_void => returned == "V",
+                Operation::areturn => returned.starts_with('L') || returned.starts_with('['),
+                _ => true,
+            };
+            if !returns_correctly {
+                return Err(format!(
+                    "Operation {} ({:?}) cannot return {}",
+                    idx, operation, returned
+                ));
+            }
+        }
+
+        let stack_size = frames::check_code(class, is_static, name, descriptor, operations)?;
+        if stack_size > *max_stack as usize {
+            return Err(format!(
+                "Stack of {} values exceeds the max stack {}",
+                stack_size, max_stack
+            ));
+        }
+        let has_branches = operations
+            .iter()
+            .any(|operation| !operation.successors().0.is_empty());
+        if has_branches && method.stack_map.is_none() {
+            return Err(String::from("Missing frames for the branch targets"));
+        }
+    }
+    Ok(())
+}
+
+/// Verifies a class before writing it, describing the first error found
+pub fn verify_class(class: &JavaClass) -> Result<(), String> {
+    if class.is_pool_overflowed() {
+        return Err(String::from("Class pool overflowed"));
+    }
+    for (idx, element) in class.pool_iter() {
+        check_pool_element(class, element)
+            .map_err(|e| format!("Invalid pool entry {}: {}", idx, e))?;
+    }
+
+    get_class_name(class, class.class_id)?;
+    get_class_name(class, class.super_class_id)?;
+    for interface in &class.interfaces {
+        get_class_name(class, *interface)?;
+    }
+    if let Some(source_file) = class.source_file {
+        get_utf8(class, source_file)?;
+    }
+
+    for field in &class.fields {
+        let name = get_utf8(class, field.name_index)?;
+        let descriptor = get_utf8(class, field.descriptor_index)?;
+        check_field_descriptor(descriptor)
+            .map_err(|e| format!("Invalid descriptor {} of field {}: {}", descriptor, name, e))?;
+    }
+    for method in &class.methods {
+        let name = get_utf8(class, method.name_index)?;
+        let descriptor = get_utf8(class, method.descriptor_index)?;
+        verify_method(class, method, name, descriptor)
+            .map_err(|e| format!("Invalid method {}{}: {}", name, descriptor, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::java::constants::Type;
+    use crate::java::constructs::Signature;
+
+    fn create_class() -> JavaClass {
+        let mut class = JavaClass::new();
+        class.set_class("the/Class");
+        class.set_super_class("java/lang/Object");
+        class
+    }
+
+    fn add_method(class: &mut JavaClass, max_stack: u16, locals: u16, operations: Vec<Operation>) {
+        class
+            .create_method(
+                MethodAccess::STATIC as u16,
+                "run",
+                Signature {
+                    return_type: Type::Void,
+                    parameter_types: vec![Type::Integer],
+                },
+                vec![Attribute::Code {
+                    max_stack: 0,
+                    operations,
+                    locals,
+                }],
+            )
+            .unwrap();
+        // Declare the given stack size instead of the computed one
+        let method = class.methods.last_mut().unwrap();
+        let Attribute::Code {
+            max_stack: ref mut declared,
+            ..
+        } = method.attributes[0].1;
+        *declared = max_stack;
+    }
+
+    fn interface_signature() -> Signature {
+        Signature {
+            return_type: Type::Void,
+            parameter_types: vec![Type::Integer, Type::Object(String::from("a/B"))],
+        }
+    }
+
+    #[test]
+    fn test_check_descriptors() {
+        assert!(check_field_descriptor("I").is_ok());
+        assert!(check_field_descriptor("[[Ljava/lang/String;").is_ok());
+        assert!(check_field_descriptor("L;").is_err());
+        assert!(check_field_descriptor("II").is_err());
+        assert!(check_field_descriptor("V").is_err());
+        assert!(check_method_descriptor("()V").is_ok());
+        assert!(check_method_descriptor("(I[ILa/B;)La/C;").is_ok());
+        assert!(check_method_descriptor("(I").is_err());
+        assert!(check_method_descriptor("()").is_err());
+        assert!(check_method_descriptor("(V)I").is_err());
+        assert!(check_method_descriptor("()II").is_err());
+    }
+
+    #[test]
+    fn test_count_slots() {
+        let (parameters, _) = split_method_descriptor("(IJ[DLa/B;D)V").unwrap();
+        assert_eq!(parameters, vec!["I", "J", "[D", "La/B;", "D"]);
+        assert_eq!(count_slots(&parameters), 7);
+    }
+
+    #[test]
+    fn test_verify_valid_class() {
+        let mut class = create_class();
+        let method_idx = class.map_interface_method("a/B", "call", &interface_signature());
+        add_method(
+            &mut class,
+            3,
+            2,
+            vec![
+                Operation::aconst_null,
+                Operation::iload(0),
+                Operation::aconst_null,
+                Operation::invokeinterface(method_idx, 3),
+                Operation::aconst_null,
+                Operation::astore(1),
+                Operation::return_void,
+            ],
+        );
+        assert_eq!(verify_class(&class), Ok(()));
+    }
+
+    #[test]
+    fn test_reject_wrong_interface_counts() {
+        let mut class = create_class();
+        let method_idx = class.map_interface_method("a/B", "call", &interface_signature());
+        add_method(
+            &mut class,
+            3,
+            1,
+            vec![
+                Operation::aconst_null,
+                Operation::iload(0),
+                Operation::aconst_null,
+                Operation::invokeinterface(method_idx, 2),
+                Operation::return_void,
+            ],
+        );
+        assert!(verify_class(&class).is_err());
+    }
+
+    #[test]
+    fn test_reject_locals_out_of_bounds() {
+        let mut class = create_class();
+        add_method(
+            &mut class,
+            1,
+            1,
+            vec![
+                Operation::iload(0),
+                Operation::istore(1),
+                Operation::return_void,
+            ],
+        );
+        assert!(verify_class(&class).is_err());
+    }
+
+    #[test]
+    fn test_reject_small_stacks() {
+        let mut class = create_class();
+        add_method(
+            &mut class,
+            1,
+            1,
+            vec![
+                Operation::iload(0),
+                Operation::iload(0),
+                Operation::iadd,
+                Operation::istore(0),
+                Operation::return_void,
+            ],
+        );
+        assert!(verify_class(&class).is_err());
+    }
+
+    #[test]
+    fn test_reject_wrong_invocations() {
+        let mut class = create_class();
+        let method_idx = class.map_interface_method("a/B", "call", &interface_signature());
+        add_method(
+            &mut class,
+            3,
+            1,
+            vec![
+                Operation::aconst_null,
+                Operation::iload(0),
+                Operation::aconst_null,
+                Operation::invokevirtual(method_idx),
+                Operation::return_void,
+            ],
+        );
+        assert!(verify_class(&class).is_err());
+    }
+
+    #[test]
+    fn test_reject_wrong_returns() {
+        let mut class = create_class();
+        add_method(
+            &mut class,
+            1,
+            1,
+            vec![Operation::aconst_null, Operation::areturn],
+        );
+        assert!(verify_class(&class).is_err());
+    }
+
+    #[test]
+    fn test_reject_invalid_pool_entries() {
+        let mut class = create_class();
+        class.map_class("a.B");
+        assert!(verify_class(&class).is_err());
+    }
+}
@@ -1,11 +1,13 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io;
 use std::io::Write;
 use std::path::Path;
 
-use crate::java::class::{JavaClass, PoolElement};
+use crate::java::class::{JavaClass, PoolElement, LINE_NUMBER_TABLE, SOURCE_FILE, STACK_MAP_TABLE};
 use crate::java::constants;
-use crate::java::constructs::{Attribute, Operation};
+use crate::java::constructs::{Attribute, Label, Operation};
+use crate::java::frames::{Frame, StackMap, VerificationType};
 
 static MAGIC: [u8; 4] = [0xca_u8, 0xfe_u8, 0xba_u8, 0xbe_u8];
 static VERSIONS: [u8; 4] = [/* minor */ 0, 0, /* major */ 0, 53]; // Java 9
@@ -102,6 +104,11 @@ fn write_constant_pool(writer: &mut dyn Writer, class: &JavaClass) -> StdResult
                 write_u8(writer, constants::PoolCode::String as u8)?;
                 write_u16(writer, *value_idx)?;
             }
+            PoolElement::FieldRef(class_idx, name_idx) => {
+                write_u8(writer, constants::PoolCode::FieldRef as u8)?;
+                write_u16(writer, *class_idx)?;
+                write_u16(writer, *name_idx)?;
+            }
             PoolElement::MethodRef(class_idx, name_idx) => {
                 write_u8(writer, constants::PoolCode::MethodRef as u8)?;
                 write_u16(writer, *class_idx)?;
@@ -138,9 +145,13 @@ fn write_class_info(writer: &mut dyn Writer, class: &JavaClass) -> StdResult {
 }
 
 fn write_class_definition(writer: &mut dyn Writer, class: &JavaClass) -> StdResult {
-    // TODO write the correct writer
-    // No fields
-    write_u16(writer, 0)?;
+    write_u16(writer, class.fields.len() as u16)?;
+    for field in &class.fields {
+        write_u16(writer, field.access)?;
+        write_u16(writer, field.name_index)?;
+        write_u16(writer, field.descriptor_index)?;
+        write_u16(writer, 0)?; // No attributes
+    }
 
     // Write methods
     write_u16(writer, class.methods.len() as u16)?;
@@ -150,16 +161,28 @@ fn write_class_definition(writer: &mut dyn Writer, class: &JavaClass) -> StdResu
         write_u16(writer, method.descriptor_index)?;
         write_u16(writer, method.attributes.len() as u16)?;
         for entry in &method.attributes {
-            write_attribute(writer, entry)?;
+            write_attribute(writer, class, method.stack_map.as_ref(), entry)?;
         }
     }
 
-    // No attributes
-    write_u16(writer, 0)
+    match class.source_file {
+        Some(ref filename_idx) => {
+            let name_idx = class.find_utf8_value(SOURCE_FILE).ok_or_else(|| {
+                invalid_code(String::from("Source file is not declared in the class"))
+            })?;
+            write_u16(writer, 1)?;
+            write_u16(writer, name_idx)?;
+            write_u32(writer, 2)?;
+            write_u16(writer, *filename_idx)
+        }
+        None => write_u16(writer, 0), // No attributes
+    }
 }
 
 fn write_attribute(
     writer: &mut dyn Writer,
+    class: &JavaClass,
+    stack_map: Option<&StackMap>,
     &(ref idx, ref attribute): &(u16, Attribute),
 ) -> StdResult {
     match attribute {
@@ -170,9 +193,15 @@ fn write_attribute(
         } => {
             write_u16(writer, *idx)?;
 
+            let labels = resolve_labels(operations)?;
             let mut op_writer = VecWriter { data: Vec::new() };
+            let mut lines: Vec<(u32, u16)> = vec![];
             for operation in operations {
-                write_operation(&mut op_writer, operation)?;
+                let position = op_writer.data.len() as u32;
+                if let Operation::line(ref line) = operation {
+                    add_line(&mut lines, position, *line);
+                }
+                write_operation(&mut op_writer, operation, position, Some(&labels))?;
             }
 
             let mut attr_writer = VecWriter { data: Vec::new() };
@@ -183,7 +212,15 @@ fn write_attribute(
 
             // Not used so far
             write_u16(&mut attr_writer, 0)?; // No exception tables
-            write_u16(&mut attr_writer, 0)?; // No attributes
+            let stack_map = stack_map.filter(|stack_map| !stack_map.frames.is_empty());
+            let attribute_count = stack_map.is_some() as u16 + !lines.is_empty() as u16;
+            write_u16(&mut attr_writer, attribute_count)?;
+            if let Some(stack_map) = stack_map {
+                write_stack_map_table(&mut attr_writer, class, stack_map, &labels)?;
+            }
+            if !lines.is_empty() {
+                write_line_number_table(&mut attr_writer, class, &lines)?;
+            }
 
             write_u32(writer, attr_writer.data.len() as u32)?;
             writer.write(&attr_writer.data[..])
@@ -191,8 +228,219 @@ fn write_attribute(
     }
 }
 
-fn write_operation(writer: &mut dyn Writer, operation: &Operation) -> StdResult {
+/// Computes the size in bytes of the code of operations
+pub fn code_length(operations: &[Operation]) -> Result<u32, String> {
+    let mut sizer = VecWriter { data: Vec::new() };
+    for operation in operations {
+        let position = sizer.data.len() as u32;
+        write_operation(&mut sizer, operation, position, None).map_err(|e| e.to_string())?;
+    }
+    Ok(sizer.data.len() as u32)
+}
+
+/// Computes the byte offset of each label in the code
+fn resolve_labels(operations: &[Operation]) -> io::Result<HashMap<Label, u32>> {
+    let mut labels = HashMap::new();
+    // Operations have the same size whatever the offsets of the labels
+    let mut sizer = VecWriter { data: Vec::new() };
+    for operation in operations {
+        let position = sizer.data.len() as u32;
+        if let Operation::label(ref label) = operation {
+            if labels.insert(*label, position).is_some() {
+                return Err(invalid_code(format!("Label {} defined twice", label)));
+            }
+        }
+        write_operation(&mut sizer, operation, position, None)?;
+    }
+    Ok(labels)
+}
+
+fn write_verification_type(writer: &mut dyn Writer, value: &VerificationType) -> StdResult {
+    match value {
+        VerificationType::Top => write_u8(writer, 0),
+        VerificationType::Integer => write_u8(writer, 1),
+        VerificationType::Null => write_u8(writer, 5),
+        VerificationType::UninitializedThis => write_u8(writer, 6),
+        VerificationType::Object(ref class_idx) => {
+            write_u8(writer, 7)?;
+            write_u16(writer, *class_idx)
+        }
+    }
+}
+
+fn write_verification_types(writer: &mut dyn Writer, values: &[VerificationType]) -> StdResult {
+    for value in values {
+        write_verification_type(writer, value)?;
+    }
+    Ok(())
+}
+
+/// Writes a frame, using the most compact form given the previous frame
+fn write_frame(writer: &mut dyn Writer, delta: u16, previous: &Frame, frame: &Frame) -> StdResult {
+    let previous_count = previous.locals.len();
+    let count = frame.locals.len();
+    let same_locals = previous.locals == frame.locals;
+    if same_locals && frame.stack.is_empty() {
+        if delta < 64 {
+            // same_frame
+            write_u8(writer, delta as u8)
+        } else {
+            // same_frame_extended
+            write_u8(writer, 251)?;
+            write_u16(writer, delta)
+        }
+    } else if same_locals && frame.stack.len() == 1 {
+        if delta < 64 {
+            // same_locals_1_stack_item_frame
+            write_u8(writer, 64 + delta as u8)?;
+        } else {
+            // same_locals_1_stack_item_frame_extended
+            write_u8(writer, 247)?;
+            write_u16(writer, delta)?;
+        }
+        write_verification_type(writer, &frame.stack[0])
+    } else if frame.stack.is_empty()
+        && count < previous_count
+        && previous_count - count <= 3
+        && previous.locals.starts_with(&frame.locals)
+    {
+        // chop_frame
+        write_u8(writer, (251 - (previous_count - count)) as u8)?;
+        write_u16(writer, delta)
+    } else if frame.stack.is_empty()
+        && count > previous_count
+        && count - previous_count <= 3
+        && frame.locals.starts_with(&previous.locals)
+    {
+        // append_frame
+        write_u8(writer, (251 + (count - previous_count)) as u8)?;
+        write_u16(writer, delta)?;
+        write_verification_types(writer, &frame.locals[previous_count..])
+    } else {
+        // full_frame
+        write_u8(writer, 255)?;
+        write_u16(writer, delta)?;
+        write_u16(writer, count as u16)?;
+        write_verification_types(writer, &frame.locals)?;
+        write_u16(writer, frame.stack.len() as u16)?;
+        write_verification_types(writer, &frame.stack)
+    }
+}
+
+/// Writes the frames at each label.
+///
+/// Labels at the same offset share the frame of the last one, which all the others
+/// flow into.
+fn write_stack_map_table(
+    writer: &mut dyn Writer,
+    class: &JavaClass,
+    stack_map: &StackMap,
+    labels: &HashMap<Label, u32>,
+) -> StdResult {
+    let name_idx = class
+        .find_utf8_value(STACK_MAP_TABLE)
+        .ok_or_else(|| invalid_code(String::from("Frames are not declared in the class")))?;
+    let mut frames: Vec<(u32, &Frame)> = Vec::with_capacity(stack_map.frames.len());
+    for (label, frame) in &stack_map.frames {
+        let offset = *labels
+            .get(label)
+            .ok_or_else(|| invalid_code(format!("Undefined label {}", label)))?;
+        match frames.last_mut() {
+            Some(last) if last.0 == offset => last.1 = frame,
+            _ => frames.push((offset, frame)),
+        }
+    }
+
+    let mut frame_writer = VecWriter { data: Vec::new() };
+    write_u16(&mut frame_writer, frames.len() as u16)?;
+    let mut previous: (Option<u32>, &Frame) = (None, &stack_map.initial);
+    for (offset, frame) in frames {
+        let delta = match previous.0 {
+            Some(previous_offset) => offset - previous_offset - 1,
+            None => offset,
+        };
+        write_frame(&mut frame_writer, delta as u16, previous.1, frame)?;
+        previous = (Some(offset), frame);
+    }
+
+    write_u16(writer, name_idx)?;
+    write_u32(writer, frame_writer.data.len() as u32)?;
+    writer.write(&frame_writer.data[..])
+}
+
+/// Adds the start of a line at a position of the code.
+/// Only the last line at a position is kept, and repeated lines are merged.
+fn add_line(lines: &mut Vec<(u32, u16)>, position: u32, line: u16) {
+    match lines.last_mut() {
+        Some(last) if last.0 == position => last.1 = line,
+        Some(last) if last.1 == line => {}
+        _ => lines.push((position, line)),
+    }
+}
+
+fn write_line_number_table(
+    writer: &mut dyn Writer,
+    class: &JavaClass,
+    lines: &[(u32, u16)],
+) -> StdResult {
+    let name_idx = class
+        .find_utf8_value(LINE_NUMBER_TABLE)
+        .ok_or_else(|| invalid_code(String::from("Lines are not declared in the class")))?;
+    write_u16(writer, name_idx)?;
+    write_u32(writer, 2 + 4 * lines.len() as u32)?;
+    write_u16(writer, lines.len() as u16)?;
+    for (position, line) in lines {
+        write_u16(writer, *position as u16)?;
+        write_u16(writer, *line)?;
+    }
+    Ok(())
+}
+
+fn invalid_code(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Gets the offset of a label from an operation, or 0 while labels are not resolved
+fn branch_offset(
+    label: Label,
+    position: u32,
+    labels: Option<&HashMap<Label, u32>>,
+) -> io::Result<i32> {
+    match labels {
+        Some(positions) => positions
+            .get(&label)
+            .map(|target| *target as i32 - position as i32)
+            .ok_or_else(|| invalid_code(format!("Undefined label {}", label))),
+        None => Ok(0),
+    }
+}
+
+fn write_branch(
+    writer: &mut dyn Writer,
+    opcode: u8,
+    label: Label,
+    position: u32,
+    labels: Option<&HashMap<Label, u32>>,
+) -> StdResult {
+    let offset = branch_offset(label, position, labels)?;
+    if offset < i16::MIN as i32 || offset > i16::MAX as i32 {
+        return Err(invalid_code(format!(
+            "Label {} is too far to jump to",
+            label
+        )));
+    }
+    write_u8(writer, opcode)?;
+    write_u16(writer, offset as i16 as u16)
+}
+
+fn write_operation(
+    writer: &mut dyn Writer,
+    operation: &Operation,
+    position: u32,
+    labels: Option<&HashMap<Label, u32>>,
+) -> StdResult {
     match operation {
+        Operation::aaload => write_u8(writer, 50),
         Operation::aload(ref idx) => {
             // if idx > 3 { // TODO write the optimization
             write_u8(writer, 25)?;
@@ -209,9 +457,30 @@ fn write_operation(writer: &mut dyn Writer, operation: &Operation) -> StdResult
             write_u8(writer, *value as u8)
         }
         Operation::dup => write_u8(writer, 89),
+        Operation::getfield(ref idx) => {
+            write_u8(writer, 180)?;
+            write_u16(writer, *idx)
+        }
+        Operation::goto(ref label) => write_branch(writer, 167, *label, position, labels),
+        Operation::iadd => write_u8(writer, 96),
+        Operation::iaload => write_u8(writer, 46),
         Operation::iastore => write_u8(writer, 79),
+        Operation::iconst_0 => write_u8(writer, 3),
         Operation::iconst_1 => write_u8(writer, 4),
+        Operation::iconst_2 => write_u8(writer, 5),
+        Operation::iconst_3 => write_u8(writer, 6),
+        Operation::iconst_4 => write_u8(writer, 7),
+        Operation::iconst_5 => write_u8(writer, 8),
         Operation::iconst_m1 => write_u8(writer, 2),
+        Operation::ifeq(ref label) => write_branch(writer, 153, *label, position, labels),
+        Operation::ifne(ref label) => write_branch(writer, 154, *label, position, labels),
+        Operation::iflt(ref label) => write_branch(writer, 155, *label, position, labels),
+        Operation::ifgt(ref label) => write_branch(writer, 157, *label, position, labels),
+        Operation::iload(ref idx) => {
+            write_u8(writer, 21)?;
+            write_u8(writer, *idx)
+        }
+        Operation::ineg => write_u8(writer, 116),
         Operation::invokespecial(ref idx) => {
             write_u8(writer, 183)?;
             write_u16(writer, *idx)
@@ -239,6 +508,12 @@ fn write_operation(writer: &mut dyn Writer, operation: &Operation) -> StdResult
 
             write_u8(writer, 0) // 4th value must be 0
         }
+        Operation::istore(ref idx) => {
+            write_u8(writer, 54)?;
+            write_u8(writer, *idx)
+        }
+        Operation::isub => write_u8(writer, 100),
+        Operation::label(_) | Operation::line(_) => Ok(()),
         Operation::ldc(ref idx) => {
             // Optimize using ldc or ldc_w
             if *idx < 256 {
@@ -258,11 +533,50 @@ fn write_operation(writer: &mut dyn Writer, operation: &Operation) -> StdResult
             write_u8(writer, array_type.clone() as u8)
         }
         Operation::nop => write_u8(writer, 0),
+        Operation::pop => write_u8(writer, 87),
+        Operation::putfield(ref idx) => {
+            write_u8(writer, 181)?;
+            write_u16(writer, *idx)
+        }
         Operation::return_void => write_u8(writer, 177),
+        Operation::sipush(ref value) => {
+            write_u8(writer, 17)?;
+            write_u16(writer, *value as u16)
+        }
+        Operation::tableswitch {
+            ref default,
+            ref low,
+            ref targets,
+        } => {
+            write_u8(writer, 170)?;
+            // Offsets start at an address multiple of 4
+            for _ in 0..(3 - position % 4) {
+                write_u8(writer, 0)?;
+            }
+            write_u32(writer, branch_offset(*default, position, labels)? as u32)?;
+            write_u32(writer, *low as u32)?;
+            write_u32(writer, (*low + targets.len() as i32 - 1) as u32)?;
+            for target in targets {
+                write_u32(writer, branch_offset(*target, position, labels)? as u32)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn check_class(class: &JavaClass) -> StdResult {
+    if class.is_pool_overflowed() {
+        Err(invalid_code(format!(
+            "Class pool exceeds the limit of {} constants",
+            u16::MAX - 1
+        )))
+    } else {
+        Ok(())
     }
 }
 
 pub fn write(class: &JavaClass, output_file: &Path) -> StdResult {
+    check_class(class)?;
     // println!("class: {:?}", class);
     let mut writer = FileWriter {
         file: File::create(output_file)?,
@@ -276,3 +590,156 @@ pub fn write(class: &JavaClass, output_file: &Path) -> StdResult {
     write_class_definition(&mut writer, class)?;
     writer.flush()
 }
+
+/// Writes a class in memory, for files not written directly on the disk
+pub fn to_bytes(class: &JavaClass) -> io::Result<Vec<u8>> {
+    check_class(class)?;
+    let mut writer = VecWriter { data: Vec::new() };
+    write_header(&mut writer)?;
+    write_constant_pool(&mut writer, class)?;
+    write_class_info(&mut writer, class)?;
+    write_class_definition(&mut writer, class)?;
+    Ok(writer.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_code(operations: &[Operation]) -> Vec<u8> {
+        let labels = resolve_labels(operations).unwrap();
+        let mut writer = VecWriter { data: Vec::new() };
+        for operation in operations {
+            let position = writer.data.len() as u32;
+            write_operation(&mut writer, operation, position, Some(&labels)).unwrap();
+        }
+        writer.data
+    }
+
+    #[test]
+    fn test_resolve_labels() {
+        let code = write_code(&[
+            Operation::label(0),
+            Operation::iconst_1,
+            Operation::ifeq(1),
+            Operation::goto(0),
+            Operation::label(1),
+            Operation::return_void,
+        ]);
+        assert_eq!(code, vec![4, 153, 0, 6, 167, 255, 252, 177]);
+    }
+
+    #[test]
+    fn test_reject_duplicated_labels() {
+        assert!(resolve_labels(&[Operation::label(0), Operation::label(0)]).is_err());
+    }
+
+    #[test]
+    fn test_pad_tableswitch() {
+        let code = write_code(&[
+            Operation::iconst_1,
+            Operation::tableswitch {
+                default: 0,
+                low: 0,
+                targets: vec![0],
+            },
+            Operation::label(0),
+            Operation::return_void,
+        ]);
+        assert_eq!(
+            code,
+            vec![4, 170, 0, 0, 0, 0, 0, 19, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 19, 177]
+        );
+    }
+
+    fn frame(locals: Vec<VerificationType>, stack: Vec<VerificationType>) -> Frame {
+        Frame { locals, stack }
+    }
+
+    fn write_frame_bytes(delta: u16, previous: &Frame, current: &Frame) -> Vec<u8> {
+        let mut writer = VecWriter { data: Vec::new() };
+        write_frame(&mut writer, delta, previous, current).unwrap();
+        writer.data
+    }
+
+    #[test]
+    fn test_write_compact_frames() {
+        let int = VerificationType::Integer;
+        let base = frame(vec![VerificationType::Object(3)], vec![]);
+        assert_eq!(write_frame_bytes(5, &base, &base), vec![5]);
+        assert_eq!(write_frame_bytes(100, &base, &base), vec![251, 0, 100]);
+        assert_eq!(
+            write_frame_bytes(2, &base, &frame(base.locals.clone(), vec![int.clone()])),
+            vec![66, 1]
+        );
+        assert_eq!(
+            write_frame_bytes(2, &base, &frame(vec![], vec![])),
+            vec![250, 0, 2]
+        );
+        assert_eq!(
+            write_frame_bytes(
+                2,
+                &base,
+                &frame(vec![VerificationType::Object(3), int.clone()], vec![])
+            ),
+            vec![252, 0, 2, 1]
+        );
+        assert_eq!(
+            write_frame_bytes(2, &base, &frame(vec![int.clone()], vec![int])),
+            vec![255, 0, 2, 0, 1, 1, 0, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_merge_lines() {
+        let mut lines = vec![];
+        add_line(&mut lines, 0, 3);
+        add_line(&mut lines, 0, 4);
+        add_line(&mut lines, 5, 4);
+        add_line(&mut lines, 8, 6);
+        assert_eq!(lines, vec![(0, 4), (8, 6)]);
+    }
+
+    #[test]
+    fn test_write_debug_attributes() {
+        let mut class = JavaClass::new();
+        class.set_class("the/Class");
+        class.set_super_class("java/lang/Object");
+        class.set_source_file("sum.io");
+        class
+            .create_method(
+                constants::MethodAccess::STATIC as u16,
+                "run",
+                crate::java::constructs::Signature {
+                    return_type: constants::Type::Void,
+                    parameter_types: vec![],
+                },
+                vec![Attribute::Code {
+                    max_stack: 1,
+                    operations: vec![
+                        Operation::line(3),
+                        Operation::iconst_1,
+                        Operation::line(4),
+                        Operation::pop,
+                        Operation::return_void,
+                    ],
+                    locals: 0,
+                }],
+            )
+            .unwrap();
+        let data = to_bytes(&class).unwrap();
+
+        let source_idx = class.find_utf8_value(SOURCE_FILE).unwrap();
+        let filename_idx = class.find_utf8_value("sum.io").unwrap();
+        let mut source_file = vec![0, 1];
+        source_file.extend_from_slice(&source_idx.to_be_bytes());
+        source_file.extend_from_slice(&[0, 0, 0, 2]);
+        source_file.extend_from_slice(&filename_idx.to_be_bytes());
+        assert!(data.ends_with(&source_file));
+
+        let lines_idx = class.find_utf8_value(LINE_NUMBER_TABLE).unwrap();
+        let mut lines = lines_idx.to_be_bytes().to_vec();
+        lines.extend_from_slice(&[0, 0, 0, 10, 0, 2, 0, 0, 0, 3, 0, 1, 0, 4]);
+        assert!(data.windows(lines.len()).any(|window| window == &lines[..]));
+    }
+}
@@ -0,0 +1,697 @@
+//! Module generating programs as textual LLVM IR.
+//!
+//! Each node becomes a state-machine function executing its current operation,
+//! and returning whether it made progress. A scheduler ticks all nodes, then
+//! completes the transactions of the slots, following `JavaTzEnv#tick`.
+//! The runtime reading inputs and printing outputs is written in `runtime.ll`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use language::address::{InputSlot, Node, OutputSlot};
+use language::instruction::{MemoryPointer, Operation, ValuePointer};
+use language::syntax::{NodeBlock, Program};
+
+use crate::backend::{Backend, GenerateError};
+
+const RUNTIME: &str = include_str!("runtime.ll");
+
+/// Storage read by a node input
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Channel {
+    /// Data slot written by another node
+    Slot(usize),
+    /// Queue of values provided by the outside world
+    Queue(usize),
+}
+
+/// Slots and queues of a program
+#[derive(Debug)]
+struct Layout {
+    slot_count: usize,
+    /// Queues by program input, ordered like the values of an input line
+    queues: BTreeMap<OutputSlot, usize>,
+    /// Slots by program output, ordered like the values of an output line
+    outputs: BTreeMap<InputSlot, usize>,
+    node_inputs: Vec<HashMap<InputSlot, Channel>>,
+    node_outputs: Vec<HashMap<OutputSlot, usize>>,
+}
+
+fn node_name(node: &Node) -> String {
+    match node {
+        Node::Node(ref id) => id.clone(),
+        Node::In => String::from("<IN>"),
+        Node::Out => String::from("<OUT>"),
+    }
+}
+
+fn create_layout(program: &Program) -> Layout {
+    let input_ports: BTreeSet<OutputSlot> = program
+        .nodes
+        .iter()
+        .flat_map(|node| node.inputs.iter())
+        .filter(|input| input.from.node == Node::In)
+        .map(|input| input.from.port)
+        .collect();
+    let queues: BTreeMap<OutputSlot, usize> = input_ports
+        .into_iter()
+        .enumerate()
+        .map(|(idx, port)| (port, idx))
+        .collect();
+
+    // Slots are identified by (source node, source port, target node, target port)
+    let mut index: HashMap<(String, u8, String, u8), usize> = HashMap::new();
+    let mut slot_of = |key| {
+        let next_idx = index.len();
+        *index.entry(key).or_insert(next_idx)
+    };
+    let mut outputs = BTreeMap::new();
+    let mut node_inputs = vec![];
+    let mut node_outputs = vec![];
+    for node in &program.nodes {
+        let name = node_name(&node.node);
+        let mut inputs = HashMap::new();
+        for input in &node.inputs {
+            let channel = if input.from.node == Node::In {
+                Channel::Queue(queues[&input.from.port])
+            } else {
+                Channel::Slot(slot_of((
+                    node_name(&input.from.node),
+                    input.from.port.value(),
+                    name.clone(),
+                    input.to.value(),
+                )))
+            };
+            inputs.insert(input.to, channel);
+        }
+        node_inputs.push(inputs);
+
+        let mut slots = HashMap::new();
+        for output in &node.outputs {
+            let slot_idx = slot_of((
+                name.clone(),
+                output.from.value(),
+                node_name(&output.to.node),
+                output.to.port.value(),
+            ));
+            if output.to.node == Node::Out {
+                outputs.insert(output.to.port, slot_idx);
+            }
+            slots.insert(output.from, slot_idx);
+        }
+        node_outputs.push(slots);
+    }
+
+    Layout {
+        slot_count: index.len(),
+        queues,
+        outputs,
+        node_inputs,
+        node_outputs,
+    }
+}
+
+/// Writer of the body of a node function, creating unique temporaries
+struct NodeWriter<'a> {
+    idx: usize,
+    name: &'a str,
+    inputs: &'a HashMap<InputSlot, Channel>,
+    outputs: &'a HashMap<OutputSlot, usize>,
+    body: String,
+    temporaries: usize,
+}
+
+impl<'a> NodeWriter<'a> {
+    fn tmp(&mut self) -> String {
+        self.temporaries += 1;
+        format!("%t{}", self.temporaries)
+    }
+
+    fn line(&mut self, line: &str) {
+        self.body.push_str("  ");
+        self.body.push_str(line);
+        self.body.push('\n');
+    }
+
+    fn label(&mut self, label: &str) {
+        self.body.push('\n');
+        self.body.push_str(label);
+        self.body.push_str(":\n");
+    }
+
+    fn global(&self, field: &str) -> String {
+        format!("@node{}.{}", self.idx, field)
+    }
+
+    fn channel(&self, port: &InputSlot) -> Result<Channel, String> {
+        self.inputs
+            .get(port)
+            .cloned()
+            .ok_or_else(|| format!("Port {} is not defined in node {} inputs", port, self.name))
+    }
+
+    fn output_slot(&self, port: &OutputSlot) -> Result<usize, String> {
+        self.outputs
+            .get(port)
+            .cloned()
+            .ok_or_else(|| format!("Port {} is not defined in node {} outputs", port, self.name))
+    }
+
+    /// Emits the check that a value can be read, giving the resulting i1
+    fn can_read(&mut self, pointer: &ValuePointer) -> Result<String, String> {
+        let (function, idx) = match pointer {
+            ValuePointer::INPUT(ref port) => match self.channel(port)? {
+                Channel::Slot(idx) => ("slot_can_read", idx),
+                Channel::Queue(idx) => ("queue_can_read", idx),
+            },
+            _ => return Ok(String::from("true")),
+        };
+        let result = self.tmp();
+        self.line(&format!("{} = call i1 @{}(i32 {})", result, function, idx));
+        Ok(result)
+    }
+
+    /// Emits the read of a value, giving the resulting i32
+    fn read(&mut self, pointer: &ValuePointer) -> Result<String, String> {
+        match pointer {
+            ValuePointer::VALUE(value) => Ok((*value as i32).to_string()),
+            ValuePointer::NIL => Ok(String::from("0")),
+            ValuePointer::ACC => Ok(self.load_acc()),
+            ValuePointer::INPUT(ref port) => {
+                let (function, idx) = match self.channel(port)? {
+                    Channel::Slot(idx) => ("slot_read", idx),
+                    Channel::Queue(idx) => ("queue_read", idx),
+                };
+                let result = self.tmp();
+                self.line(&format!("{} = call i32 @{}(i32 {})", result, function, idx));
+                Ok(result)
+            }
+            ValuePointer::OUTPUT(_) => {
+                Err(format!("Cannot read from {} in {}", pointer, self.name))
+            }
+        }
+    }
+
+    fn can_write(&mut self, pointer: &ValuePointer) -> Result<String, String> {
+        match pointer {
+            ValuePointer::OUTPUT(ref port) => {
+                let idx = self.output_slot(port)?;
+                let result = self.tmp();
+                self.line(&format!(
+                    "{} = call i1 @slot_can_write(i32 {})",
+                    result, idx
+                ));
+                Ok(result)
+            }
+            _ => Ok(String::from("true")),
+        }
+    }
+
+    fn write(&mut self, pointer: &ValuePointer, value: &str) -> Result<(), String> {
+        match pointer {
+            ValuePointer::ACC => self.store_acc(value),
+            ValuePointer::NIL => {}
+            ValuePointer::OUTPUT(ref port) => {
+                let idx = self.output_slot(port)?;
+                self.line(&format!(
+                    "call void @slot_write(i32 {}, i32 {})",
+                    idx, value
+                ));
+            }
+            _ => return Err(format!("Cannot write into {} in {}", pointer, self.name)),
+        }
+        Ok(())
+    }
+
+    fn load_acc(&mut self) -> String {
+        let result = self.tmp();
+        let acc = self.global("acc");
+        self.line(&format!("{} = load i32, ptr {}", result, acc));
+        result
+    }
+
+    fn store_acc(&mut self, value: &str) {
+        let acc = self.global("acc");
+        self.line(&format!("store i32 {}, ptr {}", value, acc));
+    }
+
+    fn memory_slot(&mut self, pointer: &MemoryPointer) -> Result<String, String> {
+        let &MemoryPointer::BAK(slot) = pointer;
+        if slot == 0 {
+            return Err(format!("Memory slots start at 1 in node {}", self.name));
+        }
+        let result = self.tmp();
+        let memory = self.global("memory");
+        self.line(&format!(
+            "{} = getelementptr i32, ptr {}, i32 {}",
+            result,
+            memory,
+            slot - 1
+        ));
+        Ok(result)
+    }
+
+    /// Ends the operation, moving to the given step
+    fn finish(&mut self, step: &str) {
+        let global = self.global("step");
+        self.line(&format!("store i32 {}, ptr {}", step, global));
+        self.line("ret i1 true");
+    }
+
+    /// Runs the operation only when its values can be read and written
+    fn guard(&mut self, op_idx: usize, conditions: &[String]) {
+        let mut condition = String::from("true");
+        for other in conditions {
+            let result = self.tmp();
+            self.line(&format!("{} = and i1 {}, {}", result, condition, other));
+            condition = result;
+        }
+        self.line(&format!(
+            "br i1 {}, label %op{}.run, label %stay",
+            condition, op_idx
+        ));
+        self.label(&format!("op{}.run", op_idx));
+    }
+
+    fn jump_if(&mut self, condition: &str, target: usize, next: usize) {
+        let acc = self.load_acc();
+        let test = self.tmp();
+        self.line(&format!("{} = icmp {} i32 {}, 0", test, condition, acc));
+        let step = self.tmp();
+        self.line(&format!(
+            "{} = select i1 {}, i32 {}, i32 {}",
+            step, test, target, next
+        ));
+        self.finish(&step);
+    }
+}
+
+/// Removes the labels from the operations, giving the index of the operation following each label
+fn split_labels(operations: &[Operation]) -> (Vec<&Operation>, HashMap<&String, usize>) {
+    let mut executed = vec![];
+    let mut labels = HashMap::new();
+    for operation in operations {
+        if let Operation::LABEL(ref label) = operation {
+            labels.insert(label, executed.len());
+        } else {
+            executed.push(operation);
+        }
+    }
+    (executed, labels)
+}
+
+fn write_operation(
+    writer: &mut NodeWriter,
+    op_idx: usize,
+    operation: &Operation,
+    count: usize,
+    labels: &HashMap<&String, usize>,
+) -> Result<(), String> {
+    let next = (op_idx + 1) % count;
+    let target = |label: &String| {
+        labels
+            .get(label)
+            .map(|idx| if *idx < count { *idx } else { 0 })
+            .ok_or_else(|| format!("Label {} is not defined in node {}", label, writer.name))
+    };
+    match operation {
+        Operation::MOV(ref from, ref to) => {
+            let readable = writer.can_read(from)?;
+            let writable = writer.can_write(to)?;
+            writer.guard(op_idx, &[readable, writable]);
+            let value = writer.read(from)?;
+            writer.write(to, &value)?;
+            writer.finish(&next.to_string());
+        }
+        Operation::ADD(ref value) | Operation::SUB(ref value) => {
+            let readable = writer.can_read(value)?;
+            writer.guard(op_idx, &[readable]);
+            let value = writer.read(value)?;
+            let acc = writer.load_acc();
+            let result = writer.tmp();
+            let instruction = if let Operation::ADD(_) = operation {
+                "add"
+            } else {
+                "sub"
+            };
+            writer.line(&format!(
+                "{} = {} i32 {}, {}",
+                result, instruction, acc, value
+            ));
+            writer.store_acc(&result);
+            writer.finish(&next.to_string());
+        }
+        Operation::NEG => {
+            let acc = writer.load_acc();
+            let result = writer.tmp();
+            writer.line(&format!("{} = sub i32 0, {}", result, acc));
+            writer.store_acc(&result);
+            writer.finish(&next.to_string());
+        }
+        Operation::SAV(ref pointer) => {
+            let slot = writer.memory_slot(pointer)?;
+            let acc = writer.load_acc();
+            writer.line(&format!("store i32 {}, ptr {}", acc, slot));
+            writer.finish(&next.to_string());
+        }
+        Operation::SWP(ref pointer) => {
+            let slot = writer.memory_slot(pointer)?;
+            let acc = writer.load_acc();
+            let saved = writer.tmp();
+            writer.line(&format!("{} = load i32, ptr {}", saved, slot));
+            writer.line(&format!("store i32 {}, ptr {}", acc, slot));
+            writer.store_acc(&saved);
+            writer.finish(&next.to_string());
+        }
+        Operation::JMP(ref label) => {
+            let target = target(label)?;
+            writer.finish(&target.to_string());
+        }
+        Operation::JEZ(ref label) => writer.jump_if("eq", target(label)?, next),
+        Operation::JNZ(ref label) => writer.jump_if("ne", target(label)?, next),
+        Operation::JLZ(ref label) => writer.jump_if("slt", target(label)?, next),
+        Operation::JGZ(ref label) => writer.jump_if("sgt", target(label)?, next),
+        Operation::JRO(ref value) => {
+            let readable = writer.can_read(value)?;
+            writer.guard(op_idx, &[readable]);
+            let value = writer.read(value)?;
+            // Offsets wrap around the operations, as a euclidean remainder
+            let offset = writer.tmp();
+            writer.line(&format!("{} = sext i32 {} to i64", offset, value));
+            let shifted = writer.tmp();
+            writer.line(&format!("{} = add i64 {}, {}", shifted, offset, op_idx));
+            let rem = writer.tmp();
+            writer.line(&format!("{} = srem i64 {}, {}", rem, shifted, count));
+            let negative = writer.tmp();
+            writer.line(&format!("{} = icmp slt i64 {}, 0", negative, rem));
+            let adjusted = writer.tmp();
+            writer.line(&format!("{} = add i64 {}, {}", adjusted, rem, count));
+            let wrapped = writer.tmp();
+            writer.line(&format!(
+                "{} = select i1 {}, i64 {}, i64 {}",
+                wrapped, negative, adjusted, rem
+            ));
+            let step = writer.tmp();
+            writer.line(&format!("{} = trunc i64 {} to i32", step, wrapped));
+            writer.finish(&step);
+        }
+        Operation::LABEL(_) => return Err(String::from("Labels are not executed")),
+    }
+    Ok(())
+}
+
+/// Writes the state and the function of a node.
+///
+/// The function runs the current operation of the node, returning false if it is blocked.
+fn write_node(
+    out: &mut String,
+    idx: usize,
+    node: &NodeBlock,
+    layout: &Layout,
+) -> Result<(), String> {
+    let name = node_name(&node.node);
+    let (operations, labels) = split_labels(&node.instructions);
+    let mut writer = NodeWriter {
+        idx,
+        name: &name,
+        inputs: &layout.node_inputs[idx],
+        outputs: &layout.node_outputs[idx],
+        body: String::new(),
+        temporaries: 0,
+    };
+
+    writeln!(out, "; Node {}", name).unwrap();
+    writeln!(out, "@node{}.step = internal global i32 0", idx).unwrap();
+    writeln!(out, "@node{}.acc = internal global i32 0", idx).unwrap();
+    writeln!(
+        out,
+        "@node{}.memory = internal global [{} x i32] zeroinitializer",
+        idx,
+        node.memory_size()
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "define internal i1 @node{}() {{", idx).unwrap();
+    writeln!(out, "entry:").unwrap();
+    if operations.is_empty() {
+        writeln!(out, "  ret i1 false").unwrap();
+    } else {
+        let step = writer.tmp();
+        writer.line(&format!("{} = load i32, ptr @node{}.step", step, idx));
+        let cases: Vec<String> = (0..operations.len())
+            .map(|op_idx| format!("i32 {}, label %op{}", op_idx, op_idx))
+            .collect();
+        writer.line(&format!(
+            "switch i32 {}, label %stay [ {} ]",
+            step,
+            cases.join(" ")
+        ));
+        for (op_idx, operation) in operations.iter().enumerate() {
+            writer.label(&format!("op{}", op_idx));
+            write_operation(&mut writer, op_idx, operation, operations.len(), &labels)?;
+        }
+        writer.label("stay");
+        writer.line("ret i1 false");
+        out.push_str(&writer.body);
+    }
+    writeln!(out, "}}\n").unwrap();
+    Ok(())
+}
+
+/// Writes the tick of the program, running all nodes then printing the outputs
+fn write_scheduler(out: &mut String, program: &Program, layout: &Layout) {
+    writeln!(out, "define internal i1 @tick() {{").unwrap();
+    writeln!(out, "entry:").unwrap();
+    let mut progress = String::from("false");
+    for idx in 0..program.nodes.len() {
+        writeln!(out, "  %node{}.progress = call i1 @node{}()", idx, idx).unwrap();
+        writeln!(
+            out,
+            "  %progress{} = or i1 {}, %node{}.progress",
+            idx, progress, idx
+        )
+        .unwrap();
+        progress = format!("%progress{}", idx);
+    }
+    writeln!(out, "  call void @end_slots(i32 {})", layout.slot_count).unwrap();
+    writeln!(out, "  %printed = call i1 @print_outputs()").unwrap();
+    writeln!(out, "  %result = or i1 {}, %printed", progress).unwrap();
+    writeln!(out, "  ret i1 %result").unwrap();
+    writeln!(out, "}}\n").unwrap();
+
+    writeln!(out, "define internal i1 @print_outputs() {{").unwrap();
+    writeln!(out, "entry:").unwrap();
+    let mut any = String::from("false");
+    for (idx, slot) in layout.outputs.values().enumerate() {
+        writeln!(
+            out,
+            "  %readable{} = call i1 @slot_can_read(i32 {})",
+            idx, slot
+        )
+        .unwrap();
+        writeln!(out, "  %any{} = or i1 {}, %readable{}", idx, any, idx).unwrap();
+        any = format!("%any{}", idx);
+    }
+    writeln!(out, "  br i1 {}, label %print, label %none", any).unwrap();
+    writeln!(out, "\nprint:").unwrap();
+    writeln!(out, "  call void @print_prefix()").unwrap();
+    for (idx, slot) in layout.outputs.values().enumerate() {
+        if idx > 0 {
+            writeln!(out, "  call void @print_separator()").unwrap();
+        }
+        writeln!(out, "  call void @print_output(i32 {})", slot).unwrap();
+    }
+    writeln!(out, "  call void @print_end()").unwrap();
+    writeln!(out, "  ret i1 true").unwrap();
+    writeln!(out, "\nnone:").unwrap();
+    writeln!(out, "  ret i1 false").unwrap();
+    writeln!(out, "}}\n").unwrap();
+}
+
+/// Creates the LLVM module of a program
+fn create_module(program: &Program, name: &str, max_ticks: u32) -> Result<String, String> {
+    let layout = create_layout(program);
+    let mut out = String::new();
+    writeln!(out, "; ModuleID = '{}'", name).unwrap();
+    writeln!(out, "; Generated by the TZIO compiler\n").unwrap();
+    writeln!(
+        out,
+        "@slots = internal global [{} x %Slot] zeroinitializer",
+        layout.slot_count
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "@queues = internal global [{} x %Queue] zeroinitializer",
+        layout.queues.len()
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "@queue_count = internal constant i32 {}",
+        layout.queues.len()
+    )
+    .unwrap();
+    writeln!(out, "@max_ticks = internal constant i32 {}\n", max_ticks).unwrap();
+
+    for (idx, node) in program.nodes.iter().enumerate() {
+        write_node(&mut out, idx, node, &layout)?;
+    }
+    write_scheduler(&mut out, program, &layout);
+    out.push_str(RUNTIME);
+    Ok(out)
+}
+
+/// Backend writing programs as LLVM modules, compiled into executables with clang
+pub struct LlvmBackend {
+    /// Maximal number of ticks run for each line of inputs, stopping programs that never idle
+    max_ticks: u32,
+}
+
+impl Default for LlvmBackend {
+    fn default() -> Self {
+        LlvmBackend { max_ticks: 10000 }
+    }
+}
+
+impl Backend for LlvmBackend {
+    fn name(&self) -> &'static str {
+        "llvm"
+    }
+
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "max-ticks" => {
+                self.max_ticks = value
+                    .parse()
+                    .map_err(|_| format!("Invalid number of ticks {}", value))?;
+                Ok(())
+            }
+            _ => Err(format!("Unknown option {} for target llvm", key)),
+        }
+    }
+
+    /// Writes the module into `<name>.ll`, in the output directory
+    fn generate(
+        &self,
+        program: &Program,
+        name: &str,
+        _source_file: &str,
+        output_dir: &Path,
+    ) -> Result<PathBuf, GenerateError> {
+        let module =
+            create_module(program, name, self.max_ticks).map_err(GenerateError::Program)?;
+        fs::create_dir_all(output_dir).map_err(|error| {
+            GenerateError::Output(format!(
+                "Could not create output directory {} due to error {}",
+                output_dir.display(),
+                error
+            ))
+        })?;
+        let mut output_file = output_dir.to_path_buf();
+        output_file.push(name);
+        output_file.set_extension("ll");
+        fs::write(&output_file, module).map_err(|e| {
+            GenerateError::Output(format!("Failed to write into file. Caused by {}", e))
+        })?;
+        Ok(output_file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use language::address::Port;
+
+    /// Creates a program doubling its single input
+    fn create_double_program() -> Program {
+        Program::default().node(
+            NodeBlock::new(Node::new_node("double"))
+                .input(Port::new(Node::In, 1.into()), 1.into())
+                .output(1.into(), Port::new(Node::Out, 1.into()))
+                .instruction(Operation::MOV(
+                    ValuePointer::INPUT(1.into()),
+                    ValuePointer::ACC,
+                ))
+                .instruction(Operation::ADD(ValuePointer::ACC))
+                .instruction(Operation::MOV(
+                    ValuePointer::ACC,
+                    ValuePointer::OUTPUT(1.into()),
+                )),
+        )
+    }
+
+    #[test]
+    fn test_create_layout() {
+        let program = Program::default()
+            .node(
+                NodeBlock::new(Node::new_node("a"))
+                    .input(Port::new(Node::In, 2.into()), 1.into())
+                    .output(1.into(), Port::named_port("b", 2.into())),
+            )
+            .node(
+                NodeBlock::new(Node::new_node("b"))
+                    .input(Port::new(Node::In, 1.into()), 1.into())
+                    .input(Port::named_port("a", 1.into()), 2.into())
+                    .output(1.into(), Port::new(Node::Out, 1.into())),
+            );
+        let layout = create_layout(&program);
+        assert_eq!(layout.slot_count, 2);
+        // Queues follow the order of the ports
+        assert_eq!(layout.node_inputs[0][&1.into()], Channel::Queue(1));
+        assert_eq!(layout.node_inputs[1][&1.into()], Channel::Queue(0));
+        // Both sides of a mapping share the same slot
+        assert_eq!(layout.node_inputs[1][&2.into()], Channel::Slot(0));
+        assert_eq!(layout.node_outputs[0][&1.into()], 0);
+        assert_eq!(
+            layout.outputs.values().cloned().collect::<Vec<_>>(),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn test_create_node_function() {
+        let module = create_module(&create_double_program(), "double", 10).unwrap();
+        assert!(module.contains("define internal i1 @node0() {"));
+        assert!(module.contains(
+            "switch i32 %t1, label %stay [ i32 0, label %op0 i32 1, label %op1 i32 2, label %op2 ]"
+        ));
+        assert!(module.contains("call i1 @queue_can_read(i32 0)"));
+        assert!(module.contains("call i1 @slot_can_write(i32 0)"));
+        assert!(module.contains("@max_ticks = internal constant i32 10\n"));
+        assert!(module.contains("define i32 @main()"));
+    }
+
+    #[test]
+    fn test_wrap_jumps() {
+        let program = Program::default().node(
+            NodeBlock::new(Node::new_node("a"))
+                .instruction(Operation::LABEL(String::from("START")))
+                .instruction(Operation::NEG)
+                .instruction(Operation::JMP(String::from("END")))
+                .instruction(Operation::LABEL(String::from("END"))),
+        );
+        let module = create_module(&program, "jumps", 10).unwrap();
+        // END is after the last operation, and loops to the first one
+        assert!(module.contains("op1:\n  store i32 0, ptr @node0.step"));
+    }
+
+    #[test]
+    fn test_reject_undefined_references() {
+        let program = Program::default().node(
+            NodeBlock::new(Node::new_node("a"))
+                .instruction(Operation::JMP(String::from("NOWHERE"))),
+        );
+        assert!(create_module(&program, "invalid", 10).is_err());
+
+        let program = Program::default().node(
+            NodeBlock::new(Node::new_node("a"))
+                .instruction(Operation::ADD(ValuePointer::INPUT(2.into()))),
+        );
+        assert!(create_module(&program, "invalid", 10).is_err());
+    }
+}
@@ -0,0 +1,327 @@
+; Runtime of the TZIO programs compiled to LLVM IR.
+;
+; Slots are transactional, like in the Java core: a value written during a tick
+; can only be read at the next tick, and a slot read during a tick cannot be
+; written before the next tick.
+; Each line of stdin gives the values of the program inputs, separated by `;`.
+; Outputs are printed the same way, prefixed by `> `.
+;
+; The compiled program defines @slots, @queues, @queue_count, @max_ticks and @tick.
+
+; value, has_value, has_value_after_step
+%Slot = type { i32, i32, i32 }
+; values, head, tail, capacity
+%Queue = type { ptr, i64, i64, i64 }
+
+declare i32 @getchar()
+declare i32 @putchar(i32)
+declare i32 @printf(ptr, ...)
+declare i32 @fflush(ptr)
+declare i64 @strtol(ptr, ptr, i32)
+declare ptr @strchr(ptr, i32)
+declare ptr @memmove(ptr, ptr, i64)
+declare ptr @realloc(ptr, i64)
+declare void @abort()
+
+@int_format = private unnamed_addr constant [3 x i8] c"%d\00"
+@line = internal global [4096 x i8] zeroinitializer
+
+define internal i1 @slot_can_read(i32 %idx) {
+  %slot = getelementptr %Slot, ptr @slots, i32 %idx
+  %has_ptr = getelementptr %Slot, ptr %slot, i32 0, i32 1
+  %has = load i32, ptr %has_ptr
+  %after_ptr = getelementptr %Slot, ptr %slot, i32 0, i32 2
+  %after = load i32, ptr %after_ptr
+  %both = and i32 %has, %after
+  %result = icmp ne i32 %both, 0
+  ret i1 %result
+}
+
+define internal i32 @slot_read(i32 %idx) {
+  %slot = getelementptr %Slot, ptr @slots, i32 %idx
+  %value_ptr = getelementptr %Slot, ptr %slot, i32 0, i32 0
+  %value = load i32, ptr %value_ptr
+  %after_ptr = getelementptr %Slot, ptr %slot, i32 0, i32 2
+  store i32 0, ptr %after_ptr
+  ret i32 %value
+}
+
+define internal i1 @slot_can_write(i32 %idx) {
+  %slot = getelementptr %Slot, ptr @slots, i32 %idx
+  %has_ptr = getelementptr %Slot, ptr %slot, i32 0, i32 1
+  %has = load i32, ptr %has_ptr
+  %after_ptr = getelementptr %Slot, ptr %slot, i32 0, i32 2
+  %after = load i32, ptr %after_ptr
+  %any = or i32 %has, %after
+  %result = icmp eq i32 %any, 0
+  ret i1 %result
+}
+
+define internal void @slot_write(i32 %idx, i32 %value) {
+  %slot = getelementptr %Slot, ptr @slots, i32 %idx
+  %value_ptr = getelementptr %Slot, ptr %slot, i32 0, i32 0
+  store i32 %value, ptr %value_ptr
+  %after_ptr = getelementptr %Slot, ptr %slot, i32 0, i32 2
+  store i32 1, ptr %after_ptr
+  ret void
+}
+
+; Completes the transaction of the current tick for all slots
+define internal void @end_slots(i32 %count) {
+entry:
+  br label %loop
+
+loop:
+  %idx = phi i32 [ 0, %entry ], [ %next, %body ]
+  %done = icmp sge i32 %idx, %count
+  br i1 %done, label %exit, label %body
+
+body:
+  %slot = getelementptr %Slot, ptr @slots, i32 %idx
+  %after_ptr = getelementptr %Slot, ptr %slot, i32 0, i32 2
+  %after = load i32, ptr %after_ptr
+  %has_ptr = getelementptr %Slot, ptr %slot, i32 0, i32 1
+  store i32 %after, ptr %has_ptr
+  %next = add i32 %idx, 1
+  br label %loop
+
+exit:
+  ret void
+}
+
+define internal i1 @queue_can_read(i32 %idx) {
+  %queue = getelementptr %Queue, ptr @queues, i32 %idx
+  %head_ptr = getelementptr %Queue, ptr %queue, i32 0, i32 1
+  %head = load i64, ptr %head_ptr
+  %tail_ptr = getelementptr %Queue, ptr %queue, i32 0, i32 2
+  %tail = load i64, ptr %tail_ptr
+  %result = icmp ult i64 %head, %tail
+  ret i1 %result
+}
+
+define internal i32 @queue_read(i32 %idx) {
+  %queue = getelementptr %Queue, ptr @queues, i32 %idx
+  %values_ptr = getelementptr %Queue, ptr %queue, i32 0, i32 0
+  %values = load ptr, ptr %values_ptr
+  %head_ptr = getelementptr %Queue, ptr %queue, i32 0, i32 1
+  %head = load i64, ptr %head_ptr
+  %value_ptr = getelementptr i32, ptr %values, i64 %head
+  %value = load i32, ptr %value_ptr
+  %next = add i64 %head, 1
+  store i64 %next, ptr %head_ptr
+  ret i32 %value
+}
+
+; Appends a value to a queue, reusing the space of read values before growing
+define internal void @queue_push(i32 %idx, i32 %value) {
+entry:
+  %queue = getelementptr %Queue, ptr @queues, i32 %idx
+  %values_ptr = getelementptr %Queue, ptr %queue, i32 0, i32 0
+  %head_ptr = getelementptr %Queue, ptr %queue, i32 0, i32 1
+  %tail_ptr = getelementptr %Queue, ptr %queue, i32 0, i32 2
+  %capacity_ptr = getelementptr %Queue, ptr %queue, i32 0, i32 3
+  %tail = load i64, ptr %tail_ptr
+  %capacity = load i64, ptr %capacity_ptr
+  %full = icmp eq i64 %tail, %capacity
+  br i1 %full, label %make_room, label %push
+
+make_room:
+  %head = load i64, ptr %head_ptr
+  %values = load ptr, ptr %values_ptr
+  %count = sub i64 %tail, %head
+  %has_free = icmp ugt i64 %head, 0
+  br i1 %has_free, label %compact, label %grow
+
+compact:
+  %first = getelementptr i32, ptr %values, i64 %head
+  %bytes = mul i64 %count, 4
+  call ptr @memmove(ptr %values, ptr %first, i64 %bytes)
+  store i64 0, ptr %head_ptr
+  store i64 %count, ptr %tail_ptr
+  br label %push
+
+grow:
+  %is_empty = icmp eq i64 %capacity, 0
+  %doubled = mul i64 %capacity, 2
+  %new_capacity = select i1 %is_empty, i64 16, i64 %doubled
+  %new_bytes = mul i64 %new_capacity, 4
+  %new_values = call ptr @realloc(ptr %values, i64 %new_bytes)
+  %failed = icmp eq ptr %new_values, null
+  br i1 %failed, label %out_of_memory, label %grown
+
+out_of_memory:
+  call void @abort()
+  unreachable
+
+grown:
+  store ptr %new_values, ptr %values_ptr
+  store i64 %new_capacity, ptr %capacity_ptr
+  br label %push
+
+push:
+  %current_values = load ptr, ptr %values_ptr
+  %current_tail = load i64, ptr %tail_ptr
+  %value_ptr = getelementptr i32, ptr %current_values, i64 %current_tail
+  store i32 %value, ptr %value_ptr
+  %next = add i64 %current_tail, 1
+  store i64 %next, ptr %tail_ptr
+  ret void
+}
+
+define internal void @print_prefix() {
+  call i32 @putchar(i32 62)
+  call i32 @putchar(i32 32)
+  ret void
+}
+
+define internal void @print_separator() {
+  call i32 @putchar(i32 59)
+  ret void
+}
+
+; Prints the value of an output slot, or nothing if it is empty
+define internal void @print_output(i32 %idx) {
+entry:
+  %readable = call i1 @slot_can_read(i32 %idx)
+  br i1 %readable, label %print, label %exit
+
+print:
+  %value = call i32 @slot_read(i32 %idx)
+  call i32 (ptr, ...) @printf(ptr @int_format, i32 %value)
+  br label %exit
+
+exit:
+  ret void
+}
+
+define internal void @print_end() {
+  call i32 @putchar(i32 10)
+  call i32 @fflush(ptr null)
+  ret void
+}
+
+; Reads a line of stdin into @line, returning false at the end of the input.
+; Characters beyond the size of the buffer are dropped.
+define internal i1 @read_line() {
+entry:
+  br label %loop
+
+loop:
+  %len = phi i64 [ 0, %entry ], [ %len, %skip ], [ %next_len, %store ]
+  %char = call i32 @getchar()
+  %is_eof = icmp eq i32 %char, -1
+  br i1 %is_eof, label %eof, label %check_eol
+
+check_eol:
+  %is_eol = icmp eq i32 %char, 10
+  br i1 %is_eol, label %done, label %check_room
+
+check_room:
+  %has_room = icmp ult i64 %len, 4095
+  br i1 %has_room, label %store, label %skip
+
+store:
+  %char_ptr = getelementptr [4096 x i8], ptr @line, i64 0, i64 %len
+  %byte = trunc i32 %char to i8
+  store i8 %byte, ptr %char_ptr
+  %next_len = add i64 %len, 1
+  br label %loop
+
+skip:
+  br label %loop
+
+eof:
+  %is_empty = icmp eq i64 %len, 0
+  br i1 %is_empty, label %exit, label %done
+
+done:
+  %end_ptr = getelementptr [4096 x i8], ptr @line, i64 0, i64 %len
+  store i8 0, ptr %end_ptr
+  ret i1 true
+
+exit:
+  ret i1 false
+}
+
+; Pushes the values of @line into the input queues, in order.
+; Blank or invalid values leave their queue unchanged.
+define internal void @parse_line(i32 %count) {
+entry:
+  %end_ptr = alloca ptr
+  br label %loop
+
+loop:
+  %idx = phi i32 [ 0, %entry ], [ %next_idx, %next_field ]
+  %field = phi ptr [ @line, %entry ], [ %next_field_ptr, %next_field ]
+  %done = icmp sge i32 %idx, %count
+  br i1 %done, label %exit, label %parse
+
+parse:
+  %value = call i64 @strtol(ptr %field, ptr %end_ptr, i32 10)
+  %end = load ptr, ptr %end_ptr
+  %separator = call ptr @strchr(ptr %field, i32 59)
+  %parsed = icmp ne ptr %end, %field
+  %is_last = icmp eq ptr %separator, null
+  %before_separator = icmp ule ptr %end, %separator
+  %in_field = or i1 %is_last, %before_separator
+  %valid = and i1 %parsed, %in_field
+  br i1 %valid, label %push, label %next_field
+
+push:
+  %value32 = trunc i64 %value to i32
+  call void @queue_push(i32 %idx, i32 %value32)
+  br label %next_field
+
+next_field:
+  %terminator = call ptr @strchr(ptr %field, i32 0)
+  %after_separator = getelementptr i8, ptr %separator, i64 1
+  %next_field_ptr = select i1 %is_last, ptr %terminator, ptr %after_separator
+  %next_idx = add i32 %idx, 1
+  br label %loop
+
+exit:
+  ret void
+}
+
+; Ticks until the program stops making progress, with a limit for programs
+; that never stop
+define internal void @run_until_idle() {
+entry:
+  br label %loop
+
+loop:
+  %ticks = phi i32 [ 0, %entry ], [ %next, %continue ]
+  %max_ticks = load i32, ptr @max_ticks
+  %limit = icmp uge i32 %ticks, %max_ticks
+  br i1 %limit, label %exit, label %tick
+
+tick:
+  %progress = call i1 @tick()
+  br i1 %progress, label %continue, label %exit
+
+continue:
+  %next = add i32 %ticks, 1
+  br label %loop
+
+exit:
+  ret void
+}
+
+define i32 @main() {
+entry:
+  call void @run_until_idle()
+  br label %loop
+
+loop:
+  %has_line = call i1 @read_line()
+  br i1 %has_line, label %consume, label %exit
+
+consume:
+  %count = load i32, ptr @queue_count
+  call void @parse_line(i32 %count)
+  call void @run_until_idle()
+  br label %loop
+
+exit:
+  ret i32 0
+}
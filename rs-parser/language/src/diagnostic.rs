@@ -0,0 +1,211 @@
+use std::fmt;
+
+use crate::address::Node;
+use crate::span::Span;
+
+/// Kind of problem reported by the compiler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Code {
+    /// Source that cannot be parsed
+    Syntax,
+    /// Mapping between nodes not declared on both sides
+    UnmatchedMapping,
+    /// Mapping referencing a node not defined in the program
+    UnknownNode,
+    /// Node slot mapped multiple times
+    DuplicatedNodePort,
+    /// Instruction using a slot not mapped by the node
+    UndefinedPort,
+    /// Instruction using a memory slot out of range
+    InvalidMemorySlot,
+    UndefinedLabel,
+    DuplicatedLabel,
+    UnusedLabel,
+    /// Program input or output used multiple times
+    DuplicatedIoPort,
+    /// Gap in the program inputs or outputs
+    UnusedIoPort,
+    /// Nodes waiting on each other before producing
+    Deadlock,
+    /// Test case not matching the program inputs and outputs
+    InvalidTestCase,
+}
+
+impl Code {
+    pub const ALL: [Code; 13] = [
+        Code::Syntax,
+        Code::UnmatchedMapping,
+        Code::UnknownNode,
+        Code::DuplicatedNodePort,
+        Code::UndefinedPort,
+        Code::InvalidMemorySlot,
+        Code::UndefinedLabel,
+        Code::DuplicatedLabel,
+        Code::UnusedLabel,
+        Code::DuplicatedIoPort,
+        Code::UnusedIoPort,
+        Code::Deadlock,
+        Code::InvalidTestCase,
+    ];
+
+    /// Stable identifier of the code, grouped by checker pass
+    pub fn id(self) -> &'static str {
+        match self {
+            Code::Syntax => "TZ0001",
+            Code::UnmatchedMapping => "TZ0101",
+            Code::UnknownNode => "TZ0102",
+            Code::DuplicatedNodePort => "TZ0201",
+            Code::UndefinedPort => "TZ0301",
+            Code::InvalidMemorySlot => "TZ0302",
+            Code::UndefinedLabel => "TZ0401",
+            Code::DuplicatedLabel => "TZ0402",
+            Code::UnusedLabel => "TZ0403",
+            Code::DuplicatedIoPort => "TZ0501",
+            Code::UnusedIoPort => "TZ0502",
+            Code::Deadlock => "TZ0601",
+            Code::InvalidTestCase => "TZ0701",
+        }
+    }
+
+    /// Finds a code from its id or its name
+    pub fn from_name(name: &str) -> Option<Code> {
+        Code::ALL
+            .iter()
+            .cloned()
+            .find(|code| code.id() == name || code.name() == name)
+    }
+
+    /// Whether the code reports a suspicious construct rather than an invalid program.
+    /// Lints are warnings by default, and can be denied.
+    pub fn is_lint(self) -> bool {
+        matches!(
+            self,
+            Code::UnusedLabel | Code::UnusedIoPort | Code::Deadlock
+        )
+    }
+
+    /// Readable name of the code
+    pub fn name(self) -> &'static str {
+        match self {
+            Code::Syntax => "syntax-error",
+            Code::UnmatchedMapping => "unmatched-mapping",
+            Code::UnknownNode => "unknown-node",
+            Code::DuplicatedNodePort => "duplicated-node-port",
+            Code::UndefinedPort => "undefined-port",
+            Code::InvalidMemorySlot => "invalid-memory-slot",
+            Code::UndefinedLabel => "undefined-label",
+            Code::DuplicatedLabel => "duplicated-label",
+            Code::UnusedLabel => "unused-label",
+            Code::DuplicatedIoPort => "duplicated-io-port",
+            Code::UnusedIoPort => "unused-io-port",
+            Code::Deadlock => "deadlock",
+            Code::InvalidTestCase => "invalid-test-case",
+        }
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Problem found in a program, located in the source when possible
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub code: Code,
+    pub severity: Severity,
+    pub message: String,
+    /// Id of the node concerned by the problem
+    pub node: Option<String>,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn error(code: Code, message: String) -> Self {
+        Diagnostic::new(code, Severity::Error, message)
+    }
+
+    pub fn warning(code: Code, message: String) -> Self {
+        Diagnostic::new(code, Severity::Warning, message)
+    }
+
+    fn new(code: Code, severity: Severity, message: String) -> Self {
+        Diagnostic {
+            code,
+            severity,
+            message,
+            node: None,
+            span: None,
+        }
+    }
+
+    pub fn at(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
+    /// Attaches the diagnostic to a node. Program inputs and outputs are ignored.
+    pub fn in_node(mut self, node: &Node) -> Self {
+        if let Node::Node(ref id) = node {
+            self.node = Some(id.clone());
+        }
+        self
+    }
+
+    /// Formats the message, located as `source:line:col` when possible
+    pub fn format(&self, source: &str) -> String {
+        match self.span {
+            Some(ref span) => format!("{}:{}: {}", source, span, self.message),
+            None => self.message.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_diagnostic() {
+        let diagnostic = Diagnostic::warning(Code::UnusedLabel, String::from("w"))
+            .at(Some(Span::new(3, 1, 4)))
+            .in_node(&Node::new_node("a"));
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.node, Some(String::from("a")));
+        assert_eq!(diagnostic.format("a.io"), "a.io:1:4: w");
+
+        let diagnostic =
+            Diagnostic::error(Code::UndefinedPort, String::from("e")).in_node(&Node::In);
+        assert_eq!(diagnostic.node, None);
+        assert_eq!(diagnostic.format("a.io"), "e");
+    }
+
+    #[test]
+    fn test_find_code_from_name() {
+        assert_eq!(Code::from_name("unused-label"), Some(Code::UnusedLabel));
+        assert_eq!(Code::from_name("TZ0601"), Some(Code::Deadlock));
+        assert_eq!(Code::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn test_unique_code_ids() {
+        let ids: std::collections::HashSet<_> = Code::ALL.iter().map(|c| c.id()).collect();
+        assert_eq!(ids.len(), Code::ALL.len());
+    }
+}
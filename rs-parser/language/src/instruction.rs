@@ -110,3 +110,19 @@ impl Operation {
         }
     }
 }
+
+/// Number of memory slots used by operations.
+/// Slots start at 1 and a node always has at least one slot.
+pub fn memory_size(operations: &[Operation]) -> u8 {
+    operations
+        .iter()
+        .map(|op| match op {
+            Operation::SAV(MemoryPointer::BAK(idx)) | Operation::SWP(MemoryPointer::BAK(idx)) => {
+                *idx
+            }
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+        .max(1)
+}
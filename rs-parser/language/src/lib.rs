@@ -1,4 +1,6 @@
 pub mod address;
+pub mod diagnostic;
 pub mod instruction;
+pub mod span;
 pub mod syntax;
 pub mod test;
@@ -0,0 +1,77 @@
+use std::fmt;
+
+/// Location of an element in the source code of a program.
+///
+/// Lines and columns start at 1, the offset is the number of bytes before the element.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(offset: usize, line: usize, column: usize) -> Self {
+        Span {
+            offset,
+            line,
+            column,
+        }
+    }
+
+    /// Computes the location of the element at a given offset of the source code
+    pub fn at(source: &[u8], offset: usize) -> Self {
+        let before = &source[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|c| *c == b'\n')
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        let line = before.iter().filter(|c| **c == b'\n').count() + 1;
+        Span::new(offset, line, offset - line_start + 1)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Locations of the elements of a node block.
+///
+/// Operations are located in the same order as the operations of the block.
+/// Nodes created from code rather than parsed have no locations.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct NodeSpans {
+    pub node: Option<Span>,
+    pub operations: Vec<Span>,
+}
+
+impl NodeSpans {
+    pub fn operation(&self, idx: usize) -> Option<Span> {
+        self.operations.get(idx).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_at_start() {
+        assert_eq!(Span::at(b"abc", 0), Span::new(0, 1, 1));
+    }
+
+    #[test]
+    fn test_span_on_next_lines() {
+        let source = b"ab\ncde\nf";
+        assert_eq!(Span::at(source, 4), Span::new(4, 2, 2));
+        assert_eq!(Span::at(source, 7), Span::new(7, 3, 1));
+    }
+
+    #[test]
+    fn test_display_span() {
+        assert_eq!(Span::new(12, 3, 4).to_string(), "3:4");
+    }
+}
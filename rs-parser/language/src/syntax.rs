@@ -2,21 +2,135 @@
 pub struct InputMapping {
 	pub from: crate::address::Port<crate::address::OutputSlot>,
 	pub to: crate::address::InputSlot,
+	pub span: Option<crate::span::Span>,
 }
 #[derive(Debug, PartialEq)]
 pub struct OutputMapping {
 	pub from: crate::address::OutputSlot,
 	pub to: crate::address::Port<crate::address::InputSlot>,
+	pub span: Option<crate::span::Span>,
 }
 
-// TODO: would be better with an explicit structure
-pub type NodeBlock = (
-	crate::address::Node,
-	Vec<InputMapping>,
-	Vec<OutputMapping>,
-	Vec<crate::instruction::Operation>);
+/// Node of the program, with its mappings to other nodes and its instructions
+#[derive(Debug, PartialEq)]
+pub struct NodeBlock {
+	pub node: crate::address::Node,
+	pub inputs: Vec<InputMapping>,
+	pub outputs: Vec<OutputMapping>,
+	pub instructions: Vec<crate::instruction::Operation>,
+	pub spans: crate::span::NodeSpans,
+}
+
+impl NodeBlock {
+	/// Creates an empty node, to be completed with its mappings and instructions
+	pub fn new(node: crate::address::Node) -> Self {
+		NodeBlock {
+			node,
+			inputs: vec![],
+			outputs: vec![],
+			instructions: vec![],
+			spans: Default::default(),
+		}
+	}
+
+	/// Reads the port of another node into an input slot of this node
+	pub fn input(
+		mut self,
+		from: crate::address::Port<crate::address::OutputSlot>,
+		to: crate::address::InputSlot,
+	) -> Self {
+		self.inputs.push(InputMapping {
+			from,
+			to,
+			span: None,
+		});
+		self
+	}
+
+	/// Writes an output slot of this node to the port of another node
+	pub fn output(
+		mut self,
+		from: crate::address::OutputSlot,
+		to: crate::address::Port<crate::address::InputSlot>,
+	) -> Self {
+		self.outputs.push(OutputMapping {
+			from,
+			to,
+			span: None,
+		});
+		self
+	}
 
+	pub fn instruction(mut self, operation: crate::instruction::Operation) -> Self {
+		self.instructions.push(operation);
+		self
+	}
+
+	/// Number of memory slots used by the node instructions.
+	/// Slots start at 1 and a node always has at least one slot.
+	pub fn memory_size(&self) -> u8 {
+		crate::instruction::memory_size(&self.instructions)
+	}
+}
+
+#[derive(Debug, Default)]
 pub struct Program {
   pub nodes: Vec<NodeBlock>,
   pub tests: Option<crate::test::TestCase>,
 }
+
+impl Program {
+	pub fn node(mut self, node: NodeBlock) -> Self {
+		self.nodes.push(node);
+		self
+	}
+
+	pub fn test_case(mut self, tests: crate::test::TestCase) -> Self {
+		self.tests = Some(tests);
+		self
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::address::{Node, Port};
+	use crate::instruction::{Operation, ValuePointer};
+
+	#[test]
+	fn test_build_program() {
+		let program = Program::default()
+			.node(
+				NodeBlock::new(Node::new_node("a"))
+					.input(Port::new(Node::In, 1.into()), 1.into())
+					.output(1.into(), Port::new(Node::Out, 1.into()))
+					.instruction(Operation::MOV(
+						ValuePointer::INPUT(1.into()),
+						ValuePointer::OUTPUT(1.into()),
+					)),
+			)
+			.test_case(Default::default());
+
+		assert_eq!(program.nodes.len(), 1);
+		let node = &program.nodes[0];
+		assert_eq!(node.node, Node::new_node("a"));
+		assert_eq!(node.inputs[0].from, Port::new(Node::In, 1.into()));
+		assert_eq!(node.outputs[0].to, Port::new(Node::Out, 1.into()));
+		assert_eq!(node.instructions.len(), 1);
+		assert_eq!(node.spans.node, None);
+		assert!(program.tests.is_some());
+	}
+
+	#[test]
+	fn test_memory_size() {
+		use crate::instruction::MemoryPointer;
+
+		let node = NodeBlock::new(Node::new_node("a"));
+		assert_eq!(node.memory_size(), 1);
+
+		let node = node
+			.instruction(Operation::SAV(MemoryPointer::BAK(3)))
+			.instruction(Operation::SWP(MemoryPointer::BAK(2)));
+		assert_eq!(node.memory_size(), 3);
+	}
+}
@@ -8,6 +8,7 @@ pub struct TestCase {
     // as the ouputs of a fictious node
     pub ins: HashMap<crate::address::OutputSlot, Vec<i8>>,
     pub outs: HashMap<crate::address::InputSlot, Vec<i8>>,
+    pub span: Option<crate::span::Span>,
 }
 
 impl TestCase {
@@ -1,5 +1,5 @@
+use crate::error::IResult;
 use language::address::{Node, Port};
-use nom::IResult;
 
 fn input_node(input: &[u8]) -> IResult<&[u8], Node> {
     let (remaining, _) = nom::bytes::complete::tag("IN")(input)?;
@@ -37,9 +37,10 @@ where
 }
 
 pub fn node_header(input: &[u8]) -> IResult<&[u8], Node> {
-    let (input, _) = nom::bytes::complete::tag("Node")(input)?;
+    let (input, _) =
+        nom::error::context("`Node #id` header", nom::bytes::complete::tag("Node"))(input)?;
     let (input, _) = nom::character::complete::space0(input)?;
-    node_id(input)
+    nom::combinator::cut(nom::error::context("node id like `#name`", node_id))(input)
 }
 
 #[cfg(test)]
@@ -1,16 +1,16 @@
+use crate::error::IResult;
 use nom::character::complete::digit1;
 use nom::character::complete::space0;
 use nom::combinator as c;
-use nom::IResult;
 
 use std::str;
 
+use language::span::Span;
+
 /// Wrap a parser with space-consumers
-pub fn ws<'a, F: 'a, O, E: nom::error::ParseError<&'a [u8]>>(
-    inner: F,
-) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O, E>
+pub fn ws<'a, F: 'a, O>(inner: F) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>
 where
-    F: FnMut(&'a [u8]) -> IResult<&'a [u8], O, E>,
+    F: FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
 {
     nom::sequence::delimited(space0, inner, space0)
 }
@@ -24,6 +24,13 @@ pub fn to_string(v: &[u8]) -> Result<String, i8> {
     str::from_utf8(v).map(|s| s.to_string()).or(Err(-1))
 }
 
+/// Locates the element starting at `input` in the full source.
+/// `input` must be a part of `source`, as produced by the parsers.
+pub fn span_of(source: &[u8], input: &[u8]) -> Span {
+    use nom::Offset;
+    Span::at(source, source.offset(input))
+}
+
 fn to<T: str::FromStr>(v: &[u8]) -> Result<T, i8> {
     str::from_utf8(v)
         .or(Err(-1))
@@ -77,7 +84,10 @@ pub mod tests {
     use std::fmt::Debug;
 
     use super::*;
-    use nom::{Err, IResult};
+    use language::span::NodeSpans;
+    use language::syntax::NodeBlock;
+    use language::test::TestCase;
+    use nom::Err;
 
     fn assert_remaining_content(value: &[u8], expected: &[u8]) {
         if value != expected {
@@ -123,6 +133,22 @@ pub mod tests {
         assert_result(res, value, to_input(b""));
     }
 
+    /// Removes the locations of a parsed node, to compare it with a node built in code
+    pub fn clear_spans(mut node: NodeBlock) -> NodeBlock {
+        for input in node.inputs.iter_mut() {
+            input.span = None;
+        }
+        for output in node.outputs.iter_mut() {
+            output.span = None;
+        }
+        node.spans = NodeSpans::default();
+        node
+    }
+
+    pub fn clear_test_span(test: TestCase) -> TestCase {
+        TestCase { span: None, ..test }
+    }
+
     pub fn assert_cannot_parse<Result: PartialEq + Debug>(res: IResult<&[u8], Result>) {
         match res {
             Ok((i, o)) => {
@@ -0,0 +1,162 @@
+use std::fmt;
+
+use language::span::Span;
+use nom::error::{ContextError, ErrorKind, FromExternalError, ParseError as NomParseError};
+
+/// Error produced by the parsers, recording what was expected at the failing position.
+#[derive(Debug, PartialEq)]
+pub struct SyntaxError<I> {
+    pub input: I,
+    pub expected: Vec<&'static str>,
+}
+
+impl<I> SyntaxError<I> {
+    pub fn expecting(input: I, expected: &'static str) -> Self {
+        SyntaxError {
+            input,
+            expected: vec![expected],
+        }
+    }
+}
+
+pub type IResult<I, O> = nom::IResult<I, O, SyntaxError<I>>;
+
+/// Errors collected while recovering from broken parts of the source
+pub type SyntaxErrors<'a> = Vec<SyntaxError<&'a [u8]>>;
+
+/// Creates a failure, stopping the parsing at the given input
+pub fn fail<I>(input: I, expected: &'static str) -> nom::Err<SyntaxError<I>> {
+    nom::Err::Failure(SyntaxError::expecting(input, expected))
+}
+
+impl<I: nom::InputLength> NomParseError<I> for SyntaxError<I> {
+    fn from_error_kind(input: I, _kind: ErrorKind) -> Self {
+        SyntaxError {
+            input,
+            expected: vec![],
+        }
+    }
+
+    fn append(_input: I, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    /// Keeps the error that went the furthest, merging expectations of errors at the same place
+    fn or(mut self, other: Self) -> Self {
+        let (remaining, other_remaining) = (self.input.input_len(), other.input.input_len());
+        if other_remaining < remaining {
+            other
+        } else if remaining < other_remaining {
+            self
+        } else {
+            for expected in other.expected {
+                if !self.expected.contains(&expected) {
+                    self.expected.push(expected);
+                }
+            }
+            self
+        }
+    }
+}
+
+impl<I> ContextError<I> for SyntaxError<I> {
+    /// Describes the expected element, unless a more precise description exists
+    fn add_context(_input: I, ctx: &'static str, mut other: Self) -> Self {
+        if other.expected.is_empty() {
+            other.expected.push(ctx);
+        }
+        other
+    }
+}
+
+impl<I, E> FromExternalError<I, E> for SyntaxError<I> {
+    fn from_external_error(input: I, _kind: ErrorKind, _e: E) -> Self {
+        SyntaxError {
+            input,
+            expected: vec![],
+        }
+    }
+}
+
+/// Error returned when a program cannot be parsed
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub span: Span,
+    /// Descriptions of the elements expected at the error location
+    pub expected: Vec<String>,
+    /// Source line containing the error
+    pub line: String,
+}
+
+impl ParseError {
+    /// Creates the error for the given position of the source
+    pub fn at(source: &[u8], offset: usize, expected: &[&str]) -> Self {
+        let span = Span::at(source, offset);
+        let line_start = offset + 1 - span.column;
+        let line = source[line_start..]
+            .split(|c| *c == b'\n')
+            .next()
+            .unwrap_or(&[]);
+        ParseError {
+            span,
+            expected: expected.iter().map(|e| e.to_string()).collect(),
+            line: String::from_utf8_lossy(line).into_owned(),
+        }
+    }
+
+    pub(crate) fn from_syntax_error(source: &[u8], error: SyntaxError<&[u8]>) -> Self {
+        use nom::Offset;
+        ParseError::at(source, source.offset(error.input), &error.expected)
+    }
+
+    /// Describes what was expected, without the location
+    pub fn message(&self) -> String {
+        match self.expected.len() {
+            0 => String::from("syntax error"),
+            1 => format!("expected {}", self.expected[0]),
+            _ => format!("expected one of {}", self.expected.join(", ")),
+        }
+    }
+
+    /// Shows the source line with a caret under the error location
+    pub fn snippet(&self) -> String {
+        format!("{}\n{}^", self.line, " ".repeat(self.span.column - 1))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}\n{}", self.span, self.message(), self.snippet())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_furthest_error() {
+        let content = b"abcdef";
+        let error = SyntaxError::expecting(&content[1..], "b")
+            .or(SyntaxError::expecting(&content[3..], "d"));
+        assert_eq!(error, SyntaxError::expecting(&content[3..], "d"));
+    }
+
+    #[test]
+    fn test_merge_errors_at_same_place() {
+        let content = b"abc";
+        let error = SyntaxError::expecting(&content[1..], "b")
+            .or(SyntaxError::expecting(&content[1..], "c"));
+        assert_eq!(error.expected, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_display_parse_error() {
+        let error = ParseError::at(b"Node #1\nIN:1 - 1\n", 13, &["`->` after port reference"]);
+        assert_eq!(error.span, Span::new(13, 2, 6));
+        assert_eq!(
+            error.to_string(),
+            "2:6: expected `->` after port reference\nIN:1 - 1\n     ^"
+        );
+    }
+}
@@ -1,8 +1,11 @@
 use crate::common;
+use crate::error::IResult;
 use language::instruction::{MemoryPointer, ValuePointer};
 use nom::bytes::complete::tag;
 use nom::combinator as c;
-use nom::IResult;
+
+/// Description of the operands accepted by instructions reading a value
+pub const VALUE_OPERAND: &str = "`<n`, `ACC`, `NIL` or a number";
 
 pub fn acc_pointer(input: &[u8]) -> IResult<&[u8], ValuePointer> {
     c::value(ValuePointer::ACC, tag("ACC"))(input)
@@ -1,7 +1,9 @@
-use nom::IResult;
+use crate::error::IResult;
 
 use crate::common::to_string;
-use crate::instruction::base::{acc_pointer, input_pointer, nil_pointer, value_pointer};
+use crate::instruction::base::{
+    acc_pointer, input_pointer, nil_pointer, value_pointer, VALUE_OPERAND,
+};
 use language::instruction::Operation;
 
 fn label_name(input: &[u8]) -> IResult<&[u8], String> {
@@ -21,13 +23,12 @@ pub fn label_operation(input: &[u8]) -> IResult<&[u8], Operation> {
 macro_rules! jump_fn {
     ($name:ident, $pattern:expr, $cnstr:path) => {
         pub fn $name(input: &[u8]) -> IResult<&[u8], Operation> {
-            // TODO once we see the operation, we can deduce that it must be followed by a label
-            // Using Failure would mark the issue in the parsing
-            let (rest, (_, _, label)) = nom::sequence::tuple((
-                nom::bytes::complete::tag($pattern),
-                nom::character::complete::space1,
-                label_name,
-            ))(input)?;
+            // Once we see the operation, it must be followed by a label
+            let (rest, _) = nom::bytes::complete::tag($pattern)(input)?;
+            let (rest, label) = nom::combinator::cut(nom::error::context(
+                concat!("label after ", $pattern),
+                nom::sequence::preceded(nom::character::complete::space1, label_name),
+            ))(rest)?;
             Ok((rest, $cnstr(label)))
         }
     };
@@ -41,8 +42,10 @@ jump_fn!(jgz_operation, "JGZ", Operation::JGZ);
 pub fn jro_operation(input: &[u8]) -> IResult<&[u8], Operation> {
     let (input, _) = nom::bytes::complete::tag("JRO")(input)?;
     let (input, _) = nom::character::complete::space1(input)?;
-    let (input, value) =
-        nom::branch::alt((acc_pointer, nil_pointer, input_pointer, value_pointer))(input)?;
+    let (input, value) = nom::combinator::cut(nom::error::context(
+        VALUE_OPERAND,
+        nom::branch::alt((acc_pointer, nil_pointer, input_pointer, value_pointer)),
+    ))(input)?;
     Ok((input, Operation::JRO(value)))
 }
 
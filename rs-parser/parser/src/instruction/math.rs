@@ -1,22 +1,32 @@
+use crate::error::IResult;
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::space1;
-use nom::IResult;
+use nom::combinator::cut;
+use nom::error::context;
 
-use crate::instruction::base::{acc_pointer, input_pointer, nil_pointer, value_pointer};
+use crate::instruction::base::{
+    acc_pointer, input_pointer, nil_pointer, value_pointer, VALUE_OPERAND,
+};
 use language::instruction::Operation;
 
 pub fn add_operation(input: &[u8]) -> IResult<&[u8], Operation> {
     let (input, _) = tag("ADD")(input)?;
     let (input, _) = space1(input)?;
-    let (input, value) = alt((input_pointer, acc_pointer, nil_pointer, value_pointer))(input)?;
+    let (input, value) = cut(context(
+        VALUE_OPERAND,
+        alt((input_pointer, acc_pointer, nil_pointer, value_pointer)),
+    ))(input)?;
     Ok((input, Operation::ADD(value)))
 }
 
 pub fn sub_operation(input: &[u8]) -> IResult<&[u8], Operation> {
     let (input, _) = tag("SUB")(input)?;
     let (input, _) = space1(input)?;
-    let (input, value) = alt((input_pointer, acc_pointer, nil_pointer, value_pointer))(input)?;
+    let (input, value) = cut(context(
+        VALUE_OPERAND,
+        alt((input_pointer, acc_pointer, nil_pointer, value_pointer)),
+    ))(input)?;
     Ok((input, Operation::SUB(value)))
 }
 
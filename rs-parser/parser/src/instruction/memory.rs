@@ -1,16 +1,27 @@
 use language::instruction::{MemoryPointer, Operation};
 
-pub fn swp_operation(input: &[u8]) -> nom::IResult<&[u8], Operation> {
-    nom::combinator::value(
-        Operation::SWP(MemoryPointer::BAK(1)),
-        nom::bytes::complete::tag("SWP"),
+/// Reads the index of the memory slot, the first slot being used by default
+fn memory_pointer(input: &[u8]) -> crate::error::IResult<&[u8], MemoryPointer> {
+    nom::combinator::map(
+        nom::combinator::opt(nom::sequence::preceded(
+            nom::character::complete::space1,
+            crate::common::be_u8,
+        )),
+        |idx| MemoryPointer::BAK(idx.unwrap_or(1)),
     )(input)
 }
 
-pub fn sav_operation(input: &[u8]) -> nom::IResult<&[u8], Operation> {
-    nom::combinator::value(
-        Operation::SAV(MemoryPointer::BAK(1)),
-        nom::bytes::complete::tag("SAV"),
+pub fn swp_operation(input: &[u8]) -> crate::error::IResult<&[u8], Operation> {
+    nom::combinator::map(
+        nom::sequence::preceded(nom::bytes::complete::tag("SWP"), memory_pointer),
+        Operation::SWP,
+    )(input)
+}
+
+pub fn sav_operation(input: &[u8]) -> crate::error::IResult<&[u8], Operation> {
+    nom::combinator::map(
+        nom::sequence::preceded(nom::bytes::complete::tag("SAV"), memory_pointer),
+        Operation::SAV,
     )(input)
 }
 
@@ -29,8 +40,8 @@ mod tests {
 
     #[test]
     fn test_parse_swp_operation_to_idx() {
-        // let res = swp_operation(to_input(b"SWP 3"));
-        // assert_full_result(res, Operation::SWP(MemoryPointer::BAK(3)));
+        let res = swp_operation(to_input(b"SWP 3"));
+        assert_full_result(res, Operation::SWP(MemoryPointer::BAK(3)));
     }
 
     #[test]
@@ -41,8 +52,13 @@ mod tests {
 
     #[test]
     fn test_parse_sav_operation_to_idx() {
-        // TODO code save to other space
-        // let res = sav_operation(to_input(b"SAV 2"));
-        // assert_full_result(res, Operation::SAV(MemoryPointer::BAK(2)));
+        let res = sav_operation(to_input(b"SAV 2"));
+        assert_full_result(res, Operation::SAV(MemoryPointer::BAK(2)));
+    }
+
+    #[test]
+    fn test_parse_memory_operation_before_comment() {
+        let res = sav_operation(to_input(b"SAV // keep"));
+        assert_result(res, Operation::SAV(MemoryPointer::BAK(1)), b" // keep");
     }
 }
@@ -1,7 +1,8 @@
+use crate::error::IResult;
 use nom::branch;
 use nom::bytes::complete::tag;
 use nom::character::complete::{space0, space1};
-use nom::IResult;
+use nom::error::context;
 
 use crate::instruction::base as ptr;
 use language::instruction::Operation;
@@ -15,9 +16,12 @@ fn mov_from_in(input: &[u8]) -> IResult<&[u8], Operation> {
     let (rest, (from, _, _, _, to)) = nom::sequence::tuple((
         ptr::input_pointer,
         space0,
-        tag(","),
+        context("`,` between MOV operands", tag(",")),
         space0,
-        branch::alt((ptr::acc_pointer, ptr::nil_pointer, ptr::output_pointer)),
+        context(
+            "`>n`, `ACC` or `NIL`",
+            branch::alt((ptr::acc_pointer, ptr::nil_pointer, ptr::output_pointer)),
+        ),
     ))(input)?;
     Ok((rest, Operation::MOV(from, to)))
 }
@@ -26,9 +30,9 @@ fn mov_to_out(input: &[u8]) -> IResult<&[u8], Operation> {
     let (rest, (from, _, _, _, to)) = nom::sequence::tuple((
         branch::alt((ptr::acc_pointer, ptr::nil_pointer, ptr::value_pointer)),
         space0,
-        tag(","),
+        context("`,` between MOV operands", tag(",")),
         space0,
-        ptr::output_pointer,
+        context("`>n`", ptr::output_pointer),
     ))(input)?;
     Ok((rest, Operation::MOV(from, to)))
 }
@@ -37,16 +41,19 @@ fn mov_accs(input: &[u8]) -> IResult<&[u8], Operation> {
     let (rest, (from, _, _, _, to)) = nom::sequence::tuple((
         branch::alt((ptr::value_pointer, ptr::acc_pointer, ptr::nil_pointer)),
         space0,
-        tag(","),
+        context("`,` between MOV operands", tag(",")),
         space0,
-        ptr::acc_pointer,
+        context("`ACC`", ptr::acc_pointer),
     ))(input)?;
     Ok((rest, Operation::MOV(from, to)))
 }
 
 pub fn mov_operation(input: &[u8]) -> IResult<&[u8], Operation> {
     let (input, _) = consume_mov(input)?;
-    nom::branch::alt((mov_from_in, mov_to_out, mov_accs))(input)
+    nom::combinator::cut(context(
+        "MOV operands like `<1, ACC`",
+        nom::branch::alt((mov_from_in, mov_to_out, mov_accs)),
+    ))(input)
 }
 
 #[cfg(test)]
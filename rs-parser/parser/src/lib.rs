@@ -4,6 +4,7 @@ extern crate nom;
 
 mod address;
 mod common;
+mod error;
 mod instruction;
 mod mapping;
 mod syntax;
@@ -15,57 +16,80 @@ use language::syntax::NodeBlock;
 use language::syntax::Program;
 use language::test::TestCase;
 
-pub type ParsingResult = Result<Program, ()>;
+pub use crate::error::ParseError;
 
-fn program(input: &[u8]) -> nom::IResult<&[u8], (Vec<NodeBlock>, Option<TestCase>)> {
+/// Failure to parse a program, with all the syntax errors found.
+/// The program contains the nodes that could still be parsed.
+#[derive(Debug)]
+pub struct ParseFailure {
+    pub errors: Vec<ParseError>,
+    pub program: Box<Program>,
+}
+
+pub type ParsingResult = Result<Program, ParseFailure>;
+
+/// Nodes and test case of a program, with the errors of its broken parts
+type ParsedProgram<'a> = (
+    Vec<NodeBlock>,
+    Option<TestCase>,
+    crate::error::SyntaxErrors<'a>,
+);
+
+fn program(source: &[u8]) -> crate::error::IResult<&[u8], ParsedProgram<'_>> {
     use crate::common::opt_eol;
 
+    let input = source;
     let (input, _) = opt_eol(input)?;
-    let (input, test_case) = nom::combinator::opt(crate::test::test_case)(input)?;
-    let (input, _) = opt_eol(input)?;
-    let (input, nodes) = crate::syntax::node_list(input)?;
+    let test_span = crate::common::span_of(source, input);
+    let (input, test_case, mut errors) = match nom::combinator::opt(crate::test::test_case)(input) {
+        Ok((input, test_case)) => (input, test_case, vec![]),
+        Err(nom::Err::Failure(e)) => (crate::syntax::skip_to_node_header(e.input), None, vec![e]),
+        Err(e) => return Err(e),
+    };
+    let test_case = test_case.map(|test| TestCase {
+        span: Some(test_span),
+        ..test
+    });
     let (input, _) = opt_eol(input)?;
+    let (input, (nodes, mut node_errors)) = crate::syntax::node_list(source, input)?;
+    errors.append(&mut node_errors);
 
-    Ok((input, (nodes, test_case)))
-}
-
-fn print_error(e: &nom::error::Error<&[u8]>) {
-    println!(
-        "Error = {:#?}\n{:#?}",
-        e.code,
-        crate::common::to_string(e.input).expect("Cannot display content")
-    )
+    Ok((input, (nodes, test_case, errors)))
 }
 
+/// Parses a program.
+/// On errors, the location and the expected elements of every broken part are
+/// reported, along with the nodes that could be parsed.
 pub fn parse(input: &[u8]) -> ParsingResult {
-    let res = program(input);
-    match res {
-        Ok((i, (list, test_cases))) => {
-            if i.is_empty() {
-                let tree = Program {
-                    nodes: list,
-                    tests: test_cases,
-                };
-                Result::Ok(tree)
+    match program(input) {
+        Ok((_, (nodes, tests, errors))) => {
+            let program = Program { nodes, tests };
+            if errors.is_empty() {
+                Ok(program)
             } else {
-                println!(
-                    "Remaining unparsed content {}",
-                    crate::common::to_string(i).unwrap()
-                );
-                Result::Err(())
+                Err(ParseFailure {
+                    errors: errors
+                        .into_iter()
+                        .map(|e| ParseError::from_syntax_error(input, e))
+                        .collect(),
+                    program: Box::new(program),
+                })
             }
         }
-        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
-            // if cfg!(feature = "alloc") {
-            //     nom::error::convert_error(input, e);
-            // }
-            print_error(&e);
-            Result::Err(())
-        }
-        Err(nom::Err::Incomplete(needed)) => {
-            println!("Missing data. Needed {:?}", needed);
-            Result::Err(())
-        }
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(ParseFailure {
+            errors: vec![ParseError::from_syntax_error(input, e)],
+            program: Box::new(Program {
+                nodes: vec![],
+                tests: None,
+            }),
+        }),
+        Err(nom::Err::Incomplete(_)) => Err(ParseFailure {
+            errors: vec![ParseError::at(input, input.len(), &["more content"])],
+            program: Box::new(Program {
+                nodes: vec![],
+                tests: None,
+            }),
+        }),
     }
 }
 
@@ -76,8 +100,25 @@ mod tests {
     use common::tests::*;
     use language::address::{Node, Port};
     use language::instruction::{Operation, ValuePointer};
+    use language::span::{NodeSpans, Span};
     use language::syntax::{InputMapping, OutputMapping};
 
+    /// Parses a program, dropping the locations to compare it with nodes built in code
+    fn program_without_spans(
+        content: &[u8],
+    ) -> crate::error::IResult<&[u8], (Vec<NodeBlock>, Option<TestCase>)> {
+        program(content).map(|(rest, (nodes, tests, errors))| {
+            assert!(errors.is_empty());
+            (
+                rest,
+                (
+                    nodes.into_iter().map(clear_spans).collect(),
+                    tests.map(clear_test_span),
+                ),
+            )
+        })
+    }
+
     #[test]
     fn test_program_without_tests() {
         let content = b"// Start of the program
@@ -104,38 +145,44 @@ MOV <2, >2
 // End comment, to conclude
 ";
 
-        let res = program(content);
+        let res = program_without_spans(content);
         let nodes = vec![
-            (
-                Node::new_node("1"),
-                vec![InputMapping {
+            NodeBlock {
+                node: Node::new_node("1"),
+                inputs: vec![InputMapping {
                     from: Port::new(Node::In, 1.into()),
                     to: 1.into(),
+                    span: None,
                 }],
-                vec![OutputMapping {
+                outputs: vec![OutputMapping {
                     from: 1.into(),
                     to: Port::named_port(&"2", 2.into()),
+                    span: None,
                 }],
-                vec![Operation::MOV(
+                instructions: vec![Operation::MOV(
                     ValuePointer::INPUT(1.into()),
                     ValuePointer::OUTPUT(1.into()),
                 )],
-            ),
-            (
-                Node::new_node("2"),
-                vec![InputMapping {
+                spans: NodeSpans::default(),
+            },
+            NodeBlock {
+                node: Node::new_node("2"),
+                inputs: vec![InputMapping {
                     from: Port::named_port(&"1", 1.into()),
                     to: 2.into(),
+                    span: None,
                 }],
-                vec![OutputMapping {
+                outputs: vec![OutputMapping {
                     from: 2.into(),
                     to: Port::named_port(&"3", 3.into()),
+                    span: None,
                 }],
-                vec![Operation::MOV(
+                instructions: vec![Operation::MOV(
                     ValuePointer::INPUT(2.into()),
                     ValuePointer::OUTPUT(2.into()),
                 )],
-            ),
+                spans: NodeSpans::default(),
+            },
         ];
 
         assert_full_result(res, (nodes, None));
@@ -161,22 +208,25 @@ MOV <1,  >1
 // End comment, to conclude
 ";
 
-        let res = program(content);
-        let nodes = vec![(
-            Node::new_node("1"),
-            vec![InputMapping {
+        let res = program_without_spans(content);
+        let nodes = vec![NodeBlock {
+            node: Node::new_node("1"),
+            inputs: vec![InputMapping {
                 from: Port::new(Node::In, 1.into()),
                 to: 1.into(),
+                span: None,
             }],
-            vec![OutputMapping {
+            outputs: vec![OutputMapping {
                 from: 1.into(),
                 to: Port::named_port(&"2", 2.into()),
+                span: None,
             }],
-            vec![Operation::MOV(
+            instructions: vec![Operation::MOV(
                 ValuePointer::INPUT(1.into()),
                 ValuePointer::OUTPUT(1.into()),
             )],
-        )];
+            spans: NodeSpans::default(),
+        }];
         let test_cases = Some(
             TestCase::default()
                 .input_into(1.into(), vec![1, 2])
@@ -197,16 +247,149 @@ MOV <1,  >1
 // End comment, to conclude
    ";
 
-        let res = program(content);
-        let nodes = vec![(
-            Node::new_node("1"),
-            vec![],
-            vec![],
-            vec![Operation::MOV(
+        let res = program_without_spans(content);
+        let nodes = vec![NodeBlock {
+            node: Node::new_node("1"),
+            inputs: vec![],
+            outputs: vec![],
+            instructions: vec![Operation::MOV(
                 ValuePointer::INPUT(1.into()),
                 ValuePointer::OUTPUT(1.into()),
             )],
-        )];
+            spans: NodeSpans::default(),
+        }];
         assert_result(res, (nodes, None), b"   ");
     }
+
+    fn parse_error(content: &[u8]) -> ParseError {
+        match parse(content) {
+            Ok(_) => panic!("Expecting a parsing error"),
+            Err(mut failure) => {
+                assert_eq!(failure.errors.len(), 1);
+                failure.errors.remove(0)
+            }
+        }
+    }
+
+    #[test]
+    fn test_report_unexpected_token() {
+        let content = b"Node #1
+==========
+IN:1 - 1
+-------
+MOV <1, ACC
+=======
+";
+        let error = parse_error(content);
+        assert_eq!(error.span, Span::new(24, 3, 6));
+        assert_eq!(error.expected, vec!["`->` after port reference"]);
+        assert_eq!(
+            error.to_string(),
+            "3:6: expected `->` after port reference\nIN:1 - 1\n     ^"
+        );
+    }
+
+    #[test]
+    fn test_report_missing_jump_label() {
+        let content = b"Node #1
+==========
+start: MOV <1, ACC
+  JMP
+=======
+";
+        let error = parse_error(content);
+        assert_eq!(error.span.line, 4);
+        assert_eq!(error.span.column, 6);
+        assert_eq!(error.expected, vec!["label after JMP"]);
+    }
+
+    #[test]
+    fn test_report_unknown_instruction() {
+        let content = b"Node #1
+==========
+MOV <1, ACC
+FOO ACC
+=======
+";
+        let error = parse_error(content);
+        assert_eq!(error.span.line, 4);
+        assert_eq!(error.span.column, 1);
+        assert_eq!(error.expected, vec!["instruction"]);
+    }
+
+    #[test]
+    fn test_report_unclosed_node() {
+        let content = b"Node #1
+==========
+MOV <1, ACC
+";
+        let error = parse_error(content);
+        assert_eq!(error.span.line, 4);
+        assert_eq!(error.expected, vec!["`===` line closing the node"]);
+    }
+
+    #[test]
+    fn test_report_content_after_nodes() {
+        let content = b"Node #1
+==========
+MOV <1, ACC
+==========
+
+Nod #2
+";
+        let error = parse_error(content);
+        assert_eq!(error.span.line, 6);
+        assert_eq!(error.expected, vec!["`Node #id` header"]);
+        assert_eq!(error.snippet(), "Nod #2\n^");
+    }
+
+    #[test]
+    fn test_report_errors_of_all_broken_nodes() {
+        let content = b"Node #1
+==========
+JMP
+==========
+
+Node #2
+==========
+MOV <1, ACC
+==========
+
+Node 3
+==========
+MOV ACC, >1
+==========
+
+Node #4
+==========
+MOV <1 ACC
+";
+        let failure = match parse(content) {
+            Ok(_) => panic!("Expecting parsing errors"),
+            Err(failure) => failure,
+        };
+        let lines: Vec<usize> = failure.errors.iter().map(|e| e.span.line).collect();
+        assert_eq!(lines, vec![3, 11, 19]);
+        let nodes: Vec<Node> = failure.program.nodes.into_iter().map(|n| n.node).collect();
+        assert_eq!(nodes, vec![Node::new_node("2")]);
+    }
+
+    #[test]
+    fn test_recover_from_broken_test_case() {
+        let content = b"/>> 1: [1 2
+/<< 1: [1 2]
+Node #1
+==========
+MOV <1, >1
+==========
+";
+        let failure = match parse(content) {
+            Ok(_) => panic!("Expecting parsing errors"),
+            Err(failure) => failure,
+        };
+        assert_eq!(failure.errors.len(), 1);
+        assert_eq!(failure.errors[0].span.line, 1);
+        assert_eq!(failure.program.nodes.len(), 1);
+        assert!(failure.program.tests.is_none());
+    }
 }
@@ -1,16 +1,11 @@
-use nom::IResult;
+use crate::error::{fail, IResult, SyntaxError, SyntaxErrors};
 
+use crate::common::span_of;
 use language::instruction::Operation;
+use language::span::{NodeSpans, Span};
 use language::syntax::NodeBlock;
 use language::syntax::{InputMapping, OutputMapping};
 
-fn fail(input: &[u8]) -> nom::Err<nom::error::Error<&[u8]>> {
-    nom::Err::Failure(nom::error::Error::new(
-        input,
-        nom::error::ErrorKind::Satisfy,
-    ))
-}
-
 /// Parses a line of a given symbol, ending with optional spaces before a new-line char
 fn line_of<'a>(symbol: &'static str, input: &'a [u8]) -> IResult<&'a [u8], ()> {
     nom::combinator::value(
@@ -47,7 +42,12 @@ fn find_node_end_line(full_input: &[u8]) -> IResult<&[u8], &[u8]> {
     let mut input = full_input;
     loop {
         match find_start(input, t) {
-            None => return Err(fail(full_input)),
+            None => {
+                return Err(fail(
+                    &full_input[full_input.len()..],
+                    "`===` line closing the node",
+                ))
+            }
             Some(index) => {
                 let (node_body, rest) = input.split_at(index + 1);
                 let end_result = match node_line(rest) {
@@ -64,41 +64,43 @@ fn find_node_end_line(full_input: &[u8]) -> IResult<&[u8], &[u8]> {
     }
 }
 
-fn instruction_line(initial_input: &[u8]) -> IResult<&[u8], Vec<Operation>> {
+/// Parses a line of instructions, returning each operation with its location.
+/// A line that is neither a label nor an instruction is an error, letting the
+/// caller try other sections. Once a label or a mnemonic is read, errors are failures.
+fn instruction_line<'a>(
+    source: &'a [u8],
+    initial_input: &'a [u8],
+) -> IResult<&'a [u8], Vec<(Operation, Span)>> {
     use nom::character::complete::space0;
     let (input, _) = space0(initial_input)?; // Consume leading space
     let (input, label) =
         if let Ok((consumed, lbl)) = crate::instruction::condition::label_operation(input) {
             let (consumed, _) = space0(consumed)?;
-            (consumed, Some(lbl))
-        } else {
-            (input, None)
-        };
-    let (input, instruction) =
-        if let Ok((consumed, instruction)) = crate::instruction::parse_instruction(input) {
-            (consumed, Some(instruction))
+            (consumed, Some((lbl, span_of(source, input))))
         } else {
             (input, None)
         };
+    let (input, instruction) = match crate::instruction::parse_instruction(input) {
+        Ok((consumed, instruction)) => (consumed, Some((instruction, span_of(source, input)))),
+        Err(nom::Err::Error(e)) if label.is_none() => return Err(nom::Err::Error(e)),
+        Err(nom::Err::Error(_)) => (input, None),
+        Err(e) => return Err(e),
+    };
     // And a instruction must end with an empty line
-    let (input, _) = if let Ok((consumed, _)) = crate::common::eol(input) {
-        (consumed, ())
-    } else {
-        return Err(fail(initial_input));
+    let (input, _) = match crate::common::eol(input) {
+        Ok(result) => result,
+        Err(_) if instruction.is_none() => return Err(fail(input, "instruction")),
+        Err(_) => return Err(fail(input, "end of line")),
     };
 
-    if label.is_some() || instruction.is_some() {
-        Ok((
-            input,
-            vec![label, instruction]
-                .into_iter()
-                .filter(|v| v.is_some())
-                .map(|v| v.unwrap())
-                .collect(),
-        ))
-    } else {
-        Err(fail(initial_input))
-    }
+    Ok((
+        input,
+        vec![label, instruction]
+            .into_iter()
+            .filter(|v| v.is_some())
+            .map(|v| v.unwrap())
+            .collect(),
+    ))
 }
 
 /// Consumes all blank lines, possibly containing comments
@@ -112,77 +114,194 @@ fn consume_eols(input: &[u8]) -> IResult<&[u8], ()> {
 
 /// Collects all inputs if any
 /// If an input section is found, the section must be correctly defined.
-fn collect_inputs(input: &[u8]) -> IResult<&[u8], Vec<InputMapping>> {
+fn collect_inputs<'a>(source: &'a [u8], input: &'a [u8]) -> IResult<&'a [u8], Vec<InputMapping>> {
     use nom::character::complete::newline;
 
-    if let Ok((some, ins)) = crate::mapping::inputs(input) {
-        let (rest, _) = nom::sequence::tuple((newline, code_line))(some).map_err(|_| fail(some))?;
-        Ok((rest, ins))
-    } else {
-        Ok((input, vec![]))
+    match crate::mapping::inputs(source)(input) {
+        Ok((some, ins)) => {
+            let (rest, _) = newline::<_, SyntaxError<_>>(some)
+                .map_err(|_| fail(some, "end of line after the inputs"))?;
+            let (rest, _) =
+                code_line(rest).map_err(|_| fail(rest, "`---` line after the inputs"))?;
+            Ok((rest, ins))
+        }
+        Err(nom::Err::Error(_)) => Ok((input, vec![])),
+        Err(e) => Err(e),
     }
 }
 
 /// Collects all outputs if any.
 /// If an output section is found, the section must be correctly defined.
-fn collect_outputs(input: &[u8]) -> IResult<&[u8], Vec<OutputMapping>> {
+fn collect_outputs<'a>(source: &'a [u8], input: &'a [u8]) -> IResult<&'a [u8], Vec<OutputMapping>> {
     if let Ok((some, _)) = code_line(input) {
-        crate::mapping::outputs(some).map_err(|_| fail(input))
+        nom::combinator::cut(nom::error::context(
+            "output mapping like `1 -> OUT:1`",
+            crate::mapping::outputs(source),
+        ))(some)
     } else {
         Ok((input, vec![]))
     }
 }
 
-/// Collects all instructions of the node
-fn collect_instructions(input: &[u8]) -> IResult<&[u8], Vec<Operation>> {
+/// Collects all instructions of the node, with their locations
+fn collect_instructions<'a>(
+    source: &'a [u8],
+    input: &'a [u8],
+) -> IResult<&'a [u8], Vec<(Operation, Span)>> {
     let mut instructions = vec![];
     let mut remaining = input;
-    while let Ok((rest, mut instruction)) = instruction_line(remaining) {
-        instructions.append(&mut instruction);
-        let (more, _) = consume_eols(rest)?;
-        remaining = more;
-    }
-    if instructions.is_empty() {
-        Err(fail(input))
-    } else {
-        Ok((remaining, instructions))
+    loop {
+        match instruction_line(source, remaining) {
+            Ok((rest, mut instruction)) => {
+                instructions.append(&mut instruction);
+                let (more, _) = consume_eols(rest)?;
+                remaining = more;
+            }
+            Err(nom::Err::Error(e)) => {
+                if instructions.is_empty() {
+                    return Err(nom::Err::Failure(e));
+                }
+                break;
+            }
+            Err(e) => return Err(e),
+        }
     }
+    Ok((remaining, instructions))
 }
 
-fn parse_node(initial_input: &[u8]) -> IResult<&[u8], NodeBlock> {
-    let (input, inputs) = collect_inputs(initial_input)?;
+fn parse_node<'a>(source: &'a [u8], initial_input: &'a [u8]) -> IResult<&'a [u8], NodeBlock> {
+    let (input, inputs) = collect_inputs(source, initial_input)?;
     let (input, _) = consume_eols(input)?;
-    let (input, instructions) = collect_instructions(input)?;
-    let (input, outputs) = collect_outputs(input)?;
+    let (input, instructions) = collect_instructions(source, input)?;
+    let (input, outputs) = collect_outputs(source, input)?;
     // Here we must check that there is no more data in the input
+    let (input, _) = consume_eols(input)?;
+    if !input.is_empty() {
+        return Err(if outputs.is_empty() {
+            // Neither an instruction nor the outputs, report the line as a bad instruction
+            match instruction_line(source, input) {
+                Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => nom::Err::Failure(e),
+                _ => fail(input, "instruction"),
+            }
+        } else {
+            fail(input, "end of the node")
+        });
+    }
 
+    let (instructions, spans): (Vec<_>, Vec<_>) = instructions.into_iter().unzip();
     Ok((
         input,
-        // Using a
-        (language::address::Node::In, inputs, outputs, instructions),
+        // The node id is read from the header
+        NodeBlock {
+            node: language::address::Node::In,
+            inputs,
+            outputs,
+            instructions,
+            spans: NodeSpans {
+                node: None,
+                operations: spans,
+            },
+        },
     ))
 }
 
-pub fn node_block(initial_input: &[u8]) -> IResult<&[u8], NodeBlock> {
+/// Parses a node block.
+/// `source` is the full program, used to locate the elements of the node.
+pub fn node_block<'a>(source: &'a [u8], initial_input: &'a [u8]) -> IResult<&'a [u8], NodeBlock> {
     use nom::character::complete::newline;
 
     let (input, _) = nom::character::complete::space0(initial_input)?;
+    let node_span = span_of(source, input);
     let (input, node_id) = crate::address::node_header(input)?;
-    let (input, _) = newline(input)?;
+    let (input, _) = newline::<_, SyntaxError<_>>(input)
+        .map_err(|_| fail(input, "end of line after the node header"))?;
 
     // At this point, we must see the start of a block
     // Consume the opening node-line
-    let (input, _) = node_line(input).map_err(|_| fail(input))?;
+    let (input, _) = node_line(input).map_err(|_| fail(input, "`===` line opening the node"))?;
     // Let's find the end of the node
     let (post_node_input, node_body) = find_node_end_line(input)?;
 
-    let (_, mut node) = parse_node(node_body)?;
-    node.0 = node_id;
+    let (_, mut node) = parse_node(source, node_body)?;
+    node.node = node_id;
+    node.spans.node = Some(node_span);
     Ok((post_node_input, node))
 }
 
-pub fn node_list(input: &[u8]) -> IResult<&[u8], Vec<NodeBlock>> {
-    nom::multi::separated_list1(nom::multi::many1(crate::common::eol), node_block)(input)
+/// Skips the current line, returning the content after its new-line char
+fn skip_line(input: &[u8]) -> &[u8] {
+    match input.iter().position(|c| *c == b'\n') {
+        Some(index) => &input[index + 1..],
+        None => &input[input.len()..],
+    }
+}
+
+/// Tells if the line starts a node block, even an invalid one
+fn starts_node_header(input: &[u8]) -> bool {
+    nom::sequence::preceded(
+        nom::character::complete::space0::<_, SyntaxError<_>>,
+        nom::bytes::complete::tag("Node"),
+    )(input)
+    .is_ok()
+}
+
+/// Finds the next line starting a node block
+pub(crate) fn skip_to_node_header(input: &[u8]) -> &[u8] {
+    let mut remaining = input;
+    while !remaining.is_empty() && !starts_node_header(remaining) {
+        remaining = skip_line(remaining);
+    }
+    remaining
+}
+
+/// Finds where to resume the parsing after a broken node block.
+/// This is after the node-line closing the block, or at the next node header
+/// if the block is not closed.
+fn recover_node(input: &[u8]) -> &[u8] {
+    // Skip the header and the opening node-line
+    let mut remaining = skip_line(input);
+    if let Ok((rest, _)) = node_line(remaining) {
+        remaining = rest;
+    }
+    while !remaining.is_empty() && !starts_node_header(remaining) {
+        if let Ok((rest, _)) = node_line(remaining) {
+            return rest;
+        }
+        remaining = skip_line(remaining);
+    }
+    remaining
+}
+
+/// Parses the list of nodes.
+/// The errors of broken node blocks are collected and the parsing resumes
+/// with the next block, returning all valid nodes.
+pub fn node_list<'a>(
+    source: &'a [u8],
+    input: &'a [u8],
+) -> IResult<&'a [u8], (Vec<NodeBlock>, SyntaxErrors<'a>)> {
+    let mut nodes = vec![];
+    let mut errors = vec![];
+    let mut remaining = input;
+    loop {
+        let (rest, _) = crate::common::opt_eol(remaining)?;
+        if rest.iter().all(|c| c.is_ascii_whitespace()) {
+            if nodes.is_empty() && errors.is_empty() {
+                errors.push(SyntaxError::expecting(rest, "`Node #id` header"));
+            }
+            return Ok((rest, (nodes, errors)));
+        }
+        match node_block(source, rest) {
+            Ok((more, node)) => {
+                nodes.push(node);
+                remaining = more;
+            }
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                errors.push(e);
+                remaining = recover_node(rest);
+            }
+            Err(e) => return Err(e),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -228,54 +347,76 @@ mod tests {
 
     #[test]
     fn test_parse_instruction_line_with_label_only() {
-        let res = instruction_line(to_input(b"LBL:  \n"));
-        assert_full_result(res, vec![Operation::LABEL(String::from("LBL"))]);
+        let content = to_input(b"LBL:  \n");
+        let res = instruction_line(content, content);
+        assert_full_result(
+            res,
+            vec![(Operation::LABEL(String::from("LBL")), Span::new(0, 1, 1))],
+        );
     }
 
     #[test]
     fn test_parse_instruction_line_with_instruction_only() {
-        let res = instruction_line(to_input(b"SWP  \n"));
-        assert_full_result(res, vec![Operation::SWP(MemoryPointer::BAK(1))]);
+        let content = to_input(b"SWP  \n");
+        let res = instruction_line(content, content);
+        assert_full_result(
+            res,
+            vec![(Operation::SWP(MemoryPointer::BAK(1)), Span::new(0, 1, 1))],
+        );
     }
 
     #[test]
     fn test_parse_instruction_line_with_label_then_instruction() {
-        let res = instruction_line(to_input(b"LBL:SWP \n"));
+        let content = to_input(b"LBL:SWP \n");
+        let res = instruction_line(content, content);
         assert_full_result(
             res,
             vec![
-                Operation::LABEL(String::from("LBL")),
-                Operation::SWP(MemoryPointer::BAK(1)),
+                (Operation::LABEL(String::from("LBL")), Span::new(0, 1, 1)),
+                (Operation::SWP(MemoryPointer::BAK(1)), Span::new(4, 1, 5)),
             ],
         );
     }
 
     #[test]
     fn test_parse_empty_instruction_line() {
-        let res = instruction_line(to_input(b" \n"));
+        let content = to_input(b" \n");
+        let res = instruction_line(content, content);
         assert_cannot_parse(res);
     }
 
     #[test]
     fn test_parse_with_consecutive_labels() {
-        let res = instruction_line(to_input(b"L1: L2:\n"));
+        let content = to_input(b"L1: L2:\n");
+        let res = instruction_line(content, content);
         assert_cannot_parse(res);
     }
 
     #[test]
     fn test_parse_instruction_with_comment() {
-        let res = instruction_line(to_input(b"ADD <2 // Sum the values\n"));
-        assert_full_result(res, vec![Operation::ADD(ValuePointer::INPUT(2.into()))]);
+        let content = to_input(b"ADD <2 // Sum the values\n");
+        let res = instruction_line(content, content);
+        assert_full_result(
+            res,
+            vec![(
+                Operation::ADD(ValuePointer::INPUT(2.into())),
+                Span::new(0, 1, 1),
+            )],
+        );
     }
 
     #[test]
     fn test_parse_label_and_instruction_with_comment() {
-        let res = instruction_line(to_input(b"LBL: SUB <3 // Sum the values\n"));
+        let content = to_input(b"LBL: SUB <3 // Sum the values\n");
+        let res = instruction_line(content, content);
         assert_full_result(
             res,
             vec![
-                Operation::LABEL(String::from("LBL")),
-                Operation::SUB(ValuePointer::INPUT(3.into())),
+                (Operation::LABEL(String::from("LBL")), Span::new(0, 1, 1)),
+                (
+                    Operation::SUB(ValuePointer::INPUT(3.into())),
+                    Span::new(5, 1, 6),
+                ),
             ],
         );
     }
@@ -287,16 +428,22 @@ MOV <1, ACC
 F1:SWP
 MOV ACC, >1
 JEZ F1\n";
-        let res = collect_instructions(to_input(content));
+        let res = collect_instructions(content, to_input(content));
         assert_full_result(
             res,
             vec![
-                Operation::LABEL(String::from("START")),
-                Operation::MOV(ValuePointer::INPUT(1.into()), ValuePointer::ACC),
-                Operation::LABEL(String::from("F1")),
-                Operation::SWP(MemoryPointer::BAK(1)),
-                Operation::MOV(ValuePointer::ACC, ValuePointer::OUTPUT(1.into())),
-                Operation::JEZ(String::from("F1")),
+                (Operation::LABEL(String::from("START")), Span::new(0, 1, 1)),
+                (
+                    Operation::MOV(ValuePointer::INPUT(1.into()), ValuePointer::ACC),
+                    Span::new(7, 2, 1),
+                ),
+                (Operation::LABEL(String::from("F1")), Span::new(19, 3, 1)),
+                (Operation::SWP(MemoryPointer::BAK(1)), Span::new(22, 3, 4)),
+                (
+                    Operation::MOV(ValuePointer::ACC, ValuePointer::OUTPUT(1.into())),
+                    Span::new(26, 4, 1),
+                ),
+                (Operation::JEZ(String::from("F1")), Span::new(38, 5, 1)),
             ],
         );
     }
@@ -315,25 +462,53 @@ MOV ACC, >1
 =======
 ";
 
-        let res = node_block(to_input(content));
+        let res =
+            node_block(content, to_input(content)).map(|(rest, node)| (rest, clear_spans(node)));
         assert_full_result(
             res,
-            (
-                Node::new_node("123"),
-                vec![InputMapping {
+            NodeBlock {
+                node: Node::new_node("123"),
+                inputs: vec![InputMapping {
                     from: Port::new(Node::In, 1.into()),
                     to: 1.into(),
+                    span: None,
                 }],
-                vec![OutputMapping {
+                outputs: vec![OutputMapping {
                     from: 1.into(),
                     to: Port::new(Node::Out, 1.into()),
+                    span: None,
                 }],
-                vec![
+                instructions: vec![
                     Operation::MOV(ValuePointer::INPUT(1.into()), ValuePointer::ACC),
                     Operation::SWP(MemoryPointer::BAK(1)),
                     Operation::MOV(ValuePointer::ACC, ValuePointer::OUTPUT(1.into())),
                 ],
-            ),
+                spans: NodeSpans::default(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_locate_node_elements() {
+        let content = b"Node #1
+=====
+IN:1 -> 1
+---
+  MOV <1, >1
+---
+1 -> OUT:1
+=====
+";
+
+        let (_, node) = node_block(content, to_input(content)).unwrap();
+        assert_eq!(node.inputs[0].span, Some(Span::new(14, 3, 1)));
+        assert_eq!(node.outputs[0].span, Some(Span::new(45, 7, 1)));
+        assert_eq!(
+            node.spans,
+            NodeSpans {
+                node: Some(Span::new(0, 1, 1)),
+                operations: vec![Span::new(30, 5, 3)],
+            }
         );
     }
 
@@ -345,10 +520,11 @@ SWP
 =======
 ";
 
-        let res = node_block(to_input(content));
-        let (_, (_, res_inputs, res_outputs, _)) = res.unwrap();
-        assert_eq!(res_inputs, vec![]);
-        assert_eq!(res_outputs, vec![]);
+        let res =
+            node_block(content, to_input(content)).map(|(rest, node)| (rest, clear_spans(node)));
+        let (_, node) = res.unwrap();
+        assert_eq!(node.inputs, vec![]);
+        assert_eq!(node.outputs, vec![]);
     }
 
     #[test]
@@ -361,15 +537,17 @@ SWP
 =======
 ";
 
-        let res = node_block(to_input(content));
+        let res =
+            node_block(content, to_input(content)).map(|(rest, node)| (rest, clear_spans(node)));
         assert_full_result(
             res,
-            (
-                Node::new_node("1"),
-                vec![],
-                vec![],
-                vec![Operation::SWP(MemoryPointer::BAK(1))],
-            ),
+            NodeBlock {
+                node: Node::new_node("1"),
+                inputs: vec![],
+                outputs: vec![],
+                instructions: vec![Operation::SWP(MemoryPointer::BAK(1))],
+                spans: NodeSpans::default(),
+            },
         );
     }
 
@@ -381,15 +559,17 @@ SWP // commenting operation
 =======
 ";
 
-        let res = node_block(to_input(content));
+        let res =
+            node_block(content, to_input(content)).map(|(rest, node)| (rest, clear_spans(node)));
         assert_full_result(
             res,
-            (
-                Node::new_node("1"),
-                vec![],
-                vec![],
-                vec![Operation::SWP(MemoryPointer::BAK(1))],
-            ),
+            NodeBlock {
+                node: Node::new_node("1"),
+                inputs: vec![],
+                outputs: vec![],
+                instructions: vec![Operation::SWP(MemoryPointer::BAK(1))],
+                spans: NodeSpans::default(),
+            },
         );
     }
 
@@ -402,15 +582,17 @@ SWP
 =======
 ";
 
-        let res = node_block(to_input(content));
+        let res =
+            node_block(content, to_input(content)).map(|(rest, node)| (rest, clear_spans(node)));
         assert_full_result(
             res,
-            (
-                Node::new_node("3"),
-                vec![],
-                vec![],
-                vec![Operation::SWP(MemoryPointer::BAK(1))],
-            ),
+            NodeBlock {
+                node: Node::new_node("3"),
+                inputs: vec![],
+                outputs: vec![],
+                instructions: vec![Operation::SWP(MemoryPointer::BAK(1))],
+                spans: NodeSpans::default(),
+            },
         );
     }
 
@@ -424,15 +606,17 @@ SWP
 =======
 ";
 
-        let res = node_block(to_input(content));
+        let res =
+            node_block(content, to_input(content)).map(|(rest, node)| (rest, clear_spans(node)));
         assert_full_result(
             res,
-            (
-                Node::new_node("1"),
-                vec![],
-                vec![],
-                vec![Operation::SWP(MemoryPointer::BAK(1))],
-            ),
+            NodeBlock {
+                node: Node::new_node("1"),
+                inputs: vec![],
+                outputs: vec![],
+                instructions: vec![Operation::SWP(MemoryPointer::BAK(1))],
+                spans: NodeSpans::default(),
+            },
         );
     }
 
@@ -446,15 +630,17 @@ SWP
 =======
 ";
 
-        let res = node_block(to_input(content));
+        let res =
+            node_block(content, to_input(content)).map(|(rest, node)| (rest, clear_spans(node)));
         assert_full_result(
             res,
-            (
-                Node::new_node("1"),
-                vec![],
-                vec![],
-                vec![Operation::SWP(MemoryPointer::BAK(1))],
-            ),
+            NodeBlock {
+                node: Node::new_node("1"),
+                inputs: vec![],
+                outputs: vec![],
+                instructions: vec![Operation::SWP(MemoryPointer::BAK(1))],
+                spans: NodeSpans::default(),
+            },
         );
     }
 
@@ -472,31 +658,36 @@ MOV ACC, >1
 =========
 ";
 
-        let res = node_block(to_input(content));
+        let res =
+            node_block(content, to_input(content)).map(|(rest, node)| (rest, clear_spans(node)));
         assert_full_result(
             res,
-            (
-                Node::new_node("1"),
-                vec![
+            NodeBlock {
+                node: Node::new_node("1"),
+                inputs: vec![
                     InputMapping {
                         from: Port::named_port(&"1", 1.into()),
                         to: 1.into(),
+                        span: None,
                     },
                     InputMapping {
                         from: Port::named_port(&"2", 1.into()),
                         to: 2.into(),
+                        span: None,
                     },
                 ],
-                vec![OutputMapping {
+                outputs: vec![OutputMapping {
                     from: 1.into(),
                     to: Port::new(Node::Out, 1.into()),
+                    span: None,
                 }],
-                vec![
+                instructions: vec![
                     Operation::MOV(ValuePointer::INPUT(1.into()), ValuePointer::ACC),
                     Operation::ADD(ValuePointer::INPUT(2.into())),
                     Operation::MOV(ValuePointer::ACC, ValuePointer::OUTPUT(1.into())),
                 ],
-            ),
+                spans: NodeSpans::default(),
+            },
         );
     }
 
@@ -530,55 +721,67 @@ MOV <3, >3
 ==========
 ";
 
-        let res = node_list(to_input(content));
+        let res = node_list(content, to_input(content)).map(|(rest, (nodes, errors))| {
+            assert!(errors.is_empty());
+            (rest, nodes.into_iter().map(clear_spans).collect())
+        });
         assert_full_result(
             res,
             vec![
-                (
-                    Node::new_node("1"),
-                    vec![InputMapping {
+                NodeBlock {
+                    node: Node::new_node("1"),
+                    inputs: vec![InputMapping {
                         from: Port::new(Node::In, 1.into()),
                         to: 1.into(),
+                        span: None,
                     }],
-                    vec![OutputMapping {
+                    outputs: vec![OutputMapping {
                         from: 1.into(),
                         to: Port::named_port(&"2", 2.into()),
+                        span: None,
                     }],
-                    vec![Operation::MOV(
+                    instructions: vec![Operation::MOV(
                         ValuePointer::INPUT(1.into()),
                         ValuePointer::OUTPUT(1.into()),
                     )],
-                ),
-                (
-                    Node::new_node("2"),
-                    vec![InputMapping {
+                    spans: NodeSpans::default(),
+                },
+                NodeBlock {
+                    node: Node::new_node("2"),
+                    inputs: vec![InputMapping {
                         from: Port::named_port(&"1", 1.into()),
                         to: 2.into(),
+                        span: None,
                     }],
-                    vec![OutputMapping {
+                    outputs: vec![OutputMapping {
                         from: 2.into(),
                         to: Port::named_port(&"3", 3.into()),
+                        span: None,
                     }],
-                    vec![Operation::MOV(
+                    instructions: vec![Operation::MOV(
                         ValuePointer::INPUT(2.into()),
                         ValuePointer::OUTPUT(2.into()),
                     )],
-                ),
-                (
-                    Node::new_node("3"),
-                    vec![InputMapping {
+                    spans: NodeSpans::default(),
+                },
+                NodeBlock {
+                    node: Node::new_node("3"),
+                    inputs: vec![InputMapping {
                         from: Port::named_port(&"2", 2.into()),
                         to: 3.into(),
+                        span: None,
                     }],
-                    vec![OutputMapping {
+                    outputs: vec![OutputMapping {
                         from: 3.into(),
                         to: Port::new(Node::Out, 1.into()),
+                        span: None,
                     }],
-                    vec![Operation::MOV(
+                    instructions: vec![Operation::MOV(
                         ValuePointer::INPUT(3.into()),
                         ValuePointer::OUTPUT(3.into()),
                     )],
-                ),
+                    spans: NodeSpans::default(),
+                },
             ],
         );
     }
@@ -1,11 +1,13 @@
 use crate::common::ws;
+use crate::error::IResult;
 use language::{
     address::{InputSlot, OutputSlot},
     test::TestCase,
 };
 use nom::bytes::complete as bytes;
 use nom::character::complete::space0;
-use nom::IResult;
+use nom::combinator::cut;
+use nom::error::context;
 
 pub fn values(input: &[u8]) -> IResult<&[u8], Vec<i8>> {
     nom::multi::separated_list1(nom::character::complete::space1, crate::common::be_i8)(input)
@@ -23,13 +25,16 @@ where
 {
     move |input| {
         let (input, _) = bytes::tag(tag)(input)?;
-        // TODO at this point, we are in a test comment, the syntax must be correct
-        let (input, (slot, _)) = ws(nom::sequence::tuple((
-            crate::common::be_u8,
-            bytes::tag(":"),
-        )))(input)?;
-        let (input, values) = ws(array)(input)?;
-        let (rest, _) = nom::sequence::tuple((space0, bytes::tag("\n")))(input)?;
+        // At this point, we are in a test comment, the syntax must be correct
+        let (input, (slot, _)) = cut(ws(nom::sequence::tuple((
+            context("port number", crate::common::be_u8),
+            context("`:` after the port number", bytes::tag(":")),
+        ))))(input)?;
+        let (input, values) = cut(context("values like `[1 2 3]`", ws(array)))(input)?;
+        let (rest, _) = cut(context(
+            "end of line",
+            nom::sequence::tuple((space0, bytes::tag("\n"))),
+        ))(input)?;
         Ok((rest, (slot.into(), values)))
     }
 }
@@ -46,26 +51,34 @@ pub fn test_case(input: &[u8]) -> IResult<&[u8], TestCase> {
     let mut test: Option<TestCase> = None;
     let mut remaining = input;
     loop {
-        if let Ok((rest, (input_slot, input_values))) = test_input_values(remaining) {
-            test = test
-                .or_else(|| Some(Default::default()))
-                .map(|t| t.input_into(input_slot, input_values));
-            remaining = rest;
-            continue;
+        match test_input_values(remaining) {
+            Ok((rest, (input_slot, input_values))) => {
+                test = test
+                    .or_else(|| Some(Default::default()))
+                    .map(|t| t.input_into(input_slot, input_values));
+                remaining = rest;
+                continue;
+            }
+            Err(nom::Err::Error(_)) => {}
+            Err(e) => return Err(e),
         }
-        if let Ok((rest, (output_slot, output_values))) = test_output_values(remaining) {
-            test = test
-                .or_else(|| Some(Default::default()))
-                .map(|t| t.output_from(output_slot, output_values));
-            remaining = rest;
-            continue;
+        match test_output_values(remaining) {
+            Ok((rest, (output_slot, output_values))) => {
+                test = test
+                    .or_else(|| Some(Default::default()))
+                    .map(|t| t.output_from(output_slot, output_values));
+                remaining = rest;
+                continue;
+            }
+            Err(nom::Err::Error(_)) => {}
+            Err(e) => return Err(e),
         }
 
         return test.map_or_else(
             || {
-                Err(nom::Err::Error(nom::error::Error::new(
+                Err(nom::Err::Error(crate::error::SyntaxError::expecting(
                     input,
-                    nom::error::ErrorKind::Satisfy,
+                    "test values like `/>> 1: [1 2]`",
                 )))
             },
             |t| Ok((remaining, t)),
@@ -1,7 +1,15 @@
 mod mapping;
 
+use language::diagnostic::Diagnostic;
 use language::syntax::Program;
 
-pub fn process(tree: Program) -> Program {
+/// Completes the mappings between nodes, reporting the references to undefined nodes
+pub fn process(tree: Program) -> Result<Program, Vec<Diagnostic>> {
   mapping::complete_mappings(tree)
 }
+
+/// Completes the mappings of a program missing some nodes, like nodes that could not be parsed.
+/// The mappings to the missing nodes are kept without being completed.
+pub fn process_partial(tree: Program) -> Program {
+  mapping::complete_known_mappings(tree)
+}
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
 use language::address::{InputSlot, Node, OutputSlot, Port};
+use language::diagnostic::{Code, Diagnostic};
+use language::span::Span;
 use language::syntax::Program;
 use language::syntax::{InputMapping, NodeBlock, OutputMapping};
 
@@ -8,7 +10,7 @@ use language::syntax::{InputMapping, NodeBlock, OutputMapping};
 type Index = HashMap<String, usize>;
 fn map_node_to_idx(tree: &Program) -> Index {
     let mut index = HashMap::new();
-    for (i, &(ref node, _, _, _)) in tree.nodes.iter().enumerate() {
+    for (i, NodeBlock { node, .. }) in tree.nodes.iter().enumerate() {
         if let Node::Node(ref node_id) = node {
             index.insert(node_id.clone(), i);
         }
@@ -16,24 +18,61 @@ fn map_node_to_idx(tree: &Program) -> Index {
     index
 }
 
+/// Finds the position of a node in the program, if defined
+fn find_node(index: &Index, node: &Node) -> Option<usize> {
+    match node {
+        Node::Node(ref id) => index.get(id).cloned(),
+        _ => None,
+    }
+}
+
+/// Reports the mappings referencing nodes that are not defined in the program
+fn check_unknown_nodes(tree: &Program, index: &Index) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for node in tree.nodes.iter() {
+        let mapped_nodes = node
+            .inputs
+            .iter()
+            .map(|input| (&input.from.node, input.span))
+            .chain(
+                node.outputs
+                    .iter()
+                    .map(|output| (&output.to.node, output.span)),
+            );
+        for (mapped_node, span) in mapped_nodes {
+            if let Node::Node(ref id) = mapped_node {
+                if !index.contains_key(id) {
+                    diagnostics.push(
+                        Diagnostic::error(
+                            Code::UnknownNode,
+                            format!("{} mapped by {} is not defined", mapped_node, node.node),
+                        )
+                        .at(span)
+                        .in_node(&node.node),
+                    );
+                }
+            }
+        }
+    }
+    diagnostics
+}
+
 /// Complete nodes inputs with the outputs referenced by nodes
 fn complete_inputs(mut tree: Program, index: &Index) -> Program {
     let mut additions = Vec::new();
     for node in tree.nodes.iter() {
-        let this_id = &node.0.get_id();
+        let this_id = &node.node.get_id();
         // Read outputs and add them to their sources
-        let outputs: &Vec<OutputMapping> = &node.2;
+        let outputs: &Vec<OutputMapping> = &node.outputs;
         for output in outputs.iter() {
-            if let Node::Node(ref dst_id) = &output.to.node {
-                let idx = index
-                    .get(dst_id)
-                    .unwrap_or_else(|| panic!("No reference to node {}", dst_id));
-                let dst_node = &tree.nodes[*idx];
+            // References to unknown nodes are not completed
+            if let Some(idx) = find_node(index, &output.to.node) {
+                let dst_node = &tree.nodes[idx];
                 // this output m: i -> n:j => input n: m:i -> j
                 let addtional_input =
-                    complete_input(dst_node, this_id, output.from, output.to.port);
+                    complete_input(dst_node, this_id, output.from, output.to.port, output.span);
                 if let Some(input) = addtional_input {
-                    additions.push((*idx, input));
+                    additions.push((idx, input));
                 }
             }
         }
@@ -41,7 +80,7 @@ fn complete_inputs(mut tree: Program, index: &Index) -> Program {
 
     for (i, input) in additions {
         let node = &mut tree.nodes[i];
-        node.1.push(input);
+        node.inputs.push(input);
     }
 
     tree
@@ -52,9 +91,10 @@ fn complete_input(
     src_id: &str,
     from: OutputSlot,
     to: InputSlot,
+    span: Option<Span>,
 ) -> Option<InputMapping> {
     // Skip if the port is already present
-    let inputs: &Vec<InputMapping> = &node.1;
+    let inputs: &Vec<InputMapping> = &node.inputs;
     if !inputs.iter().any(|input| match input.from.node {
         Node::Node(ref id) => id == src_id && input.from.port == from,
         _ => false,
@@ -65,6 +105,8 @@ fn complete_input(
                 port: from,
             },
             to,
+            // Located at the declaration of the mirrored output
+            span,
         })
     } else {
         None
@@ -75,19 +117,16 @@ fn complete_input(
 fn complete_outputs(mut tree: Program, index: &Index) -> Program {
     let mut additions = Vec::new();
     for node in tree.nodes.iter() {
-        let this_id = &node.0.get_id();
+        let this_id = &node.node.get_id();
         // Read inputs and add them to the source
-        let inputs: &Vec<InputMapping> = &node.1;
+        let inputs: &Vec<InputMapping> = &node.inputs;
         for input in inputs.iter() {
-            if let Node::Node(ref dst_id) = input.from.node {
-                let idx = index
-                    .get(dst_id)
-                    .unwrap_or_else(|| panic!("No reference to node {}", dst_id));
-                let src_node = &tree.nodes[*idx];
+            if let Some(idx) = find_node(index, &input.from.node) {
+                let src_node = &tree.nodes[idx];
                 let addtional_output =
-                    complete_output(src_node, this_id, input.from.port, input.to);
+                    complete_output(src_node, this_id, input.from.port, input.to, input.span);
                 if let Some(o) = addtional_output {
-                    additions.push((*idx, o));
+                    additions.push((idx, o));
                 }
             }
         }
@@ -95,7 +134,7 @@ fn complete_outputs(mut tree: Program, index: &Index) -> Program {
 
     for (i, output) in additions {
         let node = &mut tree.nodes[i];
-        node.2.push(output);
+        node.outputs.push(output);
     }
 
     tree
@@ -106,9 +145,10 @@ fn complete_output(
     dst_id: &str,
     from: OutputSlot,
     to: InputSlot,
+    span: Option<Span>,
 ) -> Option<OutputMapping> {
     // Skip if the port is already present
-    let outputs: &Vec<OutputMapping> = &node.2;
+    let outputs: &Vec<OutputMapping> = &node.outputs;
     if !outputs.iter().any(|output| match output.to.node {
         Node::Node(ref id) => id == dst_id && output.to.port == to,
         _ => false,
@@ -119,37 +159,51 @@ fn complete_output(
                 node: Node::Node(dst_id.to_owned()),
                 port: to,
             },
+            // Located at the declaration of the mirrored input
+            span,
         })
     } else {
         None
     }
 }
 
-pub fn complete_mappings(tree: Program) -> Program {
+/// Completes the mappings between the nodes of the program.
+/// The mappings to nodes absent from the program are kept as is.
+pub fn complete_known_mappings(tree: Program) -> Program {
     let nodes = map_node_to_idx(&tree);
     let tree = complete_inputs(tree, &nodes);
     complete_outputs(tree, &nodes)
 }
 
+pub fn complete_mappings(tree: Program) -> Result<Program, Vec<Diagnostic>> {
+    let diagnostics = check_unknown_nodes(&tree, &map_node_to_idx(&tree));
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+    Ok(complete_known_mappings(tree))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use language::address::Port;
+    use language::span::NodeSpans;
     use language::syntax::{InputMapping, OutputMapping};
 
     #[test]
     fn test_complete_node_inputs() {
-        let src = (
-            Node::new_node(&"a"),
-            vec![],
-            vec![
+        let src = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![],
+            outputs: vec![
                 OutputMapping {
                     from: 1.into(),
                     to: Port {
                         node: Node::new_node(&"b"),
                         port: 2.into(),
                     },
+                    span: None,
                 },
                 OutputMapping {
                     from: 2.into(),
@@ -157,39 +211,56 @@ mod tests {
                         node: Node::Out,
                         port: 1.into(),
                     },
+                    span: None,
                 },
             ],
-            vec![],
-        );
-        let dst = (Node::new_node(&"b"), vec![], vec![], vec![]);
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
+        let dst = NodeBlock {
+            node: Node::new_node(&"b"),
+            inputs: vec![],
+            outputs: vec![],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         let tree = complete_mappings(Program {
             nodes: vec![src, dst],
             tests: None,
-        });
+        })
+        .unwrap();
         assert_eq!(
-            tree.nodes[1].1,
+            tree.nodes[1].inputs,
             vec![InputMapping {
                 from: Port {
                     node: Node::new_node(&"a"),
                     port: 1.into()
                 },
-                to: 2.into()
+                to: 2.into(),
+                span: None,
             }]
         );
     }
 
     #[test]
     fn test_complete_node_outputs() {
-        let src = (Node::new_node(&"a"), vec![], vec![], vec![]);
-        let dst = (
-            Node::new_node(&"b"),
-            vec![
+        let src = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![],
+            outputs: vec![],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
+        let dst = NodeBlock {
+            node: Node::new_node(&"b"),
+            inputs: vec![
                 InputMapping {
                     from: Port {
                         node: Node::In,
                         port: 1.into(),
                     },
                     to: 1.into(),
+                    span: None,
                 },
                 InputMapping {
                     from: Port {
@@ -197,94 +268,200 @@ mod tests {
                         port: 1.into(),
                     },
                     to: 2.into(),
+                    span: None,
                 },
             ],
-            vec![],
-            vec![],
-        );
+            outputs: vec![],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         let tree = complete_mappings(Program {
             nodes: vec![src, dst],
             tests: None,
-        });
+        })
+        .unwrap();
         assert_eq!(
-            tree.nodes[0].2,
+            tree.nodes[0].outputs,
             vec![OutputMapping {
                 from: 1.into(),
                 to: Port {
                     node: Node::new_node(&"b"),
                     port: 2.into()
-                }
+                },
+                span: None,
             }]
         );
     }
 
     #[test]
     fn test_complete_partial_definitions() {
-        let src = (
-            Node::new_node(&"a"),
-            vec![],
-            vec![OutputMapping {
+        let src = NodeBlock {
+            node: Node::new_node(&"a"),
+            inputs: vec![],
+            outputs: vec![OutputMapping {
                 from: 2.into(),
                 to: Port {
                     node: Node::new_node(&"b"),
                     port: 2.into(),
                 },
+                span: None,
             }],
-            vec![],
-        );
-        let dst = (
-            Node::new_node(&"b"),
-            vec![InputMapping {
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
+        let dst = NodeBlock {
+            node: Node::new_node(&"b"),
+            inputs: vec![InputMapping {
                 from: Port {
                     node: Node::new_node(&"a"),
                     port: 1.into(),
                 },
                 to: 1.into(),
+                span: None,
             }],
-            vec![],
-            vec![],
-        );
+            outputs: vec![],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
         let tree = complete_mappings(Program {
             nodes: vec![src, dst],
             tests: None,
-        });
+        })
+        .unwrap();
         assert_eq!(
-            tree.nodes[0].2,
+            tree.nodes[0].outputs,
             vec![
                 OutputMapping {
                     from: 2.into(),
                     to: Port {
                         node: Node::new_node(&"b"),
                         port: 2.into()
-                    }
+                    },
+                    span: None,
                 },
                 OutputMapping {
                     from: 1.into(),
                     to: Port {
                         node: Node::new_node(&"b"),
                         port: 1.into()
-                    }
+                    },
+                    span: None,
                 }
             ]
         );
         assert_eq!(
-            tree.nodes[1].1,
+            tree.nodes[1].inputs,
             vec![
                 InputMapping {
                     from: Port {
                         node: Node::new_node(&"a"),
                         port: 1.into()
                     },
-                    to: 1.into()
+                    to: 1.into(),
+                    span: None,
                 },
                 InputMapping {
                     from: Port {
                         node: Node::new_node(&"a"),
                         port: 2.into()
                     },
-                    to: 2.into()
+                    to: 2.into(),
+                    span: None,
                 }
             ]
         );
     }
+
+    #[test]
+    fn test_complete_known_mappings() {
+        let node = NodeBlock {
+            node: Node::new_node("a"),
+            inputs: vec![InputMapping {
+                from: Port {
+                    node: Node::new_node("b"),
+                    port: 1.into(),
+                },
+                to: 1.into(),
+                span: None,
+            }],
+            outputs: vec![
+                OutputMapping {
+                    from: 1.into(),
+                    to: Port {
+                        node: Node::new_node("c"),
+                        port: 1.into(),
+                    },
+                    span: None,
+                },
+                OutputMapping {
+                    from: 2.into(),
+                    to: Port {
+                        node: Node::new_node("d"),
+                        port: 1.into(),
+                    },
+                    span: None,
+                },
+            ],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
+        let tree = complete_known_mappings(Program {
+            nodes: vec![node, NodeBlock::new(Node::new_node("d"))],
+            tests: None,
+        });
+        // Mappings to the absent nodes b and c are kept
+        let sources: Vec<_> = tree.nodes[0]
+            .inputs
+            .iter()
+            .map(|input| &input.from.node)
+            .collect();
+        assert_eq!(sources, vec![&Node::new_node("b")]);
+        let destinations: Vec<_> = tree.nodes[0]
+            .outputs
+            .iter()
+            .map(|output| &output.to.node)
+            .collect();
+        assert_eq!(
+            destinations,
+            vec![&Node::new_node("c"), &Node::new_node("d")]
+        );
+        assert_eq!(
+            tree.nodes[1].inputs,
+            vec![InputMapping {
+                from: Port {
+                    node: Node::new_node("a"),
+                    port: 2.into(),
+                },
+                to: 1.into(),
+                span: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_report_unknown_nodes() {
+        let node = NodeBlock {
+            node: Node::new_node("a"),
+            inputs: vec![],
+            outputs: vec![OutputMapping {
+                from: 1.into(),
+                to: Port {
+                    node: Node::new_node("zz"),
+                    port: 1.into(),
+                },
+                span: Some(Span::new(30, 3, 1)),
+            }],
+            instructions: vec![],
+            spans: NodeSpans::default(),
+        };
+        let diagnostics = complete_mappings(Program {
+            nodes: vec![node],
+            tests: None,
+        })
+        .unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Code::UnknownNode);
+        assert_eq!(diagnostics[0].node, Some(String::from("a")));
+        assert_eq!(diagnostics[0].span, Some(Span::new(30, 3, 1)));
+    }
 }
@@ -0,0 +1,270 @@
+mod node;
+mod slot;
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::node::NodeExecution;
+use crate::slot::Slot;
+use language::address::{InputSlot, Node, OutputSlot};
+use language::syntax::Program;
+
+/// Key identifying a slot, as `(source node, source port, target node, target port)`
+type SlotKey = (String, u8, String, u8);
+
+/// Simulator executing a TZ-IO program, tick after tick.
+///
+/// This follows the semantics of `JavaTzEnv#tick` from the Java core. The program is
+/// expected to have its mappings completed by the postprocessor.
+#[derive(Debug)]
+pub struct Simulator {
+    slots: Vec<Slot>,
+    nodes: Vec<NodeExecution>,
+    /// Indexes of the slots fed by the program inputs
+    inputs: BTreeMap<OutputSlot, usize>,
+    /// Indexes of the slots producing the program outputs
+    outputs: BTreeMap<InputSlot, usize>,
+    cycles: usize,
+}
+
+fn map_slot(index: &mut HashMap<SlotKey, usize>, key: SlotKey) -> usize {
+    let next_idx = index.len();
+    *index.entry(key).or_insert(next_idx)
+}
+
+impl Simulator {
+    pub fn new(program: &Program) -> Result<Self, String> {
+        let mut index = HashMap::new();
+        let mut inputs = BTreeMap::new();
+        let mut outputs = BTreeMap::new();
+        let mut node_slots = Vec::new();
+
+        for node in &program.nodes {
+            let node_name = node.node.to_string();
+            let mut node_inputs = HashMap::new();
+            for input in &node.inputs {
+                let slot_idx = map_slot(
+                    &mut index,
+                    (
+                        input.from.node.to_string(),
+                        input.from.port.value(),
+                        node_name.clone(),
+                        input.to.value(),
+                    ),
+                );
+                node_inputs.insert(input.to, slot_idx);
+                if input.from.node == Node::In && inputs.insert(input.from.port, slot_idx).is_some()
+                {
+                    return Err(format!(
+                        "Input port {} is used more than once",
+                        input.from.port
+                    ));
+                }
+            }
+
+            let mut node_outputs = HashMap::new();
+            for output in &node.outputs {
+                let slot_idx = map_slot(
+                    &mut index,
+                    (
+                        node_name.clone(),
+                        output.from.value(),
+                        output.to.node.to_string(),
+                        output.to.port.value(),
+                    ),
+                );
+                node_outputs.insert(output.from, slot_idx);
+                if output.to.node == Node::Out && outputs.insert(output.to.port, slot_idx).is_some()
+                {
+                    return Err(format!(
+                        "Output port {} is used more than once",
+                        output.to.port
+                    ));
+                }
+            }
+            node_slots.push((node_inputs, node_outputs));
+        }
+
+        let mut slots: Vec<Slot> = (0..index.len()).map(|_| Slot::data()).collect();
+        for slot_idx in inputs.values() {
+            slots[*slot_idx] = Slot::queue();
+        }
+
+        let nodes = program
+            .nodes
+            .iter()
+            .zip(node_slots)
+            .map(|(node, (node_inputs, node_outputs))| {
+                NodeExecution::new(
+                    node.node.get_id(),
+                    &node.instructions,
+                    node_inputs,
+                    node_outputs,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Simulator {
+            slots,
+            nodes,
+            inputs,
+            outputs,
+            cycles: 0,
+        })
+    }
+
+    /// Gets the ports of the program inputs, in increasing order
+    pub fn input_ports(&self) -> Vec<OutputSlot> {
+        self.inputs.keys().cloned().collect()
+    }
+
+    /// Gets the ports of the program outputs, in increasing order.
+    ///
+    /// Values produced by #tick are ordered like these ports.
+    pub fn output_ports(&self) -> Vec<InputSlot> {
+        self.outputs.keys().cloned().collect()
+    }
+
+    /// Gets the number of ticks executed so far
+    pub fn cycle_count(&self) -> usize {
+        self.cycles
+    }
+
+    /// Feeds values into an input of the program.
+    pub fn consume(&mut self, port: OutputSlot, values: &[i32]) -> Result<(), String> {
+        let slot_idx = self
+            .inputs
+            .get(&port)
+            .ok_or_else(|| format!("No input port {} in the program", port))?;
+        for value in values {
+            self.slots[*slot_idx].enqueue(*value);
+        }
+        Ok(())
+    }
+
+    /// Executes a tick of all nodes.
+    ///
+    /// Nodes are run in the order of the program. Since slots are transactional,
+    /// the order does not change the result.
+    /// This returns the values produced on the program outputs, if any.
+    pub fn tick(&mut self) -> Option<Vec<Option<i32>>> {
+        for node in self.nodes.iter_mut() {
+            node.run_step(&mut self.slots);
+        }
+        for slot in self.slots.iter_mut() {
+            slot.on_step_end();
+        }
+        self.cycles += 1;
+
+        let slots = &mut self.slots;
+        if self.outputs.values().any(|idx| slots[*idx].can_read()) {
+            Some(
+                self.outputs
+                    .values()
+                    .map(|idx| {
+                        let slot = &mut slots[*idx];
+                        if slot.can_read() {
+                            Some(slot.read())
+                        } else {
+                            None
+                        }
+                    })
+                    .collect(),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Runs the program for a given number of ticks, collecting all produced outputs.
+    pub fn run(&mut self, cycles: usize) -> Vec<Vec<Option<i32>>> {
+        (0..cycles).filter_map(|_| self.tick()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use language::address::Port;
+    use language::instruction::{Operation, ValuePointer};
+    use language::syntax::NodeBlock;
+
+    /// Creates the program of `language-samples/sum.io`, with completed mappings
+    fn create_sum_program() -> Program {
+        let double = NodeBlock::new(Node::new_node("1"))
+            .input(Port::new(Node::In, 1.into()), 1.into())
+            .output(1.into(), Port::named_port("3", 1.into()))
+            .instruction(Operation::MOV(
+                ValuePointer::INPUT(1.into()),
+                ValuePointer::ACC,
+            ))
+            .instruction(Operation::ADD(ValuePointer::ACC))
+            .instruction(Operation::MOV(
+                ValuePointer::ACC,
+                ValuePointer::OUTPUT(1.into()),
+            ));
+        let increment = NodeBlock::new(Node::new_node("2"))
+            .input(Port::new(Node::In, 2.into()), 1.into())
+            .output(1.into(), Port::named_port("3", 2.into()))
+            .instruction(Operation::MOV(
+                ValuePointer::INPUT(1.into()),
+                ValuePointer::ACC,
+            ))
+            .instruction(Operation::ADD(ValuePointer::VALUE(1)))
+            .instruction(Operation::MOV(
+                ValuePointer::ACC,
+                ValuePointer::OUTPUT(1.into()),
+            ));
+        let sum = NodeBlock::new(Node::new_node("3"))
+            .input(Port::named_port("1", 1.into()), 1.into())
+            .input(Port::named_port("2", 1.into()), 2.into())
+            .output(1.into(), Port::new(Node::Out, 1.into()))
+            .instruction(Operation::MOV(
+                ValuePointer::INPUT(1.into()),
+                ValuePointer::ACC,
+            ))
+            .instruction(Operation::ADD(ValuePointer::INPUT(2.into())))
+            .instruction(Operation::MOV(
+                ValuePointer::ACC,
+                ValuePointer::OUTPUT(1.into()),
+            ));
+        Program::default().node(double).node(increment).node(sum)
+    }
+
+    #[test]
+    fn test_create_slots() {
+        let simulator = Simulator::new(&create_sum_program()).unwrap();
+        assert_eq!(simulator.slots.len(), 5);
+        assert_eq!(simulator.input_ports(), vec![1.into(), 2.into()]);
+        assert_eq!(simulator.output_ports(), vec![1.into()]);
+    }
+
+    #[test]
+    fn test_run_sum_program() {
+        let mut simulator = Simulator::new(&create_sum_program()).unwrap();
+        simulator.consume(1.into(), &[0, 10, -43]).unwrap();
+        simulator.consume(2.into(), &[1, 5, 86]).unwrap();
+
+        let outputs = simulator.run(100);
+        assert_eq!(outputs, vec![vec![Some(2)], vec![Some(26)], vec![Some(1)]]);
+        assert_eq!(simulator.cycle_count(), 100);
+    }
+
+    #[test]
+    fn test_output_produced_after_transaction() {
+        let mut simulator = Simulator::new(&create_sum_program()).unwrap();
+        simulator.consume(1.into(), &[1]).unwrap();
+        simulator.consume(2.into(), &[1]).unwrap();
+
+        // Read inputs, compute, write to node 3, then read, add, write to output
+        let ticks: Vec<_> = (0..6).map(|_| simulator.tick()).collect();
+        assert_eq!(ticks[..5], vec![None; 5][..]);
+        assert_eq!(ticks[5], Some(vec![Some(4)]));
+    }
+
+    #[test]
+    fn test_consume_unknown_port() {
+        let mut simulator = Simulator::new(&create_sum_program()).unwrap();
+        assert!(simulator.consume(3.into(), &[1]).is_err());
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+
+use crate::slot::Slot;
+use language::address::{InputSlot, OutputSlot};
+use language::instruction::{self, MemoryPointer, Operation, ValuePointer};
+
+/// Shift to apply to the operation index after executing an operation
+enum Shift {
+    Next,
+    Stay,
+    /// Jump to the given operation index
+    Jump(usize),
+    /// Offset the current index, as done by JRO
+    Offset(i32),
+}
+
+/// Execution of the operations of a single node.
+///
+/// This mirrors `NodeExecution` and `Node` of the Java core: labels are not operations
+/// but markers on the next operation, and an operation blocked on its inputs or outputs
+/// is executed again at the next tick.
+#[derive(Debug)]
+pub struct NodeExecution {
+    name: String,
+    acc: i32,
+    memory: Vec<i32>,
+    /// Indexes of the environment slots, by node input
+    inputs: HashMap<InputSlot, usize>,
+    /// Indexes of the environment slots, by node output
+    outputs: HashMap<OutputSlot, usize>,
+    operations: Vec<Operation>,
+    /// Index of the operation following each label
+    labels: HashMap<String, usize>,
+    step_idx: usize,
+}
+
+impl NodeExecution {
+    /// Creates the execution of a node.
+    ///
+    /// This fails if an operation references an undefined label or a port that
+    /// is not mapped to a slot.
+    pub fn new(
+        name: &str,
+        operations: &[Operation],
+        inputs: HashMap<InputSlot, usize>,
+        outputs: HashMap<OutputSlot, usize>,
+    ) -> Result<Self, String> {
+        let mut executed = Vec::new();
+        let mut labels = HashMap::new();
+        for operation in operations {
+            if let Operation::LABEL(ref label) = operation {
+                labels.insert(label.clone(), executed.len());
+            } else {
+                executed.push(operation.clone());
+            }
+        }
+
+        let node = NodeExecution {
+            name: name.to_string(),
+            acc: 0,
+            memory: vec![0; instruction::memory_size(operations).into()],
+            inputs,
+            outputs,
+            operations: executed,
+            labels,
+            step_idx: 0,
+        };
+        for operation in &node.operations {
+            node.validate(operation)?;
+        }
+        Ok(node)
+    }
+
+    fn validate(&self, operation: &Operation) -> Result<(), String> {
+        let valid = match operation {
+            Operation::MOV(ref from, ref to) => {
+                self.is_valid_input(from) && self.is_valid_output(to)
+            }
+            Operation::ADD(ref value) | Operation::SUB(ref value) | Operation::JRO(ref value) => {
+                self.is_valid_input(value)
+            }
+            Operation::JMP(ref label)
+            | Operation::JEZ(ref label)
+            | Operation::JNZ(ref label)
+            | Operation::JLZ(ref label)
+            | Operation::JGZ(ref label) => self.labels.contains_key(label),
+            Operation::SAV(MemoryPointer::BAK(idx)) | Operation::SWP(MemoryPointer::BAK(idx)) => {
+                *idx > 0
+            }
+            _ => true,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "Cannot execute {} in node {}: invalid reference",
+                operation, self.name
+            ))
+        }
+    }
+
+    fn is_valid_input(&self, pointer: &ValuePointer) -> bool {
+        match pointer {
+            ValuePointer::INPUT(ref port) => self.inputs.contains_key(port),
+            ValuePointer::OUTPUT(_) => false,
+            _ => true,
+        }
+    }
+
+    fn is_valid_output(&self, pointer: &ValuePointer) -> bool {
+        match pointer {
+            ValuePointer::OUTPUT(ref port) => self.outputs.contains_key(port),
+            ValuePointer::ACC | ValuePointer::NIL => true,
+            _ => false,
+        }
+    }
+
+    fn can_read(&self, pointer: &ValuePointer, slots: &[Slot]) -> bool {
+        match pointer {
+            ValuePointer::INPUT(ref port) => slots[self.inputs[port]].can_read(),
+            _ => true,
+        }
+    }
+
+    fn read(&self, pointer: &ValuePointer, slots: &mut [Slot]) -> i32 {
+        match pointer {
+            ValuePointer::VALUE(value) => *value as i32,
+            ValuePointer::ACC => self.acc,
+            ValuePointer::NIL => 0,
+            ValuePointer::INPUT(ref port) => slots[self.inputs[port]].read(),
+            ValuePointer::OUTPUT(_) => panic!("Cannot read from an output"),
+        }
+    }
+
+    fn can_write(&self, pointer: &ValuePointer, slots: &[Slot]) -> bool {
+        match pointer {
+            ValuePointer::OUTPUT(ref port) => slots[self.outputs[port]].can_write(),
+            _ => true,
+        }
+    }
+
+    fn write(&mut self, pointer: &ValuePointer, value: i32, slots: &mut [Slot]) {
+        match pointer {
+            ValuePointer::ACC => self.acc = value,
+            ValuePointer::NIL => {}
+            ValuePointer::OUTPUT(ref port) => slots[self.outputs[port]].write(value),
+            _ => panic!("Cannot write into {}", pointer),
+        }
+    }
+
+    fn jump_if(&self, condition: bool, label: &str) -> Shift {
+        if condition {
+            Shift::Jump(self.labels[label])
+        } else {
+            Shift::Next
+        }
+    }
+
+    fn execute(&mut self, operation: &Operation, slots: &mut [Slot]) -> Shift {
+        match operation {
+            Operation::MOV(ref from, ref to) => {
+                if self.can_read(from, slots) && self.can_write(to, slots) {
+                    let value = self.read(from, slots);
+                    self.write(to, value, slots);
+                    Shift::Next
+                } else {
+                    Shift::Stay
+                }
+            }
+            Operation::ADD(ref input) | Operation::SUB(ref input) => {
+                if self.can_read(input, slots) {
+                    let value = self.read(input, slots);
+                    self.acc = if let Operation::ADD(_) = operation {
+                        self.acc.wrapping_add(value)
+                    } else {
+                        self.acc.wrapping_sub(value)
+                    };
+                    Shift::Next
+                } else {
+                    Shift::Stay
+                }
+            }
+            Operation::NEG => {
+                self.acc = self.acc.wrapping_neg();
+                Shift::Next
+            }
+            Operation::SAV(MemoryPointer::BAK(idx)) => {
+                self.memory[*idx as usize - 1] = self.acc;
+                Shift::Next
+            }
+            Operation::SWP(MemoryPointer::BAK(idx)) => {
+                std::mem::swap(&mut self.acc, &mut self.memory[*idx as usize - 1]);
+                Shift::Next
+            }
+            Operation::JMP(ref label) => self.jump_if(true, label),
+            Operation::JEZ(ref label) => self.jump_if(self.acc == 0, label),
+            Operation::JNZ(ref label) => self.jump_if(self.acc != 0, label),
+            Operation::JLZ(ref label) => self.jump_if(self.acc < 0, label),
+            Operation::JGZ(ref label) => self.jump_if(self.acc > 0, label),
+            Operation::JRO(ref input) => {
+                if self.can_read(input, slots) {
+                    Shift::Offset(self.read(input, slots))
+                } else {
+                    Shift::Stay
+                }
+            }
+            Operation::LABEL(_) => panic!("Labels are not executed"),
+        }
+    }
+
+    /// Runs the current operation of the node.
+    ///
+    /// A node without operations stays idle.
+    pub fn run_step(&mut self, slots: &mut [Slot]) {
+        let count = self.operations.len();
+        if count == 0 {
+            return;
+        }
+
+        let operation = self.operations[self.step_idx].clone();
+        self.step_idx = match self.execute(&operation, slots) {
+            Shift::Next => (self.step_idx + 1) % count,
+            Shift::Stay => self.step_idx,
+            Shift::Jump(idx) => {
+                if idx < count {
+                    idx
+                } else {
+                    0
+                }
+            }
+            Shift::Offset(increment) => {
+                (self.step_idx as i64 + increment as i64).rem_euclid(count as i64) as usize
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_node(operations: Vec<Operation>) -> NodeExecution {
+        let inputs = vec![(1.into(), 0)].into_iter().collect();
+        let outputs = vec![(1.into(), 1)].into_iter().collect();
+        NodeExecution::new("a", &operations, inputs, outputs).unwrap()
+    }
+
+    #[test]
+    fn test_mov_waits_for_input() {
+        let mut slots = vec![Slot::queue(), Slot::data()];
+        let mut node = create_node(vec![Operation::MOV(
+            ValuePointer::INPUT(1.into()),
+            ValuePointer::ACC,
+        )]);
+
+        node.run_step(&mut slots);
+        assert_eq!(node.step_idx, 0);
+
+        slots[0].enqueue(3);
+        node.run_step(&mut slots);
+        assert_eq!(node.acc, 3);
+    }
+
+    #[test]
+    fn test_mov_waits_for_output() {
+        let mut slots = vec![Slot::queue(), Slot::data()];
+        let mut node = create_node(vec![
+            Operation::MOV(ValuePointer::VALUE(1), ValuePointer::OUTPUT(1.into())),
+            Operation::MOV(ValuePointer::VALUE(2), ValuePointer::OUTPUT(1.into())),
+        ]);
+
+        node.run_step(&mut slots);
+        slots[1].on_step_end();
+        node.run_step(&mut slots);
+        assert_eq!(node.step_idx, 1);
+        assert_eq!(slots[1].read(), 1);
+    }
+
+    #[test]
+    fn test_math_operations() {
+        let mut slots = vec![Slot::queue(), Slot::data()];
+        let mut node = create_node(vec![
+            Operation::ADD(ValuePointer::VALUE(3)),
+            Operation::SUB(ValuePointer::VALUE(5)),
+            Operation::NEG,
+        ]);
+        node.run_step(&mut slots);
+        node.run_step(&mut slots);
+        assert_eq!(node.acc, -2);
+        node.run_step(&mut slots);
+        assert_eq!(node.acc, 2);
+    }
+
+    #[test]
+    fn test_memory_operations() {
+        let mut slots = vec![];
+        let mut node = create_node(vec![
+            Operation::ADD(ValuePointer::VALUE(3)),
+            Operation::SAV(MemoryPointer::BAK(1)),
+            Operation::ADD(ValuePointer::VALUE(1)),
+            Operation::SWP(MemoryPointer::BAK(1)),
+        ]);
+        for _ in 0..4 {
+            node.run_step(&mut slots);
+        }
+        assert_eq!(node.acc, 3);
+        assert_eq!(node.memory, vec![4]);
+    }
+
+    #[test]
+    fn test_jumps_to_labels() {
+        let mut slots = vec![];
+        let mut node = create_node(vec![
+            Operation::LABEL(String::from("START")),
+            Operation::ADD(ValuePointer::VALUE(1)),
+            Operation::JGZ(String::from("END")),
+            Operation::NEG,
+            Operation::LABEL(String::from("END")),
+        ]);
+        node.run_step(&mut slots);
+        node.run_step(&mut slots);
+        // Label at the end of the operations loops to the start
+        assert_eq!(node.step_idx, 0);
+        assert_eq!(node.acc, 1);
+    }
+
+    #[test]
+    fn test_jro_offsets() {
+        let mut slots = vec![];
+        let mut node = create_node(vec![
+            Operation::JRO(ValuePointer::VALUE(2)),
+            Operation::NEG,
+            Operation::JRO(ValuePointer::VALUE((-1i32) as u32)),
+        ]);
+        node.run_step(&mut slots);
+        assert_eq!(node.step_idx, 2);
+        node.run_step(&mut slots);
+        assert_eq!(node.step_idx, 1);
+    }
+
+    #[test]
+    fn test_undefined_label() {
+        let node = NodeExecution::new(
+            "a",
+            &[Operation::JMP(String::from("NOWHERE"))],
+            HashMap::new(),
+            HashMap::new(),
+        );
+        assert!(node.is_err());
+    }
+
+    #[test]
+    fn test_unmapped_port() {
+        let node = NodeExecution::new(
+            "a",
+            &[Operation::ADD(ValuePointer::INPUT(2.into()))],
+            HashMap::new(),
+            HashMap::new(),
+        );
+        assert!(node.is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+/// Slot connecting two nodes, or a node to the outside world.
+///
+/// Like in the Java core, slots are transactional: a value written during a tick
+/// can only be read at the next tick, and a slot read during a tick cannot be
+/// written before the next tick.
+#[derive(Debug)]
+pub enum Slot {
+    /// Slot storing a single value, between two nodes or towards an output
+    Data {
+        value: i32,
+        /// Flag marking that a value is stored since the start of the tick
+        has_value: bool,
+        /// Flag marking that a value will be stored at the end of the tick
+        has_value_after_step: bool,
+    },
+    /// Input slot storing all values provided by the outside world
+    Queue(VecDeque<i32>),
+}
+
+impl Slot {
+    pub fn data() -> Self {
+        Slot::Data {
+            value: 0,
+            has_value: false,
+            has_value_after_step: false,
+        }
+    }
+
+    pub fn queue() -> Self {
+        Slot::Queue(VecDeque::new())
+    }
+
+    /// Adds a value to an input queue.
+    ///
+    /// Panics if the slot is not a queue.
+    pub fn enqueue(&mut self, value: i32) {
+        match self {
+            Slot::Queue(ref mut values) => values.push_back(value),
+            Slot::Data { .. } => panic!("Cannot enqueue values into a data slot"),
+        }
+    }
+
+    pub fn can_read(&self) -> bool {
+        match self {
+            Slot::Data {
+                has_value,
+                has_value_after_step,
+                ..
+            } => *has_value && *has_value_after_step,
+            Slot::Queue(ref values) => !values.is_empty(),
+        }
+    }
+
+    /// Reads the value of the slot.
+    ///
+    /// This assumes that #can_read is true.
+    pub fn read(&mut self) -> i32 {
+        match self {
+            Slot::Data {
+                value,
+                has_value_after_step,
+                ..
+            } => {
+                *has_value_after_step = false;
+                *value
+            }
+            Slot::Queue(ref mut values) => values
+                .pop_front()
+                .expect("Cannot read from an empty input slot"),
+        }
+    }
+
+    pub fn can_write(&self) -> bool {
+        match self {
+            Slot::Data {
+                has_value,
+                has_value_after_step,
+                ..
+            } => !*has_value && !*has_value_after_step,
+            Slot::Queue(_) => false,
+        }
+    }
+
+    /// Writes a value into the slot.
+    ///
+    /// This assumes that #can_write is true.
+    pub fn write(&mut self, new_value: i32) {
+        match self {
+            Slot::Data {
+                value,
+                has_value_after_step,
+                ..
+            } => {
+                *value = new_value;
+                *has_value_after_step = true;
+            }
+            Slot::Queue(_) => panic!("Cannot write into an input slot"),
+        }
+    }
+
+    /// Completes the transaction of the current tick
+    pub fn on_step_end(&mut self) {
+        if let Slot::Data {
+            has_value,
+            has_value_after_step,
+            ..
+        } = self
+        {
+            *has_value = *has_value_after_step;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_data_slot() {
+        let slot = Slot::data();
+        assert!(!slot.can_read());
+        assert!(slot.can_write());
+    }
+
+    #[test]
+    fn test_written_value_readable_after_step() {
+        let mut slot = Slot::data();
+        slot.write(12);
+        assert!(!slot.can_read());
+        assert!(!slot.can_write());
+
+        slot.on_step_end();
+        assert!(slot.can_read());
+        assert_eq!(slot.read(), 12);
+    }
+
+    #[test]
+    fn test_read_slot_writable_after_step() {
+        let mut slot = Slot::data();
+        slot.write(1);
+        slot.on_step_end();
+        slot.read();
+        assert!(!slot.can_read());
+        assert!(!slot.can_write());
+
+        slot.on_step_end();
+        assert!(slot.can_write());
+    }
+
+    #[test]
+    fn test_queue_slot() {
+        let mut slot = Slot::queue();
+        assert!(!slot.can_read());
+        assert!(!slot.can_write());
+
+        slot.enqueue(1);
+        slot.enqueue(2);
+        assert!(slot.can_read());
+        assert_eq!(slot.read(), 1);
+        assert_eq!(slot.read(), 2);
+        assert!(!slot.can_read());
+    }
+}
@@ -1,6 +1,7 @@
 package com.kineolyan.tzio.v1.java;
 
 import com.kineolyan.tzio.v1.api.TzEnv;
+import com.kineolyan.tzio.v1.api.nodes.CompiledNode;
 import com.kineolyan.tzio.v1.api.ops.OperationType;
 import com.kineolyan.tzio.v1.java.execs.StaticExecutor;
 import com.kineolyan.tzio.v1.java.execs.SystemExecutor;
@@ -33,8 +34,8 @@ public class JavaTzEnv implements TzEnv {
 
 	private final TzAdapter adapter;
 
-	/** Map of node executions indexed by node names */
-	private final Map<String, NodeExecution> nodes;
+	/** Map of node steps indexed by node names */
+	private final Map<String, Runnable> nodes;
 	/**
 	 * All slots defined in the environment.
 	 * <p>
@@ -135,11 +136,7 @@ public class JavaTzEnv implements TzEnv {
 			getInputs(this.slots, inputs),
 			getOutputs(this.slots, outputs));
 		final NodeExecution execution = new NodeExecution(node, operations);
-
-		final NodeExecution previousExecution = this.nodes.put(name, execution);
-		if (previousExecution != null) {
-			throw new IllegalStateException("Existing node registered under " + name);
-		}
+		registerNode(name, execution::runStep);
 
 		if (logger.isLoggable(Level.FINE)) {
 			logger.fine(String.format(
@@ -153,6 +150,40 @@ public class JavaTzEnv implements TzEnv {
 		return this;
 	}
 
+	@Override
+	public JavaTzEnv addCompiledNode(
+		final String name,
+		final int[] inputs,
+		final int[] outputs,
+		final CompiledNode node) {
+		node.connect(
+			getInputs(this.slots, inputs),
+			getOutputs(this.slots, outputs));
+		registerNode(name, node::step);
+
+		if (logger.isLoggable(Level.FINE)) {
+			logger.fine(String.format(
+				"New compiled node added to environment: %s.%nInputs are %s%nOutputs are%s",
+				name,
+				Arrays.toString(inputs),
+				Arrays.toString(outputs)));
+		}
+
+		return this;
+	}
+
+	/**
+	 * Registers the step of a node under its name.
+	 * @param name name of the node
+	 * @param step action running one step of the node
+	 */
+	private void registerNode(final String name, final Runnable step) {
+		final Runnable previousStep = this.nodes.put(name, step);
+		if (previousStep != null) {
+			throw new IllegalStateException("Existing node registered under " + name);
+		}
+	}
+
 	/**
 	 * Configures a consumer for this environment data.
 	 * <p>
@@ -189,7 +220,7 @@ public class JavaTzEnv implements TzEnv {
 	public void tick() {
 		Stream.of(this.slots).forEach(TransactionalElement::onStepStart);
 
-		this.nodes.values().forEach(NodeExecution::runStep);
+		this.nodes.values().forEach(Runnable::run);
 		// Complete transaction for each element
 		Stream.of(this.slots).forEach(TransactionalElement::onStepEnd);
 
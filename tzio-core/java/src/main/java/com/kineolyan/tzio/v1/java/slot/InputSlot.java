@@ -1,9 +1,11 @@
 package com.kineolyan.tzio.v1.java.slot;
 
+import com.kineolyan.tzio.v1.api.nodes.InputPort;
+
 /**
  * Representation of an input slot, that can be read if some data are available.
  */
-public interface InputSlot {
+public interface InputSlot extends InputPort {
 
 	/**
 	 * Gets if data are available and can be read with {@link #read()}.
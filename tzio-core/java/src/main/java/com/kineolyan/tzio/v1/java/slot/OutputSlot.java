@@ -1,9 +1,11 @@
 package com.kineolyan.tzio.v1.java.slot;
 
+import com.kineolyan.tzio.v1.api.nodes.OutputPort;
+
 /**
  * Representation of a node output slot, where values can be written.
  */
-public interface OutputSlot {
+public interface OutputSlot extends OutputPort {
 
 	/**
 	 * Gets if it is possible to write one new value in the output.
@@ -8,6 +8,7 @@ import java.util.stream.Collectors;
 import java.util.stream.IntStream;
 import java.util.stream.Stream;
 
+import com.kineolyan.tzio.v1.api.nodes.CompiledNode;
 import com.kineolyan.tzio.v1.api.ops.OperationType;
 
 /**
@@ -47,6 +48,20 @@ public interface TzEnv {
 			final int[] outputs,
 			final List<OperationType> operations);
 
+	/**
+	 * Adds a node whose operations are compiled into a class.
+	 * @param name name of the node
+	 * @param inputs indexes of the slots to use as this node inputs
+	 * @param outputs indexes of the slots to use as this node outputs
+	 * @param node compiled node, connected to the slots by this environment
+	 * @return this
+	 */
+	TzEnv addCompiledNode(
+			final String name,
+			final int[] inputs,
+			final int[] outputs,
+			final CompiledNode node);
+
 	/**
 	 * Runs this environment using the Java system.
 	 * @param args program arguments
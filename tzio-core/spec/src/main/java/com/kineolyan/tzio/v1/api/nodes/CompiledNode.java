@@ -0,0 +1,35 @@
+package com.kineolyan.tzio.v1.api.nodes;
+
+/**
+ * Base of the nodes whose operations are compiled into bytecode.
+ * <p>
+ *   Generated subclasses hold the node value, memory and current operation,
+ *   and run the current operation in {@link #step()}.
+ * </p>
+ */
+public abstract class CompiledNode {
+
+	/** Node inputs, indexed by declaration order */
+	protected InputPort[] inputs;
+	/** Node outputs, indexed by declaration order */
+	protected OutputPort[] outputs;
+
+	/**
+	 * Connects this node to the slots of its environment.
+	 * @param inputs ports to use as this node inputs
+	 * @param outputs ports to use as this node outputs
+	 */
+	public final void connect(final InputPort[] inputs, final OutputPort[] outputs) {
+		this.inputs = inputs;
+		this.outputs = outputs;
+	}
+
+	/**
+	 * Runs the current operation of the node.
+	 * <p>
+	 *   The operation is left unchanged while its inputs cannot be read or its outputs written.
+	 * </p>
+	 */
+	public abstract void step();
+
+}
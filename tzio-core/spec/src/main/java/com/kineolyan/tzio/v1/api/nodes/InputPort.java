@@ -0,0 +1,23 @@
+package com.kineolyan.tzio.v1.api.nodes;
+
+/**
+ * Representation of a port a compiled node can read values from.
+ */
+public interface InputPort {
+
+	/**
+	 * Gets if data are available and can be read with {@link #read()}.
+	 * @return true if there are data, false otherwise
+	 */
+	boolean canRead();
+
+	/**
+	 * Reads one value in the available data.
+	 * <p>
+	 *   This assumes that {@link #canRead()} is true.
+	 * </p>
+	 * @return read value
+	 */
+	int read();
+
+}
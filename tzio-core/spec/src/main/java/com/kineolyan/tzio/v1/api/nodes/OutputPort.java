@@ -0,0 +1,23 @@
+package com.kineolyan.tzio.v1.api.nodes;
+
+/**
+ * Representation of a port a compiled node can write values into.
+ */
+public interface OutputPort {
+
+	/**
+	 * Gets if it is possible to write one new value in the output.
+	 * @return true if the output is writable, false otherwise
+	 */
+	boolean canWrite();
+
+	/**
+	 * Writes a value into the output.
+	 * <p>
+	 *   This assumes that {@link #canWrite()} is true.
+	 * </p>
+	 * @param value value to write
+	 */
+	void write(int value);
+
+}